@@ -50,11 +50,26 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 "   [REMOVE] {}",
                 op.target.strip_prefix(&target_dir).unwrap().display()
             ),
+            OpType::Relink => println!(
+                "   [RELINK] {} -> {}",
+                op.target.strip_prefix(&target_dir).unwrap().display(),
+                op.source.strip_prefix(&stow_dir).unwrap().display()
+            ),
+            OpType::Replace { .. } => println!(
+                "   [REPLACE] {} -> {}",
+                op.target.strip_prefix(&target_dir).unwrap().display(),
+                op.source.strip_prefix(&stow_dir).unwrap().display()
+            ),
             OpType::Skip(reason) => println!(
                 "   [SKIP] {}: {}",
                 op.target.strip_prefix(&target_dir).unwrap().display(),
                 reason
             ),
+            OpType::Conflict(reason) => println!(
+                "   [CONFLICT] {}: {}",
+                op.target.strip_prefix(&target_dir).unwrap().display(),
+                reason
+            ),
         }
     }
     println!();
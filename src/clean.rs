@@ -0,0 +1,272 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::daemon::is_process_running;
+use crate::remote::git_command;
+
+/// Suffix `create_template` appends to the file it templates.
+const TEMPLATE_SUFFIX: &str = ".template";
+
+/// Suffix of the copies made before a conflicting target is replaced.
+const BACKUP_SUFFIX: &str = ".backup";
+
+/// `.template` files under `stow_dir` whose original file no longer exists.
+/// `.git` directories are not searched.
+pub fn orphaned_templates(stow_dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut found = Vec::new();
+    collect_orphaned_templates(stow_dir, &mut found)?;
+    found.sort();
+    Ok(found)
+}
+
+fn collect_orphaned_templates(dir: &Path, found: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        if path.is_dir() && !path.is_symlink() {
+            if name != ".git" {
+                collect_orphaned_templates(&path, found)?;
+            }
+            continue;
+        }
+
+        if let Some(original) = name.strip_suffix(TEMPLATE_SUFFIX) {
+            if !original.is_empty() && !path.with_file_name(original).exists() {
+                found.push(path);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The `<target>.backup` files next to `targets` that were last modified
+/// more than `max_age` before `now`.
+pub fn stale_backups(targets: &[PathBuf], max_age: Duration, now: SystemTime) -> Vec<PathBuf> {
+    let mut found: Vec<PathBuf> = targets
+        .iter()
+        .map(|target| PathBuf::from(format!("{}{}", target.display(), BACKUP_SUFFIX)))
+        .filter(|backup| {
+            fs::symlink_metadata(backup)
+                .and_then(|m| m.modified())
+                .is_ok_and(|modified| now.duration_since(modified).is_ok_and(|age| age > max_age))
+        })
+        .collect();
+    found.sort();
+    found.dedup();
+    found
+}
+
+/// Cached clones under `repos_dir` that none of `stow_dirs` lives in. A clone
+/// is any directory holding a `.git`; its subdirectories are not searched.
+/// Paths are compared with symlinks resolved.
+pub fn unreferenced_repos(repos_dir: &Path, stow_dirs: &[PathBuf]) -> io::Result<Vec<PathBuf>> {
+    let stow_dirs: Vec<PathBuf> = stow_dirs
+        .iter()
+        .map(|dir| fs::canonicalize(dir).unwrap_or_else(|_| dir.clone()))
+        .collect();
+    let mut found = Vec::new();
+    if repos_dir.is_dir() {
+        collect_unreferenced_repos(repos_dir, &stow_dirs, &mut found)?;
+    }
+    found.sort();
+    Ok(found)
+}
+
+fn collect_unreferenced_repos(
+    dir: &Path,
+    stow_dirs: &[PathBuf],
+    found: &mut Vec<PathBuf>,
+) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if !path.is_dir() || path.is_symlink() {
+            continue;
+        }
+        if path.join(".git").exists() {
+            let real = fs::canonicalize(&path)?;
+            if !stow_dirs.iter().any(|dir| dir.starts_with(&real)) {
+                found.push(path);
+            }
+        } else {
+            collect_unreferenced_repos(&path, stow_dirs, found)?;
+        }
+    }
+    Ok(())
+}
+
+/// Whether the clone at `repo` has uncommitted changes or commits no remote
+/// has, which removing it would lose. A clone git cannot inspect counts as
+/// having them.
+pub fn has_local_work(repo: &Path) -> bool {
+    let output = |args: &[&str]| {
+        git_command()
+            .current_dir(repo)
+            .args(args)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| output.stdout)
+    };
+
+    let status = output(&["status", "--porcelain"]);
+    let unpushed = output(&["log", "--branches", "--not", "--remotes", "--format=%H"]);
+    match (status, unpushed) {
+        (Some(status), Some(unpushed)) => !status.is_empty() || !unpushed.is_empty(),
+        _ => true,
+    }
+}
+
+/// `pid_path` if it names no running process (or can't be read as a pid).
+pub fn stale_pid_file(pid_path: &Path) -> Option<PathBuf> {
+    let contents = fs::read_to_string(pid_path).ok()?;
+    match contents.trim().parse::<u32>() {
+        Ok(pid) if is_process_running(pid) => None,
+        _ => Some(pid_path.to_path_buf()),
+    }
+}
+
+/// Removes a file, or a directory and everything in it.
+pub fn remove_artifact(path: &Path) -> io::Result<()> {
+    if path.is_dir() && !path.is_symlink() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_orphaned_templates() {
+        let dir = tempfile::tempdir().unwrap();
+        let zsh = dir.path().join("zsh");
+        fs::create_dir_all(zsh.join(".git")).unwrap();
+        fs::write(zsh.join(".zshrc"), "export A=1").unwrap();
+        fs::write(zsh.join(".zshrc.template"), "export A=${A}").unwrap();
+        fs::write(zsh.join("env.sh.template"), "export B=${B}").unwrap();
+        fs::write(zsh.join(".git/orphan.template"), "packed").unwrap();
+
+        assert_eq!(
+            orphaned_templates(dir.path()).unwrap(),
+            vec![zsh.join("env.sh.template")]
+        );
+    }
+
+    #[test]
+    fn test_stale_backups() {
+        let dir = tempfile::tempdir().unwrap();
+        let old = dir.path().join(".zshrc");
+        let recent = dir.path().join(".vimrc");
+        let none = dir.path().join(".tmux.conf");
+        fs::write(dir.path().join(".zshrc.backup"), "old").unwrap();
+        fs::write(dir.path().join(".vimrc.backup"), "recent").unwrap();
+
+        let day = Duration::from_secs(24 * 60 * 60);
+        let now = SystemTime::now() + day * 10;
+        let targets = vec![old.clone(), recent.clone(), none];
+
+        fs::File::options()
+            .write(true)
+            .open(dir.path().join(".vimrc.backup"))
+            .unwrap()
+            .set_modified(now - day)
+            .unwrap();
+
+        assert_eq!(
+            stale_backups(&targets, day * 7, now),
+            vec![dir.path().join(".zshrc.backup")]
+        );
+        assert_eq!(
+            stale_backups(&targets, day * 30, now),
+            Vec::<PathBuf>::new()
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_stale_pid_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let pid_path = dir.path().join("daemon.pid");
+        assert_eq!(stale_pid_file(&pid_path), None);
+
+        fs::write(&pid_path, std::process::id().to_string()).unwrap();
+        assert_eq!(stale_pid_file(&pid_path), None);
+
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        child.wait().unwrap();
+        fs::write(&pid_path, child.id().to_string()).unwrap();
+        assert_eq!(stale_pid_file(&pid_path), Some(pid_path.clone()));
+
+        fs::write(&pid_path, "garbage").unwrap();
+        assert_eq!(stale_pid_file(&pid_path), Some(pid_path));
+    }
+
+    #[test]
+    fn test_unreferenced_repos() {
+        let dir = tempfile::tempdir().unwrap();
+        let current = dir.path().join("github.com/user/dotfiles");
+        let old = dir.path().join("github.com/user/old-dotfiles");
+        let other = dir.path().join("git/example.com/team/configs");
+        for repo in [&current, &old, &other] {
+            fs::create_dir_all(repo.join(".git")).unwrap();
+        }
+
+        assert_eq!(
+            unreferenced_repos(dir.path(), &[current.join("home")]).unwrap(),
+            vec![other.clone(), old.clone()]
+        );
+        assert_eq!(
+            unreferenced_repos(dir.path(), &[current.clone(), other.clone()]).unwrap(),
+            vec![old.clone()]
+        );
+        assert_eq!(
+            unreferenced_repos(&dir.path().join("missing"), &[current]).unwrap(),
+            Vec::<PathBuf>::new()
+        );
+
+        remove_artifact(&old).unwrap();
+        assert!(!old.exists());
+    }
+
+    #[test]
+    fn test_has_local_work() {
+        let git = |dir: &Path, args: &[&str]| {
+            let status = std::process::Command::new("git")
+                .current_dir(dir)
+                .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+                .args(args)
+                .output()
+                .unwrap()
+                .status;
+            assert!(status.success(), "git {:?} failed", args);
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let upstream = dir.path().join("upstream");
+        fs::create_dir_all(&upstream).unwrap();
+        git(&upstream, &["init", "-q"]);
+        fs::write(upstream.join(".zshrc"), "export A=1\n").unwrap();
+        git(&upstream, &["add", "."]);
+        git(&upstream, &["commit", "-qm", "init"]);
+        // Commits that exist nowhere else.
+        assert!(has_local_work(&upstream));
+
+        git(dir.path(), &["clone", "-q", "upstream", "clone"]);
+        let clone = dir.path().join("clone");
+        assert!(!has_local_work(&clone));
+
+        fs::write(clone.join(".zshrc"), "export A=2\n").unwrap();
+        assert!(has_local_work(&clone));
+        git(&clone, &["commit", "-qam", "local"]);
+        assert!(has_local_work(&clone));
+
+        assert!(has_local_work(&dir.path().join("missing")));
+    }
+}
@@ -1,23 +1,54 @@
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use colored::*;
-use indicatif::{ProgressBar, ProgressStyle};
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use notify::{RecursiveMode, Watcher};
+use serde::Serialize;
 use std::fs;
-use std::io::{self, Write};
+use std::io::{self, IsTerminal, Write};
+use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 
-use crate::config::{auto_detect_stow_dir, config_path, load_config, save_config, Config};
+use crate::clean::{
+    has_local_work, orphaned_templates, remove_artifact, stale_backups, stale_pid_file,
+    unreferenced_repos,
+};
+use crate::config::{
+    auto_detect_stow_dir, config_path, daemon_pid_path, load_config, save_config, set_config_dir,
+    state_path, stow_dir_candidates, AutoSyncConfig, Config, ConflictResolution,
+};
 use crate::daemon::{
-    daemon_status, get_daemon_pid, is_daemon_running, run_daemon, start_daemon_background,
-    stop_daemon,
+    daemon_stats, daemon_status, format_elapsed, get_daemon_pid, is_daemon_running, reconcile_once,
+    run_daemon, send_control_command, signal_sync, start_daemon_background, stop_daemon,
+    target_is_newer, unix_now, ControlCommand, DaemonStats,
 };
 use crate::error::{Result, SlinkyError};
-use crate::remote::{clone_or_update, get_repo_cache_path, parse_repo_spec};
-use crate::secrets::{create_template, encrypt_secrets, scan_file_for_secrets, scan_shell_configs};
+use crate::hooks::HookEvent;
+use crate::migrate::{detect_stow_layout, read_stowrc, STOW_GLOBAL_IGNORE};
+use crate::parallel::{parallel_map, resolve_jobs};
+use crate::remote::{
+    changed_files, check_git_installed, clone_or_update, get_head, get_remote_url,
+    get_repo_cache_path, git_command, init_repo, local_install_path, output_with_timeout,
+    parse_repo_spec, repo_cache_dir, spec_from_cache_path, tracked_files, RepoSpec,
+};
+use crate::secrets::{
+    add_to_store, create_template, edit_store, encrypt_secrets, is_template_file, restore_in_place,
+    restore_templates, scan_file_for_secrets_with, scan_shell_configs, secrets_store_path,
+    template_in_place, Secret, SecretStore,
+};
 use crate::service::{
-    get_platform_info, get_service_status, install_service, is_service_installed, service_logs,
-    uninstall_service,
+    get_platform_info, get_service_status, install_service, is_service_installed, parse_since,
+    preview_service, service_logs, service_logs_since, uninstall_service,
+};
+use crate::slinky::{PackageReport, Slinky};
+use crate::state::{record_package_link, LinkState};
+use crate::stow::{
+    analyze_package_with, execute_operations_with, find_duplicate_packages, find_link_owner,
+    find_nested_stow_dir, find_orphaned_links, find_packages, find_packages_with,
+    load_package_config, package_files, package_status, plan_relink, read_plan, relink_package,
+    write_plan, AnalyzeOptions, OpType, PackageConfig, PackageState, PackageStatus, StowPackage,
+    SymlinkMode, SymlinkOp, STOW_IGNORE_FILE,
 };
-use crate::stow::{analyze_package, execute_operations, find_packages, OpType};
 
 #[derive(Parser)]
 #[command(
@@ -37,6 +68,20 @@ pub struct Cli {
     #[arg(long, global = true, help = "Preview changes without applying")]
     pub dry_run: bool,
 
+    #[arg(
+        long,
+        global = true,
+        help = "Apply changes when require_apply is set in the config"
+    )]
+    pub apply: bool,
+
+    #[arg(
+        long,
+        global = true,
+        help = "Skip all git operations (same as git_enabled = false)"
+    )]
+    pub no_git: bool,
+
     #[arg(
         short = 'y',
         long = "yes",
@@ -45,6 +90,13 @@ pub struct Cli {
     )]
     pub yes: bool,
 
+    #[arg(
+        long,
+        global = true,
+        help = "Fail instead of prompting when input would be needed"
+    )]
+    pub no_input: bool,
+
     #[arg(
         long,
         global = true,
@@ -52,6 +104,77 @@ pub struct Cli {
         help = "Override target directory"
     )]
     pub target: Option<PathBuf>,
+
+    #[arg(
+        long,
+        global = true,
+        value_name = "DIR",
+        help = "Override the stow directory for this run"
+    )]
+    pub stow_dir: Option<PathBuf>,
+
+    #[arg(
+        long,
+        global = true,
+        value_name = "N",
+        help = "Run at most N clones or package scans at once (default: SLINKY_JOBS or the CPU count)"
+    )]
+    pub jobs: Option<NonZeroUsize>,
+
+    #[arg(
+        long,
+        global = true,
+        value_name = "N",
+        help = "Limit how many directory levels of a package are scanned"
+    )]
+    pub max_depth: Option<usize>,
+
+    #[arg(
+        long,
+        global = true,
+        help = "Link symlinks inside packages to their resolved destination (package_symlinks = \"deref\")"
+    )]
+    pub deref_symlinks: bool,
+
+    #[arg(
+        long,
+        global = true,
+        value_name = "DIR",
+        help = "Keep config, daemon state and secrets in DIR instead of ~/.config/slinky"
+    )]
+    pub config_dir: Option<PathBuf>,
+
+    #[arg(long, global = true, help = "Never color output (same as NO_COLOR=1)")]
+    pub no_color: bool,
+
+    #[arg(
+        long,
+        global = true,
+        conflicts_with = "no_color",
+        help = "Color output even when it is not a terminal (same as CLICOLOR_FORCE=1)"
+    )]
+    pub force_color: bool,
+
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        default_value_t = OutputFormat::Table,
+        value_name = "FORMAT",
+        help = "Output format for status, packages, config show and daemon status"
+    )]
+    pub output: OutputFormat,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Colored, aligned output for people
+    #[default]
+    Table,
+    /// Tab-separated fields without color, for awk and cut
+    Plain,
+    /// JSON, for scripts
+    Json,
 }
 
 #[derive(Subcommand)]
@@ -63,24 +186,86 @@ pub enum Commands {
 
         #[arg(long, help = "Force re-initialization even if config exists")]
         force: bool,
+
+        #[arg(long, help = "Create the dotfiles directory as a new git repository")]
+        git: bool,
+
+        #[arg(
+            long,
+            value_name = "URL",
+            requires = "git",
+            help = "Set the origin remote"
+        )]
+        remote: Option<String>,
+    },
+
+    #[command(about = "Import an existing GNU Stow setup as slinky's stow directory")]
+    MigrateFromStow {
+        #[arg(
+            help = "Stow directory (default: from ~/.stowrc, else the usual dotfiles locations)"
+        )]
+        dir: Option<PathBuf>,
     },
 
     #[command(about = "Clone a repository and discover its packages", alias = "i")]
     Install {
-        #[arg(help = "Repository (e.g., user/repo, github.com/user/repo, https://...)")]
-        repo: String,
+        #[arg(
+            required = true,
+            help = "Repositories (e.g., user/repo, github.com/user/repo, https://...) or a local directory"
+        )]
+        repos: Vec<String>,
 
         #[arg(long, help = "Link all packages after cloning")]
         link: bool,
     },
 
+    #[command(about = "Clone or refresh a cached repository without re-linking")]
+    Update {
+        #[arg(help = "Repository (e.g., user/repo, github.com/user/repo, https://...)")]
+        repo: String,
+    },
+
     #[command(about = "Link a package to the target directory", alias = "l")]
     Link {
-        #[arg(help = "Package name to link (or use --all)")]
-        package: Option<String>,
+        #[arg(
+            value_name = "PACKAGE[=TARGET]",
+            help = "Packages to link (or use --all); `name=dir` links that package into dir"
+        )]
+        packages: Vec<String>,
 
         #[arg(long, short = 'a', help = "Link all available packages")]
         all: bool,
+
+        #[arg(
+            long,
+            help = "Re-point links owned by another package and replace conflicting files"
+        )]
+        force: bool,
+
+        #[arg(
+            long,
+            help = "Unfold directory symlinks owned by another package into per-file links"
+        )]
+        restow_on_conflict: bool,
+
+        #[arg(
+            long,
+            help = "Re-point symlinks that lead to the wrong file in the package (e.g. after a move)"
+        )]
+        repair: bool,
+
+        #[arg(
+            long,
+            value_name = "FILE",
+            help = "Write the planned operations to a JSON file instead of linking"
+        )]
+        plan: Option<PathBuf>,
+    },
+
+    #[command(about = "Apply a plan file written by `link --plan`")]
+    Apply {
+        #[arg(help = "Path to the plan file")]
+        plan: PathBuf,
     },
 
     #[command(about = "Unlink a package from the target directory", alias = "u")]
@@ -90,12 +275,38 @@ pub enum Commands {
 
         #[arg(long, short = 'a', help = "Unlink all linked packages")]
         all: bool,
+
+        #[arg(
+            long,
+            help = "Replace each symlink with a copy of its file instead of removing it"
+        )]
+        detach: bool,
+    },
+
+    #[command(about = "Remove a package's links and create its current set in one pass")]
+    Relink {
+        #[arg(help = "Package name to relink (or use --all)")]
+        package: Option<String>,
+
+        #[arg(
+            long,
+            short = 'a',
+            conflicts_with = "package",
+            help = "Relink every package"
+        )]
+        all: bool,
     },
 
     #[command(about = "Update repository and re-link all packages")]
     Sync {
         #[arg(long, help = "Only update, don't re-link")]
         no_link: bool,
+
+        #[arg(
+            long,
+            help = "Remove symlinks whose source was deleted from the stow dir"
+        )]
+        prune: bool,
     },
 
     #[command(
@@ -104,8 +315,95 @@ pub enum Commands {
         alias = "st"
     )]
     Status {
+        #[arg(help = "Only show this package, file by file")]
+        package: Option<String>,
+
         #[arg(long, help = "Show detailed file-by-file status")]
         detailed: bool,
+
+        #[arg(long, short = 'w', help = "Redraw the status on changes until Ctrl+C")]
+        watch: bool,
+
+        #[arg(
+            long,
+            conflicts_with = "watch",
+            help = "Print the status as JSON (same as --output json)"
+        )]
+        json: bool,
+
+        #[arg(
+            long,
+            conflicts_with_all = ["watch", "json"],
+            help = "Print one stable, uncolored line per package: <L|P|U|C|E> <name> <linked>/<total>"
+        )]
+        porcelain: bool,
+
+        #[arg(
+            long,
+            conflicts_with_all = ["watch", "json", "porcelain"],
+            help = "Also scan the files git tracks in the stow directory for secrets"
+        )]
+        security: bool,
+
+        #[arg(
+            long,
+            default_value = "2",
+            value_name = "SECS",
+            help = "Refresh interval for --watch"
+        )]
+        interval: u64,
+    },
+
+    #[command(about = "Verify every package links cleanly; exits non-zero on problems (for CI)")]
+    Check,
+
+    #[command(
+        about = "Remove stale templates, backups, pid files and cached repos (all unless some are picked)"
+    )]
+    Clean {
+        #[arg(long, help = "Remove .template files whose original file is gone")]
+        templates: bool,
+
+        #[arg(long, help = "Remove old .backup files next to linked targets")]
+        backups: bool,
+
+        #[arg(long, help = "Remove cached repositories the stow directory is not in")]
+        repos: bool,
+
+        #[arg(long, help = "Remove a daemon pid file whose process is gone")]
+        pid_file: bool,
+
+        #[arg(
+            long,
+            value_name = "DAYS",
+            default_value_t = 30,
+            help = "Only remove backups older than DAYS"
+        )]
+        older_than: u64,
+    },
+
+    #[command(about = "Show each package's .slinky.toml settings")]
+    Packages {
+        #[arg(long, help = "Print the package metadata as JSON")]
+        json: bool,
+    },
+
+    #[command(about = "Show which package owns a linked file")]
+    Which {
+        #[arg(help = "Path to a file in the target directory")]
+        path: PathBuf,
+    },
+
+    #[command(about = "Print a shell script that reproduces the current setup")]
+    Export {
+        // `--output` is the global format selector, hence `--file`.
+        #[arg(
+            long,
+            short = 'o',
+            value_name = "FILE",
+            help = "Write the script to a file instead of stdout"
+        )]
+        file: Option<PathBuf>,
     },
 
     #[command(about = "View or modify configuration")]
@@ -125,6 +423,19 @@ pub enum Commands {
         #[command(subcommand)]
         command: DaemonCommands,
     },
+
+    #[command(about = "View daemon logs (same as `daemon logs`)")]
+    Log {
+        #[arg(long, short = 'n', default_value = "20", help = "Number of lines")]
+        lines: usize,
+
+        #[arg(
+            long,
+            value_name = "DURATION",
+            help = "Show lines newer than this (e.g. 30m, 1h, 2d) instead of a line count"
+        )]
+        since: Option<String>,
+    },
 }
 
 #[derive(Subcommand)]
@@ -150,14 +461,39 @@ pub enum ConfigCommands {
 
 #[derive(Subcommand)]
 pub enum SecretsCommands {
-    #[command(about = "Scan a file for potential secrets")]
+    #[command(about = "Scan a file or directory for potential secrets")]
     Scan {
-        #[arg(help = "File to scan for secrets")]
-        file: PathBuf,
+        #[arg(help = "File or directory to scan for secrets")]
+        file: Option<PathBuf>,
+
+        #[arg(
+            long,
+            conflicts_with = "file",
+            help = "Scan every file in the stow directory (exits 1 if any secret is found)"
+        )]
+        all: bool,
     },
 
     #[command(about = "Encrypt detected secrets in dotfiles")]
-    Encrypt,
+    Encrypt {
+        #[arg(
+            long,
+            help = "Replace secrets with placeholders in the original files (backed up first)"
+        )]
+        in_place: bool,
+    },
+
+    #[command(about = "Restore encrypted secrets into their files")]
+    Decrypt {
+        #[arg(
+            long,
+            help = "Fill placeholders in the original files instead of writing from .template files"
+        )]
+        in_place: bool,
+    },
+
+    #[command(about = "Edit the stored secrets in $EDITOR and re-encrypt them")]
+    Edit,
 }
 
 #[derive(Subcommand)]
@@ -166,11 +502,24 @@ pub enum DaemonCommands {
     Start {
         #[arg(long, help = "Run in foreground (don't daemonize)")]
         foreground: bool,
+
+        #[arg(
+            long,
+            conflicts_with = "foreground",
+            help = "Pull, re-link and prune once, then exit (for cron or timers)"
+        )]
+        once: bool,
     },
 
     #[command(about = "Stop the background daemon")]
     Stop,
 
+    #[command(about = "Make the running daemon re-read its config")]
+    Reload,
+
+    #[command(about = "Make the running daemon pull and re-link now")]
+    Sync,
+
     #[command(about = "Check daemon status")]
     Status {
         #[arg(long, short = 'l', help = "Show recent log entries")]
@@ -178,6 +527,9 @@ pub enum DaemonCommands {
 
         #[arg(long, default_value = "10", help = "Number of log lines to show")]
         lines: usize,
+
+        #[arg(long, help = "Print the status as JSON (same as --output json)")]
+        json: bool,
     },
 
     #[command(about = "Install as system service (auto-start on boot)")]
@@ -193,19 +545,86 @@ pub enum DaemonCommands {
 
         #[arg(long, short = 'f', help = "Follow log output")]
         follow: bool,
+
+        #[arg(
+            long,
+            value_name = "DURATION",
+            help = "Show lines newer than this (e.g. 30m, 1h, 2d) instead of a line count"
+        )]
+        since: Option<String>,
     },
 
     #[command(hide = true, about = "Run daemon in foreground (internal)")]
-    Run,
+    Run {
+        #[arg(long, help = "Pull, re-link and prune once, then exit")]
+        once: bool,
+    },
 }
 
-pub fn run(cli: Cli) -> Result<()> {
+pub fn run(mut cli: Cli) -> Result<()> {
+    if dirs_home().is_none() {
+        return Err(SlinkyError::HomeNotSet);
+    }
+
+    // `colored` already honors NO_COLOR and CLICOLOR_FORCE; the flags win
+    // over both, and machine-readable output is never colored.
+    if cli.output != OutputFormat::Table || cli.no_color {
+        colored::control::set_override(false);
+    } else if cli.force_color {
+        colored::control::set_override(true);
+    }
+    NO_INPUT.store(cli.no_input, Ordering::Relaxed);
+
+    if let Some(dir) = &cli.config_dir {
+        let dir = match std::env::current_dir() {
+            Ok(cwd) if dir.is_relative() => cwd.join(dir),
+            _ => dir.clone(),
+        };
+        set_config_dir(dir);
+    }
+
     let is_first_run = !config_path().exists();
-    let config = if is_first_run {
+    let mut config = if is_first_run {
         Config::default()
     } else {
-        load_config().unwrap_or_else(|_| Config::default())
+        match load_config() {
+            Ok(config) => config,
+            // `config edit` and `config path` must still work to repair a
+            // broken file; the `config` commands load it themselves.
+            Err(_) if matches!(cli.command, Some(Commands::Config { .. })) => Config::default(),
+            Err(e) => return Err(SlinkyError::Config(format!("{:#}", e))),
+        }
     };
+    // `clean --repos` must keep the clone config.toml points at, whatever
+    // --stow-dir or SLINKY_STOW_DIR say for this run.
+    let configured_stow_dir = config.stow_dir.clone();
+    config.apply_env_overrides();
+
+    if cli.no_git {
+        config.git_enabled = false;
+    }
+
+    if let Ok(cwd) = std::env::current_dir() {
+        config.resolve_relative_paths(&cwd);
+        if let Some(target) = cli.target.as_mut().filter(|t| t.is_relative()) {
+            *target = cwd.join(&*target);
+        }
+        if let Some(stow_dir) = cli.stow_dir.as_mut().filter(|d| d.is_relative()) {
+            *stow_dir = cwd.join(&*stow_dir);
+        }
+    }
+    if let Some(stow_dir) = &cli.stow_dir {
+        config.stow_dir = stow_dir.clone();
+    }
+
+    if config.require_apply && !cli.apply && !cli.yes && !cli.dry_run && is_destructive(&cli) {
+        cli.dry_run = true;
+        println!(
+            "{} Safe mode: previewing only. Pass {} to apply these changes.",
+            "🔍".bright_blue(),
+            "--apply".bright_white()
+        );
+    }
 
     match &cli.command {
         None => {
@@ -217,42 +636,157 @@ pub fn run(cli: Cli) -> Result<()> {
                     "slnky init".bright_white().bold()
                 );
             } else {
-                show_status_command(&cli, &config, false)?;
+                show_status_command(None, &cli, &config, false)?;
             }
             Ok(())
         }
-        Some(Commands::Init { stow_dir, force }) => init_slinky(stow_dir.clone(), *force, &cli),
-        Some(Commands::Install { repo, link }) => install_repo(repo, *link, &cli, &config),
-        Some(Commands::Link { package, all }) => {
+        Some(Commands::Init {
+            stow_dir,
+            force,
+            git,
+            remote,
+        }) => init_slinky(stow_dir.clone(), *force, *git, remote.as_deref(), &cli),
+        Some(Commands::MigrateFromStow { dir }) => migrate_from_stow(dir.as_deref(), &cli, &config),
+        Some(Commands::Install { repos, link }) => match repos.as_slice() {
+            [repo] => install_repo(repo, *link, &cli, &config),
+            _ if *link => Err(SlinkyError::Other(
+                "--link can only be used when installing a single repository".to_string(),
+            )),
+            _ => install_repos(repos, &cli, &config),
+        },
+        Some(Commands::Update { repo }) => update_repo(repo, &cli, &config),
+        Some(Commands::Link {
+            packages,
+            all,
+            force,
+            plan,
+            ..
+        }) => {
+            let requests = parse_link_requests(packages)?;
+            require_stow_dir(&config)?;
+            if let Some(plan_path) = plan {
+                write_link_plan(&requests, *all, plan_path, &cli, &config)
+            } else if *all {
+                print_header("Linking All Packages");
+                link_all_packages(*force, &requests, &cli, &config)
+                    .map(|report| print_link_report(&report, &config))
+            } else if requests.is_empty() {
+                Err(SlinkyError::Other(
+                    "Specify a package name or use --all".to_string(),
+                ))
+            } else {
+                for (name, target) in &requests {
+                    link_package(name, target.as_deref(), *force, &cli, &config)?;
+                }
+                Ok(())
+            }
+        }
+        Some(Commands::Apply { plan }) => apply_plan(plan, config.dir_mode(), &cli),
+        Some(Commands::Unlink {
+            package,
+            all,
+            detach,
+        }) => {
             if *all {
-                link_all_packages(&cli, &config)
+                unlink_all_packages(*detach, &cli, &config)
             } else if let Some(pkg) = package {
-                link_package(pkg, &cli, &config)
+                unlink_package(pkg, *detach, &cli, &config)
             } else {
                 Err(SlinkyError::Other(
                     "Specify a package name or use --all".to_string(),
                 ))
             }
         }
-        Some(Commands::Unlink { package, all }) => {
+        Some(Commands::Relink { package, all }) => {
+            require_stow_dir(&config)?;
             if *all {
-                unlink_all_packages(&cli, &config)
-            } else if let Some(pkg) = package {
-                unlink_package(pkg, &cli, &config)
+                let packages =
+                    find_packages_with(&config.stow_dir, &config.include_hidden_packages)
+                        .map_err(|e| SlinkyError::Stow(e.to_string()))?;
+                relink_packages(&packages, &cli, &config)
+            } else if let Some(name) = package {
+                let package_path = config.stow_dir.join(name);
+                if !package_path.is_dir() {
+                    return Err(package_not_found(name, &config));
+                }
+                let package = StowPackage {
+                    name: name.clone(),
+                    path: package_path,
+                };
+                relink_packages(&[package], &cli, &config)
             } else {
                 Err(SlinkyError::Other(
                     "Specify a package name or use --all".to_string(),
                 ))
             }
         }
-        Some(Commands::Sync { no_link }) => sync_dotfiles(*no_link, &cli, &config),
-        Some(Commands::Status { detailed }) => show_status_command(&cli, &config, *detailed),
+        Some(Commands::Sync { no_link, prune }) => sync_dotfiles(*no_link, *prune, &cli, &config),
+        Some(Commands::Status {
+            package,
+            detailed,
+            watch,
+            json,
+            porcelain,
+            security,
+            interval,
+        }) => {
+            let package = package.as_deref();
+            let format = output_format(&cli, *json);
+            if *porcelain {
+                show_status_porcelain(package, &cli, &config)
+            } else if format != OutputFormat::Table {
+                show_status_report(format, package, &cli, &config)
+            } else if *watch {
+                watch_status(package, &cli, &config, *detailed, *interval)
+            } else {
+                show_status_command(package, &cli, &config, *detailed)?;
+                if *security {
+                    show_tracked_secrets(&cli, &config)?;
+                }
+                Ok(())
+            }
+        }
+        Some(Commands::Check) => check_packages(&cli, &config),
+        Some(Commands::Clean {
+            templates,
+            backups,
+            repos,
+            pid_file,
+            older_than,
+        }) => {
+            let all = !(*templates || *backups || *repos || *pid_file);
+            // Deleting clones is never implied; it takes --repos.
+            let categories = CleanCategories {
+                templates: all || *templates,
+                backups: all || *backups,
+                repos: *repos,
+                pid_file: all || *pid_file,
+            };
+            let keep = [configured_stow_dir.clone(), config.stow_dir.clone()];
+            clean_artifacts(categories, *older_than, &keep, &cli, &config)
+        }
+        Some(Commands::Packages { json }) => show_packages(output_format(&cli, *json), &config),
+        Some(Commands::Which { path }) => show_link_owner(path, &config),
+        Some(Commands::Export { file }) => export_setup(file.as_deref(), &cli, &config),
         Some(Commands::Config { command }) => handle_config_command(command.as_ref(), &cli),
         Some(Commands::Secrets { command }) => match command {
-            SecretsCommands::Scan { file } => scan_secrets(file, &cli),
-            SecretsCommands::Encrypt => encrypt_all_secrets(&cli, &config),
+            SecretsCommands::Scan { file, all } => {
+                if *all {
+                    scan_stow_dir_secrets(&cli, &config)
+                } else if let Some(file) = file {
+                    scan_secrets(file, &cli, &config)
+                } else {
+                    Err(SlinkyError::Other(
+                        "Specify a file or use --all".to_string(),
+                    ))
+                }
+            }
+            SecretsCommands::Encrypt { in_place } => encrypt_all_secrets(*in_place, &cli, &config),
+            SecretsCommands::Decrypt { in_place } => decrypt_secrets(*in_place, &cli, &config),
+            SecretsCommands::Edit => edit_secrets(&cli, &config),
         },
         Some(Commands::Daemon { command }) => handle_daemon_command(command, &cli, &config),
+        Some(Commands::Log { lines, since }) => show_daemon_logs(*lines, false, since.as_deref()),
     }
 }
 
@@ -308,7 +842,13 @@ fn show_welcome() {
     }
 }
 
-fn init_slinky(stow_dir: Option<PathBuf>, force: bool, cli: &Cli) -> Result<()> {
+fn init_slinky(
+    stow_dir: Option<PathBuf>,
+    force: bool,
+    git: bool,
+    remote: Option<&str>,
+    cli: &Cli,
+) -> Result<()> {
     print_header("Initializing Slinky");
 
     let config_file = config_path();
@@ -356,15 +896,19 @@ fn init_slinky(stow_dir: Option<PathBuf>, force: bool, cli: &Cli) -> Result<()>
     let config = Config {
         stow_dir: final_stow_dir.clone(),
         target_dir: home.clone(),
-        packages: Vec::new(),
-        secrets_enabled: true,
-        auto_sync: crate::config::AutoSyncConfig::default(),
+        ..Config::default()
     };
 
     if cli.dry_run {
         println!("{} Would create config:", "🔍".bright_blue());
         println!("  stow_dir: {}", config.stow_dir.display());
         println!("  target_dir: {}", config.target_dir.display());
+        if git {
+            println!(
+                "{} Would initialize a git repository there",
+                "🔍".bright_blue()
+            );
+        }
         return Ok(());
     }
 
@@ -375,6 +919,20 @@ fn init_slinky(stow_dir: Option<PathBuf>, force: bool, cli: &Cli) -> Result<()>
         "✓".green(),
         config_file.display().to_string().bright_white()
     );
+
+    if git {
+        init_repo(&final_stow_dir, remote, cli.verbose)
+            .map_err(|e| SlinkyError::Git(e.to_string()))?;
+        println!(
+            "{} Initialized git repository in {}",
+            "✓".green(),
+            final_stow_dir.display().to_string().bright_white()
+        );
+        if let Some(url) = remote {
+            println!("{} Origin set to {}", "✓".green(), url.bright_white());
+        }
+    }
+
     println!(
         "\n{} Dotfiles directory: {}",
         "→".cyan(),
@@ -386,7 +944,18 @@ fn init_slinky(stow_dir: Option<PathBuf>, force: bool, cli: &Cli) -> Result<()>
         config.target_dir.display().to_string().bright_white()
     );
 
-    if !final_stow_dir.exists() {
+    if git {
+        println!(
+            "\n{} Add packages as subdirectories (e.g. {}), then run {}",
+            "→".cyan(),
+            final_stow_dir
+                .join("zsh")
+                .display()
+                .to_string()
+                .bright_white(),
+            "slnky link --all".bright_white()
+        );
+    } else if !final_stow_dir.exists() {
         println!("\n{} Dotfiles directory doesn't exist yet.", "⚠".yellow());
         println!(
             "{} Run {} to clone your dotfiles",
@@ -410,15 +979,8 @@ fn init_slinky(stow_dir: Option<PathBuf>, force: bool, cli: &Cli) -> Result<()>
 
 fn detect_dotfiles_dir() -> Option<PathBuf> {
     let home = dirs_home()?;
-    let candidates = [
-        home.join(".dotfiles"),
-        home.join("dotfiles"),
-        home.join(".config/dotfiles"),
-        home.join("code/dotfiles"),
-        home.join("projects/dotfiles"),
-    ];
-
-    for candidate in candidates {
+
+    for candidate in stow_dir_candidates(&home) {
         if candidate.exists() && candidate.is_dir() {
             if let Ok(packages) = find_packages(&candidate) {
                 if !packages.is_empty() {
@@ -434,28 +996,176 @@ fn detect_dotfiles_dir() -> Option<PathBuf> {
     None
 }
 
-fn sync_dotfiles(no_link: bool, cli: &Cli, config: &Config) -> Result<()> {
-    print_header("Syncing Dotfiles");
+/// Adopts a directory already managed with GNU Stow: finds it (`dir`,
+/// `--stow-dir`, `~/.stowrc` or the usual locations, preferring the one with
+/// the most links in place), shows which packages slinky sees as linked and
+/// writes it to the config as `stow_dir` and `target_dir`.
+fn migrate_from_stow(dir: Option<&Path>, cli: &Cli, config: &Config) -> Result<()> {
+    print_header("Migrating from GNU Stow");
+
+    let home = dirs_home()
+        .ok_or_else(|| SlinkyError::Other("Could not determine home directory".to_string()))?;
+    let stowrc = read_stowrc(&home);
+    let candidates = match dir
+        .map(Path::to_path_buf)
+        .or_else(|| cli.stow_dir.clone())
+        .or_else(|| stowrc.dir.clone())
+    {
+        Some(dir) => vec![dir],
+        None => stow_dir_candidates(&home),
+    };
+    let target = cli.target.clone().or_else(|| stowrc.target.clone());
+
+    let layout = detect_stow_layout(
+        &candidates,
+        target.as_deref(),
+        &status_options(cli, config),
+        &config.include_hidden_packages,
+    )
+    .map_err(|e| SlinkyError::Stow(e.to_string()))?
+        .ok_or_else(|| {
+            SlinkyError::Other(format!(
+                "No stow directory with packages found in {}. Pass its path: slnky migrate-from-stow <DIR>",
+                candidates
+                    .iter()
+                    .map(|dir| dir.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))
+        })?;
 
-    if !config.stow_dir.exists() {
-        return Err(SlinkyError::Other(format!(
-            "Dotfiles directory not found: {}\nRun 'slnky install user/repo' first",
-            config.stow_dir.display()
-        )));
+    println!(
+        "{} Stow directory: {}",
+        "→".cyan(),
+        layout.stow_dir.display().to_string().bright_white()
+    );
+    println!(
+        "{} Target directory: {}",
+        "→".cyan(),
+        layout.target_dir.display().to_string().bright_white()
+    );
+    println!();
+    for (package, status) in &layout.packages {
+        let icon = match status.state {
+            PackageState::Linked => "✓".green(),
+            PackageState::Partial => "◐".yellow(),
+            PackageState::Conflicted => "⚠".red(),
+            PackageState::Unlinked | PackageState::Empty | PackageState::Error => "○".dimmed(),
+        };
+        println!(
+            "  {} {} ({}/{} linked)",
+            icon,
+            package.name.bright_white(),
+            status.linked,
+            status.total
+        );
+    }
+
+    for ignore in [
+        layout.stow_dir.join(STOW_IGNORE_FILE),
+        home.join(STOW_GLOBAL_IGNORE),
+    ] {
+        if ignore.is_file() {
+            println!(
+                "\n{} {} is not read; copy its patterns into each package's {}",
+                "⚠".yellow(),
+                ignore.display(),
+                STOW_IGNORE_FILE
+            );
+        }
+    }
+    if layout.linked_files() == 0 {
+        println!(
+            "\n{} None of these packages are linked yet; run {} after migrating",
+            "⚠".yellow(),
+            "slnky link --all".bright_white()
+        );
+    }
+
+    let config_file = config_path();
+    if cli.dry_run {
+        println!(
+            "\n{} Would write to {}:",
+            "🔍".bright_blue(),
+            config_file.display()
+        );
+        println!("  stow_dir: {}", layout.stow_dir.display());
+        println!("  target_dir: {}", layout.target_dir.display());
+        return Ok(());
+    }
+
+    if !cli.yes && !confirm(&format!("\nWrite this to {}?", config_file.display()), true)? {
+        println!("{} Cancelled", "→".cyan());
+        return Ok(());
+    }
+
+    let mut saved = load_config().map_err(|e| SlinkyError::Config(e.to_string()))?;
+    saved.stow_dir = layout.stow_dir;
+    saved.target_dir = layout.target_dir;
+    save_config(&saved).map_err(|e| SlinkyError::Config(e.to_string()))?;
+
+    println!(
+        "{} Config written to {}",
+        "✓".green(),
+        config_file.display().to_string().bright_white()
+    );
+    println!(
+        "\n{} Run {} to see every package",
+        "→".cyan(),
+        "slnky status".bright_white()
+    );
+    Ok(())
+}
+
+/// The `slnky install` command that would bring back `stow_dir`: the repository
+/// it belonged to when it points into the (since deleted) repo cache.
+fn install_hint(stow_dir: &Path) -> String {
+    match spec_from_cache_path(stow_dir, &repo_cache_dir()) {
+        Some(spec) => format!("slnky install {}", spec),
+        None => "slnky install user/repo".to_string(),
+    }
+}
+
+fn stow_dir_not_found(stow_dir: &Path) -> SlinkyError {
+    SlinkyError::Other(format!(
+        "Dotfiles directory not found: {}\nRun '{}' first",
+        stow_dir.display(),
+        install_hint(stow_dir)
+    ))
+}
+
+fn require_stow_dir(config: &Config) -> Result<()> {
+    if config.stow_dir.exists() {
+        Ok(())
+    } else {
+        Err(stow_dir_not_found(&config.stow_dir))
     }
+}
 
-    if config.stow_dir.join(".git").exists() {
+fn sync_dotfiles(no_link: bool, prune: bool, cli: &Cli, config: &Config) -> Result<()> {
+    print_header("Syncing Dotfiles");
+
+    require_stow_dir(config)?;
+
+    if !config.git_enabled {
+        if cli.verbose {
+            println!("{} Git disabled, skipping pull", "→".cyan());
+        }
+    } else if config.stow_dir.join(".git").exists() {
+        require_git(config)?;
         let spinner = create_spinner("Pulling latest changes...");
 
         if cli.dry_run {
             spinner
                 .finish_with_message(format!("{} Would pull latest changes", "🔍".bright_blue()));
         } else {
-            let output = std::process::Command::new("git")
-                .current_dir(&config.stow_dir)
-                .args(["pull", "--ff-only"])
-                .output()
-                .map_err(|e| SlinkyError::Git(e.to_string()))?;
+            let output = output_with_timeout(
+                git_command()
+                    .current_dir(&config.stow_dir)
+                    .args(["pull", "--ff-only"]),
+                config.git_timeout(),
+            )
+            .map_err(|e| SlinkyError::Git(e.to_string()))?;
 
             if output.status.success() {
                 spinner.finish_with_message(format!("{} Repository updated", "✓".green()));
@@ -478,20 +1188,117 @@ fn sync_dotfiles(no_link: bool, cli: &Cli, config: &Config) -> Result<()> {
 
     if !no_link {
         println!();
-        link_all_packages(cli, config)?;
+        print_header("Linking All Packages");
+        print_link_report(&link_all_packages(false, &[], cli, config)?, config);
+    }
+
+    if prune {
+        println!();
+        prune_orphaned_links(cli, config)?;
     }
 
     Ok(())
 }
 
-fn handle_config_command(command: Option<&ConfigCommands>, cli: &Cli) -> Result<()> {
-    match command {
-        None | Some(ConfigCommands::Show) => {
-            print_header("Configuration");
+fn prune_orphaned_links(cli: &Cli, config: &Config) -> Result<()> {
+    let target = cli
+        .target
+        .as_ref()
+        .cloned()
+        .unwrap_or_else(|| config.target_dir.clone());
 
-            let path = config_path();
-            if !path.exists() {
-                println!(
+    let orphans = find_orphaned_links(&config.stow_dir, &target, &config.include_hidden_packages)
+        .map_err(|e| SlinkyError::Stow(e.to_string()))?;
+
+    if orphans.is_empty() {
+        println!("{} No orphaned symlinks", "✓".green());
+        return Ok(());
+    }
+
+    for orphan in &orphans {
+        if cli.dry_run {
+            println!(
+                "  {} Would remove {}",
+                "🔍".bright_blue(),
+                orphan.display().to_string().dimmed()
+            );
+        } else {
+            fs::remove_file(orphan).map_err(SlinkyError::Io)?;
+            println!(
+                "  {} Removed {}",
+                "✓".green(),
+                orphan.display().to_string().dimmed()
+            );
+        }
+    }
+
+    println!(
+        "{} {} orphaned symlink(s) pruned",
+        "✓".green(),
+        orphans.len().to_string().bright_white()
+    );
+
+    Ok(())
+}
+
+/// Turns a JSON object into `key, value` rows for plain output, naming nested
+/// fields like `auto_sync.enabled` and joining lists with commas.
+fn flatten_value(prefix: &str, value: &serde_json::Value, rows: &mut Vec<[String; 2]>) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, value) in map {
+                let key = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                flatten_value(&key, value, rows);
+            }
+        }
+        serde_json::Value::Array(items) => {
+            let items: Vec<String> = items.iter().map(plain_value).collect();
+            rows.push([prefix.to_string(), items.join(",")]);
+        }
+        value => rows.push([prefix.to_string(), plain_value(value)]),
+    }
+}
+
+fn plain_value(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Null => String::new(),
+        value => value.to_string(),
+    }
+}
+
+fn handle_config_command(command: Option<&ConfigCommands>, cli: &Cli) -> Result<()> {
+    match command {
+        None | Some(ConfigCommands::Show) if cli.output != OutputFormat::Table => {
+            let path = config_path();
+            if !path.exists() {
+                return Err(SlinkyError::Config(format!(
+                    "No config file found at {}",
+                    path.display()
+                )));
+            }
+            let config = load_config().map_err(|e| SlinkyError::Config(e.to_string()))?;
+            if cli.output == OutputFormat::Json {
+                return print_json(&config);
+            }
+
+            let value =
+                serde_json::to_value(&config).map_err(|e| SlinkyError::Other(e.to_string()))?;
+            let mut rows = Vec::new();
+            flatten_value("", &value, &mut rows);
+            print_plain(&rows);
+            Ok(())
+        }
+        None | Some(ConfigCommands::Show) => {
+            print_header("Configuration");
+
+            let path = config_path();
+            if !path.exists() {
+                println!(
                     "{} No config file found. Run {} to create one.",
                     "⚠".yellow(),
                     "slnky init".bright_white()
@@ -523,10 +1330,22 @@ fn handle_config_command(command: Option<&ConfigCommands>, cli: &Cli) -> Result<
                 config.secrets_enabled.to_string().bright_white()
             );
 
+            if config.require_apply {
+                println!(
+                    "  {} {}",
+                    "require_apply:".bright_blue(),
+                    "true".bright_white()
+                );
+            }
+
             if !config.packages.is_empty() {
                 println!("  {} {:?}", "packages:".bright_blue(), config.packages);
             }
 
+            if !config.include.is_empty() {
+                println!("  {} {:?}", "include:".bright_blue(), config.include);
+            }
+
             Ok(())
         }
         Some(ConfigCommands::Edit) => {
@@ -601,7 +1420,53 @@ fn handle_config_command(command: Option<&ConfigCommands>, cli: &Cli) -> Result<
     }
 }
 
+/// Commands that `require_apply` turns into dry runs.
+fn is_destructive(cli: &Cli) -> bool {
+    matches!(
+        cli.command,
+        Some(Commands::Link { .. })
+            | Some(Commands::Apply { .. })
+            | Some(Commands::Clean { .. })
+            | Some(Commands::Install { link: true, .. })
+            | Some(Commands::MigrateFromStow { .. })
+            | Some(Commands::Unlink { .. })
+            | Some(Commands::Relink { .. })
+            | Some(Commands::Sync { .. })
+            | Some(Commands::Daemon {
+                command: DaemonCommands::Start { once: true, .. }
+                    | DaemonCommands::Run { once: true }
+            })
+            | Some(Commands::Secrets {
+                command: SecretsCommands::Encrypt { .. }
+                    | SecretsCommands::Decrypt { .. }
+                    | SecretsCommands::Edit
+            })
+    )
+}
+
+/// Set from `--no-input`; prompts then fail instead of asking.
+static NO_INPUT: AtomicBool = AtomicBool::new(false);
+
+/// Errors out instead of prompting when `--no-input` is set or stdin is not
+/// a terminal, so unattended runs fail fast rather than hang.
+fn require_input(question: &str) -> Result<()> {
+    if NO_INPUT.load(Ordering::Relaxed) {
+        return Err(SlinkyError::Other(format!(
+            "input required but --no-input set: {}",
+            question
+        )));
+    }
+    if !io::stdin().is_terminal() {
+        return Err(SlinkyError::Other(format!(
+            "input required but stdin is not a terminal: {} (pass --yes to skip confirmations)",
+            question
+        )));
+    }
+    Ok(())
+}
+
 fn confirm(prompt: &str, default: bool) -> Result<bool> {
+    require_input(prompt.trim())?;
     let default_hint = if default { "[Y/n]" } else { "[y/N]" };
     print!(
         "{} {} {} ",
@@ -622,7 +1487,14 @@ fn confirm(prompt: &str, default: bool) -> Result<bool> {
     })
 }
 
+fn read_passphrase(question: &str) -> Result<String> {
+    require_input(question)?;
+    rpassword::read_password()
+        .map_err(|e| SlinkyError::Other(format!("Failed to read passphrase: {}", e)))
+}
+
 fn prompt_path(prompt: &str, default: &Path) -> Result<PathBuf> {
+    require_input(prompt)?;
     print!(
         "{} {} [{}]: ",
         "?".bright_blue(),
@@ -652,131 +1524,429 @@ fn shellexpand_tilde(path: &str) -> String {
 }
 
 fn dirs_home() -> Option<PathBuf> {
-    std::env::var_os("HOME").map(PathBuf::from)
+    std::env::var_os("HOME")
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
 }
 
-fn link_all_packages(cli: &Cli, config: &Config) -> Result<()> {
-    print_header("Linking All Packages");
+/// A package named on the `link` command line, with the target given by
+/// `name=dir` if any.
+type LinkRequest = (String, Option<PathBuf>);
+
+/// Splits `link` arguments of the form `name` or `name=dir`. Target dirs get
+/// `~/` expanded and are resolved against the current directory, like
+/// `--target`.
+fn parse_link_requests(args: &[String]) -> Result<Vec<LinkRequest>> {
+    args.iter()
+        .map(|arg| {
+            let Some((name, target)) = arg.split_once('=') else {
+                return Ok((arg.clone(), None));
+            };
+            if name.is_empty() || target.is_empty() {
+                return Err(SlinkyError::Other(format!(
+                    "Invalid package target '{}': expected name=dir",
+                    arg
+                )));
+            }
+            let target = PathBuf::from(shellexpand_tilde(target));
+            let target = if target.is_relative() {
+                std::env::current_dir()
+                    .map_err(SlinkyError::Io)?
+                    .join(target)
+            } else {
+                target
+            };
+            Ok((name.to_string(), Some(target)))
+        })
+        .collect()
+}
 
-    let packages = find_packages(&config.stow_dir).map_err(|e| SlinkyError::Stow(e.to_string()))?;
+/// The `name=dir` target requested for `name`, if one was given.
+fn requested_target<'a>(requests: &'a [LinkRequest], name: &str) -> Option<&'a Path> {
+    requests
+        .iter()
+        .find(|(package, _)| package == name)
+        .and_then(|(_, target)| target.as_deref())
+}
 
-    if packages.is_empty() {
+/// What linking one package did.
+#[derive(Debug, PartialEq)]
+enum LinkOutcome {
+    /// Symlinks were created; `warnings` are from failed post-link hooks.
+    Linked {
+        created: usize,
+        ignored: usize,
+        warnings: Vec<String>,
+    },
+    /// Symlinks would be created (`--dry-run`).
+    WouldLink {
+        created: usize,
+        ignored: usize,
+    },
+    AlreadyLinked {
+        ignored: usize,
+    },
+    /// Left unlinked because a target conflicts.
+    Conflict(String),
+    /// Failed for a reason other than a conflict.
+    Failed(String),
+}
+
+/// What `link_all_packages` did, for `print_link_report` to show.
+#[derive(Debug, Default, PartialEq)]
+struct LinkReport {
+    /// Where packages were linked, unless a `name=dir` request moved one.
+    target: PathBuf,
+    /// Each package with its outcome, in the order they were linked.
+    packages: Vec<(String, LinkOutcome)>,
+}
+
+impl LinkReport {
+    /// The packages whose outcome matches `filter`.
+    fn names(&self, filter: impl Fn(&LinkOutcome) -> bool) -> Vec<&str> {
+        self.packages
+            .iter()
+            .filter(|(_, outcome)| filter(outcome))
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+}
+
+/// Links every package, or the ones named in `requests` to their own
+/// targets, without printing anything; see `print_link_report`.
+fn link_all_packages(
+    force: bool,
+    requests: &[LinkRequest],
+    cli: &Cli,
+    config: &Config,
+) -> Result<LinkReport> {
+    let packages = find_packages_with(&config.stow_dir, &config.include_hidden_packages)
+        .map_err(|e| SlinkyError::Stow(e.to_string()))?;
+
+    if let Some((name, _)) = requests
+        .iter()
+        .find(|(name, _)| !packages.iter().any(|p| &p.name == name))
+    {
+        return Err(SlinkyError::PackageNotFound(name.clone()));
+    }
+
+    let mut report = LinkReport {
+        target: cli
+            .target
+            .as_ref()
+            .cloned()
+            .unwrap_or_else(|| config.target_dir.clone()),
+        packages: Vec::new(),
+    };
+    for package in &packages {
+        let target = requested_target(requests, &package.name).unwrap_or(&report.target);
+        let outcome =
+            match link_single_package(&package.name, &package.path, target, force, cli, config) {
+                Ok(outcome) => outcome,
+                Err(e @ SlinkyError::Conflict(_)) => LinkOutcome::Conflict(e.to_string()),
+                Err(e) => LinkOutcome::Failed(e.to_string()),
+            };
+        report.packages.push((package.name.clone(), outcome));
+    }
+
+    Ok(report)
+}
+
+fn print_link_report(report: &LinkReport, config: &Config) {
+    if report.packages.is_empty() {
         println!(
             "{} No packages found in {}",
             "⚠".yellow(),
             config.stow_dir.display()
         );
-        return Ok(());
+        return;
     }
 
-    let target = cli
-        .target
-        .as_ref()
-        .cloned()
-        .unwrap_or_else(|| config.target_dir.clone());
-
     println!(
         "{} Linking {} package(s) to {}\n",
         "→".cyan(),
-        packages.len().to_string().bright_white(),
-        target.display().to_string().bright_white()
+        report.packages.len().to_string().bright_white(),
+        report.target.display().to_string().bright_white()
     );
-
-    let mut success_count = 0;
-    let mut already_linked_count = 0;
-    let mut error_count = 0;
-
-    for package in &packages {
-        let result = link_single_package(&package.name, &package.path, &target, cli);
-        match result {
-            Ok(linked) => {
-                if linked {
-                    success_count += 1;
-                } else {
-                    already_linked_count += 1;
-                }
+    for (name, outcome) in &report.packages {
+        match outcome {
+            LinkOutcome::Linked {
+                created,
+                ignored,
+                warnings,
+            } => {
+                println!(
+                    "  {} {} - {} symlink(s) created{}",
+                    "✓".green(),
+                    name.bright_white(),
+                    created,
+                    ignored_suffix(*ignored)
+                );
+                print_hook_warnings(warnings);
             }
-            Err(e) => {
-                println!("  {} {} - {}", "✗".red(), package.name.bright_white(), e);
-                error_count += 1;
+            LinkOutcome::WouldLink { created, ignored } => println!(
+                "  {} {} - would create {} symlink(s){}",
+                "🔍".bright_blue(),
+                name.bright_white(),
+                created,
+                ignored_suffix(*ignored)
+            ),
+            LinkOutcome::AlreadyLinked { ignored } => println!(
+                "  {} {} {}",
+                "→".dimmed(),
+                name.dimmed(),
+                format!("(already linked{})", ignored_suffix(*ignored)).dimmed()
+            ),
+            LinkOutcome::Conflict(e) | LinkOutcome::Failed(e) => {
+                println!("  {} {} - {}", "✗".red(), name.bright_white(), e)
             }
         }
     }
 
+    let linked = report.names(|o| {
+        matches!(
+            o,
+            LinkOutcome::Linked { .. } | LinkOutcome::WouldLink { .. }
+        )
+    });
+    let already_linked = report.names(|o| matches!(o, LinkOutcome::AlreadyLinked { .. }));
+    let conflicts = report.names(|o| matches!(o, LinkOutcome::Conflict(_)));
+    let failed = report.names(|o| matches!(o, LinkOutcome::Failed(_)));
+
     println!();
-    if success_count > 0 {
+    if !linked.is_empty() {
         println!(
             "{} {} package(s) linked",
             "✓".green(),
-            success_count.to_string().bright_white()
+            linked.len().to_string().bright_white()
         );
     }
-    if already_linked_count > 0 {
+    if !already_linked.is_empty() {
         println!(
             "{} {} package(s) already linked",
             "→".cyan(),
-            already_linked_count.to_string().dimmed()
+            already_linked.len().to_string().dimmed()
         );
     }
-    if error_count > 0 {
+    if !conflicts.is_empty() {
+        println!(
+            "{} {} package(s) have conflicts: {}",
+            "✗".red(),
+            conflicts.len().to_string().bright_red(),
+            conflicts.join(", ")
+        );
+    }
+    if !failed.is_empty() {
         println!(
             "{} {} package(s) failed",
             "✗".red(),
-            error_count.to_string().bright_red()
+            failed.len().to_string().bright_red()
         );
     }
 
-    if success_count == 0 && already_linked_count > 0 && error_count == 0 {
+    if linked.is_empty() && conflicts.is_empty() && failed.is_empty() {
         println!("\n{} All packages are already linked!", "✓".green());
     }
+}
+
+/// Links one package for `link_all_packages`, leaving the printing to
+/// `print_link_report`.
+fn link_single_package(
+    name: &str,
+    package_path: &Path,
+    target: &Path,
+    force: bool,
+    cli: &Cli,
+    config: &Config,
+) -> Result<LinkOutcome> {
+    let slinky = Slinky::new(config.clone());
+    let options = AnalyzeOptions {
+        force,
+        report_conflicts: force,
+        ..analyze_options(cli, config)
+    };
+    let mut operations = slinky.plan_link(package_path, target, &options)?;
+    resolve_forced_conflicts(&mut operations, cli, config)?;
+
+    let created = operations
+        .iter()
+        .filter(|op| op.op_type.creates_link())
+        .count();
+    let ignored = package_status(&operations).ignored;
+
+    if created == 0 {
+        return Ok(LinkOutcome::AlreadyLinked { ignored });
+    }
+
+    if cli.dry_run {
+        return Ok(LinkOutcome::WouldLink { created, ignored });
+    }
 
+    let result = slinky.apply_link(name, package_path, target, &operations, |_, _, _| {})?;
+    Ok(LinkOutcome::Linked {
+        created,
+        ignored,
+        warnings: result.warnings,
+    })
+}
+
+/// Notes in `state.toml` that `package` was just linked. This is only
+/// bookkeeping for `status --detailed`, so a failed write is ignored.
+fn record_link(package: &str) {
+    let _ = record_package_link(&state_path(), package, unix_now());
+}
+
+/// Runs the `hooks.d` scripts for `event` around a package operation (see
+/// `Slinky::run_hooks`), printing a failed post-hook as a warning.
+fn run_package_hooks(
+    event: HookEvent,
+    name: &str,
+    package_path: &Path,
+    target: &Path,
+    config: &Config,
+) -> Result<()> {
+    let warning = Slinky::new(config.clone()).run_hooks(event, name, package_path, target)?;
+    print_hook_warnings(warning.as_slice());
     Ok(())
 }
 
-fn link_single_package(name: &str, package_path: &Path, target: &Path, cli: &Cli) -> Result<bool> {
-    let operations =
-        analyze_package(package_path, target).map_err(|e| SlinkyError::Stow(e.to_string()))?;
+fn print_hook_warnings(warnings: &[String]) {
+    for warning in warnings {
+        println!("  {} {}", "⚠".yellow(), warning);
+    }
+}
 
-    let create_ops: Vec<_> = operations
-        .iter()
-        .filter(|op| matches!(op.op_type, OpType::Create))
-        .collect();
+/// ", N ignored" when ignore rules skipped files, so intentional skips are
+/// not mistaken for already-linked files or conflicts.
+fn ignored_note(operations: &[SymlinkOp]) -> String {
+    ignored_suffix(package_status(operations).ignored)
+}
 
-    if create_ops.is_empty() {
+fn ignored_suffix(ignored: usize) -> String {
+    match ignored {
+        0 => String::new(),
+        ignored => format!(", {} ignored", ignored),
+    }
+}
+
+fn relink_packages(packages: &[StowPackage], cli: &Cli, config: &Config) -> Result<()> {
+    print_header("Relinking Packages");
+
+    let target = cli
+        .target
+        .as_ref()
+        .cloned()
+        .unwrap_or_else(|| config.target_dir.clone());
+    let options = analyze_options(cli, config);
+
+    if let [package] = packages {
+        return relink_single_package(package, &target, &options, cli, config);
+    }
+
+    let mut relinked = 0;
+    let mut failed = Vec::new();
+    for package in packages {
+        match relink_single_package(package, &target, &options, cli, config) {
+            Ok(()) => relinked += 1,
+            Err(e) => {
+                println!("  {} {} - {}", "✗".red(), package.name.bright_white(), e);
+                failed.push(package.name.clone());
+            }
+        }
+    }
+
+    println!();
+    if relinked > 0 && !cli.dry_run {
         println!(
-            "  {} {} {}",
-            "→".dimmed(),
-            name.dimmed(),
-            "(already linked)".dimmed()
+            "{} {} package(s) relinked",
+            "✓".green(),
+            relinked.to_string().bright_white()
+        );
+    }
+    if !failed.is_empty() {
+        println!(
+            "{} {} package(s) failed: {}",
+            "✗".red(),
+            failed.len().to_string().bright_red(),
+            failed.join(", ")
         );
-        return Ok(false);
+        return Err(SlinkyError::Other(format!(
+            "Relink failed: {} package(s) could not be relinked",
+            failed.len()
+        )));
     }
 
+    Ok(())
+}
+
+/// Relinks one package between its link hooks, or lists what that would
+/// remove and create under `--dry-run`.
+fn relink_single_package(
+    package: &StowPackage,
+    target: &Path,
+    options: &AnalyzeOptions,
+    cli: &Cli,
+    config: &Config,
+) -> Result<()> {
     if cli.dry_run {
+        let plan = plan_relink(&package.path, target, options)?;
         println!(
-            "  {} {} - would create {} symlink(s)",
+            "  {} {} - would remove {} link(s) and create {}",
             "🔍".bright_blue(),
-            name.bright_white(),
-            create_ops.len()
+            package.name.bright_white(),
+            plan.remove.len(),
+            plan.create.len()
         );
-        return Ok(true);
+        for removed in &plan.remove {
+            println!(
+                "    {} {}",
+                "-".red(),
+                removed.display().to_string().dimmed()
+            );
+        }
+        for op in &plan.create {
+            println!(
+                "    {} {} → {}",
+                "+".green(),
+                op.target.display().to_string().dimmed(),
+                op.source.display().to_string().bright_white()
+            );
+        }
+        return Ok(());
     }
 
-    execute_operations(&operations, false).map_err(|e| SlinkyError::Stow(e.to_string()))?;
+    run_package_hooks(
+        HookEvent::PreLink,
+        &package.name,
+        &package.path,
+        target,
+        config,
+    )?;
+    let summary = relink_package(&package.path, target, options, config.dir_mode())?;
+    record_link(&package.name);
+    run_package_hooks(
+        HookEvent::PostLink,
+        &package.name,
+        &package.path,
+        target,
+        config,
+    )?;
+
     println!(
-        "  {} {} - {} symlink(s) created",
+        "  {} {} - {} removed, {} created",
         "✓".green(),
-        name.bright_white(),
-        create_ops.len()
+        package.name.bright_white(),
+        summary.removed,
+        summary.created
     );
-
-    Ok(true)
+    Ok(())
 }
 
-fn unlink_all_packages(cli: &Cli, config: &Config) -> Result<()> {
+fn unlink_all_packages(detach: bool, cli: &Cli, config: &Config) -> Result<()> {
     print_header("Unlinking All Packages");
 
-    let packages = find_packages(&config.stow_dir).map_err(|e| SlinkyError::Stow(e.to_string()))?;
+    let packages = find_packages_with(&config.stow_dir, &config.include_hidden_packages)
+        .map_err(|e| SlinkyError::Stow(e.to_string()))?;
 
     if packages.is_empty() {
         println!("{} No packages found", "⚠".yellow());
@@ -791,8 +1961,9 @@ fn unlink_all_packages(cli: &Cli, config: &Config) -> Result<()> {
 
     if !cli.yes && !cli.dry_run {
         println!(
-            "{} This will unlink {} package(s)",
+            "{} This will {} {} package(s)",
             "⚠".yellow(),
+            if detach { "detach" } else { "unlink" },
             packages.len()
         );
         if !confirm("Continue?", false)? {
@@ -802,26 +1973,22 @@ fn unlink_all_packages(cli: &Cli, config: &Config) -> Result<()> {
     }
 
     for package in &packages {
-        unlink_single_package(&package.name, &package.path, &target, cli)?;
+        unlink_single_package(&package.name, &package.path, &target, detach, cli, config)?;
     }
 
     Ok(())
 }
 
-fn unlink_single_package(name: &str, package_path: &Path, target: &Path, cli: &Cli) -> Result<()> {
-    let operations =
-        analyze_package(package_path, target).map_err(|e| SlinkyError::Stow(e.to_string()))?;
-
-    let linked_ops: Vec<_> = operations
-        .iter()
-        .filter(|op| {
-            if let OpType::Skip(reason) = &op.op_type {
-                reason.contains("Already linked")
-            } else {
-                false
-            }
-        })
-        .collect();
+fn unlink_single_package(
+    name: &str,
+    package_path: &Path,
+    target: &Path,
+    detach: bool,
+    cli: &Cli,
+    config: &Config,
+) -> Result<()> {
+    let slinky = Slinky::new(config.clone());
+    let linked_ops = slinky.plan_unlink(package_path, target, &analyze_options(cli, config))?;
 
     if linked_ops.is_empty() {
         println!(
@@ -835,26 +2002,36 @@ fn unlink_single_package(name: &str, package_path: &Path, target: &Path, cli: &C
 
     if cli.dry_run {
         println!(
-            "  {} {} - would remove {} symlink(s)",
+            "  {} {} - would {} {} symlink(s)",
             "🔍".bright_blue(),
             name.bright_white(),
+            if detach { "detach" } else { "remove" },
             linked_ops.len()
         );
         return Ok(());
     }
 
-    for op in &linked_ops {
-        if op.target.is_symlink() {
-            fs::remove_file(&op.target).map_err(SlinkyError::Io)?;
-        }
-    }
+    let result = slinky.apply_unlink(
+        name,
+        package_path,
+        target,
+        &linked_ops,
+        detach,
+        |_, _, _| {},
+    )?;
 
     println!(
-        "  {} {} - {} symlink(s) removed",
+        "  {} {} - {} symlink(s) {}",
         "✓".green(),
         name.bright_white(),
-        linked_ops.len()
+        result.removed.len(),
+        if detach {
+            "replaced with copies"
+        } else {
+            "removed"
+        }
     );
+    print_hook_warnings(&result.warnings);
 
     Ok(())
 }
@@ -862,14 +2039,34 @@ fn unlink_single_package(name: &str, package_path: &Path, target: &Path, cli: &C
 fn install_repo(repo: &str, link_after: bool, cli: &Cli, config: &Config) -> Result<()> {
     print_header("Installing Repository");
 
+    if let Some(local) = local_install_path(repo) {
+        if cli.dry_run {
+            println!(
+                "{} Would use local directory: {}",
+                "🔍".bright_blue(),
+                local.display().to_string().bright_white()
+            );
+            return Ok(());
+        }
+        println!(
+            "{} Using local directory {}",
+            "✓".green(),
+            local.display().to_string().bright_white()
+        );
+        return adopt_repo(&local, link_after, cli, config);
+    }
+
+    require_git(config)?;
+
     let repo_spec =
         parse_repo_spec(repo).map_err(|e| SlinkyError::InvalidRepoSpec(e.to_string()))?;
 
     if cli.verbose {
         println!("{} Parsing repository: {}", "→".cyan(), repo.bright_white());
         println!(
-            "{} Owner: {}, Repo: {}",
+            "{} Host: {}, Owner: {}, Repo: {}",
             "→".cyan(),
+            repo_spec.host.bright_white(),
             repo_spec.owner.bright_white(),
             repo_spec.repo.bright_white()
         );
@@ -895,7 +2092,7 @@ fn install_repo(repo: &str, link_after: bool, cli: &Cli, config: &Config) -> Res
         "Cloning repository..."
     };
     let spinner = create_spinner(spinner_msg);
-    let repo_path = clone_or_update(&repo_spec).map_err(|e| SlinkyError::Remote(e.to_string()))?;
+    let repo_path = clone_with_spinner(&repo_spec, &spinner, cli, config)?;
 
     let finish_msg = if is_update {
         format!(
@@ -912,7 +2109,28 @@ fn install_repo(repo: &str, link_after: bool, cli: &Cli, config: &Config) -> Res
     };
     spinner.finish_with_message(finish_msg);
 
-    let packages = find_packages(&repo_path).map_err(|e| SlinkyError::Stow(e.to_string()))?;
+    adopt_repo(&repo_path, link_after, cli, config)
+}
+
+/// Lists the packages in an installed repository (or a nested package
+/// directory in it), offers to make it the stow directory and optionally
+/// links everything.
+fn adopt_repo(repo_path: &Path, link_after: bool, cli: &Cli, config: &Config) -> Result<()> {
+    let mut stow_dir = repo_path.to_path_buf();
+    let mut packages = find_packages_with(&stow_dir, &config.include_hidden_packages)
+        .map_err(|e| SlinkyError::Stow(e.to_string()))?;
+
+    if let Some(nested) = find_nested_stow_dir(repo_path) {
+        let prompt = format!(
+            "\nPackages found in {}. Use it as the stow directory?",
+            nested.display()
+        );
+        if cli.yes || confirm(&prompt, true)? {
+            packages = find_packages_with(&nested, &config.include_hidden_packages)
+                .map_err(|e| SlinkyError::Stow(e.to_string()))?;
+            stow_dir = nested;
+        }
+    }
 
     if packages.is_empty() {
         println!("\n{} No packages found in repository", "⚠".yellow());
@@ -934,18 +2152,23 @@ fn install_repo(repo: &str, link_after: bool, cli: &Cli, config: &Config) -> Res
     }
 
     let mut updated_config = config.clone();
-    if updated_config.stow_dir != repo_path {
-        updated_config.stow_dir = repo_path.clone();
+    if updated_config.stow_dir != stow_dir {
+        updated_config.stow_dir = stow_dir.clone();
 
         if cli.yes || confirm("\nUpdate config to use this repository?", true)? {
-            save_config(&updated_config).map_err(|e| SlinkyError::Config(e.to_string()))?;
+            // Save the file as it is on disk, without this run's overrides.
+            let mut saved = load_config().map_err(|e| SlinkyError::Config(e.to_string()))?;
+            saved.stow_dir = stow_dir.clone();
+            save_config(&saved).map_err(|e| SlinkyError::Config(e.to_string()))?;
             println!("{} Config updated with new stow_dir", "✓".green());
         }
     }
 
     if link_after {
         println!();
-        link_all_packages(cli, &updated_config)?;
+        print_header("Linking All Packages");
+        let report = link_all_packages(false, &[], cli, &updated_config)?;
+        print_link_report(&report, &updated_config);
     } else {
         println!(
             "\n{} Run {} to link packages",
@@ -957,60 +2180,278 @@ fn install_repo(repo: &str, link_after: bool, cli: &Cli, config: &Config) -> Res
     Ok(())
 }
 
-fn link_package(package: &str, cli: &Cli, config: &Config) -> Result<()> {
-    print_header("Linking Package");
-
-    let target = cli
-        .target
-        .as_ref()
-        .cloned()
-        .unwrap_or_else(|| config.target_dir.clone());
-
-    if cli.verbose {
-        println!("{} Package: {}", "→".cyan(), package.bright_white());
-        println!(
-            "{} Target: {}",
-            "→".cyan(),
-            target.display().to_string().bright_white()
-        );
-        println!(
-            "{} Stow dir: {}",
-            "→".cyan(),
-            config.stow_dir.display().to_string().bright_white()
-        );
+fn require_git(config: &Config) -> Result<()> {
+    if !config.git_enabled {
+        return Err(SlinkyError::Git(
+            "git is disabled (--no-git or git_enabled = false)".to_string(),
+        ));
     }
+    check_git_installed().map_err(|e| SlinkyError::Git(e.to_string()))
+}
 
-    let package_path = config.stow_dir.join(package);
-    if !package_path.exists() {
-        let available = find_packages(&config.stow_dir)
-            .map(|pkgs| {
-                pkgs.iter()
-                    .map(|p| p.name.clone())
-                    .collect::<Vec<_>>()
-                    .join(", ")
-            })
-            .unwrap_or_default();
+fn install_repos(repos: &[String], cli: &Cli, config: &Config) -> Result<()> {
+    print_header("Installing Repositories");
+    require_git(config)?;
 
-        let hint = if available.is_empty() {
-            format!("No packages found in {}", config.stow_dir.display())
-        } else {
-            format!("Available packages: {}", available)
-        };
+    let specs = repos
+        .iter()
+        .map(|repo| {
+            parse_repo_spec(repo)
+                .map(|spec| (repo.as_str(), spec))
+                .map_err(|e| SlinkyError::InvalidRepoSpec(e.to_string()))
+        })
+        .collect::<Result<Vec<_>>>()?;
 
-        return Err(SlinkyError::PackageNotFound(format!(
-            "{}\n{} {}",
-            package,
+    if cli.dry_run {
+        for (repo, spec) in &specs {
+            let action = if get_repo_cache_path(spec).exists() {
+                "update"
+            } else {
+                "clone"
+            };
+            println!(
+                "{} Would {}: {}",
+                "🔍".bright_blue(),
+                action,
+                repo.bright_white()
+            );
+        }
+        return Ok(());
+    }
+
+    let multi = MultiProgress::new();
+    let bars: Vec<Spinner> = specs
+        .iter()
+        .map(|(repo, _)| create_spinner(&format!("Cloning {}...", repo)).added_to(&multi))
+        .collect();
+
+    // Each spec has its own cache path, so workers never touch the same clone.
+    let results = parallel_map(&specs, resolve_jobs(cli.jobs), |position, (repo, spec)| {
+        let result = clone_or_update(spec, config.min_free_space_mb, config.git_timeout(), false)
+            .map_err(|e| e.to_string());
+        bars[position].finish_with_message(match &result {
+            Ok(_) => format!("{} {}", "✓".green(), repo.bright_white()),
+            Err(e) => format!("{} {}: {}", "✗".red(), repo.bright_white(), e),
+        });
+        result
+    });
+
+    println!();
+    let mut stow_dirs = Vec::new();
+    let mut total_packages = 0;
+    for (position, result) in results.into_iter().enumerate() {
+        let repo = specs[position].0;
+        let Ok(repo_path) = result else {
+            continue;
+        };
+
+        let stow_dir = find_nested_stow_dir(&repo_path).unwrap_or(repo_path);
+        let packages = find_packages_with(&stow_dir, &config.include_hidden_packages)
+            .map_err(|e| SlinkyError::Stow(e.to_string()))?;
+        total_packages += packages.len();
+
+        println!(
+            "{} {} ({})",
             "→".cyan(),
-            hint.dimmed()
+            repo.bright_white().bold(),
+            stow_dir.display().to_string().dimmed()
+        );
+        for package in &packages {
+            println!("  {} {}", "•".bright_blue(), package.name.bright_white());
+        }
+        stow_dirs.push(stow_dir);
+    }
+    let installed = stow_dirs.len();
+
+    println!(
+        "\n{} Found {} package(s) across {} repositories",
+        "✓".green(),
+        total_packages.to_string().bright_white(),
+        installed.to_string().bright_white()
+    );
+
+    let duplicates = find_duplicate_packages(&stow_dirs, &config.include_hidden_packages)
+        .map_err(|e| SlinkyError::Stow(e.to_string()))?;
+    for (name, paths) in &duplicates {
+        println!(
+            "{} Package {} is defined in more than one repository:",
+            "⚠".yellow(),
+            name.bright_white()
+        );
+        for path in paths {
+            println!("  {} {}", "•".yellow(), path.display().to_string().dimmed());
+        }
+    }
+    println!(
+        "{} Run {} with one repository to make it your stow directory",
+        "→".cyan(),
+        "slnky install".bright_white()
+    );
+
+    if installed < specs.len() {
+        return Err(SlinkyError::Remote(format!(
+            "{} of {} repositories failed to install",
+            specs.len() - installed,
+            specs.len()
+        )));
+    }
+    if !duplicates.is_empty() {
+        return Err(SlinkyError::Stow(format!(
+            "{} package(s) are defined in more than one repository; install the one to use as your stow directory on its own",
+            duplicates.len()
         )));
     }
 
-    let operations =
-        analyze_package(&package_path, &target).map_err(|e| SlinkyError::Stow(e.to_string()))?;
+    Ok(())
+}
+
+/// Runs `clone_or_update`, hiding the spinner while git streams its output in
+/// verbose mode.
+fn clone_with_spinner(
+    repo_spec: &RepoSpec,
+    spinner: &Spinner,
+    cli: &Cli,
+    config: &Config,
+) -> Result<PathBuf> {
+    let clone = || {
+        clone_or_update(
+            repo_spec,
+            config.min_free_space_mb,
+            config.git_timeout(),
+            cli.verbose,
+        )
+    };
+    let result = if cli.verbose {
+        spinner.suspend(clone)
+    } else {
+        clone()
+    };
+    result.map_err(|e| SlinkyError::Remote(e.to_string()))
+}
+
+fn update_repo(repo: &str, cli: &Cli, config: &Config) -> Result<()> {
+    print_header("Updating Repository");
+    require_git(config)?;
+
+    let repo_spec =
+        parse_repo_spec(repo).map_err(|e| SlinkyError::InvalidRepoSpec(e.to_string()))?;
+    let repo_path = get_repo_cache_path(&repo_spec);
+    let old_head = get_head(&repo_path);
+
+    if cli.dry_run {
+        let action = if old_head.is_some() {
+            "update"
+        } else {
+            "clone"
+        };
+        println!(
+            "{} Would {}: {}",
+            "🔍".bright_blue(),
+            action,
+            repo_path.display().to_string().bright_white()
+        );
+        return Ok(());
+    }
+
+    let spinner = create_spinner(if old_head.is_some() {
+        "Updating repository..."
+    } else {
+        "Cloning repository..."
+    });
+    let repo_path = clone_with_spinner(&repo_spec, &spinner, cli, config)?;
+    let new_head = get_head(&repo_path).unwrap_or_default();
+
+    let Some(old_head) = old_head else {
+        spinner.finish_with_message(format!(
+            "{} Repository cloned to {} at {}",
+            "✓".green(),
+            repo_path.display().to_string().bright_white(),
+            short_rev(&new_head).bright_white()
+        ));
+        return Ok(());
+    };
+
+    if old_head == new_head {
+        spinner.finish_with_message(format!(
+            "{} Already up to date at {}",
+            "✓".green(),
+            short_rev(&new_head).bright_white()
+        ));
+        return Ok(());
+    }
+
+    spinner.finish_with_message(format!(
+        "{} Updated {} → {}",
+        "✓".green(),
+        short_rev(&old_head).dimmed(),
+        short_rev(&new_head).bright_white()
+    ));
+
+    let files = changed_files(&repo_path, &old_head, &new_head)
+        .map_err(|e| SlinkyError::Remote(e.to_string()))?;
+    println!("\n{} {} file(s) changed:", "→".cyan(), files.len());
+    for file in &files {
+        println!("  {} {}", "•".bright_blue(), file.bright_white());
+    }
+
+    println!(
+        "\n{} Run {} to apply the changes",
+        "→".cyan(),
+        "slnky link --all".bright_white()
+    );
+
+    Ok(())
+}
+
+fn short_rev(rev: &str) -> &str {
+    &rev[..rev.len().min(7)]
+}
+
+fn link_package(
+    package: &str,
+    target: Option<&Path>,
+    force: bool,
+    cli: &Cli,
+    config: &Config,
+) -> Result<()> {
+    print_header("Linking Package");
+
+    let target = target
+        .map(Path::to_path_buf)
+        .or_else(|| cli.target.clone())
+        .unwrap_or_else(|| config.target_dir.clone());
+
+    if cli.verbose {
+        println!("{} Package: {}", "→".cyan(), package.bright_white());
+        println!(
+            "{} Target: {}",
+            "→".cyan(),
+            target.display().to_string().bright_white()
+        );
+        println!(
+            "{} Stow dir: {}",
+            "→".cyan(),
+            config.stow_dir.display().to_string().bright_white()
+        );
+    }
+
+    let package_path = config.stow_dir.join(package);
+    if !package_path.exists() {
+        return Err(package_not_found(package, config));
+    }
+
+    let options = AnalyzeOptions {
+        force,
+        report_conflicts: force,
+        ..analyze_options(cli, config)
+    };
+    let slinky = Slinky::new(config.clone());
+    let mut operations = slinky.plan_link(&package_path, &target, &options)?;
+    resolve_forced_conflicts(&mut operations, cli, config)?;
 
     let create_ops: Vec<_> = operations
         .iter()
-        .filter(|op| matches!(op.op_type, OpType::Create))
+        .filter(|op| op.op_type.creates_link())
         .collect();
 
     let skip_ops: Vec<_> = operations
@@ -1024,19 +2465,23 @@ fn link_package(package: &str, cli: &Cli, config: &Config) -> Result<()> {
         })
         .collect();
 
+    let ignored = ignored_note(&operations);
+
     if create_ops.is_empty() {
         if !skip_ops.is_empty() {
             println!(
-                "{} Package {} already linked ({} symlink(s))",
+                "{} Package {} already linked ({} symlink(s){})",
                 "✓".green(),
                 package.bright_white(),
-                skip_ops.len()
+                skip_ops.len(),
+                ignored
             );
         } else {
             println!(
-                "{} Package {} has no files to link",
+                "{} Package {} has no files to link{}",
                 "→".cyan(),
-                package.bright_white()
+                package.bright_white(),
+                ignored
             );
         }
         return Ok(());
@@ -1049,11 +2494,17 @@ fn link_package(package: &str, cli: &Cli, config: &Config) -> Result<()> {
             create_ops.len().to_string().bright_white()
         );
         for op in &create_ops {
+            let replaced = match op.op_type {
+                OpType::Replace { backup: true } => " (existing file backed up)",
+                OpType::Replace { backup: false } => " (existing file deleted)",
+                _ => "",
+            };
             println!(
-                "  {} {} → {}",
+                "  {} {} → {}{}",
                 "•".bright_blue(),
                 op.target.display().to_string().dimmed(),
-                op.source.display().to_string().bright_white()
+                op.source.display().to_string().bright_white(),
+                replaced.yellow()
             );
         }
         if !skip_ops.is_empty() {
@@ -1063,12 +2514,13 @@ fn link_package(package: &str, cli: &Cli, config: &Config) -> Result<()> {
                 skip_ops.len()
             );
         }
+        if !ignored.is_empty() {
+            let count = package_status(&operations).ignored;
+            println!("{} {} file(s) ignored", "→".cyan(), count);
+        }
         return Ok(());
     }
 
-    let spinner = create_spinner(&format!("Linking {}...", package));
-    execute_operations(&operations, false).map_err(|e| SlinkyError::Stow(e.to_string()))?;
-
     let mut msg = format!(
         "{} Package {} linked ({} symlinks created)",
         "✓".green(),
@@ -1078,12 +2530,242 @@ fn link_package(package: &str, cli: &Cli, config: &Config) -> Result<()> {
     if !skip_ops.is_empty() {
         msg.push_str(&format!(", {} already linked", skip_ops.len()));
     }
-    spinner.finish_with_message(msg);
+    msg.push_str(&ignored);
+
+    // Started on the first operation and finished on the last, so the
+    // hooks `apply_link` runs before and after print outside the spinner.
+    let mut spinner: Option<Spinner> = None;
+    let result = slinky.apply_link(
+        package,
+        &package_path,
+        &target,
+        &operations,
+        |done, total, _| {
+            let spinner =
+                spinner.get_or_insert_with(|| create_spinner(&format!("Linking {}...", package)));
+            spinner.set_message(format!("Linking {}... ({}/{})", package, done, total));
+            if done == total {
+                spinner.finish_with_message(msg.clone());
+            }
+        },
+    )?;
+    print_hook_warnings(&result.warnings);
+
+    Ok(())
+}
+
+/// A `PackageNotFound` error listing the packages that do exist.
+fn package_not_found(package: &str, config: &Config) -> SlinkyError {
+    let stow_dir = &config.stow_dir;
+    let available = find_packages_with(stow_dir, &config.include_hidden_packages)
+        .map(|pkgs| {
+            pkgs.iter()
+                .map(|p| p.name.clone())
+                .collect::<Vec<_>>()
+                .join(", ")
+        })
+        .unwrap_or_default();
+
+    let hint = if available.is_empty() {
+        format!("No packages found in {}", stow_dir.display())
+    } else {
+        format!("Available packages: {}", available)
+    };
+
+    SlinkyError::PackageNotFound(format!("{}\n{} {}", package, "→".cyan(), hint.dimmed()))
+}
+
+/// Clears conflicting real files for `--force` using the configured
+/// `conflict_resolution`, turning their conflicts into creates or skips.
+/// Conflicts with foreign symlinks are still reported as errors.
+fn resolve_forced_conflicts(
+    operations: &mut [SymlinkOp],
+    cli: &Cli,
+    config: &Config,
+) -> Result<()> {
+    let resolution = config.auto_sync.conflict_resolution;
+
+    for op in operations.iter_mut() {
+        let OpType::Conflict(reason) = &op.op_type else {
+            continue;
+        };
+        // What is reached through a directory symlink into the repo is a repo
+        // file, never something to move aside.
+        if op.target.is_symlink() || is_under(&op.target, &config.stow_dir) {
+            return Err(SlinkyError::Conflict(reason.clone()));
+        }
+
+        // Edits made directly to the target after the repo copy changed would
+        // be lost, so those need a second confirmation.
+        if resolution != ConflictResolution::Skip && target_is_newer(&op.target, &op.source) {
+            let prompt = format!(
+                "{} was modified after its repo copy and may have unsaved changes. Replace it?",
+                op.target.display()
+            );
+            if cli.dry_run || !(cli.yes || confirm(&prompt, false)?) {
+                op.op_type = OpType::Skip(
+                    "Target is newer than the repo copy (kept; confirm to replace)".to_string(),
+                );
+                continue;
+            }
+        }
+
+        // Only decided here; the file is moved or deleted when the operations
+        // run, after the pre-link hook and never under --dry-run.
+        op.op_type = match resolution {
+            ConflictResolution::Backup => OpType::Replace { backup: true },
+            ConflictResolution::Overwrite => OpType::Replace { backup: false },
+            ConflictResolution::Skip => {
+                OpType::Skip("Conflicting file kept (conflict_resolution = skip)".to_string())
+            }
+        };
+    }
+
+    Ok(())
+}
+
+/// Whether `path`, with symlinks followed, lies inside `dir`.
+fn is_under(path: &Path, dir: &Path) -> bool {
+    match (fs::canonicalize(path), fs::canonicalize(dir)) {
+        (Ok(path), Ok(dir)) => path.starts_with(dir),
+        _ => false,
+    }
+}
+
+fn write_link_plan(
+    requests: &[LinkRequest],
+    all: bool,
+    plan_path: &Path,
+    cli: &Cli,
+    config: &Config,
+) -> Result<()> {
+    print_header("Writing Link Plan");
+
+    let target = cli
+        .target
+        .as_ref()
+        .cloned()
+        .unwrap_or_else(|| config.target_dir.clone());
+
+    let packages = if all {
+        find_packages_with(&config.stow_dir, &config.include_hidden_packages)
+            .map_err(|e| SlinkyError::Stow(e.to_string()))?
+    } else if !requests.is_empty() {
+        let mut packages = Vec::new();
+        for (name, _) in requests {
+            let package_path = config.stow_dir.join(name);
+            if !package_path.exists() {
+                return Err(SlinkyError::PackageNotFound(name.to_string()));
+            }
+            packages.push(StowPackage {
+                name: name.to_string(),
+                path: package_path,
+            });
+        }
+        packages
+    } else {
+        return Err(SlinkyError::Other(
+            "Specify a package name or use --all".to_string(),
+        ));
+    };
+
+    let mut operations: Vec<SymlinkOp> = Vec::new();
+    let mut error_count = 0;
+
+    for package in &packages {
+        let target = requested_target(requests, &package.name).unwrap_or(&target);
+        match analyze_package_with(&package.path, target, &analyze_options(cli, config)) {
+            Ok(ops) => {
+                let create_count = ops.iter().filter(|op| op.op_type.creates_link()).count();
+                println!(
+                    "  {} {} - {} symlink(s) to create",
+                    "•".bright_blue(),
+                    package.name.bright_white(),
+                    create_count
+                );
+                operations.extend(ops);
+            }
+            Err(e) => {
+                println!("  {} {} - {}", "✗".red(), package.name.bright_white(), e);
+                error_count += 1;
+            }
+        }
+    }
+
+    let conflict_count = operations
+        .iter()
+        .filter(|op| matches!(op.op_type, OpType::Conflict(_)))
+        .count();
+    if error_count > 0 || conflict_count > 0 {
+        return Err(SlinkyError::Other(format!(
+            "{} package(s) could not be planned and {} target(s) conflict; no plan written",
+            error_count, conflict_count
+        )));
+    }
+
+    write_plan(plan_path, &operations).map_err(|e| SlinkyError::Stow(e.to_string()))?;
+
+    println!(
+        "\n{} Plan with {} operation(s) written to {}",
+        "✓".green(),
+        operations.len().to_string().bright_white(),
+        plan_path.display().to_string().bright_white()
+    );
+    println!(
+        "{} Run {} to apply it",
+        "→".cyan(),
+        format!("slnky apply {}", plan_path.display()).bright_white()
+    );
+
+    Ok(())
+}
+
+fn apply_plan(plan_path: &Path, dir_mode: Option<u32>, cli: &Cli) -> Result<()> {
+    print_header("Applying Plan");
+
+    let operations = read_plan(plan_path).map_err(|e| SlinkyError::Stow(e.to_string()))?;
+
+    if cli.verbose {
+        println!(
+            "{} Plan: {} ({} operation(s))",
+            "→".cyan(),
+            plan_path.display().to_string().bright_white(),
+            operations.len()
+        );
+    }
+
+    let results = execute_operations_with(&operations, cli.dry_run, dir_mode, |_, _, _| {})
+        .map_err(|e| SlinkyError::Stow(e.to_string()))?;
+
+    let create_count = operations
+        .iter()
+        .filter(|op| op.op_type.creates_link())
+        .count();
+
+    if cli.dry_run || cli.verbose {
+        for result in &results {
+            println!("  {} {}", "•".bright_blue(), result.dimmed());
+        }
+    }
+
+    if cli.dry_run {
+        println!(
+            "\n{} Would create {} symlink(s)",
+            "🔍".bright_blue(),
+            create_count.to_string().bright_white()
+        );
+    } else {
+        println!(
+            "{} Plan applied ({} symlink(s) created)",
+            "✓".green(),
+            create_count.to_string().bright_white()
+        );
+    }
 
     Ok(())
 }
 
-fn unlink_package(package: &str, cli: &Cli, config: &Config) -> Result<()> {
+fn unlink_package(package: &str, detach: bool, cli: &Cli, config: &Config) -> Result<()> {
     print_header("Unlinking Package");
 
     let target = cli
@@ -1106,19 +2788,8 @@ fn unlink_package(package: &str, cli: &Cli, config: &Config) -> Result<()> {
         return Err(SlinkyError::PackageNotFound(package.to_string()));
     }
 
-    let operations =
-        analyze_package(&package_path, &target).map_err(|e| SlinkyError::Stow(e.to_string()))?;
-
-    let linked_ops: Vec<_> = operations
-        .iter()
-        .filter(|op| {
-            if let OpType::Skip(reason) = &op.op_type {
-                reason.contains("Already linked")
-            } else {
-                false
-            }
-        })
-        .collect();
+    let slinky = Slinky::new(config.clone());
+    let linked_ops = slinky.plan_unlink(&package_path, &target, &analyze_options(cli, config))?;
 
     if linked_ops.is_empty() {
         println!(
@@ -1129,10 +2800,16 @@ fn unlink_package(package: &str, cli: &Cli, config: &Config) -> Result<()> {
         return Ok(());
     }
 
+    let action = if detach {
+        "replace with copies"
+    } else {
+        "remove"
+    };
     if !cli.yes && !cli.dry_run {
         println!(
-            "{} This will remove {} symlink(s)",
+            "{} This will {} {} symlink(s)",
             "⚠".yellow(),
+            action,
             linked_ops.len()
         );
         if !confirm("Continue?", true)? {
@@ -1143,8 +2820,9 @@ fn unlink_package(package: &str, cli: &Cli, config: &Config) -> Result<()> {
 
     if cli.dry_run {
         println!(
-            "{} Would remove {} symlink(s):",
+            "{} Would {} {} symlink(s):",
             "🔍".bright_blue(),
+            action,
             linked_ops.len().to_string().bright_white()
         );
         for op in &linked_ops {
@@ -1157,37 +2835,113 @@ fn unlink_package(package: &str, cli: &Cli, config: &Config) -> Result<()> {
         return Ok(());
     }
 
-    let spinner = create_spinner(&format!("Unlinking {}...", package));
-    let mut removed = 0;
-    for op in &linked_ops {
-        if op.target.is_symlink() {
-            fs::remove_file(&op.target).map_err(SlinkyError::Io)?;
-            removed += 1;
-        }
-    }
-    spinner.finish_with_message(format!(
-        "{} Package {} unlinked ({} symlinks removed)",
-        "✓".green(),
-        package.bright_white(),
-        removed
-    ));
+    // As in `link_package`, the spinner only runs while links are removed.
+    let mut spinner: Option<Spinner> = None;
+    let removed = linked_ops
+        .iter()
+        .filter(|op| op.target.is_symlink())
+        .count();
+    let result = slinky.apply_unlink(
+        package,
+        &package_path,
+        &target,
+        &linked_ops,
+        detach,
+        |done, total, _| {
+            let spinner =
+                spinner.get_or_insert_with(|| create_spinner(&format!("Unlinking {}...", package)));
+            if done == total {
+                spinner.finish_with_message(format!(
+                    "{} Package {} {} ({} symlinks {})",
+                    "✓".green(),
+                    package.bright_white(),
+                    if detach { "detached" } else { "unlinked" },
+                    removed,
+                    if detach {
+                        "replaced with copies"
+                    } else {
+                        "removed"
+                    }
+                ));
+            }
+        },
+    )?;
+    print_hook_warnings(&result.warnings);
 
     Ok(())
 }
 
-fn show_status_command(cli: &Cli, config: &Config, detailed: bool) -> Result<()> {
-    print_header("Package Status");
+fn watch_status(
+    package: Option<&str>,
+    cli: &Cli,
+    config: &Config,
+    detailed: bool,
+    interval: u64,
+) -> Result<()> {
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if event.is_ok() {
+            let _ = tx.send(());
+        }
+    })
+    .map_err(|e| SlinkyError::Other(format!("Failed to create watcher: {}", e)))?;
 
-    let mut effective_config = config.clone();
-    let mut auto_detected = false;
+    for dir in [&config.stow_dir, &config.target_dir] {
+        if dir.exists() {
+            let mode = if dir == &config.stow_dir {
+                RecursiveMode::Recursive
+            } else {
+                RecursiveMode::NonRecursive
+            };
+            let _ = watcher.watch(dir, mode);
+        }
+    }
 
-    if !config.stow_dir.exists() {
-        if let Some(detected_dir) = auto_detect_stow_dir() {
-            println!(
-                "{} Auto-detected dotfiles directory: {}",
-                "→".cyan(),
-                detected_dir.display().to_string().bright_white()
-            );
+    let interval = std::time::Duration::from_secs(interval.max(1));
+
+    loop {
+        print!("\x1B[2J\x1B[H");
+        show_status_command(package, cli, config, detailed)?;
+        println!(
+            "\n{} Watching for changes (refresh every {}s). Press Ctrl+C to exit",
+            "→".dimmed(),
+            interval.as_secs()
+        );
+        io::stdout().flush().map_err(SlinkyError::Io)?;
+
+        match rx.recv_timeout(interval) {
+            Ok(()) => {
+                std::thread::sleep(std::time::Duration::from_millis(200));
+                while rx.try_recv().is_ok() {}
+            }
+            Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {}
+            Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => {
+                std::thread::sleep(interval);
+            }
+        }
+    }
+}
+
+/// Prints the state of every package, or with `only` just that package in
+/// the detailed view.
+fn show_status_command(
+    only: Option<&str>,
+    cli: &Cli,
+    config: &Config,
+    detailed: bool,
+) -> Result<()> {
+    print_header("Package Status");
+
+    let mut effective_config = config.clone();
+    let mut auto_detected = false;
+
+    if !config.stow_dir.exists() {
+        if let Some(detected_dir) = auto_detect_stow_dir() {
+            println!(
+                "{} Auto-detected dotfiles directory: {}",
+                "→".cyan(),
+                detected_dir.display().to_string().bright_white()
+            );
             effective_config.stow_dir = detected_dir;
             auto_detected = true;
         } else {
@@ -1199,14 +2953,25 @@ fn show_status_command(cli: &Cli, config: &Config, detailed: bool) -> Result<()>
             println!(
                 "\n{} Run {} to clone your dotfiles",
                 "→".cyan(),
-                "slnky install user/repo".bright_white()
+                install_hint(&config.stow_dir).bright_white()
             );
             return Ok(());
         }
     }
 
-    let packages =
-        find_packages(&effective_config.stow_dir).map_err(|e| SlinkyError::Stow(e.to_string()))?;
+    let mut packages = find_packages_with(
+        &effective_config.stow_dir,
+        &effective_config.include_hidden_packages,
+    )
+    .map_err(|e| SlinkyError::Stow(e.to_string()))?;
+
+    if let Some(name) = only {
+        packages.retain(|package| package.name == name);
+        if packages.is_empty() {
+            return Err(package_not_found(name, &effective_config));
+        }
+    }
+    let detailed = detailed || only.is_some();
 
     if packages.is_empty() {
         println!(
@@ -1242,58 +3007,115 @@ fn show_status_command(cli: &Cli, config: &Config, detailed: bool) -> Result<()>
         target.display().to_string().bright_white()
     );
 
+    let options = status_options(cli, &effective_config);
+    let analyses = parallel_map(&packages, resolve_jobs(cli.jobs), |_, package| {
+        analyze_package_with(&package.path, &target, &options)
+    });
+
+    let link_state = LinkState::load(&state_path());
     let mut linked_count = 0;
     let mut partial_count = 0;
     let mut unlinked_count = 0;
+    let mut conflicted_count = 0;
+    let mut empty_count = 0;
+    let mut error_count = 0;
 
-    for package in &packages {
-        let ops = analyze_package(&package.path, &target).unwrap_or_default();
+    for (package, ops) in packages.iter().zip(analyses) {
+        let (ops, package_state, error) = match ops {
+            Ok(ops) => {
+                let package_state = package_status(&ops);
+                (ops, package_state, None)
+            }
+            Err(e) => {
+                let package_state = PackageStatus {
+                    state: PackageState::Error,
+                    linked: 0,
+                    total: 0,
+                    conflicts: 0,
+                    ignored: 0,
+                };
+                (Vec::new(), package_state, Some(e.to_string()))
+            }
+        };
+        let linked_files = package_state.linked;
 
-        let total_files = ops.len();
-        let linked_files = ops
-            .iter()
-            .filter(|op| {
-                if let OpType::Skip(reason) = &op.op_type {
-                    reason.contains("Already linked")
-                } else {
-                    false
-                }
-            })
-            .count();
-        let _create_needed = ops
-            .iter()
-            .filter(|op| matches!(op.op_type, OpType::Create))
-            .count();
-
-        let (icon, status, status_color) = if linked_files == total_files && total_files > 0 {
-            linked_count += 1;
-            ("✓", "linked".to_string(), "green")
-        } else if linked_files > 0 {
-            partial_count += 1;
-            (
-                "◐",
-                format!("partial ({}/{})", linked_files, total_files),
-                "yellow",
-            )
-        } else {
-            unlinked_count += 1;
-            ("○", "not linked".to_string(), "dimmed")
+        let (icon, status, status_color) = match package_state.state {
+            PackageState::Linked => {
+                linked_count += 1;
+                ("✓", "linked".to_string(), "green")
+            }
+            PackageState::Partial => {
+                partial_count += 1;
+                (
+                    "◐",
+                    format!("partial ({}/{})", linked_files, package_state.total),
+                    "yellow",
+                )
+            }
+            PackageState::Unlinked => {
+                unlinked_count += 1;
+                ("○", "not linked".to_string(), "dimmed")
+            }
+            PackageState::Conflicted => {
+                conflicted_count += 1;
+                (
+                    "✗",
+                    format!(
+                        "conflicted ({} conflict(s), {}/{} linked)",
+                        package_state.conflicts, linked_files, package_state.total
+                    ),
+                    "red",
+                )
+            }
+            PackageState::Empty => {
+                empty_count += 1;
+                ("∅", "empty".to_string(), "dimmed")
+            }
+            PackageState::Error => {
+                error_count += 1;
+                ("✗", format!("error: {}", error.unwrap_or_default()), "red")
+            }
         };
 
         let status_display = match status_color {
             "green" => format!("({})", status).green(),
             "yellow" => format!("({})", status).yellow(),
+            "red" => format!("({})", status).red(),
             _ => format!("({})", status).dimmed(),
         };
 
+        let icon = if matches!(
+            package_state.state,
+            PackageState::Conflicted | PackageState::Error
+        ) {
+            icon.red()
+        } else {
+            icon.bright_blue()
+        };
+
+        let ignored = match package_state.ignored {
+            0 => String::new(),
+            ignored => format!(" {} ignored", ignored),
+        };
         println!(
-            "  {} {} {}",
-            icon.bright_blue(),
+            "  {} {} {}{}",
+            icon,
             package.name.bright_white(),
-            status_display
+            status_display,
+            ignored.dimmed()
         );
 
-        if detailed && (cli.verbose || linked_files > 0) {
+        if detailed {
+            if let Some(at) = link_state.last_linked(&package.name) {
+                println!(
+                    "    {} last linked: {} ago",
+                    "⏱".dimmed(),
+                    format_elapsed(unix_now().saturating_sub(at))
+                );
+            }
+        }
+
+        if detailed && (cli.verbose || only.is_some() || linked_files > 0) {
             for op in &ops {
                 let (file_icon, file_status) = match &op.op_type {
                     OpType::Skip(reason) if reason.contains("Already linked") => {
@@ -1303,11 +3125,23 @@ fn show_status_command(cli: &Cli, config: &Config, detailed: bool) -> Result<()>
                         "  ○".dimmed(),
                         format!("{} (would link)", op.target.display()).dimmed(),
                     ),
+                    OpType::Relink => (
+                        "  ↻".yellow(),
+                        format!("{} (would re-point)", op.target.display()).dimmed(),
+                    ),
+                    OpType::Replace { .. } => (
+                        "  ↻".yellow(),
+                        format!("{} (would replace)", op.target.display()).dimmed(),
+                    ),
                     OpType::Skip(reason) => (
                         "  ⊘".yellow(),
                         format!("{} ({})", op.target.display(), reason).dimmed(),
                     ),
                     OpType::Remove => ("  ✗".red(), op.target.display().to_string().dimmed()),
+                    OpType::Conflict(reason) => (
+                        "  ✗".red(),
+                        format!("{} ({})", op.target.display(), reason).red(),
+                    ),
                 };
                 println!("    {} {}", file_icon, file_status);
             }
@@ -1316,71 +3150,735 @@ fn show_status_command(cli: &Cli, config: &Config, detailed: bool) -> Result<()>
 
     println!();
     println!(
-        "{} {} linked, {} partial, {} not linked",
+        "{} {} linked, {} partial, {} not linked, {} conflicted, {} empty",
         "Summary:".bright_white().bold(),
         linked_count.to_string().green(),
         partial_count.to_string().yellow(),
-        unlinked_count.to_string().dimmed()
+        unlinked_count.to_string().dimmed(),
+        conflicted_count.to_string().red(),
+        empty_count.to_string().dimmed()
+    );
+    if error_count > 0 {
+        println!(
+            "\n{} {} package(s) could not be analyzed",
+            "✗".red(),
+            error_count.to_string().bright_white()
+        );
+    }
+
+    if conflicted_count > 0 {
+        println!(
+            "\n{} {} package(s) have conflicting files; run {} for details",
+            "✗".red(),
+            conflicted_count.to_string().bright_white(),
+            "slnky status --detailed".bright_white()
+        );
+    }
+
+    if empty_count > 0 {
+        println!(
+            "\n{} {} package(s) have no linkable files (empty or fully ignored)",
+            "→".cyan(),
+            empty_count.to_string().bright_white()
+        );
+    }
+
+    if auto_detected {
+        println!(
+            "\n{} Run {} to save this configuration",
+            "→".cyan(),
+            "slnky init".bright_white()
+        );
+    }
+
+    if unlinked_count > 0 || partial_count > 0 {
+        println!(
+            "\n{} Run {} to link all packages",
+            "→".cyan(),
+            "slnky link --all".bright_white()
+        );
+    }
+
+    Ok(())
+}
+
+fn analyze_options(cli: &Cli, config: &Config) -> AnalyzeOptions {
+    AnalyzeOptions {
+        max_depth: cli.max_depth.or(config.max_depth),
+        symlinks: if cli.deref_symlinks {
+            SymlinkMode::Deref
+        } else {
+            config.package_symlinks
+        },
+        unfold: matches!(
+            cli.command,
+            Some(Commands::Link {
+                restow_on_conflict: true,
+                ..
+            })
+        ),
+        repair: matches!(cli.command, Some(Commands::Link { repair: true, .. })),
+        trace_ignores: cli.verbose,
+        ..Default::default()
+    }
+}
+
+/// Analysis options for status reporting, where conflicts are shown rather
+/// than aborting the scan.
+fn status_options(cli: &Cli, config: &Config) -> AnalyzeOptions {
+    AnalyzeOptions {
+        report_conflicts: true,
+        ..analyze_options(cli, config)
+    }
+}
+
+/// The format a command prints in; its own `--json` flag is shorthand for
+/// `--output json`.
+fn output_format(cli: &Cli, json: bool) -> OutputFormat {
+    if json {
+        OutputFormat::Json
+    } else {
+        cli.output
+    }
+}
+
+fn print_json<T: Serialize + ?Sized>(value: &T) -> Result<()> {
+    let json =
+        serde_json::to_string_pretty(value).map_err(|e| SlinkyError::Other(e.to_string()))?;
+    println!("{}", json);
+    Ok(())
+}
+
+/// Prints each row as one tab-separated line, for `--output plain`.
+fn print_plain<R: AsRef<[String]>>(rows: &[R]) {
+    for row in rows {
+        println!("{}", row.as_ref().join("\t"));
+    }
+}
+
+/// `status` for `--output json` and `--output plain`; plain prints
+/// `name, state, linked, total, conflicts` per package.
+fn show_status_report(
+    format: OutputFormat,
+    only: Option<&str>,
+    cli: &Cli,
+    config: &Config,
+) -> Result<()> {
+    let reports = status_reports(only, cli, config)?;
+    if format == OutputFormat::Json {
+        return print_json(&reports);
+    }
+    let rows: Vec<[String; 5]> = reports
+        .iter()
+        .map(|report| {
+            [
+                report.name.clone(),
+                report.state.as_str().to_string(),
+                report.linked.to_string(),
+                report.total.to_string(),
+                report.conflicts.len().to_string(),
+            ]
+        })
+        .collect();
+    print_plain(&rows);
+    Ok(())
+}
+
+/// `status --porcelain`: `<state> <name> <linked>/<total>` per package, where
+/// state is `L`inked, `P`artial, `U`nlinked, `C`onflicted, `E`mpty or `X`
+/// for a package that could not be analyzed (shown as 0/0). This format is a
+/// stable interface for scripts; do not change it.
+fn show_status_porcelain(only: Option<&str>, cli: &Cli, config: &Config) -> Result<()> {
+    for report in status_reports(only, cli, config)? {
+        let state = match report.state {
+            PackageState::Linked => 'L',
+            PackageState::Partial => 'P',
+            PackageState::Unlinked => 'U',
+            PackageState::Conflicted => 'C',
+            PackageState::Empty => 'E',
+            PackageState::Error => 'X',
+        };
+        println!(
+            "{} {} {}/{}",
+            state, report.name, report.linked, report.total
+        );
+    }
+    Ok(())
+}
+
+/// Per-package status for the machine-readable formats, honoring `--target`
+/// and `--max-depth`.
+fn status_reports(only: Option<&str>, cli: &Cli, config: &Config) -> Result<Vec<PackageReport>> {
+    let mut config = config.clone();
+    if !config.stow_dir.exists() {
+        config.stow_dir =
+            auto_detect_stow_dir().ok_or_else(|| stow_dir_not_found(&config.stow_dir))?;
+    }
+    if let Some(target) = &cli.target {
+        config.target_dir = target.clone();
+    }
+    config.max_depth = cli.max_depth.or(config.max_depth);
+
+    let mut reports = Slinky::new(config.clone()).status()?;
+    if let Some(name) = only {
+        reports.retain(|report| report.name == name);
+        if reports.is_empty() {
+            return Err(package_not_found(name, &config));
+        }
+    }
+    Ok(reports)
+}
+
+#[derive(Serialize)]
+struct PackageMetadata {
+    name: String,
+    #[serde(flatten)]
+    config: PackageConfig,
+}
+
+/// Lists every package with the settings from its `.slinky.toml`; packages
+/// without one show the defaults.
+fn show_packages(format: OutputFormat, config: &Config) -> Result<()> {
+    let packages = find_packages_with(&config.stow_dir, &config.include_hidden_packages)
+        .map_err(|e| SlinkyError::Stow(e.to_string()))?
+        .into_iter()
+        .map(|package| {
+            load_package_config(&package.path)
+                .map(|config| PackageMetadata {
+                    name: package.name,
+                    config,
+                })
+                .map_err(|e| SlinkyError::Stow(e.to_string()))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    match format {
+        OutputFormat::Json => return print_json(&packages),
+        OutputFormat::Plain => {
+            let rows: Vec<[String; 4]> = packages
+                .iter()
+                .map(|package| {
+                    [
+                        package.name.clone(),
+                        package.config.targets.join(","),
+                        package.config.hosts.join(","),
+                        package.config.description.clone().unwrap_or_default(),
+                    ]
+                })
+                .collect();
+            print_plain(&rows);
+            return Ok(());
+        }
+        OutputFormat::Table => {}
+    }
+
+    print_header("Packages");
+    if packages.is_empty() {
+        println!(
+            "{} No packages found in {}",
+            "⚠".yellow(),
+            config.stow_dir.display().to_string().bright_white()
+        );
+        return Ok(());
+    }
+
+    let rows: Vec<[String; 4]> = packages
+        .iter()
+        .map(|package| {
+            let targets = if package.config.targets.is_empty() {
+                "-".to_string()
+            } else {
+                package.config.targets.join(", ")
+            };
+            let hosts = if package.config.hosts.is_empty() {
+                "-".to_string()
+            } else {
+                package.config.hosts.join(", ")
+            };
+            [
+                package.name.clone(),
+                targets,
+                hosts,
+                package.config.description.clone().unwrap_or_default(),
+            ]
+        })
+        .collect();
+    let width = |column: usize, header: &str| {
+        rows.iter()
+            .map(|row| row[column].chars().count())
+            .chain([header.len()])
+            .max()
+            .unwrap_or(0)
+    };
+    let (name_width, target_width, hosts_width) =
+        (width(0, "PACKAGE"), width(1, "TARGET"), width(2, "HOSTS"));
+
+    println!(
+        "{:<name_width$}  {:<target_width$}  {:<hosts_width$}  {}",
+        "PACKAGE".bold(),
+        "TARGET".bold(),
+        "HOSTS".bold(),
+        "DESCRIPTION".bold()
+    );
+    for [name, targets, hosts, description] in &rows {
+        println!(
+            "{:<name_width$}  {:<target_width$}  {:<hosts_width$}  {}",
+            name.bright_white(),
+            targets,
+            hosts,
+            description.dimmed()
+        );
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy)]
+struct CleanCategories {
+    templates: bool,
+    backups: bool,
+    repos: bool,
+    pid_file: bool,
+}
+
+/// Removes the artifacts of the selected categories. Cached clones holding
+/// any of the `stow_dirs` are kept, and so are clones with uncommitted
+/// changes or unpushed commits.
+fn clean_artifacts(
+    categories: CleanCategories,
+    older_than_days: u64,
+    stow_dirs: &[PathBuf],
+    cli: &Cli,
+    config: &Config,
+) -> Result<()> {
+    print_header("Cleaning Up");
+
+    let mut artifacts: Vec<(&str, PathBuf)> = Vec::new();
+
+    if categories.templates && config.stow_dir.is_dir() {
+        for path in orphaned_templates(&config.stow_dir).map_err(SlinkyError::Io)? {
+            artifacts.push(("orphaned template", path));
+        }
+    }
+
+    if categories.backups && config.stow_dir.is_dir() {
+        let target = cli
+            .target
+            .as_ref()
+            .cloned()
+            .unwrap_or_else(|| config.target_dir.clone());
+        let packages = find_packages_with(&config.stow_dir, &config.include_hidden_packages)
+            .map_err(|e| SlinkyError::Stow(e.to_string()))?;
+        let mut targets = Vec::new();
+        for package in &packages {
+            if let Ok(ops) =
+                analyze_package_with(&package.path, &target, &status_options(cli, config))
+            {
+                targets.extend(ops.into_iter().map(|op| op.target));
+            }
+        }
+        let max_age = std::time::Duration::from_secs(older_than_days * 24 * 60 * 60);
+        for path in stale_backups(&targets, max_age, std::time::SystemTime::now()) {
+            artifacts.push(("old backup", path));
+        }
+    }
+
+    if categories.pid_file {
+        if let Some(path) = stale_pid_file(&daemon_pid_path()) {
+            artifacts.push(("stale pid file", path));
+        }
+    }
+
+    if categories.repos {
+        for path in unreferenced_repos(&repo_cache_dir(), stow_dirs).map_err(SlinkyError::Io)? {
+            if has_local_work(&path) {
+                println!(
+                    "  {} Keeping {} (uncommitted changes or unpushed commits)",
+                    "⚠".yellow(),
+                    path.display().to_string().bright_white()
+                );
+                continue;
+            }
+            artifacts.push(("unused cached repo", path));
+        }
+    }
+
+    if artifacts.is_empty() {
+        println!("{} Nothing to clean", "✓".green());
+        return Ok(());
+    }
+
+    for (kind, path) in &artifacts {
+        println!(
+            "  {} {} {}",
+            "•".bright_blue(),
+            path.display().to_string().bright_white(),
+            format!("({})", kind).dimmed()
+        );
+    }
+
+    if cli.dry_run {
+        println!(
+            "\n{} Would remove {} item(s)",
+            "🔍".bright_blue(),
+            artifacts.len().to_string().bright_white()
+        );
+        return Ok(());
+    }
+
+    if !cli.yes && !confirm(&format!("Remove {} item(s)?", artifacts.len()), false)? {
+        println!("{} Cancelled", "→".cyan());
+        return Ok(());
+    }
+
+    for (_, path) in &artifacts {
+        remove_artifact(path).map_err(SlinkyError::Io)?;
+    }
+    println!(
+        "{} Removed {} item(s)",
+        "✓".green(),
+        artifacts.len().to_string().bright_white()
     );
 
-    if auto_detected {
+    Ok(())
+}
+
+/// Prints one tab-separated line per package (or per problem) and fails if
+/// any package has conflicts, missing sources, or cannot be analyzed.
+fn check_packages(cli: &Cli, config: &Config) -> Result<()> {
+    let target = cli
+        .target
+        .as_ref()
+        .cloned()
+        .unwrap_or_else(|| config.target_dir.clone());
+
+    let packages = find_packages_with(&config.stow_dir, &config.include_hidden_packages)
+        .map_err(|e| SlinkyError::Stow(e.to_string()))?;
+
+    let mut problems = 0;
+    for package in &packages {
+        let ops = match analyze_package_with(&package.path, &target, &status_options(cli, config)) {
+            Ok(ops) => ops,
+            Err(e) => {
+                problems += 1;
+                println!("error\t{}\t-\t{}", package.name, e);
+                continue;
+            }
+        };
+
+        let mut clean = true;
+        for op in &ops {
+            match &op.op_type {
+                OpType::Conflict(reason) => {
+                    println!(
+                        "conflict\t{}\t{}\t{}",
+                        package.name,
+                        op.target.display(),
+                        reason
+                    );
+                    clean = false;
+                }
+                OpType::Create if !op.source.exists() => {
+                    println!(
+                        "missing\t{}\t{}\tSource does not exist: {}",
+                        package.name,
+                        op.target.display(),
+                        op.source.display()
+                    );
+                    clean = false;
+                }
+                _ => {}
+            }
+        }
+
+        if clean {
+            println!("ok\t{}", package.name);
+        } else {
+            problems += 1;
+        }
+    }
+
+    if problems > 0 {
+        return Err(SlinkyError::Other(format!(
+            "Check failed: {} package(s) would not link cleanly",
+            problems
+        )));
+    }
+
+    Ok(())
+}
+
+fn show_link_owner(path: &Path, config: &Config) -> Result<()> {
+    let path = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir().map_err(SlinkyError::Io)?.join(path)
+    };
+
+    if !path.is_symlink() && !path.exists() {
+        return Err(SlinkyError::Other(format!(
+            "File not found: {}",
+            path.display()
+        )));
+    }
+
+    match find_link_owner(&path, &config.stow_dir).map_err(|e| SlinkyError::Stow(e.to_string()))? {
+        Some(owner) => {
+            println!(
+                "{} {} is owned by package {}",
+                "✓".green(),
+                path.display().to_string().bright_white(),
+                owner.package.bright_white().bold()
+            );
+            println!(
+                "  {} {}",
+                "→".cyan(),
+                owner.source.display().to_string().dimmed()
+            );
+            if !owner.source.exists() {
+                println!("  {} Source no longer exists", "⚠".yellow());
+            }
+        }
+        None => {
+            println!(
+                "{} {} is not managed by slinky",
+                "→".cyan(),
+                path.display().to_string().bright_white()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn scan_secrets(file: &Path, cli: &Cli, config: &Config) -> Result<()> {
+    print_header("Scanning for Secrets");
+
+    if !file.exists() {
+        return Err(SlinkyError::Other(format!(
+            "File not found: {}",
+            file.display()
+        )));
+    }
+
+    if file.is_dir() {
+        return scan_dir_secrets(file, cli, config);
+    }
+
+    if cli.verbose {
+        println!(
+            "{} File: {}",
+            "→".cyan(),
+            file.display().to_string().bright_white()
+        );
+    }
+
+    let spinner = create_spinner("Scanning for secrets...");
+    let secrets = scan_file_for_secrets_with(file, &config.secret_name_rules())
+        .map_err(|e| SlinkyError::Secrets(e.to_string()))?;
+    spinner.finish_and_clear();
+
+    if secrets.is_empty() {
+        println!("{} No secrets detected", "✓".green());
+    } else {
+        println!(
+            "{} Found {} potential secret(s):",
+            "⚠".yellow(),
+            secrets.len().to_string().bright_white()
+        );
+        for secret in secrets {
+            println!("  {} {}", "•".red(), secret.name.bright_white());
+        }
+    }
+
+    Ok(())
+}
+
+fn scan_stow_dir_secrets(cli: &Cli, config: &Config) -> Result<()> {
+    print_header("Scanning Dotfiles for Secrets");
+
+    let stow_dir = &config.stow_dir;
+    if !stow_dir.is_dir() {
+        return Err(SlinkyError::Other(format!(
+            "Dotfiles directory not found: {}",
+            stow_dir.display()
+        )));
+    }
+
+    let mut files = Vec::new();
+    for entry in fs::read_dir(stow_dir).map_err(SlinkyError::Io)? {
+        let path = entry.map_err(SlinkyError::Io)?.path();
+        if path.file_name() == Some(".git".as_ref()) || path.is_symlink() {
+            continue;
+        }
+        if path.is_dir() {
+            files.extend(package_files(&path).map_err(|e| SlinkyError::Stow(e.to_string()))?);
+        } else {
+            files.push(path);
+        }
+    }
+
+    let findings = scan_files_for_secrets(&files, cli, config);
+    if findings.is_empty() {
+        println!(
+            "{} No secrets detected in {} file(s)",
+            "✓".green(),
+            files.len().to_string().bright_white()
+        );
+        return Ok(());
+    }
+
+    let total = print_secret_findings(&findings, stow_dir);
+    println!();
+    Err(SlinkyError::Secrets(format!(
+        "Found {} potential secret(s) in {} file(s)",
+        total,
+        findings.len()
+    )))
+}
+
+/// The files git tracks in the stow directory that could carry secrets to
+/// the remote, leaving out templates. Files a package's ignore rules exclude
+/// are still pushed, so they are checked too.
+fn tracked_secret_candidates(config: &Config) -> Result<Vec<PathBuf>> {
+    let tracked = tracked_files(&config.stow_dir).map_err(|e| SlinkyError::Git(e.to_string()))?;
+
+    Ok(tracked
+        .into_iter()
+        .filter(|file| file.is_file() && !is_template_file(file))
+        .collect())
+}
+
+/// The `status --security` section: warns about secrets in tracked files
+/// before they are pushed.
+fn show_tracked_secrets(cli: &Cli, config: &Config) -> Result<()> {
+    println!("\n{}", "Security:".bright_white().bold());
+
+    let files = match tracked_secret_candidates(config) {
+        Ok(files) => files,
+        Err(e) => {
+            println!("  {} Secret check skipped: {}", "⚠".yellow(), e);
+            return Ok(());
+        }
+    };
+
+    let findings = scan_files_for_secrets(&files, cli, config);
+    if findings.is_empty() {
         println!(
-            "\n{} Run {} to save this configuration",
-            "→".cyan(),
-            "slnky init".bright_white()
+            "  {} No secrets found in {} tracked file(s)",
+            "✓".green(),
+            files.len().to_string().bright_white()
         );
+        return Ok(());
     }
 
-    if unlinked_count > 0 || partial_count > 0 {
-        println!(
-            "\n{} Run {} to link all packages",
-            "→".cyan(),
-            "slnky link --all".bright_white()
-        );
+    for (file, secrets) in &findings {
+        let display = file.strip_prefix(&config.stow_dir).unwrap_or(file);
+        for secret in secrets {
+            println!(
+                "  {} {}:{} {}",
+                "⚠".yellow(),
+                display.display().to_string().bright_white(),
+                secret.line_number,
+                secret.name.dimmed()
+            );
+        }
     }
-
+    println!(
+        "\n{} Tracked files contain potential secrets; run {} before pushing",
+        "⚠".yellow(),
+        "slnky secrets encrypt".bright_white()
+    );
     Ok(())
 }
 
-fn scan_secrets(file: &Path, cli: &Cli) -> Result<()> {
-    print_header("Scanning for Secrets");
-
-    if !file.exists() {
-        return Err(SlinkyError::Other(format!(
-            "File not found: {}",
-            file.display()
-        )));
+fn scan_dir_secrets(dir: &Path, cli: &Cli, config: &Config) -> Result<()> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        for entry in fs::read_dir(&current).map_err(SlinkyError::Io)? {
+            let path = entry.map_err(SlinkyError::Io)?.path();
+            if path.file_name() == Some(".git".as_ref()) || path.is_symlink() {
+                continue;
+            }
+            if path.is_dir() {
+                pending.push(path);
+            } else {
+                files.push(path);
+            }
+        }
     }
+    files.sort();
 
-    if cli.verbose {
+    let findings = scan_files_for_secrets(&files, cli, config);
+    if findings.is_empty() {
         println!(
-            "{} File: {}",
-            "→".cyan(),
-            file.display().to_string().bright_white()
+            "{} No secrets detected in {} file(s)",
+            "✓".green(),
+            files.len().to_string().bright_white()
         );
+        return Ok(());
     }
 
+    let total = print_secret_findings(&findings, dir);
+    println!();
+    println!(
+        "{} Found {} potential secret(s) in {} file(s)",
+        "⚠".yellow(),
+        total.to_string().bright_white(),
+        findings.len().to_string().bright_white()
+    );
+
+    Ok(())
+}
+
+/// Scans each file and keeps those with findings. Binary files come back
+/// empty from `scan_file_for_secrets`; unreadable ones are skipped.
+fn scan_files_for_secrets<'a>(
+    files: &'a [PathBuf],
+    cli: &Cli,
+    config: &Config,
+) -> Vec<(&'a PathBuf, Vec<Secret>)> {
+    let rules = config.secret_name_rules();
     let spinner = create_spinner("Scanning for secrets...");
-    let secrets = scan_file_for_secrets(file).map_err(|e| SlinkyError::Secrets(e.to_string()))?;
+    let mut findings = Vec::new();
+    for file in files {
+        match scan_file_for_secrets_with(file, &rules) {
+            Ok(secrets) if !secrets.is_empty() => findings.push((file, secrets)),
+            Ok(_) => {}
+            Err(e) => {
+                if cli.verbose {
+                    spinner
+                        .suspend(|| println!("{} Skipped {}: {}", "⚠".yellow(), file.display(), e));
+                }
+            }
+        }
+    }
     spinner.finish_and_clear();
+    findings
+}
 
-    if secrets.is_empty() {
-        println!("{} No secrets detected", "✓".green());
-    } else {
-        println!(
-            "{} Found {} potential secret(s):",
-            "⚠".yellow(),
-            secrets.len().to_string().bright_white()
-        );
+/// Prints findings grouped by file, relative to `base`, and returns the
+/// number of secrets.
+fn print_secret_findings(findings: &[(&PathBuf, Vec<Secret>)], base: &Path) -> usize {
+    let mut total = 0;
+    for (file, secrets) in findings {
+        let display = file.strip_prefix(base).unwrap_or(file);
+        println!("{}", display.display().to_string().bright_white().bold());
         for secret in secrets {
-            println!("  {} {}", "•".red(), secret.name.bright_white());
+            println!(
+                "  {} line {}: {}",
+                "•".red(),
+                secret.line_number.to_string().bright_white(),
+                secret.name
+            );
         }
+        total += secrets.len();
     }
-
-    Ok(())
+    total
 }
 
-fn encrypt_all_secrets(cli: &Cli, _config: &Config) -> Result<()> {
+fn encrypt_all_secrets(in_place: bool, cli: &Cli, config: &Config) -> Result<()> {
     print_header("Encrypting Secrets");
 
     if cli.dry_run {
@@ -1392,9 +3890,10 @@ fn encrypt_all_secrets(cli: &Cli, _config: &Config) -> Result<()> {
     let files = scan_shell_configs().map_err(|e| SlinkyError::Secrets(e.to_string()))?;
     spinner.finish_and_clear();
 
+    let rules = config.secret_name_rules();
     let mut all_secrets = Vec::new();
     for file in &files {
-        if let Ok(secrets) = scan_file_for_secrets(file) {
+        if let Ok(secrets) = scan_file_for_secrets_with(file, &rules) {
             all_secrets.extend(secrets);
         }
     }
@@ -1411,28 +3910,192 @@ fn encrypt_all_secrets(cli: &Cli, _config: &Config) -> Result<()> {
     );
 
     println!("\n{} Enter passphrase to encrypt secrets:", "🔒".cyan());
-    let passphrase = rpassword::read_password()
-        .map_err(|e| SlinkyError::Other(format!("Failed to read passphrase: {}", e)))?;
+    let passphrase = read_passphrase("passphrase to encrypt secrets")?;
+
+    // Encrypt first so an in-place rewrite never loses the only copy.
+    let spinner = create_spinner("Encrypting secrets...");
+    let store_path = secrets_store_path(config).map_err(|e| SlinkyError::Secrets(e.to_string()))?;
+    let encrypt = if in_place {
+        add_to_store
+    } else {
+        encrypt_secrets
+    };
+    encrypt(&all_secrets, &passphrase, &store_path, config.secrets_armor)
+        .map_err(|e| SlinkyError::Encryption(e.to_string()))?;
+    spinner.finish_with_message(format!("{} Secrets encrypted", "✓".green()));
 
-    let spinner = create_spinner("Creating templates...");
+    let spinner = create_spinner(if in_place {
+        "Replacing secrets with placeholders..."
+    } else {
+        "Creating templates..."
+    });
+    let mut backups = Vec::new();
     for file in &files {
         let file_secrets: Vec<_> = all_secrets
             .iter()
             .filter(|s| s.file == *file)
             .cloned()
             .collect();
-        if !file_secrets.is_empty() {
+        if file_secrets.is_empty() {
+            continue;
+        }
+        if in_place {
+            backups.push(
+                template_in_place(file, &file_secrets)
+                    .map_err(|e| SlinkyError::Secrets(e.to_string()))?,
+            );
+        } else {
             create_template(file, &file_secrets)
                 .map_err(|e| SlinkyError::Secrets(e.to_string()))?;
         }
     }
-    spinner.finish_with_message(format!("{} Templates created", "✓".green()));
+    if in_place {
+        spinner.finish_with_message(format!("{} Placeholders written", "✓".green()));
+        for backup in &backups {
+            println!(
+                "  {} Original kept at {}",
+                "→".cyan(),
+                backup.display().to_string().dimmed()
+            );
+        }
+    } else {
+        spinner.finish_with_message(format!("{} Templates created", "✓".green()));
+    }
 
-    let spinner = create_spinner("Encrypting secrets...");
-    encrypt_secrets(&all_secrets, &passphrase)
-        .map_err(|e| SlinkyError::Encryption(e.to_string()))?;
-    spinner.finish_with_message(format!("{} Secrets encrypted", "✓".green()));
+    Ok(())
+}
+
+fn edit_secrets(cli: &Cli, config: &Config) -> Result<()> {
+    print_header("Editing Secrets");
+
+    let store_path = secrets_store_path(config).map_err(|e| SlinkyError::Secrets(e.to_string()))?;
+    if !store_path.exists() {
+        return Err(SlinkyError::Secrets(format!(
+            "No encrypted secrets found at {}",
+            store_path.display()
+        )));
+    }
+
+    let editor = std::env::var("EDITOR").unwrap_or_else(|_| "vim".to_string());
+    if cli.dry_run {
+        println!(
+            "{} Would open the secrets in {} in {}",
+            "🔍".bright_blue(),
+            store_path.display().to_string().bright_white(),
+            editor.bright_white()
+        );
+        return Ok(());
+    }
+
+    let mut store =
+        SecretStore::load(&store_path).map_err(|e| SlinkyError::Decryption(e.to_string()))?;
+
+    println!("{} Enter passphrase to decrypt secrets:", "🔒".cyan());
+    let passphrase = read_passphrase("passphrase to decrypt secrets")?;
+
+    let changed = edit_store(&mut store, &passphrase, &editor)
+        .map_err(|e| SlinkyError::Secrets(e.to_string()))?;
+    if changed {
+        println!(
+            "{} Secrets re-encrypted to {}",
+            "✓".green(),
+            store_path.display().to_string().bright_white()
+        );
+    } else {
+        println!("{} No changes", "→".cyan());
+    }
+
+    Ok(())
+}
+
+fn decrypt_secrets(in_place: bool, cli: &Cli, config: &Config) -> Result<()> {
+    print_header("Decrypting Secrets");
+
+    let store_path = secrets_store_path(config).map_err(|e| SlinkyError::Secrets(e.to_string()))?;
+    if !store_path.exists() {
+        return Err(SlinkyError::Secrets(format!(
+            "No encrypted secrets found at {}",
+            store_path.display()
+        )));
+    }
+
+    if cli.dry_run {
+        println!(
+            "{} Would restore secrets from {}",
+            "🔍".bright_blue(),
+            store_path.display().to_string().bright_white()
+        );
+        return Ok(());
+    }
+
+    let store =
+        SecretStore::load(&store_path).map_err(|e| SlinkyError::Decryption(e.to_string()))?;
+
+    println!("{} Enter passphrase to decrypt secrets:", "🔒".cyan());
+    let passphrase = read_passphrase("passphrase to decrypt secrets")?;
+
+    let restored = if in_place {
+        restore_in_place(&store, &passphrase)
+    } else {
+        restore_templates(&store, &passphrase)
+    }
+    .map_err(|e| SlinkyError::Decryption(e.to_string()))?;
+
+    if restored.is_empty() {
+        println!("{} Nothing to restore", "→".cyan());
+    }
+    for file in &restored {
+        println!(
+            "  {} Restored {}",
+            "✓".green(),
+            file.display().to_string().bright_white()
+        );
+    }
+
+    Ok(())
+}
+
+/// The `daemon status --json` object. Fields are only ever added, never
+/// renamed or removed, so status-bar scripts can rely on them.
+#[derive(Serialize)]
+struct DaemonStatusReport<'a> {
+    running: bool,
+    pid: Option<u32>,
+    platform: &'static str,
+    init_system: &'static str,
+    service_installed: bool,
+    service_active: bool,
+    auto_sync: &'a AutoSyncConfig,
+    /// Unix time of the daemon's last link, pull or conflict, if any.
+    last_activity: Option<u64>,
+    stats: Option<DaemonStats>,
+}
+
+/// `daemon status` for `--output json` and `--output plain`.
+fn show_daemon_status_report(format: OutputFormat, config: &Config) -> Result<()> {
+    let (running, pid, _) = daemon_status();
+    let (platform, init_system) = get_platform_info();
+    let (service_installed, service_active) = get_service_status().unwrap_or((false, false));
+    let stats = daemon_stats();
+    let report = DaemonStatusReport {
+        running,
+        pid,
+        platform,
+        init_system,
+        service_installed,
+        service_active,
+        auto_sync: &config.auto_sync,
+        last_activity: stats.as_ref().and_then(|s| s.last_activity),
+        stats,
+    };
 
+    if format == OutputFormat::Json {
+        return print_json(&report);
+    }
+    let value = serde_json::to_value(&report).map_err(|e| SlinkyError::Other(e.to_string()))?;
+    let mut rows = Vec::new();
+    flatten_value("", &value, &mut rows);
+    print_plain(&rows);
     Ok(())
 }
 
@@ -1441,7 +4104,54 @@ fn print_header(title: &str) {
     println!("{}\n", "─".repeat(title.len()).dimmed());
 }
 
-fn create_spinner(msg: &str) -> ProgressBar {
+/// A spinner on stderr when it is a terminal. When output is redirected or
+/// `CI` is set, the message is printed once as a plain line instead, and so
+/// is the finishing message, keeping control sequences out of logs.
+struct Spinner {
+    bar: Option<ProgressBar>,
+}
+
+impl Spinner {
+    /// Hands the spinner to `multi` so several can run at once.
+    fn added_to(self, multi: &MultiProgress) -> Self {
+        Self {
+            bar: self.bar.map(|bar| multi.add(bar)),
+        }
+    }
+
+    fn set_message(&self, msg: String) {
+        if let Some(bar) = &self.bar {
+            bar.set_message(msg);
+        }
+    }
+
+    fn finish_with_message(&self, msg: String) {
+        match &self.bar {
+            Some(bar) => bar.finish_with_message(msg),
+            None => eprintln!("{}", msg),
+        }
+    }
+
+    fn finish_and_clear(&self) {
+        if let Some(bar) = &self.bar {
+            bar.finish_and_clear();
+        }
+    }
+
+    fn suspend<R>(&self, f: impl FnOnce() -> R) -> R {
+        match &self.bar {
+            Some(bar) => bar.suspend(f),
+            None => f(),
+        }
+    }
+}
+
+fn create_spinner(msg: &str) -> Spinner {
+    if !io::stderr().is_terminal() || std::env::var_os("CI").is_some() {
+        eprintln!("{}", msg);
+        return Spinner { bar: None };
+    }
+
     let spinner = ProgressBar::new_spinner();
     spinner.set_style(
         ProgressStyle::default_spinner()
@@ -1451,12 +4161,56 @@ fn create_spinner(msg: &str) -> ProgressBar {
     );
     spinner.set_message(msg.to_string());
     spinner.enable_steady_tick(std::time::Duration::from_millis(80));
-    spinner
+    Spinner { bar: Some(spinner) }
+}
+
+fn show_daemon_logs(lines: usize, follow: bool, since: Option<&str>) -> Result<()> {
+    print_header("Daemon Logs");
+
+    let logs = match since {
+        Some(spec) => {
+            let duration = parse_since(spec).ok_or_else(|| {
+                SlinkyError::Other(format!(
+                    "Invalid --since value '{}' (expected e.g. 30m, 1h, 2d)",
+                    spec
+                ))
+            })?;
+            service_logs_since(duration)
+        }
+        None => {
+            if follow {
+                println!(
+                    "{} Follow mode not yet implemented. Showing last {} lines:",
+                    "⚠".yellow(),
+                    lines
+                );
+            }
+            service_logs(lines)
+        }
+    };
+
+    match logs {
+        Ok(content) => {
+            if content.is_empty() || content == "No logs available" {
+                println!("{}", "No logs available".dimmed());
+            } else {
+                println!("{}", content);
+            }
+        }
+        Err(e) => {
+            println!("{} Failed to read logs: {}", "✗".red(), e);
+        }
+    }
+    Ok(())
 }
 
 fn handle_daemon_command(command: &DaemonCommands, cli: &Cli, config: &Config) -> Result<()> {
     match command {
-        DaemonCommands::Start { foreground } => {
+        DaemonCommands::Start { once: true, .. } | DaemonCommands::Run { once: true } => {
+            reconcile_once(cli.stow_dir.clone(), cli.dry_run)
+                .map_err(|e| SlinkyError::Other(e.to_string()))
+        }
+        DaemonCommands::Start { foreground, .. } => {
             if *foreground {
                 print_header("Starting Daemon (Foreground)");
                 println!(
@@ -1471,9 +4225,12 @@ fn handle_daemon_command(command: &DaemonCommands, cli: &Cli, config: &Config) -
                 );
                 println!("{} Press Ctrl+C to stop\n", "→".cyan());
 
-                run_daemon().map_err(|e| SlinkyError::Other(e.to_string()))?;
+                run_daemon(cli.stow_dir.clone()).map_err(|e| SlinkyError::Other(e.to_string()))?;
             } else {
                 print_header("Starting Daemon");
+                if config.git_enabled && config.auto_sync.auto_git_pull {
+                    require_git(config)?;
+                }
 
                 if is_daemon_running() {
                     let pid = get_daemon_pid().unwrap_or(0);
@@ -1491,7 +4248,7 @@ fn handle_daemon_command(command: &DaemonCommands, cli: &Cli, config: &Config) -
                 }
 
                 let spinner = create_spinner("Starting daemon...");
-                match start_daemon_background() {
+                match start_daemon_background(cli.stow_dir.as_deref()) {
                     Ok(pid) => {
                         spinner.finish_with_message(format!(
                             "{} Daemon started (PID: {})",
@@ -1555,7 +4312,54 @@ fn handle_daemon_command(command: &DaemonCommands, cli: &Cli, config: &Config) -
             Ok(())
         }
 
-        DaemonCommands::Status { logs, lines } => {
+        DaemonCommands::Reload => {
+            print_header("Reloading Daemon");
+
+            if cli.dry_run {
+                println!("{} Would reload the daemon's config", "🔍".bright_blue());
+                return Ok(());
+            }
+
+            let response = send_control_command(ControlCommand::Reload)
+                .map_err(|e| SlinkyError::Other(e.to_string()))?;
+            let message = response.message.unwrap_or_default();
+            if !response.ok {
+                return Err(SlinkyError::Other(message));
+            }
+            println!("{} {}", "✓".green(), message);
+            Ok(())
+        }
+
+        DaemonCommands::Sync => {
+            print_header("Syncing Daemon");
+
+            if cli.dry_run {
+                println!(
+                    "{} Would ask the daemon to pull and re-link",
+                    "🔍".bright_blue()
+                );
+                return Ok(());
+            }
+
+            let message = match send_control_command(ControlCommand::SyncNow) {
+                Ok(response) if response.ok => response.message.unwrap_or_default(),
+                Ok(response) => {
+                    return Err(SlinkyError::Other(response.message.unwrap_or_default()))
+                }
+                Err(_) => {
+                    signal_sync().map_err(|e| SlinkyError::Other(e.to_string()))?;
+                    "Sync requested".to_string()
+                }
+            };
+            println!("{} {}", "✓".green(), message);
+            Ok(())
+        }
+
+        DaemonCommands::Status { logs, lines, json } => {
+            let format = output_format(cli, *json);
+            if format != OutputFormat::Table {
+                return show_daemon_status_report(format, config);
+            }
             print_header("Daemon Status");
 
             let (running, pid, log_excerpt) = daemon_status();
@@ -1579,6 +4383,14 @@ fn handle_daemon_command(command: &DaemonCommands, cli: &Cli, config: &Config) -
                 println!("{} Status: {}", "○".dimmed(), "Not running".dimmed());
             }
 
+            if let Some(stats) = daemon_stats() {
+                println!(
+                    "{} Activity: {}",
+                    "→".cyan(),
+                    stats.summary(unix_now()).bright_white()
+                );
+            }
+
             let (installed, service_running) = get_service_status().unwrap_or((false, false));
             if installed {
                 let status = if service_running {
@@ -1679,12 +4491,21 @@ fn handle_daemon_command(command: &DaemonCommands, cli: &Cli, config: &Config) -
             }
 
             if cli.dry_run {
-                println!("{} Would install system service", "🔍".bright_blue());
+                let (path, content) = preview_service(&config.stow_dir)
+                    .map_err(|e| SlinkyError::Other(e.to_string()))?;
+                println!(
+                    "{} Would write service definition to {}:\n",
+                    "🔍".bright_blue(),
+                    path.display().to_string().bright_white()
+                );
+                for line in content.lines() {
+                    println!("  {}", line.dimmed());
+                }
                 return Ok(());
             }
 
             let spinner = create_spinner("Installing service...");
-            match install_service() {
+            match install_service(&config.stow_dir) {
                 Ok(msg) => {
                     spinner.finish_with_message(format!(
                         "{} Service installed and enabled",
@@ -1749,32 +4570,149 @@ fn handle_daemon_command(command: &DaemonCommands, cli: &Cli, config: &Config) -
             Ok(())
         }
 
-        DaemonCommands::Logs { lines, follow } => {
-            print_header("Daemon Logs");
+        DaemonCommands::Logs {
+            lines,
+            follow,
+            since,
+        } => show_daemon_logs(*lines, *follow, since.as_deref()),
 
-            if *follow {
-                println!(
-                    "{} Follow mode not yet implemented. Showing last {} lines:",
-                    "⚠".yellow(),
-                    lines
-                );
-            }
+        DaemonCommands::Run { .. } => {
+            run_daemon(cli.stow_dir.clone()).map_err(|e| SlinkyError::Other(e.to_string()))
+        }
+    }
+}
 
-            match service_logs(*lines) {
-                Ok(content) => {
-                    if content.is_empty() || content == "No logs available" {
-                        println!("{}", "No logs available".dimmed());
-                    } else {
-                        println!("{}", content);
-                    }
-                }
-                Err(e) => {
-                    println!("{} Failed to read logs: {}", "✗".red(), e);
-                }
+fn export_setup(output: Option<&Path>, cli: &Cli, config: &Config) -> Result<()> {
+    let packages = find_packages_with(&config.stow_dir, &config.include_hidden_packages)
+        .map_err(|e| SlinkyError::Stow(e.to_string()))?;
+
+    let mut linked = Vec::new();
+    for package in &packages {
+        let ops = analyze_package_with(
+            &package.path,
+            &config.target_dir,
+            &status_options(cli, config),
+        )
+        .map_err(|e| SlinkyError::Stow(e.to_string()))?;
+        if package_status(&ops).linked > 0 {
+            linked.push(package.name.clone());
+        }
+    }
+    linked.sort();
+
+    let remote = get_remote_url(&config.stow_dir);
+    let target = dirs_home()
+        .filter(|home| *home != config.target_dir)
+        .map(|_| config.target_dir.as_path());
+    let script = export_script(remote.as_deref(), target, &linked);
+
+    match output {
+        Some(path) => {
+            fs::write(path, &script).map_err(SlinkyError::Io)?;
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+                    .map_err(SlinkyError::Io)?;
             }
-            Ok(())
+            println!(
+                "{} Wrote install script to {}",
+                "✓".green(),
+                path.display().to_string().bright_white()
+            );
         }
+        None => print!("{}", script),
+    }
+
+    Ok(())
+}
+
+fn export_script(remote: Option<&str>, target: Option<&Path>, packages: &[String]) -> String {
+    let mut script = String::from("#!/bin/sh\n# Generated by `slnky export`\nset -e\n\n");
+
+    match remote {
+        Some(url) => script.push_str(&format!("slnky --yes install {}\n", shell_quote(url))),
+        None => script.push_str(
+            "# The dotfiles directory has no git remote; copy it to this machine\n\
+             # and run `slnky init --stow-dir <DIR>` before linking.\n",
+        ),
+    }
+
+    let target_arg = target
+        .map(|t| format!(" --target {}", shell_quote(&t.to_string_lossy())))
+        .unwrap_or_default();
+    for package in packages {
+        script.push_str(&format!(
+            "slnky link{} {}\n",
+            target_arg,
+            shell_quote(package)
+        ));
+    }
+
+    script
+}
+
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
 
-        DaemonCommands::Run => run_daemon().map_err(|e| SlinkyError::Other(e.to_string())),
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::set_config_dir;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_link_all_packages_reports_outcomes() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        set_config_dir(std::env::temp_dir().join("slinky_test_cli_config"));
+        let stow_dir = dir.path().join("dotfiles");
+        let home = dir.path().join("home");
+        fs::create_dir_all(&home).unwrap();
+        for (package, file) in [
+            ("zsh", ".zshrc"),
+            ("git", ".gitconfig"),
+            ("tmux", ".tmux.conf"),
+            ("broken", ".brokenrc"),
+        ] {
+            fs::create_dir_all(stow_dir.join(package)).unwrap();
+            fs::write(stow_dir.join(package).join(file), package).unwrap();
+        }
+        std::os::unix::fs::symlink(stow_dir.join("git/.gitconfig"), home.join(".gitconfig"))
+            .unwrap();
+        fs::write(home.join(".tmux.conf"), "local").unwrap();
+        let hook = stow_dir.join("broken/.slinky/hooks.d/pre-link");
+        fs::create_dir_all(hook.parent().unwrap()).unwrap();
+        fs::write(&hook, "#!/bin/sh\nexit 1\n").unwrap();
+        fs::set_permissions(&hook, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let cli = Cli::parse_from(["slnky", "link", "--all"]);
+        let config = Config {
+            stow_dir: stow_dir.clone(),
+            target_dir: home.clone(),
+            ..Config::default()
+        };
+        let report = link_all_packages(false, &[], &cli, &config).unwrap();
+
+        let outcome = |name: &str| {
+            &report
+                .packages
+                .iter()
+                .find(|(package, _)| package == name)
+                .unwrap()
+                .1
+        };
+        assert!(matches!(
+            outcome("zsh"),
+            LinkOutcome::Linked { created: 1, .. }
+        ));
+        assert_eq!(outcome("git"), &LinkOutcome::AlreadyLinked { ignored: 0 });
+        assert!(matches!(outcome("tmux"), LinkOutcome::Conflict(_)));
+        assert!(matches!(outcome("broken"), LinkOutcome::Failed(_)));
+        assert_eq!(report.target, home);
+        assert!(home.join(".zshrc").is_symlink());
+        assert!(!home.join(".brokenrc").exists());
     }
 }
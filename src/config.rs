@@ -1,7 +1,13 @@
 use anyhow::{Context, Result};
+use colored::*;
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use crate::secrets::SecretNameRules;
+use crate::stow::SymlinkMode;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
 #[serde(rename_all = "lowercase")]
@@ -20,10 +26,23 @@ pub struct AutoSyncConfig {
     pub auto_link_new_packages: bool,
     #[serde(default = "default_true")]
     pub auto_git_pull: bool,
+    /// Stash uncommitted changes around the daemon's pull and re-apply them.
+    #[serde(default)]
+    pub autostash: bool,
+    /// How `link --force` replaces conflicting files. The daemon never does.
     #[serde(default)]
     pub conflict_resolution: ConflictResolution,
     #[serde(default = "default_debounce_ms")]
     pub debounce_ms: u64,
+    #[serde(default = "default_true")]
+    pub watch_target: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_sync_command: Option<String>,
+    #[serde(default = "default_post_sync_timeout_secs")]
+    pub post_sync_timeout_secs: u64,
+    /// Scan changed files in the stow dir for secrets and log what is found.
+    #[serde(default)]
+    pub auto_secret_scan: bool,
 }
 
 fn default_true() -> bool {
@@ -34,24 +53,111 @@ fn default_debounce_ms() -> u64 {
     1000
 }
 
+fn default_post_sync_timeout_secs() -> u64 {
+    30
+}
+
 impl Default for AutoSyncConfig {
     fn default() -> Self {
         Self {
             enabled: true,
             auto_link_new_packages: true,
             auto_git_pull: true,
+            autostash: false,
             conflict_resolution: ConflictResolution::Backup,
             debounce_ms: 1000,
+            watch_target: true,
+            post_sync_command: None,
+            post_sync_timeout_secs: 30,
+            auto_secret_scan: false,
         }
     }
 }
 
+const CONFIG_KEYS: &[&str] = &[
+    "include",
+    "stow_dir",
+    "target_dir",
+    "packages",
+    "include_hidden_packages",
+    "secrets_enabled",
+    "max_depth",
+    "min_free_space_mb",
+    "git_timeout_secs",
+    "dir_mode",
+    "package_symlinks",
+    "secrets_store_path",
+    "secrets_armor",
+    "secret_patterns",
+    "secret_ignore_names",
+    "require_apply",
+    "git_enabled",
+    "auto_sync",
+];
+
+const AUTO_SYNC_KEYS: &[&str] = &[
+    "enabled",
+    "auto_link_new_packages",
+    "auto_git_pull",
+    "autostash",
+    "conflict_resolution",
+    "debounce_ms",
+    "watch_target",
+    "post_sync_command",
+    "post_sync_timeout_secs",
+    "auto_secret_scan",
+];
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Config {
+    /// Further config files merged in before this one, relative to the file
+    /// naming them; later files override earlier ones and the including file
+    /// overrides them all.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<PathBuf>,
     pub stow_dir: PathBuf,
     pub target_dir: PathBuf,
     pub packages: Vec<String>,
+    /// Top-level dot-directories of the stow dir to treat as packages (for
+    /// example `.config`); other dot-directories such as `.github` are not.
+    pub include_hidden_packages: Vec<String>,
     pub secrets_enabled: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_depth: Option<usize>,
+    pub min_free_space_mb: u64,
+    /// Seconds a network git command (clone, pull) may run before it is
+    /// killed; 0 waits indefinitely.
+    pub git_timeout_secs: u64,
+    /// Octal permissions (e.g. `"0700"`) for directories created while
+    /// linking; unset leaves them to the umask. Unix only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dir_mode: Option<String>,
+    /// Whether symlinks inside packages are linked as they are (`link`) or
+    /// to their resolved destination (`deref`).
+    pub package_symlinks: SymlinkMode,
+    /// Where `secrets encrypt` writes the age store; relative paths are
+    /// inside `stow_dir`. Defaults to the data directory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secrets_store_path: Option<PathBuf>,
+    /// Write the store as ASCII-armored (PEM-style) text instead of binary
+    /// age, so it diffs cleanly in git. Either format is read back.
+    pub secrets_armor: bool,
+    /// Extra sensitive-name patterns (regexes, e.g. `_PAT$`, `DSN`) checked
+    /// along with the built-in ones when scanning for secrets.
+    pub secret_patterns: Vec<String>,
+    /// Variable names never reported as secrets, for known false positives
+    /// such as `PASSWORD_MIN_LENGTH`.
+    pub secret_ignore_names: Vec<String>,
+    /// Treat commands that change files as dry runs unless `--apply` or
+    /// `--yes` is passed: `link`, `apply`, `install --link`,
+    /// `migrate-from-stow`, `clean`, `unlink`, `relink`, `sync`,
+    /// `daemon run --once`, `daemon start --once` and
+    /// `secrets encrypt`/`decrypt`/`edit`.
+    pub require_apply: bool,
+    /// Set to false for stow directories that are not git repositories (for
+    /// example a synced folder); every git operation is then skipped.
+    pub git_enabled: bool,
     #[serde(default)]
     pub auto_sync: AutoSyncConfig,
 }
@@ -60,10 +166,23 @@ impl Default for Config {
     fn default() -> Self {
         let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("/"));
         Self {
+            include: Vec::new(),
             stow_dir: home.join(".dotfiles"),
             target_dir: home,
             packages: Vec::new(),
+            include_hidden_packages: Vec::new(),
             secrets_enabled: true,
+            max_depth: None,
+            min_free_space_mb: 100,
+            git_timeout_secs: 300,
+            dir_mode: None,
+            package_symlinks: SymlinkMode::Link,
+            secrets_store_path: None,
+            secrets_armor: false,
+            secret_patterns: Vec::new(),
+            secret_ignore_names: Vec::new(),
+            require_apply: false,
+            git_enabled: true,
             auto_sync: AutoSyncConfig::default(),
         }
     }
@@ -79,18 +198,96 @@ impl Config {
     pub fn save(&self) -> Result<()> {
         save_config(self)
     }
+
+    /// `git_timeout_secs` as a limit, or `None` when it is disabled.
+    pub fn git_timeout(&self) -> Option<Duration> {
+        (self.git_timeout_secs > 0).then(|| Duration::from_secs(self.git_timeout_secs))
+    }
+
+    /// `dir_mode` as permission bits. Invalid values are rejected when the
+    /// config is parsed, so this is only `None` when unset.
+    pub fn dir_mode(&self) -> Option<u32> {
+        self.dir_mode.as_deref().and_then(parse_dir_mode)
+    }
+
+    /// The configured additions to secret scanning.
+    pub fn secret_name_rules(&self) -> SecretNameRules {
+        SecretNameRules {
+            patterns: self.secret_patterns.clone(),
+            ignore_names: self.secret_ignore_names.clone(),
+        }
+    }
+
+    /// Applies `SLINKY_STOW_DIR` and `SLINKY_TARGET_DIR` over the loaded
+    /// values; empty variables are ignored.
+    pub fn apply_env_overrides(&mut self) {
+        if let Some(dir) = env_path(STOW_DIR_ENV) {
+            self.stow_dir = dir;
+        }
+        if let Some(dir) = env_path(TARGET_DIR_ENV) {
+            self.target_dir = dir;
+        }
+    }
+
+    /// Resolves a relative `stow_dir` or `target_dir` against `base`, normally
+    /// the directory slinky was invoked from. Absolute paths are unchanged.
+    pub fn resolve_relative_paths(&mut self, base: &Path) {
+        if self.stow_dir.is_relative() {
+            self.stow_dir = base.join(&self.stow_dir);
+        }
+        if self.target_dir.is_relative() {
+            self.target_dir = base.join(&self.target_dir);
+        }
+    }
+}
+
+/// Overrides `stow_dir`; also how `daemon start` hands `--stow-dir` to the
+/// background daemon.
+pub const STOW_DIR_ENV: &str = "SLINKY_STOW_DIR";
+
+/// Overrides `target_dir`.
+pub const TARGET_DIR_ENV: &str = "SLINKY_TARGET_DIR";
+
+fn env_path(name: &str) -> Option<PathBuf> {
+    std::env::var_os(name)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+static CONFIG_DIR_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Makes `config_dir()`, and every path derived from it, use `dir` for the
+/// rest of the process (`--config-dir`). Only the first call has an effect.
+pub fn set_config_dir(dir: PathBuf) {
+    let _ = CONFIG_DIR_OVERRIDE.set(dir);
+}
+
+/// The directory given with `--config-dir`, if any.
+pub fn config_dir_override() -> Option<&'static Path> {
+    CONFIG_DIR_OVERRIDE.get().map(PathBuf::as_path)
 }
 
 pub fn config_path() -> PathBuf {
-    let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("/"));
-    home.join(".config").join("slinky").join("config.toml")
+    config_dir().join("config.toml")
 }
 
 pub fn config_dir() -> PathBuf {
+    if let Some(dir) = config_dir_override() {
+        return dir.to_path_buf();
+    }
     let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("/"));
     home.join(".config").join("slinky")
 }
 
+/// Where the encrypted secrets store lives: the `--config-dir` override when
+/// given, otherwise `slinky` under the platform's local data directory.
+pub fn secrets_dir() -> Option<PathBuf> {
+    if let Some(dir) = config_dir_override() {
+        return Some(dir.to_path_buf());
+    }
+    directories::BaseDirs::new().map(|dirs| dirs.data_local_dir().join("slinky"))
+}
+
 pub fn daemon_pid_path() -> PathBuf {
     config_dir().join("daemon.pid")
 }
@@ -99,17 +296,37 @@ pub fn daemon_log_path() -> PathBuf {
     config_dir().join("daemon.log")
 }
 
+pub fn daemon_stats_path() -> PathBuf {
+    config_dir().join("daemon.stats")
+}
+
+pub fn daemon_socket_path() -> PathBuf {
+    config_dir().join("daemon.sock")
+}
+
+/// Where slinky records when each package was last linked.
+pub fn state_path() -> PathBuf {
+    config_dir().join("state.toml")
+}
+
+/// The usual places for a dotfiles directory under `home`, most likely first.
+pub fn stow_dir_candidates(home: &Path) -> Vec<PathBuf> {
+    [
+        ".dotfiles",
+        "dotfiles",
+        ".config/dotfiles",
+        "code/dotfiles",
+        "projects/dotfiles",
+    ]
+    .iter()
+    .map(|dir| home.join(dir))
+    .collect()
+}
+
 pub fn auto_detect_stow_dir() -> Option<PathBuf> {
     let home = dirs::home_dir()?;
-    let candidates = [
-        home.join(".dotfiles"),
-        home.join("dotfiles"),
-        home.join(".config/dotfiles"),
-        home.join("code/dotfiles"),
-        home.join("projects/dotfiles"),
-    ];
-
-    for candidate in candidates {
+
+    for candidate in stow_dir_candidates(&home) {
         if candidate.exists() && candidate.is_dir() {
             if candidate.join(".git").exists() {
                 return Some(candidate);
@@ -143,15 +360,198 @@ pub fn load_config() -> Result<Config> {
         return Ok(config);
     }
 
-    let contents = fs::read_to_string(&path)
+    let mut unknown_keys = Vec::new();
+    let value = read_config_value(&path, &mut Vec::new(), &mut unknown_keys)?;
+    let config = config_from_value(value)?;
+    for (file, key) in unknown_keys {
+        eprintln!(
+            "{} Unknown config key '{}' in {} (ignored)",
+            "⚠".yellow(),
+            key,
+            file.display()
+        );
+    }
+
+    Ok(config)
+}
+
+#[cfg(test)]
+fn parse_config(contents: &str) -> Result<(Config, Vec<String>)> {
+    let value: toml::Value =
+        toml::from_str(contents).with_context(|| "Failed to parse config file")?;
+    let unknown = unknown_keys(&value);
+    Ok((config_from_value(value)?, unknown))
+}
+
+/// Reads the config file at `path` with its `include`s merged underneath it.
+/// `chain` holds the files being read further up, to catch include cycles;
+/// each file's unknown keys are added to `unknown` along with its path.
+fn read_config_value(
+    path: &Path,
+    chain: &mut Vec<PathBuf>,
+    unknown: &mut Vec<(PathBuf, String)>,
+) -> Result<toml::Value> {
+    let canonical = fs::canonicalize(path)
+        .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+    if chain.contains(&canonical) {
+        let cycle: Vec<String> = chain
+            .iter()
+            .chain([&canonical])
+            .map(|p| p.display().to_string())
+            .collect();
+        anyhow::bail!("Config include cycle: {}", cycle.join(" -> "));
+    }
+
+    let contents = fs::read_to_string(path)
         .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+    let value: toml::Value = toml::from_str(&contents)
+        .with_context(|| format!("Failed to parse config file: {}", path.display()))?;
+    unknown.extend(
+        unknown_keys(&value)
+            .into_iter()
+            .map(|key| (path.to_path_buf(), key)),
+    );
+    let includes = included_paths(&value, path)?;
+    if includes.is_empty() {
+        return Ok(value);
+    }
 
-    let config: Config =
-        toml::from_str(&contents).with_context(|| "Failed to parse config file")?;
+    chain.push(canonical);
+    let mut merged = merge_includes(&includes, chain, unknown)?;
+    chain.pop();
+
+    merge_config_values(&mut merged, value);
+    Ok(merged)
+}
+
+/// The files in `includes` merged in order, without their own `include` keys.
+fn merge_includes(
+    includes: &[PathBuf],
+    chain: &mut Vec<PathBuf>,
+    unknown: &mut Vec<(PathBuf, String)>,
+) -> Result<toml::Value> {
+    let mut merged = toml::Value::Table(toml::map::Map::new());
+    for include in includes {
+        let mut included = read_config_value(include, chain, unknown)?;
+        if let Some(table) = included.as_table_mut() {
+            table.remove("include");
+        }
+        merge_config_values(&mut merged, included);
+    }
+    Ok(merged)
+}
+
+/// The `include` entries of `value`, resolved against the directory of the
+/// file (`path`) they were read from.
+fn included_paths(value: &toml::Value, path: &Path) -> Result<Vec<PathBuf>> {
+    let Some(include) = value.get("include") else {
+        return Ok(Vec::new());
+    };
+    let base = path.parent().unwrap_or(Path::new(""));
+    include
+        .as_array()
+        .with_context(|| format!("'include' must be a list of files in {}", path.display()))?
+        .iter()
+        .map(|entry| {
+            entry
+                .as_str()
+                .map(|file| base.join(file))
+                .with_context(|| format!("'include' must be a list of files in {}", path.display()))
+        })
+        .collect()
+}
+
+/// Merges `overlay` into `base`: tables are merged key by key, anything else
+/// in `overlay` replaces what `base` has.
+fn merge_config_values(base: &mut toml::Value, overlay: toml::Value) {
+    match (base, overlay) {
+        (toml::Value::Table(base), toml::Value::Table(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_config_values(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// What `config`'s includes contribute when it is the file at `path`.
+fn included_config_value(config: &Config, path: &Path) -> Result<toml::Value> {
+    let base = path.parent().unwrap_or(Path::new(""));
+    let includes: Vec<PathBuf> = config.include.iter().map(|file| base.join(file)).collect();
+    let mut chain = vec![fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())];
+    merge_includes(&includes, &mut chain, &mut Vec::new())
+}
+
+/// Removes from `value` every setting that `included` has with the same
+/// value, dropping tables that end up empty.
+fn strip_included_values(value: &mut toml::Value, included: &toml::Value) {
+    let (Some(table), Some(included)) = (value.as_table_mut(), included.as_table()) else {
+        return;
+    };
+    table.retain(|key, value| match included.get(key) {
+        Some(inc) if inc == value => false,
+        Some(inc) if value.is_table() => {
+            strip_included_values(value, inc);
+            value.as_table().is_some_and(|t| !t.is_empty())
+        }
+        _ => true,
+    });
+}
+
+fn config_from_value(value: toml::Value) -> Result<Config> {
+    let config: Config = value
+        .try_into()
+        .with_context(|| "Failed to parse config file")?;
+    if let Some(mode) = &config.dir_mode {
+        if parse_dir_mode(mode).is_none() {
+            anyhow::bail!(
+                "Invalid dir_mode '{}': expected octal such as \"0700\"",
+                mode
+            );
+        }
+    }
 
     Ok(config)
 }
 
+/// Parses an octal mode such as `0700`, `700` or `0o755`.
+fn parse_dir_mode(mode: &str) -> Option<u32> {
+    let digits = mode.trim();
+    let digits = digits.strip_prefix("0o").unwrap_or(digits);
+    u32::from_str_radix(digits, 8)
+        .ok()
+        .filter(|mode| *mode <= 0o7777)
+}
+
+fn unknown_keys(value: &toml::Value) -> Vec<String> {
+    let mut unknown = Vec::new();
+    let Some(table) = value.as_table() else {
+        return unknown;
+    };
+
+    for (key, value) in table {
+        if !CONFIG_KEYS.contains(&key.as_str()) {
+            unknown.push(key.clone());
+        } else if key == "auto_sync" {
+            if let Some(auto_sync) = value.as_table() {
+                unknown.extend(
+                    auto_sync
+                        .keys()
+                        .filter(|k| !AUTO_SYNC_KEYS.contains(&k.as_str()))
+                        .map(|k| format!("auto_sync.{}", k)),
+                );
+            }
+        }
+    }
+
+    unknown
+}
+
 pub fn save_config(config: &Config) -> Result<()> {
     let path = config_path();
 
@@ -160,7 +560,18 @@ pub fn save_config(config: &Config) -> Result<()> {
             .with_context(|| format!("Failed to create config directory: {}", parent.display()))?;
     }
 
-    let contents = toml::to_string_pretty(config).with_context(|| "Failed to serialize config")?;
+    let contents = match included_config_value(config, &path) {
+        // Leave settings the included files already provide to them, so
+        // saving does not copy every included value into this file.
+        Ok(included) if !config.include.is_empty() => {
+            let mut value =
+                toml::Value::try_from(config).with_context(|| "Failed to serialize config")?;
+            strip_included_values(&mut value, &included);
+            toml::to_string_pretty(&value)
+        }
+        _ => toml::to_string_pretty(config),
+    }
+    .with_context(|| "Failed to serialize config")?;
 
     fs::write(&path, contents)
         .with_context(|| format!("Failed to write config file: {}", path.display()))?;
@@ -168,10 +579,116 @@ pub fn save_config(config: &Config) -> Result<()> {
     Ok(())
 }
 
-mod dirs {
+pub(crate) mod dirs {
     use std::path::PathBuf;
 
     pub fn home_dir() -> Option<PathBuf> {
-        std::env::var_os("HOME").map(PathBuf::from)
+        std::env::var_os("HOME")
+            .filter(|home| !home.is_empty())
+            .map(PathBuf::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unknown_keys_are_reported() {
+        let contents = r#"
+stowdir = "/home/user/.dotfiles"
+target_dir = "/home/user"
+packages = []
+secrets_enabled = true
+
+[auto_sync]
+enabled = false
+debounce = 500
+"#;
+
+        let (config, unknown) = parse_config(contents).unwrap();
+
+        assert_eq!(unknown, vec!["auto_sync.debounce", "stowdir"]);
+        assert_eq!(config.target_dir, PathBuf::from("/home/user"));
+        assert!(!config.auto_sync.enabled);
+    }
+
+    #[test]
+    fn test_relative_paths_resolve_against_base() {
+        let (mut config, _) =
+            parse_config("stow_dir = \"/srv/dotfiles\"\ntarget_dir = \"sandbox\"\n").unwrap();
+        config.resolve_relative_paths(Path::new("/work/project"));
+
+        assert_eq!(config.stow_dir, PathBuf::from("/srv/dotfiles"));
+        assert_eq!(config.target_dir, PathBuf::from("/work/project/sandbox"));
+    }
+
+    #[test]
+    fn test_dir_mode_is_octal() {
+        let (config, _) = parse_config("dir_mode = \"0700\"\n").unwrap();
+        assert_eq!(config.dir_mode(), Some(0o700));
+        assert_eq!(Config::default().dir_mode(), None);
+
+        for invalid in ["\"0799\"", "\"\"", "\"17777\""] {
+            assert!(parse_config(&format!("dir_mode = {}\n", invalid)).is_err());
+        }
+    }
+
+    #[test]
+    fn test_includes_merge_under_main_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let main = dir.path().join("config.toml");
+        fs::write(
+            &main,
+            "include = [\"work.toml\", \"personal.toml\"]\ntarget_dir = \"/home/me\"\n\n[auto_sync]\nenabled = false\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("work.toml"),
+            "stow_dir = \"/srv/work\"\npackages = [\"git\"]\n\n[auto_sync]\ndebounce_ms = 500\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("personal.toml"),
+            "packages = [\"zsh\"]\nstowdir = \"typo\"\n",
+        )
+        .unwrap();
+
+        let mut unknown = Vec::new();
+        let value = read_config_value(&main, &mut Vec::new(), &mut unknown).unwrap();
+        let config = config_from_value(value).unwrap();
+        assert_eq!(
+            unknown,
+            vec![(dir.path().join("personal.toml"), "stowdir".to_string())]
+        );
+        assert_eq!(config.stow_dir, PathBuf::from("/srv/work"));
+        assert_eq!(config.target_dir, PathBuf::from("/home/me"));
+        assert_eq!(config.packages, ["zsh"]);
+        assert!(!config.auto_sync.enabled);
+        assert_eq!(config.auto_sync.debounce_ms, 500);
+
+        let mut saved = toml::Value::try_from(&config).unwrap();
+        strip_included_values(&mut saved, &included_config_value(&config, &main).unwrap());
+        assert!(saved.get("stow_dir").is_none());
+        assert_eq!(saved["target_dir"].as_str(), Some("/home/me"));
+        assert!(saved["auto_sync"].get("debounce_ms").is_none());
+    }
+
+    #[test]
+    fn test_include_cycle_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.toml"), "include = [\"b.toml\"]\n").unwrap();
+        fs::write(dir.path().join("b.toml"), "include = [\"a.toml\"]\n").unwrap();
+
+        let error = read_config_value(&dir.path().join("a.toml"), &mut Vec::new(), &mut Vec::new())
+            .unwrap_err();
+        assert!(error.to_string().contains("include cycle"), "{}", error);
+    }
+
+    #[test]
+    fn test_known_keys_produce_no_warnings() {
+        let contents = toml::to_string_pretty(&Config::default()).unwrap();
+        let (_, unknown) = parse_config(&contents).unwrap();
+        assert!(unknown.is_empty());
     }
 }
@@ -1,18 +1,32 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fs::{self, File, OpenOptions};
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 use std::process::{self, Command, Stdio};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
-use std::time::Duration;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use notify::{RecommendedWatcher, RecursiveMode, Watcher};
 use notify_debouncer_full::{new_debouncer, DebounceEventResult, Debouncer, FileIdMap};
-use tokio::sync::mpsc;
-
-use crate::config::{daemon_log_path, daemon_pid_path, load_config, Config, ConflictResolution};
-use crate::stow::{analyze_package, execute_operations, find_packages, OpType};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::config::{
+    config_dir_override, daemon_log_path, daemon_pid_path, daemon_socket_path, daemon_stats_path,
+    load_config, state_path, Config, STOW_DIR_ENV,
+};
+use crate::remote::{
+    changed_files, check_git_installed, get_head, git_command, output_with_timeout,
+};
+use crate::secrets::{
+    is_template_file, scan_file_for_secrets_since, ScanMark, Secret, SecretNameRules,
+};
+use crate::state::record_package_link;
+use crate::stow::{
+    analyze_package_with, execute_operations_with, find_orphaned_links, find_packages_with,
+    is_package_name, AnalyzeOptions, OpType, StowError,
+};
 
 #[derive(Debug)]
 pub enum DaemonError {
@@ -20,6 +34,7 @@ pub enum DaemonError {
     NotRunning,
     Io(std::io::Error),
     Config(String),
+    Git(String),
     #[allow(dead_code)]
     Watch(String),
 }
@@ -31,6 +46,7 @@ impl std::fmt::Display for DaemonError {
             DaemonError::NotRunning => write!(f, "Daemon is not running"),
             DaemonError::Io(e) => write!(f, "IO error: {}", e),
             DaemonError::Config(s) => write!(f, "Config error: {}", s),
+            DaemonError::Git(s) => write!(f, "{}", s),
             DaemonError::Watch(s) => write!(f, "Watch error: {}", s),
         }
     }
@@ -44,14 +60,215 @@ impl From<std::io::Error> for DaemonError {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug)]
 pub enum DaemonEvent {
     DotfileChanged(PathBuf),
     NewPackage(String),
     GitChanged,
     SymlinkDeleted(PathBuf),
+    /// The watcher for `path` could not start and its thread has stopped.
+    WatchFailed {
+        path: PathBuf,
+        error: DaemonError,
+    },
     #[allow(dead_code)]
     Shutdown,
+    /// A command read from the control socket, answered through `reply`.
+    Control {
+        command: ControlCommand,
+        reply: oneshot::Sender<ControlResponse>,
+    },
+}
+
+/// Commands the daemon accepts on its control socket, one per line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlCommand {
+    Status,
+    SyncNow,
+    Reload,
+    Shutdown,
+}
+
+impl ControlCommand {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ControlCommand::Status => "status",
+            ControlCommand::SyncNow => "sync-now",
+            ControlCommand::Reload => "reload",
+            ControlCommand::Shutdown => "shutdown",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "status" => Some(ControlCommand::Status),
+            "sync-now" => Some(ControlCommand::SyncNow),
+            "reload" => Some(ControlCommand::Reload),
+            "shutdown" => Some(ControlCommand::Shutdown),
+            _ => None,
+        }
+    }
+}
+
+/// The JSON line the daemon answers a control command with.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ControlResponse {
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stats: Option<DaemonStats>,
+}
+
+impl ControlResponse {
+    fn ok(message: impl Into<String>) -> Self {
+        Self {
+            ok: true,
+            message: Some(message.into()),
+            ..Default::default()
+        }
+    }
+
+    fn error(message: impl Into<String>) -> Self {
+        Self {
+            ok: false,
+            message: Some(message.into()),
+            ..Default::default()
+        }
+    }
+}
+
+/// How long a client waits for the daemon to answer; the daemon only reads
+/// commands between syncs, so this covers a slow pull.
+const CONTROL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Extra time, on top of the debounce interval, during which events for
+/// paths the daemon itself touched are dropped.
+const SELF_WRITE_GRACE: Duration = Duration::from_millis(500);
+
+/// Paths the daemon has recently written, shared with the watcher threads so
+/// they can drop the events those writes trigger. A recorded directory
+/// covers everything under it.
+#[derive(Debug, Clone)]
+pub struct RecentWrites {
+    window: Duration,
+    paths: Arc<Mutex<HashMap<PathBuf, Instant>>>,
+}
+
+impl RecentWrites {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            paths: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn record(&self, path: &Path) {
+        if let Ok(mut paths) = self.paths.lock() {
+            let now = Instant::now();
+            paths.retain(|_, at| now.duration_since(*at) < self.window);
+            paths.insert(path.to_path_buf(), now);
+        }
+    }
+
+    pub fn is_recent(&self, path: &Path) -> bool {
+        let Ok(paths) = self.paths.lock() else {
+            return false;
+        };
+        path.ancestors()
+            .filter_map(|ancestor| paths.get(ancestor))
+            .any(|at| at.elapsed() < self.window)
+    }
+}
+
+/// Records what the daemon's own pull wrote in `stow_dir`: everything in
+/// `.git`, and the worktree files that changed since the `before` commit.
+/// Without this the pull's writes come back as `GitChanged`, which would
+/// schedule another pull.
+fn record_pull_writes(recent_writes: &RecentWrites, stow_dir: &Path, before: Option<&str>) {
+    recent_writes.record(&stow_dir.join(".git"));
+    let (Some(before), Some(after)) = (before, get_head(stow_dir)) else {
+        return;
+    };
+    if let Ok(files) = changed_files(stow_dir, before, &after) {
+        for file in files {
+            recent_writes.record(&stow_dir.join(file));
+        }
+    }
+}
+
+/// Running totals kept in `daemon.stats` so `daemon status` can summarize
+/// what the daemon has done without parsing its log. Reset on each start.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DaemonStats {
+    pub started_at: u64,
+    pub links_created: u64,
+    /// Pulls that brought in changes.
+    pub pulls: u64,
+    pub conflicts: u64,
+    pub errors: u64,
+    pub last_activity: Option<u64>,
+}
+
+impl DaemonStats {
+    pub fn load(path: &Path) -> Option<Self> {
+        serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+    }
+
+    /// Writes through a temporary file so readers never see a partial file.
+    pub fn save(&self, path: &Path) -> Result<(), DaemonError> {
+        let json = serde_json::to_string(self).map_err(|e| DaemonError::Io(e.into()))?;
+        let tmp = path.with_extension("stats.tmp");
+        fs::write(&tmp, json)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    fn record_link(&mut self, result: &Result<usize, StowError>) {
+        match result {
+            Ok(created) => self.links_created += *created as u64,
+            Err(
+                StowError::ConflictDetected(_)
+                | StowError::OwnedByPackage { .. }
+                | StowError::ForeignSymlink { .. },
+            ) => self.conflicts += 1,
+            Err(_) => self.errors += 1,
+        }
+    }
+
+    /// One line such as "42 links created, 3 pulls, 1 conflict, last
+    /// activity 2m ago".
+    pub fn summary(&self, now: u64) -> String {
+        let plural =
+            |n: u64, word: &str| format!("{} {}{}", n, word, if n == 1 { "" } else { "s" });
+        let mut parts = vec![
+            format!("{} created", plural(self.links_created, "link")),
+            plural(self.pulls, "pull"),
+            plural(self.conflicts, "conflict"),
+        ];
+        if self.errors > 0 {
+            parts.push(plural(self.errors, "error"));
+        }
+        parts.push(match self.last_activity {
+            Some(at) => format!(
+                "last activity {} ago",
+                format_elapsed(now.saturating_sub(at))
+            ),
+            None => "no activity yet".to_string(),
+        });
+        parts.join(", ")
+    }
+}
+
+pub fn format_elapsed(secs: u64) -> String {
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m", secs / 60),
+        3600..=86399 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86400),
+    }
 }
 
 pub struct DaemonState {
@@ -60,19 +277,44 @@ pub struct DaemonState {
     known_packages: HashSet<String>,
     running: Arc<AtomicBool>,
     log_file: Option<File>,
+    /// How far each changed file has been scanned for secrets.
+    secret_scans: HashMap<PathBuf, ScanMark>,
 }
 
 impl DaemonState {
     pub fn new(config: Config) -> Self {
-        let known_packages = find_packages(&config.stow_dir)
-            .map(|pkgs| pkgs.into_iter().map(|p| p.name).collect())
-            .unwrap_or_default();
+        let known_packages = package_names(&config).into_iter().collect();
 
         Self {
             config,
             known_packages,
             running: Arc::new(AtomicBool::new(true)),
             log_file: None,
+            secret_scans: HashMap::new(),
+        }
+    }
+
+    /// Scans what was written to `path` since its last scan and logs each
+    /// secret found.
+    fn scan_changed_file(&mut self, path: &Path, rules: &SecretNameRules) -> Vec<Secret> {
+        let since = self.secret_scans.get(path).copied();
+        match scan_file_for_secrets_since(path, rules, since) {
+            Ok((secrets, mark)) => {
+                self.secret_scans.insert(path.to_path_buf(), mark);
+                for secret in &secrets {
+                    self.log(&format!(
+                        "WARNING: possible secret {} in {}:{}",
+                        secret.name,
+                        path.display(),
+                        secret.line_number
+                    ));
+                }
+                secrets
+            }
+            Err(_) => {
+                self.secret_scans.remove(path);
+                Vec::new()
+            }
         }
     }
 
@@ -103,16 +345,69 @@ impl DaemonState {
     }
 }
 
+/// Names of every package in the stow dir, empty if it can't be read.
+fn package_names(config: &Config) -> Vec<String> {
+    find_packages_with(&config.stow_dir, &config.include_hidden_packages)
+        .map(|pkgs| pkgs.into_iter().map(|p| p.name).collect())
+        .unwrap_or_default()
+}
+
 fn chrono_lite_now() -> String {
+    format_log_timestamp(unix_now())
+}
+
+pub fn unix_now() -> u64 {
     use std::time::{SystemTime, UNIX_EPOCH};
-    let duration = SystemTime::now()
+    SystemTime::now()
         .duration_since(UNIX_EPOCH)
-        .unwrap_or_default();
-    let secs = duration.as_secs();
-    let hours = (secs / 3600) % 24;
-    let mins = (secs / 60) % 60;
-    let s = secs % 60;
-    format!("{:02}:{:02}:{:02}", hours, mins, s)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Formats seconds since the epoch as `YYYY-MM-DD HH:MM:SS` in UTC.
+pub fn format_log_timestamp(secs: u64) -> String {
+    let days = secs / 86400;
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        (secs / 3600) % 24,
+        (secs / 60) % 60,
+        secs % 60
+    )
+}
+
+/// Reads the timestamp from a `[YYYY-MM-DD HH:MM:SS] message` log line.
+pub fn parse_log_timestamp(line: &str) -> Option<u64> {
+    let stamp = line.strip_prefix('[')?.get(..19)?;
+    let field = |range: std::ops::Range<usize>| stamp.get(range)?.parse::<u64>().ok();
+
+    let (year, month, day) = (field(0..4)?, field(5..7)?, field(8..10)?);
+    let (hours, mins, secs) = (field(11..13)?, field(14..16)?, field(17..19)?);
+    if year < 1970 || !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    let y = year - u64::from(month <= 2);
+    let era = y / 400;
+    let yoe = y - era * 400;
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+
+    Some(days * 86400 + hours * 3600 + mins * 60 + secs)
 }
 
 fn should_ignore_path(path: &Path) -> bool {
@@ -169,44 +464,29 @@ fn is_git_dir_change(path: &Path, stow_dir: &Path) -> bool {
     path.starts_with(&git_dir)
 }
 
-fn get_package_from_path(path: &Path, stow_dir: &Path) -> Option<String> {
+fn get_package_from_path(
+    path: &Path,
+    stow_dir: &Path,
+    include_hidden: &[String],
+) -> Option<String> {
     let relative = path.strip_prefix(stow_dir).ok()?;
     let first_component = relative.components().next()?;
     let name = first_component.as_os_str().to_string_lossy().to_string();
 
-    if name.starts_with('.') {
+    if !is_package_name(&name, include_hidden) {
         return None;
     }
 
     Some(name)
 }
 
-fn backup_file(path: &Path) -> Result<PathBuf, std::io::Error> {
-    let backup_path = PathBuf::from(format!("{}.backup", path.display()));
-    fs::copy(path, &backup_path)?;
-    Ok(backup_path)
-}
-
-fn handle_conflict(target: &Path, resolution: ConflictResolution) -> Result<bool, std::io::Error> {
-    match resolution {
-        ConflictResolution::Backup => {
-            if target.exists() && !target.is_symlink() {
-                backup_file(target)?;
-                fs::remove_file(target)?;
-            }
-            Ok(true)
-        }
-        ConflictResolution::Skip => Ok(false),
-        ConflictResolution::Overwrite => {
-            if target.exists() {
-                if target.is_dir() && !target.is_symlink() {
-                    fs::remove_dir_all(target)?;
-                } else {
-                    fs::remove_file(target)?;
-                }
-            }
-            Ok(true)
-        }
+/// Whether `target` was modified more recently than `source`, meaning edits
+/// made in place would be lost by replacing it.
+pub fn target_is_newer(target: &Path, source: &Path) -> bool {
+    let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+    match (modified(target), modified(source)) {
+        (Some(target), Some(source)) => target > source,
+        _ => false,
     }
 }
 
@@ -231,7 +511,7 @@ pub fn get_daemon_pid() -> Option<u32> {
     }
 }
 
-fn is_process_running(pid: u32) -> bool {
+pub fn is_process_running(pid: u32) -> bool {
     #[cfg(unix)]
     {
         let result = Command::new("kill")
@@ -277,9 +557,23 @@ pub fn is_daemon_running() -> bool {
     get_daemon_pid().is_some()
 }
 
+/// Asks the daemon to shut down over its control socket, falling back to
+/// terminating the process.
 pub fn stop_daemon() -> Result<(), DaemonError> {
     let pid = get_daemon_pid().ok_or(DaemonError::NotRunning)?;
 
+    if send_control_command(ControlCommand::Shutdown).is_ok_and(|response| response.ok) {
+        // The daemon removes its pid file last thing before exiting.
+        let pid_path = daemon_pid_path();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while pid_path.exists() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        if !pid_path.exists() {
+            return Ok(());
+        }
+    }
+
     #[cfg(unix)]
     {
         let status = Command::new("kill")
@@ -310,7 +604,127 @@ pub fn stop_daemon() -> Result<(), DaemonError> {
     Ok(())
 }
 
-pub fn start_daemon_background() -> Result<u32, DaemonError> {
+/// Sends `command` to the running daemon's control socket and waits for the
+/// answer. `DaemonError::NotRunning` means nothing is listening.
+pub fn send_control_command(command: ControlCommand) -> Result<ControlResponse, DaemonError> {
+    send_control_command_to(&daemon_socket_path(), command)
+}
+
+#[cfg(unix)]
+fn send_control_command_to(
+    socket_path: &Path,
+    command: ControlCommand,
+) -> Result<ControlResponse, DaemonError> {
+    use std::io::{BufRead, BufReader};
+    use std::os::unix::net::UnixStream;
+
+    let mut stream = UnixStream::connect(socket_path).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound | std::io::ErrorKind::ConnectionRefused => {
+            DaemonError::NotRunning
+        }
+        _ => DaemonError::Io(e),
+    })?;
+    stream.set_read_timeout(Some(CONTROL_TIMEOUT))?;
+    writeln!(stream, "{}", command.as_str())?;
+
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+    serde_json::from_str(&line).map_err(|e| DaemonError::Io(e.into()))
+}
+
+#[cfg(not(unix))]
+fn send_control_command_to(
+    _socket_path: &Path,
+    _command: ControlCommand,
+) -> Result<ControlResponse, DaemonError> {
+    Err(DaemonError::NotRunning)
+}
+
+/// Asks the running daemon to pull and re-link now by sending it SIGUSR1,
+/// for when its control socket can't be reached.
+pub fn signal_sync() -> Result<(), DaemonError> {
+    let pid = get_daemon_pid().ok_or(DaemonError::NotRunning)?;
+
+    #[cfg(unix)]
+    {
+        let status = Command::new("kill")
+            .args(["-USR1", &pid.to_string()])
+            .status()?;
+        if !status.success() {
+            return Err(DaemonError::Io(std::io::Error::other(
+                "Failed to send SIGUSR1",
+            )));
+        }
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = pid;
+        Err(DaemonError::Io(std::io::Error::other(
+            "Triggering a sync needs the control socket, which is Unix-only",
+        )))
+    }
+}
+
+/// Binds the control socket, replacing one left behind by a daemon that did
+/// not shut down cleanly. Only called once no daemon holds the pid file.
+#[cfg(unix)]
+fn bind_control_socket(path: &Path) -> Result<tokio::net::UnixListener, DaemonError> {
+    if path.exists() || path.is_symlink() {
+        fs::remove_file(path)?;
+    }
+    Ok(tokio::net::UnixListener::bind(path)?)
+}
+
+/// Accepts control connections and forwards each command to the main loop.
+#[cfg(unix)]
+fn spawn_control_listener(listener: tokio::net::UnixListener, tx: mpsc::Sender<DaemonEvent>) {
+    tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    tokio::spawn(serve_control_connection(stream, tx.clone()));
+                }
+                Err(_) => tokio::time::sleep(Duration::from_millis(100)).await,
+            }
+        }
+    });
+}
+
+/// Reads one command line and writes back one JSON response line.
+#[cfg(unix)]
+async fn serve_control_connection(stream: tokio::net::UnixStream, tx: mpsc::Sender<DaemonEvent>) {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    let (read, mut write) = stream.into_split();
+    let mut line = String::new();
+    if BufReader::new(read).read_line(&mut line).await.is_err() {
+        return;
+    }
+
+    let response = match ControlCommand::parse(line.trim()) {
+        Some(command) => {
+            let (reply, response) = oneshot::channel();
+            match tx.send(DaemonEvent::Control { command, reply }).await {
+                Ok(()) => response
+                    .await
+                    .unwrap_or_else(|_| ControlResponse::error("daemon is shutting down")),
+                Err(_) => ControlResponse::error("daemon is shutting down"),
+            }
+        }
+        None => ControlResponse::error(format!("unknown command '{}'", line.trim())),
+    };
+
+    if let Ok(mut json) = serde_json::to_string(&response) {
+        json.push('\n');
+        let _ = write.write_all(json.as_bytes()).await;
+    }
+}
+
+/// Spawns `slnky daemon run` detached. A `stow_dir` override is passed to it
+/// through `SLINKY_STOW_DIR`.
+pub fn start_daemon_background(stow_dir: Option<&Path>) -> Result<u32, DaemonError> {
     if let Some(pid) = get_daemon_pid() {
         return Err(DaemonError::AlreadyRunning(pid));
     }
@@ -327,7 +741,14 @@ pub fn start_daemon_background() -> Result<u32, DaemonError> {
         .append(true)
         .open(&log_path)?;
 
-    let child = Command::new(&exe)
+    let mut command = Command::new(&exe);
+    if let Some(dir) = config_dir_override() {
+        command.arg("--config-dir").arg(dir);
+    }
+    if let Some(dir) = stow_dir {
+        command.env(STOW_DIR_ENV, dir);
+    }
+    let child = command
         .args(["daemon", "run"])
         .stdin(Stdio::null())
         .stdout(Stdio::from(log_file.try_clone()?))
@@ -341,13 +762,139 @@ pub fn start_daemon_background() -> Result<u32, DaemonError> {
     Ok(pid)
 }
 
+/// Does one pass of what the daemon does continuously, then returns: pull
+/// (when auto-pull is on), re-link every package, prune orphaned symlinks and
+/// run `post_sync_command`. No watcher is started and no pid file is written,
+/// so this suits cron jobs and systemd timers. With `dry_run` nothing is
+/// pulled, linked, pruned or run, and the log file is left alone; the pass
+/// only reports what it would do.
 #[tokio::main]
-pub async fn run_daemon() -> Result<(), DaemonError> {
+pub async fn reconcile_once(stow_dir: Option<PathBuf>, dry_run: bool) -> Result<(), DaemonError> {
+    let mut config = load_config().map_err(|e| DaemonError::Config(e.to_string()))?;
+    config.apply_env_overrides();
+    if let Some(dir) = stow_dir {
+        config.stow_dir = dir;
+    }
+
+    if !config.stow_dir.exists() {
+        return Err(DaemonError::Config(format!(
+            "Stow directory does not exist: {}",
+            config.stow_dir.display()
+        )));
+    }
+
+    let mut state = DaemonState::new(config.clone());
+    if dry_run {
+        state.log("Previewing one reconcile pass (dry run)...");
+    } else {
+        state.open_log()?;
+        state.log("Reconciling once...");
+    }
+
+    let pulls = config.git_enabled
+        && config.auto_sync.auto_git_pull
+        && config.stow_dir.join(".git").exists();
+    if pulls && dry_run {
+        state.log("Would pull latest changes");
+    } else if pulls {
+        check_git_installed().map_err(|e| DaemonError::Git(e.to_string()))?;
+        state.log("Pulling latest changes...");
+        match git_pull(
+            &config.stow_dir,
+            config.auto_sync.autostash,
+            config.git_timeout(),
+        ) {
+            Ok(PullOutcome::Updated) => state.log("Git pull completed with changes"),
+            Ok(PullOutcome::UpToDate) => state.log("Already up to date"),
+            Ok(PullOutcome::StashConflict) => state.log(
+                "WARNING: re-applying local changes after the pull conflicted; \
+                 they are kept in `git stash`",
+            ),
+            Err(e) => state.log(&format!("Git pull failed: {}", e)),
+        }
+    }
+
+    let recent_writes = RecentWrites::new(Duration::ZERO);
+    let mut relink_failed = false;
+    for package in
+        find_packages_with(&config.stow_dir, &config.include_hidden_packages).unwrap_or_default()
+    {
+        match link_package_auto(
+            &package.path,
+            &config.target_dir,
+            &config,
+            &recent_writes,
+            dry_run,
+        ) {
+            Ok(count) if count > 0 => {
+                state.log(&format!(
+                    "{} package '{}': {} symlinks",
+                    if dry_run {
+                        "Would re-link"
+                    } else {
+                        "Re-linked"
+                    },
+                    package.name,
+                    count
+                ));
+            }
+            Ok(_) => {}
+            Err(e) => {
+                relink_failed = true;
+                state.log(&format!("Failed to re-link '{}': {}", package.name, e));
+            }
+        }
+    }
+
+    match find_orphaned_links(
+        &config.stow_dir,
+        &config.target_dir,
+        &config.include_hidden_packages,
+    ) {
+        Ok(orphans) => {
+            for orphan in orphans {
+                if dry_run {
+                    state.log(&format!(
+                        "Would prune orphaned symlink: {}",
+                        orphan.display()
+                    ));
+                    continue;
+                }
+                match fs::remove_file(&orphan) {
+                    Ok(()) => state.log(&format!("Pruned orphaned symlink: {}", orphan.display())),
+                    Err(e) => state.log(&format!("Failed to prune {}: {}", orphan.display(), e)),
+                }
+            }
+        }
+        Err(e) => state.log(&format!("Failed to look for orphaned symlinks: {}", e)),
+    }
+
+    if !relink_failed {
+        if let Some(command) = &config.auto_sync.post_sync_command {
+            if dry_run {
+                state.log(&format!("Would run post-sync command: {}", command));
+                return Ok(());
+            }
+            let timeout = Duration::from_secs(config.auto_sync.post_sync_timeout_secs);
+            run_post_sync_command(&mut state, command, timeout).await;
+        }
+    }
+
+    state.log("Reconcile finished");
+    Ok(())
+}
+
+#[tokio::main]
+pub async fn run_daemon(stow_dir: Option<PathBuf>) -> Result<(), DaemonError> {
     if let Some(pid) = get_daemon_pid() {
         return Err(DaemonError::AlreadyRunning(pid));
     }
 
-    let config = load_config().map_err(|e| DaemonError::Config(e.to_string()))?;
+    let mut config = load_config().map_err(|e| DaemonError::Config(e.to_string()))?;
+    config.apply_env_overrides();
+    if let Some(dir) = stow_dir {
+        config.stow_dir = dir;
+    }
 
     if !config.auto_sync.enabled {
         return Err(DaemonError::Config(
@@ -362,6 +909,10 @@ pub async fn run_daemon() -> Result<(), DaemonError> {
         )));
     }
 
+    if config.git_enabled && config.auto_sync.auto_git_pull {
+        check_git_installed().map_err(|e| DaemonError::Git(e.to_string()))?;
+    }
+
     write_pid_file()?;
 
     let mut state = DaemonState::new(config.clone());
@@ -402,129 +953,108 @@ pub async fn run_daemon() -> Result<(), DaemonError> {
 
     let (tx, mut rx) = mpsc::channel::<DaemonEvent>(100);
 
-    let stow_dir = config.stow_dir.clone();
-    let target_dir = config.target_dir.clone();
-    let debounce_duration = Duration::from_millis(config.auto_sync.debounce_ms);
-
-    let tx_watcher = tx.clone();
-    let stow_dir_watcher = stow_dir.clone();
-
-    let (debouncer_tx, mut debouncer_rx) = mpsc::channel::<DebounceEventResult>(100);
-
-    std::thread::spawn(move || {
-        let rt = tokio::runtime::Builder::new_current_thread()
-            .enable_all()
-            .build()
-            .expect("Failed to build runtime");
-
-        rt.block_on(async move {
-            let mut debouncer: Debouncer<RecommendedWatcher, FileIdMap> = new_debouncer(
-                debounce_duration,
-                None,
-                move |result: DebounceEventResult| {
-                    let _ = debouncer_tx.blocking_send(result);
-                },
-            )
-            .expect("Failed to create debouncer");
-
-            debouncer
-                .watcher()
-                .watch(&stow_dir_watcher, RecursiveMode::Recursive)
-                .expect("Failed to watch stow directory");
-
-            debouncer
-                .cache()
-                .add_root(&stow_dir_watcher, RecursiveMode::Recursive);
-
-            while let Some(result) = debouncer_rx.recv().await {
-                match result {
-                    Ok(events) => {
-                        for event in events {
-                            for path in &event.paths {
-                                if should_ignore_path(path) {
-                                    continue;
-                                }
+    #[cfg(unix)]
+    let socket_path = daemon_socket_path();
+    #[cfg(unix)]
+    match bind_control_socket(&socket_path) {
+        Ok(listener) => spawn_control_listener(listener, tx.clone()),
+        Err(e) => state.log(&format!(
+            "WARNING: control socket {} unavailable: {}",
+            socket_path.display(),
+            e
+        )),
+    }
 
-                                if is_git_dir_change(path, &stow_dir_watcher) {
-                                    let _ = tx_watcher.send(DaemonEvent::GitChanged).await;
-                                } else if let Some(pkg) =
-                                    get_package_from_path(path, &stow_dir_watcher)
-                                {
-                                    let _ = tx_watcher
-                                        .send(DaemonEvent::DotfileChanged(path.clone()))
-                                        .await;
-                                    let _ = tx_watcher.send(DaemonEvent::NewPackage(pkg)).await;
-                                }
-                            }
-                        }
-                    }
-                    Err(errors) => {
-                        for error in errors {
-                            eprintln!("Watch error: {:?}", error);
-                        }
-                    }
+    // SIGUSR1 is the fallback trigger for `daemon sync` when the socket is
+    // unreachable; nobody waits for the reply. It is registered before the
+    // daemon reports that it started, since the default action terminates.
+    #[cfg(unix)]
+    {
+        let sync_tx = tx.clone();
+        let mut sigusr1 =
+            tokio::signal::unix::signal(tokio::signal::unix::SignalKind::user_defined1())?;
+        tokio::spawn(async move {
+            while sigusr1.recv().await.is_some() {
+                let (reply, _) = oneshot::channel();
+                let command = ControlCommand::SyncNow;
+                if sync_tx
+                    .send(DaemonEvent::Control { command, reply })
+                    .await
+                    .is_err()
+                {
+                    break;
                 }
             }
         });
-    });
-
-    let tx_target = tx.clone();
-    let target_dir_watcher = target_dir.clone();
-
-    std::thread::spawn(move || {
-        let (target_debouncer_tx, target_debouncer_rx) =
-            std::sync::mpsc::channel::<DebounceEventResult>();
+    }
 
-        let mut target_debouncer: Debouncer<RecommendedWatcher, FileIdMap> = new_debouncer(
-            debounce_duration,
-            None,
-            move |result: DebounceEventResult| {
-                let _ = target_debouncer_tx.send(result);
-            },
-        )
-        .expect("Failed to create target debouncer");
-
-        target_debouncer
-            .watcher()
-            .watch(&target_dir_watcher, RecursiveMode::NonRecursive)
-            .expect("Failed to watch target directory");
+    let stow_dir = config.stow_dir.clone();
+    let target_dir = config.target_dir.clone();
+    let debounce_duration = Duration::from_millis(config.auto_sync.debounce_ms);
 
-        while let Ok(result) = target_debouncer_rx.recv() {
-            if let Ok(events) = result {
-                for event in events {
-                    use notify::EventKind;
-                    if matches!(event.kind, EventKind::Remove(_)) {
-                        for path in &event.paths {
-                            if path.is_symlink()
-                                || (!path.exists()
-                                    && path
-                                        .file_name()
-                                        .map(|n| !n.to_string_lossy().starts_with('.'))
-                                        .unwrap_or(false))
-                            {
-                                let _ = tx_target
-                                    .blocking_send(DaemonEvent::SymlinkDeleted(path.clone()));
-                            }
-                        }
-                    }
-                }
-            }
+    let recent_writes = RecentWrites::new(debounce_duration + SELF_WRITE_GRACE);
+
+    spawn_stow_watcher(
+        stow_dir.clone(),
+        debounce_duration,
+        tx.clone(),
+        recent_writes.clone(),
+        config.git_enabled,
+        config.include_hidden_packages.clone(),
+    );
+
+    if config.auto_sync.watch_target {
+        if !target_dir.is_dir() {
+            state.log(&format!(
+                "WARNING: target directory {} does not exist; it will be watched once it is created",
+                target_dir.display()
+            ));
         }
-    });
+        spawn_target_watcher(
+            target_dir.clone(),
+            debounce_duration,
+            tx.clone(),
+            recent_writes.clone(),
+            running.clone(),
+            TARGET_DIR_POLL,
+        );
+    } else {
+        state.log("Target watcher disabled; deleted symlinks will not be repaired automatically");
+    }
 
     state.log("Daemon started successfully");
 
     let mut git_pull_pending = false;
+    let mut git_pull_paused = false;
     let mut packages_to_relink: HashSet<String> = HashSet::new();
+    let mut post_sync_due: Option<Instant> = None;
+    let mut fatal_error: Option<DaemonError> = None;
+
+    let stats_path = daemon_stats_path();
+    let mut stats = DaemonStats {
+        started_at: unix_now(),
+        ..Default::default()
+    };
+    let mut stats_dirty = true;
 
     while running.load(Ordering::SeqCst) {
         tokio::select! {
             Some(event) = rx.recv() => {
+                if !matches!(event, DaemonEvent::Control { .. }) {
+                    stats.last_activity = Some(unix_now());
+                    stats_dirty = true;
+                }
                 match event {
                     DaemonEvent::DotfileChanged(path) => {
+                        // The package itself is queued by the `NewPackage`
+                        // event that accompanies every change.
                         state.log(&format!("File changed: {}", path.display()));
-                        if let Some(pkg) = get_package_from_path(&path, &stow_dir) {
-                            packages_to_relink.insert(pkg);
+                        if config.secrets_enabled
+                            && config.auto_sync.auto_secret_scan
+                            && path.is_file()
+                            && !is_template_file(&path)
+                        {
+                            state.scan_changed_file(&path, &config.secret_name_rules());
                         }
                     }
                     DaemonEvent::NewPackage(name) => {
@@ -535,7 +1065,9 @@ pub async fn run_daemon() -> Result<(), DaemonError> {
                             if config.auto_sync.auto_link_new_packages {
                                 let pkg_path = stow_dir.join(&name);
                                 if pkg_path.is_dir() {
-                                    match link_package_auto(&pkg_path, &target_dir, &config) {
+                                    let result = link_package_auto(&pkg_path, &target_dir, &config, &recent_writes, false);
+                                    stats.record_link(&result);
+                                    match result {
                                         Ok(count) => {
                                             state.log(&format!(
                                                 "Auto-linked package '{}': {} symlinks",
@@ -556,38 +1088,109 @@ pub async fn run_daemon() -> Result<(), DaemonError> {
                         }
                     }
                     DaemonEvent::GitChanged => {
-                        if config.auto_sync.auto_git_pull && !git_pull_pending {
+                        if config.git_enabled
+                            && config.auto_sync.auto_git_pull
+                            && !git_pull_pending
+                            && !git_pull_paused
+                        {
                             git_pull_pending = true;
                             state.log("Git change detected, scheduling pull...");
                         }
                     }
                     DaemonEvent::SymlinkDeleted(path) => {
                         state.log(&format!("Symlink deleted: {}", path.display()));
-                        for pkg in find_packages(&stow_dir).unwrap_or_default() {
-                            packages_to_relink.insert(pkg.name);
+                        packages_to_relink.extend(package_names(&config));
+                    }
+                    DaemonEvent::WatchFailed { path, error } => {
+                        state.log(&format!("ERROR: {}", error));
+                        if path == stow_dir {
+                            // Without the stow watcher nothing would ever sync,
+                            // so stop rather than keep running idle.
+                            state.log("Stow directory is no longer watched, shutting down");
+                            fatal_error = Some(error);
+                            running.store(false, Ordering::SeqCst);
                         }
                     }
                     DaemonEvent::Shutdown => {
                         state.log("Shutdown requested");
                         running.store(false, Ordering::SeqCst);
                     }
+                    DaemonEvent::Control { command, reply } => {
+                        let response = match command {
+                            ControlCommand::Status => ControlResponse {
+                                ok: true,
+                                pid: Some(process::id()),
+                                stats: Some(stats.clone()),
+                                ..Default::default()
+                            },
+                            ControlCommand::SyncNow => {
+                                state.log("Sync requested");
+                                if config.git_enabled
+                                    && config.auto_sync.auto_git_pull
+                                    && !git_pull_paused
+                                {
+                                    git_pull_pending = true;
+                                }
+                                packages_to_relink.extend(package_names(&config));
+                                ControlResponse::ok("Sync scheduled")
+                            }
+                            ControlCommand::Reload => match load_config() {
+                                Ok(mut reloaded) => {
+                                    // The watchers are bound to the original
+                                    // directories, so those stay as they are.
+                                    reloaded.stow_dir = stow_dir.clone();
+                                    reloaded.target_dir = target_dir.clone();
+                                    config = reloaded;
+                                    state.log("Config reloaded");
+                                    ControlResponse::ok(
+                                        "Config reloaded (stow_dir and target_dir changes need a restart)",
+                                    )
+                                }
+                                Err(e) => {
+                                    state.log(&format!("Failed to reload config: {}", e));
+                                    ControlResponse::error(format!("Failed to reload config: {}", e))
+                                }
+                            },
+                            ControlCommand::Shutdown => {
+                                state.log("Shutdown requested");
+                                running.store(false, Ordering::SeqCst);
+                                ControlResponse::ok("Daemon stopping")
+                            }
+                        };
+                        let _ = reply.send(response);
+                    }
                 }
             }
             _ = tokio::time::sleep(Duration::from_secs(2)) => {
                 if git_pull_pending {
                     git_pull_pending = false;
                     state.log("Pulling latest changes...");
-                    match git_pull(&stow_dir) {
-                        Ok(true) => {
+                    let before = get_head(&stow_dir);
+                    // Recorded up front too, for events delivered while the
+                    // pull is still running.
+                    recent_writes.record(&stow_dir.join(".git"));
+                    let pulled = git_pull(&stow_dir, config.auto_sync.autostash, config.git_timeout());
+                    record_pull_writes(&recent_writes, &stow_dir, before.as_deref());
+                    match pulled {
+                        Ok(PullOutcome::Updated) => {
+                            stats.pulls += 1;
                             state.log("Git pull completed with changes, re-linking all packages");
-                            for pkg in find_packages(&stow_dir).unwrap_or_default() {
-                                packages_to_relink.insert(pkg.name);
-                            }
+                            packages_to_relink.extend(package_names(&config));
                         }
-                        Ok(false) => {
+                        Ok(PullOutcome::UpToDate) => {
                             state.log("Already up to date");
                         }
+                        Ok(PullOutcome::StashConflict) => {
+                            git_pull_paused = true;
+                            state.log(
+                                "WARNING: re-applying local changes after the pull conflicted; \
+                                 they are kept in `git stash`. Auto-pull is paused until the \
+                                 conflicts are resolved and the daemon is restarted",
+                            );
+                            packages_to_relink.extend(package_names(&config));
+                        }
                         Err(e) => {
+                            stats.errors += 1;
                             state.log(&format!("Git pull failed: {}", e));
                         }
                     }
@@ -595,10 +1198,13 @@ pub async fn run_daemon() -> Result<(), DaemonError> {
 
                 if !packages_to_relink.is_empty() {
                     let packages: Vec<String> = packages_to_relink.drain().collect();
+                    let mut relink_failed = false;
                     for pkg_name in packages {
                         let pkg_path = stow_dir.join(&pkg_name);
                         if pkg_path.is_dir() {
-                            match link_package_auto(&pkg_path, &target_dir, &config) {
+                            let result = link_package_auto(&pkg_path, &target_dir, &config, &recent_writes, false);
+                            stats.record_link(&result);
+                            match result {
                                 Ok(count) if count > 0 => {
                                     state.log(&format!(
                                         "Re-linked package '{}': {} symlinks",
@@ -607,6 +1213,7 @@ pub async fn run_daemon() -> Result<(), DaemonError> {
                                 }
                                 Ok(_) => {}
                                 Err(e) => {
+                                    relink_failed = true;
                                     state.log(&format!(
                                         "Failed to re-link '{}': {}",
                                         pkg_name, e
@@ -615,73 +1222,381 @@ pub async fn run_daemon() -> Result<(), DaemonError> {
                             }
                         }
                     }
+
+                    if !relink_failed && config.auto_sync.post_sync_command.is_some() {
+                        post_sync_due = Some(Instant::now() + POST_SYNC_DEBOUNCE);
+                    }
+                }
+
+                if post_sync_due.is_some_and(|due| Instant::now() >= due) {
+                    post_sync_due = None;
+                    if let Some(command) = &config.auto_sync.post_sync_command {
+                        let timeout = Duration::from_secs(config.auto_sync.post_sync_timeout_secs);
+                        run_post_sync_command(&mut state, command, timeout).await;
+                    }
+                }
+
+                if stats_dirty {
+                    stats_dirty = false;
+                    if let Err(e) = stats.save(&stats_path) {
+                        state.log(&format!("Failed to write {}: {}", stats_path.display(), e));
+                    }
                 }
             }
         }
     }
 
     state.log("Daemon shutting down...");
+    #[cfg(unix)]
+    let _ = fs::remove_file(&socket_path);
     remove_pid_file();
     state.log("Daemon stopped");
 
-    Ok(())
+    match fatal_error {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+/// Watches `stow_dir` recursively on a background thread, turning file
+/// changes into package and git events. Failures to start are reported as
+/// `DaemonEvent::WatchFailed` for the main loop to act on.
+fn spawn_stow_watcher(
+    stow_dir: PathBuf,
+    debounce: Duration,
+    tx: mpsc::Sender<DaemonEvent>,
+    recent_writes: RecentWrites,
+    git_enabled: bool,
+    include_hidden: Vec<String>,
+) {
+    std::thread::spawn(move || {
+        let report = |error: String| {
+            let _ = tx.blocking_send(DaemonEvent::WatchFailed {
+                path: stow_dir.clone(),
+                error: DaemonError::Watch(error),
+            });
+        };
+
+        let rt = match tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+        {
+            Ok(rt) => rt,
+            Err(e) => return report(format!("failed to build watcher runtime: {}", e)),
+        };
+
+        let (debouncer_tx, mut debouncer_rx) = mpsc::channel::<DebounceEventResult>(100);
+        let mut debouncer: Debouncer<RecommendedWatcher, FileIdMap> =
+            match new_debouncer(debounce, None, move |result: DebounceEventResult| {
+                let _ = debouncer_tx.blocking_send(result);
+            }) {
+                Ok(debouncer) => debouncer,
+                Err(e) => return report(format!("failed to create stow watcher: {}", e)),
+            };
+
+        if let Err(e) = debouncer
+            .watcher()
+            .watch(&stow_dir, RecursiveMode::Recursive)
+        {
+            return report(format!(
+                "failed to watch stow directory {}: {}",
+                stow_dir.display(),
+                e
+            ));
+        }
+        debouncer
+            .cache()
+            .add_root(&stow_dir, RecursiveMode::Recursive);
+
+        rt.block_on(async move {
+            while let Some(result) = debouncer_rx.recv().await {
+                match result {
+                    Ok(events) => {
+                        let paths = events.iter().flat_map(|event| &event.paths);
+                        for event in batch_events(
+                            paths,
+                            &stow_dir,
+                            &recent_writes,
+                            git_enabled,
+                            &include_hidden,
+                        ) {
+                            let _ = tx.send(event).await;
+                        }
+                    }
+                    Err(errors) => {
+                        for error in errors {
+                            eprintln!("Watch error: {:?}", error);
+                        }
+                    }
+                }
+            }
+            drop(debouncer);
+        });
+    });
+}
+
+/// Turns one debounced batch of changed paths into daemon events. Editors
+/// often report a save several times (write, rename, chmod), so each path
+/// yields one `DotfileChanged`, each package one `NewPackage`, and the batch
+/// at most one `GitChanged`.
+fn batch_events<'a>(
+    paths: impl IntoIterator<Item = &'a PathBuf>,
+    stow_dir: &Path,
+    recent_writes: &RecentWrites,
+    git_enabled: bool,
+    include_hidden: &[String],
+) -> Vec<DaemonEvent> {
+    let mut events = Vec::new();
+    let mut seen_paths = HashSet::new();
+    let mut seen_packages = HashSet::new();
+    let mut git_changed = false;
+
+    for path in paths {
+        if should_ignore_path(path) || recent_writes.is_recent(path) || !seen_paths.insert(path) {
+            continue;
+        }
+
+        if is_git_dir_change(path, stow_dir) {
+            if git_enabled && !git_changed {
+                git_changed = true;
+                events.push(DaemonEvent::GitChanged);
+            }
+        } else if let Some(pkg) = get_package_from_path(path, stow_dir, include_hidden) {
+            events.push(DaemonEvent::DotfileChanged(path.clone()));
+            if seen_packages.insert(pkg.clone()) {
+                events.push(DaemonEvent::NewPackage(pkg));
+            }
+        }
+    }
+
+    events
+}
+
+/// How often the target watcher checks whether a missing target directory
+/// has been created.
+const TARGET_DIR_POLL: Duration = Duration::from_secs(2);
+
+/// Watches `target_dir` (non-recursively) for deleted symlinks on a
+/// background thread. A target directory that does not exist yet is polled
+/// for until it appears or the daemon stops; failures to start watching are
+/// reported as `DaemonEvent::WatchFailed` instead of panicking the thread.
+fn spawn_target_watcher(
+    target_dir: PathBuf,
+    debounce: Duration,
+    tx: mpsc::Sender<DaemonEvent>,
+    recent_writes: RecentWrites,
+    running: Arc<AtomicBool>,
+    poll: Duration,
+) {
+    std::thread::spawn(move || {
+        while !target_dir.is_dir() {
+            if !running.load(Ordering::SeqCst) {
+                return;
+            }
+            std::thread::sleep(poll);
+        }
+
+        let (debouncer_tx, debouncer_rx) = std::sync::mpsc::channel::<DebounceEventResult>();
+
+        let mut debouncer: Debouncer<RecommendedWatcher, FileIdMap> =
+            match new_debouncer(debounce, None, move |result: DebounceEventResult| {
+                let _ = debouncer_tx.send(result);
+            }) {
+                Ok(debouncer) => debouncer,
+                Err(e) => {
+                    let _ = tx.blocking_send(DaemonEvent::WatchFailed {
+                        path: target_dir,
+                        error: DaemonError::Watch(format!(
+                            "failed to create target watcher: {}",
+                            e
+                        )),
+                    });
+                    return;
+                }
+            };
+
+        if let Err(e) = debouncer
+            .watcher()
+            .watch(&target_dir, RecursiveMode::NonRecursive)
+        {
+            let error = DaemonError::Watch(format!(
+                "failed to watch target directory {}: {}",
+                target_dir.display(),
+                e
+            ));
+            let _ = tx.blocking_send(DaemonEvent::WatchFailed {
+                path: target_dir,
+                error,
+            });
+            return;
+        }
+
+        while let Ok(result) = debouncer_rx.recv() {
+            if let Ok(events) = result {
+                for event in events {
+                    use notify::EventKind;
+                    if matches!(event.kind, EventKind::Remove(_)) {
+                        for path in &event.paths {
+                            if recent_writes.is_recent(path) {
+                                continue;
+                            }
+                            if path.is_symlink()
+                                || (!path.exists()
+                                    && path
+                                        .file_name()
+                                        .map(|n| !n.to_string_lossy().starts_with('.'))
+                                        .unwrap_or(false))
+                            {
+                                let _ = tx.blocking_send(DaemonEvent::SymlinkDeleted(path.clone()));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    });
+}
+
+/// How long the daemon waits after the last re-link pass before running
+/// `post_sync_command`, so a burst of re-links results in one invocation.
+const POST_SYNC_DEBOUNCE: Duration = Duration::from_secs(5);
+
+async fn run_post_sync_command(state: &mut DaemonState, command: &str, timeout: Duration) {
+    state.log(&format!("Running post-sync command: {}", command));
+
+    #[cfg(unix)]
+    let mut cmd = tokio::process::Command::new("sh");
+    #[cfg(unix)]
+    cmd.args(["-c", command]);
+
+    #[cfg(windows)]
+    let mut cmd = tokio::process::Command::new("cmd");
+    #[cfg(windows)]
+    cmd.args(["/C", command]);
+
+    cmd.stdin(Stdio::null()).kill_on_drop(true);
+
+    match tokio::time::timeout(timeout, cmd.output()).await {
+        Ok(Ok(output)) => {
+            for line in String::from_utf8_lossy(&output.stdout).lines() {
+                state.log(&format!("post-sync: {}", line));
+            }
+            for line in String::from_utf8_lossy(&output.stderr).lines() {
+                state.log(&format!("post-sync: {}", line));
+            }
+            if output.status.success() {
+                state.log("Post-sync command completed");
+            } else {
+                state.log(&format!("Post-sync command failed: {}", output.status));
+            }
+        }
+        Ok(Err(e)) => state.log(&format!("Failed to run post-sync command: {}", e)),
+        Err(_) => state.log(&format!(
+            "Post-sync command timed out after {}s and was killed",
+            timeout.as_secs()
+        )),
+    }
 }
 
 fn link_package_auto(
     package_path: &Path,
     target_dir: &Path,
     config: &Config,
-) -> Result<usize, String> {
-    let operations = analyze_package(package_path, target_dir).map_err(|e| e.to_string())?;
+    recent_writes: &RecentWrites,
+    dry_run: bool,
+) -> Result<usize, StowError> {
+    let options = AnalyzeOptions {
+        max_depth: config.max_depth,
+        symlinks: config.package_symlinks,
+        ..Default::default()
+    };
+    let operations = analyze_package_with(package_path, target_dir, &options)?;
 
     for op in &operations {
-        if matches!(op.op_type, OpType::Create) && op.target.exists() {
-            match handle_conflict(&op.target, config.auto_sync.conflict_resolution) {
-                Ok(true) => {}
-                Ok(false) => continue,
-                Err(e) => {
-                    eprintln!(
-                        "Conflict resolution failed for {}: {}",
-                        op.target.display(),
-                        e
-                    );
-                    continue;
-                }
-            }
+        if !matches!(op.op_type, OpType::Skip(_)) {
+            recent_writes.record(&op.target);
         }
     }
 
-    let results = execute_operations(&operations, false).map_err(|e| e.to_string())?;
+    let results = execute_operations_with(&operations, dry_run, config.dir_mode(), |_, _, _| {})?;
+    if dry_run {
+        return Ok(results
+            .iter()
+            .filter(|r| r.contains("Would create symlink"))
+            .count());
+    }
     let created = results
         .iter()
         .filter(|r| r.contains("Created symlink"))
         .count();
 
+    if created > 0 {
+        if let Some(name) = package_path.file_name() {
+            let _ = record_package_link(&state_path(), &name.to_string_lossy(), unix_now());
+        }
+    }
+
     Ok(created)
 }
 
-fn git_pull(repo_path: &Path) -> Result<bool, String> {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PullOutcome {
+    Updated,
+    UpToDate,
+    /// The pull fast-forwarded, but re-applying the autostash conflicted and
+    /// the local changes were left in the stash.
+    StashConflict,
+}
+
+fn git_pull(
+    repo_path: &Path,
+    autostash: bool,
+    timeout: Option<Duration>,
+) -> Result<PullOutcome, String> {
     let git_dir = repo_path.join(".git");
     if !git_dir.exists() {
         return Err("Not a git repository".to_string());
     }
 
-    let output = Command::new("git")
-        .current_dir(repo_path)
-        .args(["pull", "--ff-only"])
-        .output()
+    let mut args = vec!["pull", "--ff-only"];
+    if autostash {
+        args.push("--autostash");
+    }
+
+    // The outcome is read from the repository rather than git's messages,
+    // which are translated under a non-English locale.
+    let before = get_head(repo_path);
+    let output = output_with_timeout(git_command().current_dir(repo_path).args(&args), timeout)
         .map_err(|e| e.to_string())?;
 
+    // A conflicting autostash is left as unmerged paths in the worktree.
+    if autostash && has_unmerged_paths(repo_path) {
+        return Ok(PullOutcome::StashConflict);
+    }
+
     if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        if stderr.contains("Already up to date") {
-            return Ok(false);
-        }
-        return Err(stderr.to_string());
+        return Err(String::from_utf8_lossy(&output.stderr).to_string());
     }
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    Ok(!stdout.contains("Already up to date"))
+    if get_head(repo_path) == before {
+        Ok(PullOutcome::UpToDate)
+    } else {
+        Ok(PullOutcome::Updated)
+    }
+}
+
+fn has_unmerged_paths(repo_path: &Path) -> bool {
+    git_command()
+        .current_dir(repo_path)
+        .args(["ls-files", "--unmerged"])
+        .stderr(Stdio::null())
+        .output()
+        .is_ok_and(|output| output.status.success() && !output.stdout.is_empty())
+}
+
+/// The stats written by the running (or last) daemon, if any.
+pub fn daemon_stats() -> Option<DaemonStats> {
+    DaemonStats::load(&daemon_stats_path())
 }
 
 pub fn daemon_status() -> (bool, Option<u32>, Option<String>) {
@@ -711,3 +1626,348 @@ pub fn daemon_status() -> (bool, Option<u32>, Option<String>) {
 
     (running, pid, log_excerpt)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .current_dir(dir)
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .unwrap();
+        assert!(status.success(), "git {:?} failed", args);
+    }
+
+    #[test]
+    fn test_secret_scan_picks_up_appended_secret() {
+        let dir = tempfile::tempdir().unwrap();
+        let stow_dir = dir.path().join("dotfiles");
+        fs::create_dir_all(&stow_dir).unwrap();
+        let file = stow_dir.join(".zshrc");
+        fs::write(&file, "alias ll='ls -l'\nexport API_KEY=abc123\n").unwrap();
+
+        let mut state = DaemonState::new(Config {
+            stow_dir,
+            ..Config::default()
+        });
+        let rules = SecretNameRules::default();
+        let names = |secrets: Vec<Secret>| -> Vec<(String, usize)> {
+            secrets
+                .into_iter()
+                .map(|secret| (secret.name, secret.line_number))
+                .collect()
+        };
+
+        assert_eq!(
+            names(state.scan_changed_file(&file, &rules)),
+            [("API_KEY".to_string(), 2)]
+        );
+
+        let mut appended = OpenOptions::new().append(true).open(&file).unwrap();
+        appended
+            .write_all(b"export GITHUB_TOKEN=ghp_123\n")
+            .unwrap();
+        assert_eq!(
+            names(state.scan_changed_file(&file, &rules)),
+            [("GITHUB_TOKEN".to_string(), 3)]
+        );
+        assert!(state.scan_changed_file(&file, &rules).is_empty());
+
+        fs::write(&file, "export PASSWORD=hunter2\n").unwrap();
+        assert_eq!(
+            names(state.scan_changed_file(&file, &rules)),
+            [("PASSWORD".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_control_socket_roundtrip() {
+        use std::io::{BufRead, BufReader};
+
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("daemon.sock");
+        fs::write(&socket, "stale").unwrap();
+
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let (tx, mut rx) = mpsc::channel(1);
+        rt.block_on(async {
+            spawn_control_listener(bind_control_socket(&socket).unwrap(), tx);
+        });
+        rt.spawn(async move {
+            while let Some(DaemonEvent::Control { command, reply }) = rx.recv().await {
+                let _ = reply.send(ControlResponse::ok(command.as_str()));
+            }
+        });
+
+        for command in [
+            ControlCommand::Status,
+            ControlCommand::SyncNow,
+            ControlCommand::Reload,
+            ControlCommand::Shutdown,
+        ] {
+            assert_eq!(ControlCommand::parse(command.as_str()), Some(command));
+            assert_eq!(
+                send_control_command_to(&socket, command).unwrap(),
+                ControlResponse::ok(command.as_str())
+            );
+        }
+
+        let mut stream = std::os::unix::net::UnixStream::connect(&socket).unwrap();
+        writeln!(stream, "restart").unwrap();
+        let mut line = String::new();
+        BufReader::new(stream).read_line(&mut line).unwrap();
+        let response: ControlResponse = serde_json::from_str(&line).unwrap();
+        assert_eq!(
+            response,
+            ControlResponse::error("unknown command 'restart'")
+        );
+
+        assert!(matches!(
+            send_control_command_to(&dir.path().join("missing.sock"), ControlCommand::Status),
+            Err(DaemonError::NotRunning)
+        ));
+    }
+
+    #[test]
+    fn test_git_pull_autostash() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let upstream = temp_dir.path().join("upstream");
+        let local = temp_dir.path().join("local");
+        fs::create_dir_all(&upstream).unwrap();
+        git(&upstream, &["init", "-q"]);
+        fs::write(upstream.join("rc"), "one\ntwo\nthree\nfour\nfive\n").unwrap();
+        git(&upstream, &["add", "."]);
+        git(&upstream, &["commit", "-qm", "init"]);
+        git(
+            temp_dir.path(),
+            &["clone", "-q", upstream.to_str().unwrap(), "local"],
+        );
+
+        fs::write(local.join("rc"), "one\ntwo\nthree\nfour\nlocal\n").unwrap();
+        fs::write(upstream.join("rc"), "upstream\ntwo\nthree\nfour\nfive\n").unwrap();
+        git(&upstream, &["commit", "-qam", "update first line"]);
+
+        assert!(git_pull(&local, false, None).is_err());
+        assert_eq!(git_pull(&local, true, None), Ok(PullOutcome::Updated));
+        assert_eq!(
+            fs::read_to_string(local.join("rc")).unwrap(),
+            "upstream\ntwo\nthree\nfour\nlocal\n"
+        );
+        assert_eq!(git_pull(&local, true, None), Ok(PullOutcome::UpToDate));
+
+        fs::write(upstream.join("rc"), "upstream\ntwo\nthree\nfour\nremote\n").unwrap();
+        git(&upstream, &["commit", "-qam", "update last line"]);
+        assert_eq!(git_pull(&local, true, None), Ok(PullOutcome::StashConflict));
+    }
+
+    #[test]
+    fn test_own_pull_does_not_trigger_events() {
+        // Not a tempfile dir: the watcher ignores paths containing ".tmp".
+        let temp_dir = std::env::temp_dir().join("slinky_test_own_pull");
+        let _ = fs::remove_dir_all(&temp_dir);
+        let upstream = temp_dir.join("upstream");
+        let local = temp_dir.join("local");
+        fs::create_dir_all(upstream.join("zsh")).unwrap();
+        git(&upstream, &["init", "-q"]);
+        fs::write(upstream.join("zsh/.zshrc"), "one\n").unwrap();
+        git(&upstream, &["add", "."]);
+        git(&upstream, &["commit", "-qm", "init"]);
+        git(
+            &temp_dir,
+            &["clone", "-q", upstream.to_str().unwrap(), "local"],
+        );
+        fs::write(upstream.join("zsh/.zshrc"), "two\n").unwrap();
+        git(&upstream, &["commit", "-qam", "update"]);
+
+        let recent_writes = RecentWrites::new(Duration::from_secs(5));
+        let before = get_head(&local);
+        assert_eq!(git_pull(&local, false, None), Ok(PullOutcome::Updated));
+        record_pull_writes(&recent_writes, &local, before.as_deref());
+
+        // What the watcher reports for the pull, plus an unrelated edit.
+        let edited = local.join("zsh/.aliases");
+        let paths = [
+            local.join(".git"),
+            local.join(".git/FETCH_HEAD"),
+            local.join("zsh/.zshrc"),
+            edited.clone(),
+        ];
+        let events = batch_events(&paths, &local, &recent_writes, true, &[]);
+        assert!(!events
+            .iter()
+            .any(|event| matches!(event, DaemonEvent::GitChanged)));
+        let changed: Vec<_> = events
+            .iter()
+            .filter_map(|event| match event {
+                DaemonEvent::DotfileChanged(path) => Some(path.clone()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(changed, vec![edited]);
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_target_watcher_waits_for_missing_target_dir() {
+        let temp_dir = std::env::temp_dir().join("slinky_test_missing_target");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).unwrap();
+        let target_dir = temp_dir.join("home");
+
+        let (tx, mut rx) = mpsc::channel(10);
+        let running = Arc::new(AtomicBool::new(true));
+        spawn_target_watcher(
+            target_dir.clone(),
+            Duration::from_millis(50),
+            tx,
+            RecentWrites::new(Duration::ZERO),
+            running.clone(),
+            Duration::from_millis(20),
+        );
+
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(rx.try_recv().is_err());
+
+        fs::create_dir_all(&target_dir).unwrap();
+        std::thread::sleep(Duration::from_millis(200));
+        let link = target_dir.join("zshrc");
+        std::os::unix::fs::symlink(temp_dir.join("missing"), &link).unwrap();
+        std::thread::sleep(Duration::from_millis(200));
+        fs::remove_file(&link).unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut deleted = None;
+        while deleted.is_none() && Instant::now() < deadline {
+            match rx.try_recv() {
+                Ok(DaemonEvent::SymlinkDeleted(path)) => deleted = Some(path),
+                Ok(DaemonEvent::WatchFailed { error, .. }) => panic!("{}", error),
+                _ => std::thread::sleep(Duration::from_millis(20)),
+            }
+        }
+        assert_eq!(deleted, Some(link));
+
+        running.store(false, Ordering::SeqCst);
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_stow_watcher_reports_watch_failure() {
+        let stow_dir = std::env::temp_dir().join("slinky_test_unwatchable_stow");
+        let _ = fs::remove_dir_all(&stow_dir);
+
+        let (tx, mut rx) = mpsc::channel(10);
+        spawn_stow_watcher(
+            stow_dir.clone(),
+            Duration::from_millis(50),
+            tx,
+            RecentWrites::new(Duration::ZERO),
+            true,
+            Vec::new(),
+        );
+
+        match rx.blocking_recv() {
+            Some(DaemonEvent::WatchFailed { path, error }) => {
+                assert_eq!(path, stow_dir);
+                assert!(matches!(error, DaemonError::Watch(_)));
+                assert!(error.to_string().contains("failed to watch stow directory"));
+            }
+            _ => panic!("expected a WatchFailed event"),
+        }
+    }
+
+    #[test]
+    fn test_batch_events_reports_one_save_once() {
+        let stow_dir = PathBuf::from("/dotfiles");
+        let zshrc = stow_dir.join("zsh/.zshrc");
+        let aliases = stow_dir.join("zsh/.aliases");
+        let paths = [zshrc.clone(), zshrc.clone(), zshrc.clone(), aliases.clone()];
+
+        let events = batch_events(
+            &paths,
+            &stow_dir,
+            &RecentWrites::new(Duration::from_secs(1)),
+            true,
+            &[],
+        );
+
+        let changed: Vec<_> = events
+            .iter()
+            .filter_map(|event| match event {
+                DaemonEvent::DotfileChanged(path) => Some(path.clone()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(changed, vec![zshrc, aliases]);
+        let packages = events
+            .iter()
+            .filter(|event| matches!(event, DaemonEvent::NewPackage(name) if name == "zsh"))
+            .count();
+        assert_eq!(packages, 1);
+    }
+
+    #[test]
+    fn test_daemon_stats_round_trip_and_summary() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("daemon.stats");
+        assert_eq!(DaemonStats::load(&path), None);
+
+        let mut stats = DaemonStats {
+            started_at: 1_000,
+            ..Default::default()
+        };
+        assert_eq!(
+            stats.summary(1_000),
+            "0 links created, 0 pulls, 0 conflicts, no activity yet"
+        );
+
+        stats.record_link(&Ok(41));
+        stats.record_link(&Ok(1));
+        stats.record_link(&Err(StowError::ConflictDetected("x".to_string())));
+        stats.pulls = 3;
+        stats.last_activity = Some(1_000);
+        stats.save(&path).unwrap();
+
+        let loaded = DaemonStats::load(&path).unwrap();
+        assert_eq!(loaded, stats);
+        assert_eq!(
+            loaded.summary(1_150),
+            "42 links created, 3 pulls, 1 conflict, last activity 2m ago"
+        );
+    }
+
+    #[test]
+    fn test_log_timestamp_round_trip() {
+        assert_eq!(format_log_timestamp(0), "1970-01-01 00:00:00");
+        assert_eq!(format_log_timestamp(1_709_210_096), "2024-02-29 12:34:56");
+
+        for secs in [0, 951_782_400, 1_709_210_096, 4_102_444_799] {
+            let line = format!("[{}] Re-linked zsh", format_log_timestamp(secs));
+            assert_eq!(parse_log_timestamp(&line), Some(secs));
+        }
+        assert_eq!(parse_log_timestamp("[12:34:56] old format"), None);
+        assert_eq!(parse_log_timestamp("no timestamp"), None);
+    }
+
+    #[test]
+    fn test_recent_writes_suppress_self_triggered_events() {
+        let recent_writes = RecentWrites::new(Duration::from_millis(200));
+        let own = PathBuf::from("/home/user/.zshrc");
+        let other = PathBuf::from("/home/user/.bashrc");
+
+        recent_writes.record(&own);
+
+        assert!(recent_writes.is_recent(&own));
+        assert!(!recent_writes.is_recent(&other));
+
+        std::thread::sleep(Duration::from_millis(250));
+        assert!(!recent_writes.is_recent(&own));
+    }
+}
@@ -1,6 +1,8 @@
 use std::io;
 use thiserror::Error;
 
+use crate::stow::StowError;
+
 #[derive(Error, Debug)]
 pub enum SlinkyError {
     #[error("IO error: {0}")]
@@ -29,8 +31,9 @@ pub enum SlinkyError {
     #[allow(dead_code)]
     TargetNotFound(String),
 
-    #[error("Conflict detected: {0}")]
-    #[allow(dead_code)]
+    /// A `StowError` about a conflicting target, kept apart from `Stow` so
+    /// callers can tell conflicts from other failures.
+    #[error("Stow operation failed: {0}")]
     Conflict(String),
 
     #[error("Git error: {0}")]
@@ -44,12 +47,30 @@ pub enum SlinkyError {
     #[allow(dead_code)]
     Decryption(String),
 
+    #[error("Hook failed: {0}")]
+    Hook(String),
+
     #[error("Parse error: {0}")]
     #[allow(dead_code)]
     Parse(String),
 
+    #[error(
+        "HOME is not set. Set the HOME environment variable to your home directory and try again"
+    )]
+    HomeNotSet,
+
     #[error("{0}")]
     Other(String),
 }
 
+impl From<StowError> for SlinkyError {
+    fn from(error: StowError) -> Self {
+        if error.is_conflict() {
+            SlinkyError::Conflict(error.to_string())
+        } else {
+            SlinkyError::Stow(error.to_string())
+        }
+    }
+}
+
 pub type Result<T> = std::result::Result<T, SlinkyError>;
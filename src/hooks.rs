@@ -0,0 +1,286 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use thiserror::Error;
+
+use crate::config::config_dir;
+
+#[derive(Debug, Error)]
+pub enum HookError {
+    #[error("failed to run hook {path}: {source}")]
+    Spawn {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("hook {path} exited with status {status}")]
+    Failed { path: PathBuf, status: i32 },
+
+    #[error("failed to read hooks directory: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Name of the hooks directory under the config dir.
+pub const HOOKS_DIR: &str = "hooks.d";
+
+/// Hooks directory inside a package. `.slinky` is never linked.
+pub const PACKAGE_HOOKS_DIR: &str = ".slinky/hooks.d";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookEvent {
+    PreLink,
+    PostLink,
+    PreUnlink,
+    PostUnlink,
+}
+
+impl HookEvent {
+    pub fn name(self) -> &'static str {
+        match self {
+            HookEvent::PreLink => "pre-link",
+            HookEvent::PostLink => "post-link",
+            HookEvent::PreUnlink => "pre-unlink",
+            HookEvent::PostUnlink => "post-unlink",
+        }
+    }
+
+    pub fn is_pre(self) -> bool {
+        matches!(self, HookEvent::PreLink | HookEvent::PreUnlink)
+    }
+}
+
+/// What a hook is told about the event, passed as `SLINKY_*` variables.
+#[derive(Debug, Clone, Copy)]
+pub struct HookContext<'a> {
+    pub package: &'a str,
+    pub package_dir: &'a Path,
+    pub stow_dir: &'a Path,
+    pub target_dir: &'a Path,
+}
+
+/// The global hooks directory followed by the package's own.
+pub fn hook_dirs(package_dir: &Path) -> Vec<PathBuf> {
+    vec![
+        config_dir().join(HOOKS_DIR),
+        package_dir.join(PACKAGE_HOOKS_DIR),
+    ]
+}
+
+/// Finds the hooks for `event` in `dirs`: executable files whose name, after
+/// an optional numeric prefix such as `10-`, starts with the event name
+/// (`10-pre-link`, `post-unlink.sh`). Each directory is sorted lexically and
+/// the directories are taken in the order given; missing ones are skipped.
+pub fn find_hooks(dirs: &[PathBuf], event: HookEvent) -> Result<Vec<PathBuf>, HookError> {
+    let mut hooks = Vec::new();
+
+    for dir in dirs {
+        if !dir.is_dir() {
+            continue;
+        }
+        let mut found = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+            if path.is_file() && is_executable(&path) && hook_matches(&name, event) {
+                found.push(path);
+            }
+        }
+        found.sort();
+        hooks.extend(found);
+    }
+
+    Ok(hooks)
+}
+
+fn hook_matches(file_name: &str, event: HookEvent) -> bool {
+    let name = file_name.trim_start_matches(|c: char| c.is_ascii_digit());
+    let name = if name.len() < file_name.len() {
+        name.trim_start_matches(['-', '_'])
+    } else {
+        name
+    };
+
+    name.strip_prefix(event.name())
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(['.', '-', '_']))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).is_ok_and(|m| m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(_path: &Path) -> bool {
+    true
+}
+
+/// Runs the hooks for `event` in order, from the package directory, with
+/// `SLINKY_EVENT`, `SLINKY_PACKAGE`, `SLINKY_PACKAGE_DIR`, `SLINKY_STOW_DIR` and
+/// `SLINKY_TARGET_DIR` set. A failing `pre-*` hook stops the rest so the
+/// caller can abort the event; after a failing `post-*` hook the others still
+/// run and the first failure is returned. Returns the hooks that ran.
+pub fn run_hooks(
+    dirs: &[PathBuf],
+    event: HookEvent,
+    ctx: &HookContext,
+) -> Result<Vec<PathBuf>, HookError> {
+    let hooks = find_hooks(dirs, event)?;
+    let mut first_error = None;
+
+    for hook in &hooks {
+        let status = Command::new(hook)
+            .current_dir(ctx.package_dir)
+            .env("SLINKY_EVENT", event.name())
+            .env("SLINKY_PACKAGE", ctx.package)
+            .env("SLINKY_PACKAGE_DIR", ctx.package_dir)
+            .env("SLINKY_STOW_DIR", ctx.stow_dir)
+            .env("SLINKY_TARGET_DIR", ctx.target_dir)
+            .status();
+
+        let error = match status {
+            Ok(status) if status.success() => continue,
+            Ok(status) => HookError::Failed {
+                path: hook.clone(),
+                status: status.code().unwrap_or(-1),
+            },
+            Err(source) => HookError::Spawn {
+                path: hook.clone(),
+                source,
+            },
+        };
+        if event.is_pre() {
+            return Err(error);
+        }
+        first_error.get_or_insert(error);
+    }
+
+    match first_error {
+        Some(error) => Err(error),
+        None => Ok(hooks),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    fn write_hook(dir: &Path, name: &str, body: &str) {
+        use std::os::unix::fs::PermissionsExt;
+        fs::create_dir_all(dir).unwrap();
+        let path = dir.join(name);
+        fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[test]
+    fn test_hook_matches_event_names() {
+        assert!(hook_matches("pre-link", HookEvent::PreLink));
+        assert!(hook_matches("10-pre-link", HookEvent::PreLink));
+        assert!(hook_matches("20_pre-link.sh", HookEvent::PreLink));
+        assert!(hook_matches("30-post-link-reload", HookEvent::PostLink));
+        assert!(!hook_matches("10-post-link", HookEvent::PreLink));
+        assert!(!hook_matches("10-pre-linker", HookEvent::PreLink));
+        assert!(!hook_matches("10-pre-unlink", HookEvent::PreLink));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_hooks_run_in_order_with_context() {
+        let temp_dir = std::env::temp_dir().join("slinky_test_hooks");
+        let _ = fs::remove_dir_all(&temp_dir);
+
+        let global = temp_dir.join("config/hooks.d");
+        let package_dir = temp_dir.join("dotfiles/zsh");
+        let local = package_dir.join(PACKAGE_HOOKS_DIR);
+        let log = temp_dir.join("hooks.log");
+        let append = format!(
+            "echo \"$0 $SLINKY_EVENT $SLINKY_PACKAGE $SLINKY_TARGET_DIR\" >> {}",
+            log.display()
+        );
+        write_hook(&global, "20-pre-link", &append);
+        write_hook(&global, "10-pre-link", &append);
+        write_hook(&global, "10-post-link", &append);
+        write_hook(&local, "05-pre-link", &append);
+        fs::write(global.join("15-pre-link"), "not executable").unwrap();
+
+        let ctx = HookContext {
+            package: "zsh",
+            package_dir: &package_dir,
+            stow_dir: &temp_dir.join("dotfiles"),
+            target_dir: Path::new("/home/tester"),
+        };
+        let dirs = vec![global.clone(), local.clone()];
+        let ran = run_hooks(&dirs, HookEvent::PreLink, &ctx).unwrap();
+
+        assert_eq!(
+            ran,
+            vec![
+                global.join("10-pre-link"),
+                global.join("20-pre-link"),
+                local.join("05-pre-link")
+            ]
+        );
+        let lines: Vec<String> = fs::read_to_string(&log)
+            .unwrap()
+            .lines()
+            .map(String::from)
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                format!(
+                    "{} pre-link zsh /home/tester",
+                    global.join("10-pre-link").display()
+                ),
+                format!(
+                    "{} pre-link zsh /home/tester",
+                    global.join("20-pre-link").display()
+                ),
+                format!(
+                    "{} pre-link zsh /home/tester",
+                    local.join("05-pre-link").display()
+                ),
+            ]
+        );
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_failing_pre_hook_stops_the_rest() {
+        let temp_dir = std::env::temp_dir().join("slinky_test_hooks_abort");
+        let _ = fs::remove_dir_all(&temp_dir);
+
+        let dir = temp_dir.join("hooks.d");
+        let marker = temp_dir.join("ran");
+        write_hook(&dir, "10-pre-unlink", "exit 3");
+        write_hook(
+            &dir,
+            "20-pre-unlink",
+            &format!("touch {}", marker.display()),
+        );
+
+        let ctx = HookContext {
+            package: "zsh",
+            package_dir: &temp_dir,
+            stow_dir: &temp_dir,
+            target_dir: &temp_dir,
+        };
+        match run_hooks(std::slice::from_ref(&dir), HookEvent::PreUnlink, &ctx) {
+            Err(HookError::Failed { path, status }) => {
+                assert_eq!(path, dir.join("10-pre-unlink"));
+                assert_eq!(status, 3);
+            }
+            other => panic!("expected a failed hook, got {:?}", other),
+        }
+        assert!(!marker.exists());
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+}
@@ -1,11 +1,18 @@
+pub mod clean;
 pub mod cli;
 pub mod config;
 pub mod daemon;
 pub mod error;
+pub mod hooks;
+pub mod migrate;
+pub mod parallel;
 pub mod remote;
 pub mod secrets;
 pub mod service;
+pub mod slinky;
+pub mod state;
 pub mod stow;
 
 pub use config::{config_path, load_config, save_config, Config};
 pub use error::{Result, SlinkyError};
+pub use slinky::Slinky;
@@ -2,13 +2,19 @@ use clap::Parser;
 use colored::*;
 use std::process;
 
+mod clean;
 mod cli;
 mod config;
 mod daemon;
 mod error;
+mod hooks;
+mod migrate;
+mod parallel;
 mod remote;
 mod secrets;
 mod service;
+mod slinky;
+mod state;
 mod stow;
 
 use cli::Cli;
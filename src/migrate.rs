@@ -0,0 +1,169 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::stow::{
+    analyze_package_with, find_packages_with, package_status, AnalyzeOptions, PackageStatus,
+    StowError, StowPackage,
+};
+
+/// GNU Stow's per-user option file, read from the home directory.
+pub const STOWRC: &str = ".stowrc";
+
+/// GNU Stow's ignore list for every package, which slinky does not read.
+pub const STOW_GLOBAL_IGNORE: &str = ".stow-global-ignore";
+
+/// The `--dir` and `--target` options from a `.stowrc`; its other options
+/// have no slinky equivalent.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StowRc {
+    pub dir: Option<PathBuf>,
+    pub target: Option<PathBuf>,
+}
+
+/// Parses `.stowrc` contents: whitespace-separated options in any of the
+/// forms `--dir=DIR`, `--dir DIR`, `-d DIR` or `-dDIR`. A leading `~` or
+/// `$HOME` expands to `home`, and relative paths are taken from `home`.
+pub fn parse_stowrc(contents: &str, home: &Path) -> StowRc {
+    let mut rc = StowRc::default();
+    let mut words = contents.split_whitespace();
+
+    while let Some(word) = words.next() {
+        let (slot, value) = match word {
+            "--dir" | "-d" => (&mut rc.dir, words.next()),
+            "--target" | "-t" => (&mut rc.target, words.next()),
+            _ => {
+                if let Some(value) = word.strip_prefix("--dir=") {
+                    (&mut rc.dir, Some(value))
+                } else if let Some(value) = word.strip_prefix("--target=") {
+                    (&mut rc.target, Some(value))
+                } else if let Some(value) = word.strip_prefix("-d") {
+                    (&mut rc.dir, Some(value))
+                } else if let Some(value) = word.strip_prefix("-t") {
+                    (&mut rc.target, Some(value))
+                } else {
+                    continue;
+                }
+            }
+        };
+        if let Some(value) = value.filter(|value| !value.is_empty()) {
+            *slot = Some(expand_home(value, home));
+        }
+    }
+
+    rc
+}
+
+fn expand_home(value: &str, home: &Path) -> PathBuf {
+    let rest = ["~", "$HOME"]
+        .iter()
+        .find_map(|prefix| value.strip_prefix(prefix))
+        .filter(|rest| rest.is_empty() || rest.starts_with('/'));
+    match rest {
+        Some(rest) => home.join(rest.trim_start_matches('/')),
+        None => home.join(value),
+    }
+}
+
+/// Reads `~/.stowrc`; a missing or unreadable file sets nothing.
+pub fn read_stowrc(home: &Path) -> StowRc {
+    fs::read_to_string(home.join(STOWRC))
+        .map(|contents| parse_stowrc(&contents, home))
+        .unwrap_or_default()
+}
+
+/// A stow directory as it is currently linked into its target.
+#[derive(Debug, Clone)]
+pub struct StowLayout {
+    pub stow_dir: PathBuf,
+    pub target_dir: PathBuf,
+    pub packages: Vec<(StowPackage, PackageStatus)>,
+}
+
+impl StowLayout {
+    /// How many package entries are already linked into the target.
+    pub fn linked_files(&self) -> usize {
+        self.packages.iter().map(|(_, status)| status.linked).sum()
+    }
+}
+
+/// Analyzes every package of `stow_dir` against `target_dir` without
+/// changing anything. Conflicts are counted rather than failing the scan.
+/// The dot-directories in `include_hidden` count as packages.
+pub fn inspect_layout(
+    stow_dir: &Path,
+    target_dir: &Path,
+    options: &AnalyzeOptions,
+    include_hidden: &[String],
+) -> Result<StowLayout, StowError> {
+    let options = AnalyzeOptions {
+        report_conflicts: true,
+        ..options.clone()
+    };
+    let packages = find_packages_with(stow_dir, include_hidden)?
+        .into_iter()
+        .map(|package| {
+            let ops = analyze_package_with(&package.path, target_dir, &options)?;
+            Ok((package, package_status(&ops)))
+        })
+        .collect::<Result<_, StowError>>()?;
+
+    Ok(StowLayout {
+        stow_dir: stow_dir.to_path_buf(),
+        target_dir: target_dir.to_path_buf(),
+        packages,
+    })
+}
+
+/// Picks the stow directory among `candidates` with the most links already
+/// in place, preferring earlier candidates on a tie. Each is checked against
+/// `target`, or like GNU Stow against its parent directory when no target
+/// is given. Candidates without packages are passed over.
+pub fn detect_stow_layout(
+    candidates: &[PathBuf],
+    target: Option<&Path>,
+    options: &AnalyzeOptions,
+    include_hidden: &[String],
+) -> Result<Option<StowLayout>, StowError> {
+    let mut best: Option<StowLayout> = None;
+
+    for candidate in candidates.iter().filter(|candidate| candidate.is_dir()) {
+        let Some(target_dir) = target.or_else(|| candidate.parent()) else {
+            continue;
+        };
+        let layout = inspect_layout(candidate, target_dir, options, include_hidden)?;
+        if layout.packages.is_empty() {
+            continue;
+        }
+        if best
+            .as_ref()
+            .is_none_or(|best| layout.linked_files() > best.linked_files())
+        {
+            best = Some(layout);
+        }
+    }
+
+    Ok(best)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_stowrc() {
+        let home = Path::new("/home/me");
+        let rc = parse_stowrc("--dir=~/src/dotfiles\n--target $HOME\n--verbose=2\n", home);
+        assert_eq!(
+            rc,
+            StowRc {
+                dir: Some(PathBuf::from("/home/me/src/dotfiles")),
+                target: Some(PathBuf::from("/home/me")),
+            }
+        );
+
+        let rc = parse_stowrc("-d dotfiles -t/srv/home --no-folding", home);
+        assert_eq!(rc.dir, Some(PathBuf::from("/home/me/dotfiles")));
+        assert_eq!(rc.target, Some(PathBuf::from("/srv/home")));
+        assert_eq!(parse_stowrc("--adopt", home), StowRc::default());
+    }
+}
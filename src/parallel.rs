@@ -0,0 +1,98 @@
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+/// Sets the number of parallel jobs when `--jobs` is not given.
+pub const JOBS_ENV: &str = "SLINKY_JOBS";
+
+/// How many workers to use: `requested` (`--jobs`), else `SLINKY_JOBS`, else
+/// the number of CPUs. Unparseable or zero `SLINKY_JOBS` values are ignored.
+pub fn resolve_jobs(requested: Option<NonZeroUsize>) -> usize {
+    requested
+        .or_else(|| std::env::var(JOBS_ENV).ok()?.trim().parse().ok())
+        .or_else(|| thread::available_parallelism().ok())
+        .map_or(1, NonZeroUsize::get)
+}
+
+/// Runs `work` on every item using at most `jobs` threads and returns the
+/// results in item order. `work` also gets the item's index.
+pub fn parallel_map<T, R, F>(items: &[T], jobs: usize, work: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(usize, &T) -> R + Sync,
+{
+    if items.is_empty() {
+        return Vec::new();
+    }
+
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<(usize, R)> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..jobs.clamp(1, items.len()))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let position = next.fetch_add(1, Ordering::SeqCst);
+                        let Some(item) = items.get(position) else {
+                            break;
+                        };
+                        done.push((position, work(position, item)));
+                    }
+                    done
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| {
+                worker
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+            })
+            .collect()
+    });
+    results.sort_by_key(|(position, _)| *position);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// The most workers that were ever inside `work` at once.
+    fn peak_concurrency(jobs: usize) -> usize {
+        let active = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        let items: Vec<usize> = (0..8).collect();
+
+        let results = parallel_map(&items, jobs, |position, item| {
+            let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+            peak.fetch_max(now, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(20));
+            active.fetch_sub(1, Ordering::SeqCst);
+            position + item
+        });
+
+        assert_eq!(results, vec![0, 2, 4, 6, 8, 10, 12, 14]);
+        peak.load(Ordering::SeqCst)
+    }
+
+    #[test]
+    fn test_parallel_map_respects_job_limit() {
+        assert_eq!(peak_concurrency(1), 1);
+        assert!(peak_concurrency(3) <= 3);
+        assert_eq!(
+            parallel_map(&Vec::<u8>::new(), 4, |_, item| *item),
+            Vec::<u8>::new()
+        );
+    }
+
+    #[test]
+    fn test_resolve_jobs_prefers_flag() {
+        let two = NonZeroUsize::new(2);
+        assert_eq!(resolve_jobs(two), 2);
+        assert!(resolve_jobs(None) >= 1);
+    }
+}
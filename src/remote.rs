@@ -1,5 +1,8 @@
+use std::io::Read;
 use std::path::{Path, PathBuf};
-use std::process::{Command, Stdio};
+use std::process::{Child, Command, ExitStatus, Output, Stdio};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 use thiserror::Error;
 use url::Url;
 
@@ -14,6 +17,9 @@ pub enum RemoteError {
     #[error("git command exited with status {status}: {stderr}")]
     GitExitError { status: i32, stderr: String },
 
+    #[error("git command timed out after {}s (see git_timeout_secs)", .0.as_secs())]
+    Timeout(Duration),
+
     #[error("invalid repository specification: {0}")]
     InvalidRepoSpec(String),
 
@@ -25,6 +31,15 @@ pub enum RemoteError {
 
     #[error("failed to parse URL: {0}")]
     UrlParseError(#[from] url::ParseError),
+
+    #[error(
+        "not enough free space in {path}: {available_mb} MB available, {required_mb} MB required (see min_free_space_mb)"
+    )]
+    InsufficientSpace {
+        path: PathBuf,
+        available_mb: u64,
+        required_mb: u64,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -40,6 +55,11 @@ pub struct RepoSpec {
     pub owner: String,
     pub repo: String,
     pub branch: Option<String>,
+    /// Host the repository lives on, including a non-default port.
+    pub host: String,
+    /// The spec as given for SSH and `file://` URLs, so generic hosts and
+    /// local repositories are cloned the same way.
+    pub source_url: Option<String>,
 }
 
 impl RepoSpec {
@@ -47,7 +67,10 @@ impl RepoSpec {
         match self.provider {
             Provider::GitHub => format!("https://github.com/{}/{}.git", self.owner, self.repo),
             Provider::GitLab => format!("https://gitlab.com/{}/{}.git", self.owner, self.repo),
-            Provider::GenericGit => format!("{}/{}", self.owner, self.repo),
+            Provider::GenericGit => match &self.source_url {
+                Some(url) => url.clone(),
+                None => format!("https://{}/{}/{}.git", self.host, self.owner, self.repo),
+            },
         }
     }
 
@@ -55,7 +78,7 @@ impl RepoSpec {
         match self.provider {
             Provider::GitHub => format!("github.com/{}/{}", self.owner, self.repo),
             Provider::GitLab => format!("gitlab.com/{}/{}", self.owner, self.repo),
-            Provider::GenericGit => format!("git/{}/{}", self.owner, self.repo)
+            Provider::GenericGit => format!("git/{}/{}/{}", self.host, self.owner, self.repo)
                 .replace("://", "/")
                 .replace(":", "/"),
         }
@@ -81,6 +104,7 @@ pub fn parse_repo_spec(spec: &str) -> Result<RepoSpec, RemoteError> {
         || spec.starts_with("https://")
         || spec.starts_with("git@")
         || spec.starts_with("ssh://")
+        || spec.starts_with("file://")
     {
         parse_full_url(spec)
     } else {
@@ -115,12 +139,18 @@ fn parse_shorthand(spec: &str, provider: Provider) -> Result<RepoSpec, RemoteErr
     };
 
     let repo = repo.strip_suffix(".git").unwrap_or(&repo).to_string();
+    let host = match provider {
+        Provider::GitLab => "gitlab.com",
+        Provider::GitHub | Provider::GenericGit => "github.com",
+    };
 
     Ok(RepoSpec {
         provider,
         owner: owner.to_string(),
         repo,
         branch,
+        host: host.to_string(),
+        source_url: None,
     })
 }
 
@@ -130,6 +160,10 @@ fn parse_full_url(spec: &str) -> Result<RepoSpec, RemoteError> {
     } else {
         let url = Url::parse(spec)?;
 
+        if url.scheme() == "file" {
+            return parse_file_url(spec, &url);
+        }
+
         if url.scheme() != "http" && url.scheme() != "https" && url.scheme() != "ssh" {
             return Err(RemoteError::UnsupportedScheme(url.scheme().to_string()));
         }
@@ -160,10 +194,69 @@ fn parse_full_url(spec: &str) -> Result<RepoSpec, RemoteError> {
             owner: parts[0].to_string(),
             repo: parts[1].to_string(),
             branch: None,
+            host: match url.port() {
+                Some(port) => format!("{}:{}", host, port),
+                None => host.to_string(),
+            },
+            source_url: (url.scheme() == "ssh").then(|| spec.to_string()),
         })
     }
 }
 
+/// The directory `install` should use in place, without cloning, when
+/// `repo` names a local one: an existing path given as a path (`~/dots`,
+/// `./dots`, `/mnt/dots`) or that is not a repository spec, or a `file://`
+/// URL of a working tree. Bare repositories behind `file://` are still
+/// cloned like any remote.
+pub fn local_install_path(repo: &str) -> Option<PathBuf> {
+    let repo = repo.trim();
+
+    if repo.starts_with("file://") {
+        let path = Url::parse(repo).ok()?.to_file_path().ok()?;
+        return (path.is_dir() && !is_bare_repo(&path)).then_some(path);
+    }
+
+    let path = match repo.strip_prefix("~/") {
+        Some(rest) => crate::config::dirs::home_dir()?.join(rest),
+        None => PathBuf::from(repo),
+    };
+    let path_like = path.is_absolute() || repo.starts_with('.') || repo.starts_with('~');
+    if !path.is_dir() || !(path_like || parse_repo_spec(repo).is_err()) {
+        return None;
+    }
+    if path.is_relative() {
+        return std::env::current_dir().ok().map(|cwd| cwd.join(path));
+    }
+    Some(path)
+}
+
+fn is_bare_repo(path: &Path) -> bool {
+    path.join("HEAD").is_file() && path.join("objects").is_dir()
+}
+
+/// A local repository; the last two path components stand in for owner/repo.
+fn parse_file_url(spec: &str, url: &Url) -> Result<RepoSpec, RemoteError> {
+    let path = url.path().trim_end_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+
+    let mut parts = path.rsplit('/').filter(|part| !part.is_empty());
+    let (Some(repo), Some(owner)) = (parts.next(), parts.next()) else {
+        return Err(RemoteError::InvalidRepoSpec(format!(
+            "file URL path must contain owner/repo: {}",
+            spec
+        )));
+    };
+
+    Ok(RepoSpec {
+        provider: Provider::GenericGit,
+        owner: owner.to_string(),
+        repo: repo.to_string(),
+        branch: None,
+        host: "localhost".to_string(),
+        source_url: Some(spec.to_string()),
+    })
+}
+
 fn parse_ssh_url(spec: &str) -> Result<RepoSpec, RemoteError> {
     if !spec.starts_with("git@") {
         return Err(RemoteError::InvalidRepoSpec(format!(
@@ -205,32 +298,142 @@ fn parse_ssh_url(spec: &str) -> Result<RepoSpec, RemoteError> {
         owner: path_parts[0].to_string(),
         repo: path_parts[1].to_string(),
         branch: None,
+        host: host.to_string(),
+        source_url: Some(spec.to_string()),
     })
 }
 
-pub fn get_repo_cache_path(spec: &RepoSpec) -> PathBuf {
+/// Where cloned repositories are cached.
+pub fn repo_cache_dir() -> PathBuf {
     let base_dirs = directories::BaseDirs::new().expect("failed to determine base directories");
     let data_dir = base_dirs.data_local_dir();
 
-    data_dir.join("slinky").join("repos").join(spec.cache_key())
+    data_dir.join("slinky").join("repos")
+}
+
+pub fn get_repo_cache_path(spec: &RepoSpec) -> PathBuf {
+    repo_cache_dir().join(spec.cache_key())
+}
+
+/// The `slnky install` spec whose clone would live at `path`, when `path` is
+/// inside `cache_dir` (or a subdirectory of such a clone). This undoes
+/// [`RepoSpec::cache_key`], so a generic host's port is only recovered when
+/// `path` is the clone root itself.
+pub fn spec_from_cache_path(path: &Path, cache_dir: &Path) -> Option<String> {
+    let relative = path.strip_prefix(cache_dir).ok()?;
+    let parts: Vec<&str> = relative.iter().filter_map(|part| part.to_str()).collect();
+
+    match parts.as_slice() {
+        ["github.com", owner, repo, ..] => Some(format!("{}/{}", owner, repo)),
+        ["gitlab.com", owner, repo, ..] => Some(format!("gitlab:{}/{}", owner, repo)),
+        ["git", host, port, owner, repo] if port.parse::<u16>().is_ok() => {
+            Some(format!("https://{}:{}/{}/{}", host, port, owner, repo))
+        }
+        ["git", host, owner, repo, ..] => Some(format!("https://{}/{}/{}", host, owner, repo)),
+        _ => None,
+    }
 }
 
-pub fn clone_or_update(spec: &RepoSpec) -> Result<PathBuf, RemoteError> {
+/// Clones `spec` into the cache, or updates it if already cached. A fresh
+/// clone is refused when the cache filesystem has less than `min_free_mb` MB
+/// free (0 disables the check), and git is killed if it runs past `timeout`.
+/// With `verbose`, git's output is streamed to the terminal instead of being
+/// captured.
+pub fn clone_or_update(
+    spec: &RepoSpec,
+    min_free_mb: u64,
+    timeout: Option<Duration>,
+    verbose: bool,
+) -> Result<PathBuf, RemoteError> {
     check_git_installed()?;
 
     let cache_path = get_repo_cache_path(spec);
 
     if cache_path.exists() {
-        update_repo(&cache_path, spec)?;
+        update_repo(&cache_path, spec, timeout, verbose)?;
     } else {
-        clone_repo(spec, &cache_path)?;
+        check_free_space(&cache_path, min_free_mb)?;
+        clone_repo(spec, &cache_path, timeout, verbose)?;
     }
 
     Ok(cache_path)
 }
 
-fn check_git_installed() -> Result<(), RemoteError> {
-    let result = Command::new("git")
+fn check_free_space(path: &Path, min_free_mb: u64) -> Result<(), RemoteError> {
+    if min_free_mb == 0 {
+        return Ok(());
+    }
+
+    let Some(existing) = path.ancestors().find(|p| p.exists()) else {
+        return Ok(());
+    };
+
+    match available_space_mb(existing) {
+        Some(available_mb) => ensure_free_space(existing, available_mb, min_free_mb),
+        None => Ok(()),
+    }
+}
+
+fn ensure_free_space(path: &Path, available_mb: u64, required_mb: u64) -> Result<(), RemoteError> {
+    if available_mb < required_mb {
+        return Err(RemoteError::InsufficientSpace {
+            path: path.to_path_buf(),
+            available_mb,
+            required_mb,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn available_space_mb(path: &Path) -> Option<u64> {
+    let output = Command::new("df")
+        .arg("-Pk")
+        .arg(path)
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    parse_df_available_kb(&String::from_utf8_lossy(&output.stdout)).map(|kb| kb / 1024)
+}
+
+#[cfg(not(unix))]
+fn available_space_mb(_path: &Path) -> Option<u64> {
+    None
+}
+
+/// Reads the "Available" column from POSIX `df -Pk` output.
+fn parse_df_available_kb(output: &str) -> Option<u64> {
+    output
+        .lines()
+        .nth(1)?
+        .split_whitespace()
+        .nth(3)?
+        .parse()
+        .ok()
+}
+
+/// A `git` command that never waits for input: terminal credential prompts,
+/// SSH password and host-key prompts, and Git Credential Manager dialogs are
+/// all disabled, so a repository without working credentials fails with an
+/// error instead of hanging (in the daemon, forever). A `GIT_SSH_COMMAND`
+/// already set by the user is left alone.
+pub fn git_command() -> Command {
+    let mut cmd = Command::new("git");
+    cmd.env("GIT_TERMINAL_PROMPT", "0")
+        .env("GCM_INTERACTIVE", "never");
+    if std::env::var_os("GIT_SSH_COMMAND").is_none() {
+        cmd.env("GIT_SSH_COMMAND", "ssh -oBatchMode=yes");
+    }
+    cmd
+}
+
+pub fn check_git_installed() -> Result<(), RemoteError> {
+    let result = git_command()
         .arg("--version")
         .stdout(Stdio::null())
         .stderr(Stdio::null())
@@ -242,14 +445,19 @@ fn check_git_installed() -> Result<(), RemoteError> {
     }
 }
 
-fn clone_repo(spec: &RepoSpec, target_path: &Path) -> Result<(), RemoteError> {
+fn clone_repo(
+    spec: &RepoSpec,
+    target_path: &Path,
+    timeout: Option<Duration>,
+    verbose: bool,
+) -> Result<(), RemoteError> {
     if let Some(parent) = target_path.parent() {
         std::fs::create_dir_all(parent)?;
     }
 
     let clone_url = spec.to_clone_url();
 
-    let mut cmd = Command::new("git");
+    let mut cmd = git_command();
     cmd.arg("clone");
 
     if let Some(branch) = &spec.branch {
@@ -260,10 +468,105 @@ fn clone_repo(spec: &RepoSpec, target_path: &Path) -> Result<(), RemoteError> {
     cmd.arg(&clone_url);
     cmd.arg(target_path);
 
-    let output = cmd
-        .output()
+    run_clone(&mut cmd, target_path, timeout, verbose)
+}
+
+/// Runs a clone into `target_path`, removing whatever a failed or timed-out
+/// clone left there so the next `clone_or_update` clones afresh instead of
+/// "updating" a broken checkout.
+fn run_clone(
+    cmd: &mut Command,
+    target_path: &Path,
+    timeout: Option<Duration>,
+    verbose: bool,
+) -> Result<(), RemoteError> {
+    let result = run_git(cmd, timeout, verbose);
+    if result.is_err() && target_path.exists() {
+        let _ = std::fs::remove_dir_all(target_path);
+    }
+    result
+}
+
+/// Like `Command::output`, but kills the command if it is still running
+/// after `timeout`.
+pub fn output_with_timeout(
+    cmd: &mut Command,
+    timeout: Option<Duration>,
+) -> Result<Output, RemoteError> {
+    let mut child = cmd
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
         .map_err(|e| RemoteError::GitCommandFailed(e.to_string()))?;
 
+    // Drain both pipes while waiting so a chatty command can't fill one and
+    // block forever.
+    let stdout = drain(child.stdout.take());
+    let stderr = drain(child.stderr.take());
+
+    let status = wait_with_timeout(&mut child, timeout)?;
+    Ok(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+fn drain<R: Read + Send + 'static>(pipe: Option<R>) -> JoinHandle<Vec<u8>> {
+    std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buf);
+        }
+        buf
+    })
+}
+
+fn wait_with_timeout(
+    child: &mut Child,
+    timeout: Option<Duration>,
+) -> Result<ExitStatus, RemoteError> {
+    let Some(timeout) = timeout else {
+        return child
+            .wait()
+            .map_err(|e| RemoteError::GitCommandFailed(e.to_string()));
+    };
+
+    let deadline = Instant::now() + timeout;
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => return Ok(status),
+            Ok(None) if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(RemoteError::Timeout(timeout));
+            }
+            Ok(None) => std::thread::sleep(Duration::from_millis(50)),
+            Err(e) => return Err(RemoteError::GitCommandFailed(e.to_string())),
+        }
+    }
+}
+
+fn run_git(cmd: &mut Command, timeout: Option<Duration>, verbose: bool) -> Result<(), RemoteError> {
+    if verbose {
+        let mut child = cmd
+            .stdout(Stdio::inherit())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(|e| RemoteError::GitCommandFailed(e.to_string()))?;
+        let status = wait_with_timeout(&mut child, timeout)?;
+
+        if !status.success() {
+            return Err(RemoteError::GitExitError {
+                status: status.code().unwrap_or(-1),
+                stderr: "see git output above".to_string(),
+            });
+        }
+        return Ok(());
+    }
+
+    let output = output_with_timeout(cmd, timeout)?;
+
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
         return Err(RemoteError::GitExitError {
@@ -275,18 +578,60 @@ fn clone_repo(spec: &RepoSpec, target_path: &Path) -> Result<(), RemoteError> {
     Ok(())
 }
 
-fn update_repo(repo_path: &Path, spec: &RepoSpec) -> Result<(), RemoteError> {
-    let mut cmd = Command::new("git");
-    cmd.current_dir(repo_path);
-    cmd.arg("pull");
+const STARTER_GITIGNORE: &str = ".DS_Store\n*.swp\n*.swo\n*~\n";
 
-    if let Some(branch) = &spec.branch {
-        cmd.arg("origin").arg(branch);
-    } else {
-        cmd.arg("--ff-only");
+/// Creates `path` if needed and makes it a git repository with a starter
+/// `.gitignore`. Existing repositories and files are left as they are.
+pub fn init_repo(path: &Path, remote: Option<&str>, verbose: bool) -> Result<(), RemoteError> {
+    check_git_installed()?;
+    std::fs::create_dir_all(path)?;
+
+    if !path.join(".git").exists() {
+        run_git(git_command().current_dir(path).arg("init"), None, verbose)?;
+    }
+
+    let gitignore = path.join(".gitignore");
+    if !gitignore.exists() {
+        std::fs::write(&gitignore, STARTER_GITIGNORE)?;
+    }
+
+    if let Some(url) = remote {
+        let action = if get_remote_url(path).is_some() {
+            "set-url"
+        } else {
+            "add"
+        };
+        run_git(
+            git_command()
+                .current_dir(path)
+                .args(["remote", action, "origin", url]),
+            None,
+            verbose,
+        )?;
+    }
+
+    Ok(())
+}
+
+pub fn get_head(repo_path: &Path) -> Option<String> {
+    let output = git_command()
+        .current_dir(repo_path)
+        .args(["rev-parse", "HEAD"])
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
     }
 
-    let output = cmd
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+pub fn changed_files(repo_path: &Path, from: &str, to: &str) -> Result<Vec<String>, RemoteError> {
+    let output = git_command()
+        .current_dir(repo_path)
+        .args(["diff", "--name-only", from, to])
         .output()
         .map_err(|e| RemoteError::GitCommandFailed(e.to_string()))?;
 
@@ -298,13 +643,127 @@ fn update_repo(repo_path: &Path, spec: &RepoSpec) -> Result<(), RemoteError> {
         });
     }
 
-    Ok(())
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::to_string)
+        .collect())
+}
+
+/// Every file git tracks under `repo_path` (which may be a subdirectory of
+/// the repository), as paths joined onto `repo_path`.
+pub fn tracked_files(repo_path: &Path) -> Result<Vec<PathBuf>, RemoteError> {
+    git_paths(repo_path, &["ls-files", "-z"])
+}
+
+/// Every file under `repo_path` that a commit deleted or that is missing
+/// from the working tree, as paths joined onto `repo_path`. A repository
+/// without commits has only the missing files.
+pub fn deleted_files(repo_path: &Path) -> Result<Vec<PathBuf>, RemoteError> {
+    let mut files = git_paths(repo_path, &["ls-files", "--deleted", "-z"])?;
+    if get_head(repo_path).is_some() {
+        files.extend(git_paths(
+            repo_path,
+            &[
+                "log",
+                "--diff-filter=D",
+                "--name-only",
+                "--format=",
+                "--relative",
+                "-z",
+            ],
+        )?);
+    }
+    Ok(files)
+}
+
+/// Runs a git command printing NUL-separated paths relative to `repo_path`.
+fn git_paths(repo_path: &Path, args: &[&str]) -> Result<Vec<PathBuf>, RemoteError> {
+    let output = git_command()
+        .current_dir(repo_path)
+        .args(args)
+        .output()
+        .map_err(|e| RemoteError::GitCommandFailed(e.to_string()))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(RemoteError::GitExitError {
+            status: output.status.code().unwrap_or(-1),
+            stderr: stderr.to_string(),
+        });
+    }
+
+    Ok(output
+        .stdout
+        .split(|&byte| byte == 0)
+        .filter(|name| !name.is_empty())
+        .map(|name| repo_path.join(String::from_utf8_lossy(name).as_ref()))
+        .collect())
+}
+
+pub fn get_remote_url(repo_path: &Path) -> Option<String> {
+    let output = git_command()
+        .current_dir(repo_path)
+        .args(["remote", "get-url", "origin"])
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    let url = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!url.is_empty()).then_some(url)
+}
+
+fn update_repo(
+    repo_path: &Path,
+    spec: &RepoSpec,
+    timeout: Option<Duration>,
+    verbose: bool,
+) -> Result<(), RemoteError> {
+    let mut cmd = git_command();
+    cmd.current_dir(repo_path);
+    cmd.arg("pull");
+
+    if let Some(branch) = &spec.branch {
+        cmd.arg("origin").arg(branch);
+    } else {
+        cmd.arg("--ff-only");
+    }
+
+    run_git(&mut cmd, timeout, verbose)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_spec_from_cache_path() {
+        let cache = Path::new("/data/slinky/repos");
+        for spec in ["user/dotfiles", "gitlab:team/configs"] {
+            let path = cache.join(parse_repo_spec(spec).unwrap().cache_key());
+            assert_eq!(spec_from_cache_path(&path, cache).as_deref(), Some(spec));
+        }
+
+        let generic = cache.join("git/example.com/team/configs/home");
+        assert_eq!(
+            spec_from_cache_path(&generic, cache).as_deref(),
+            Some("https://example.com/team/configs")
+        );
+        let subdir = cache.join("github.com/user/dotfiles/stow");
+        assert_eq!(
+            spec_from_cache_path(&subdir, cache).as_deref(),
+            Some("user/dotfiles")
+        );
+        assert_eq!(spec_from_cache_path(&cache.join("github.com"), cache), None);
+        assert_eq!(
+            spec_from_cache_path(Path::new("/home/user/dotfiles"), cache),
+            None
+        );
+    }
+
     #[test]
     fn test_parse_shorthand_github() {
         let spec = parse_repo_spec("user/repo").unwrap();
@@ -312,6 +771,7 @@ mod tests {
         assert_eq!(spec.owner, "user");
         assert_eq!(spec.repo, "repo");
         assert_eq!(spec.branch, None);
+        assert_eq!(spec.host, "github.com");
     }
 
     #[test]
@@ -353,6 +813,61 @@ mod tests {
         assert_eq!(spec.provider, Provider::GitHub);
         assert_eq!(spec.owner, "user");
         assert_eq!(spec.repo, "repo");
+        assert_eq!(spec.host, "github.com");
+    }
+
+    #[test]
+    fn test_self_hosted_gitea_clone_url() {
+        let spec = parse_repo_spec("https://gitea.example.com:3000/user/dotfiles.git").unwrap();
+        assert_eq!(spec.provider, Provider::GenericGit);
+        assert_eq!(spec.host, "gitea.example.com:3000");
+        assert_eq!(
+            spec.to_clone_url(),
+            "https://gitea.example.com:3000/user/dotfiles.git"
+        );
+
+        let spec = parse_repo_spec("git@gitea.example.com:user/dotfiles.git").unwrap();
+        assert_eq!(spec.host, "gitea.example.com");
+        assert_eq!(
+            spec.to_clone_url(),
+            "git@gitea.example.com:user/dotfiles.git"
+        );
+    }
+
+    #[test]
+    fn test_parse_file_url() {
+        let spec = parse_repo_spec("file:///srv/git/user/dotfiles.git").unwrap();
+        assert_eq!(spec.provider, Provider::GenericGit);
+        assert_eq!(spec.owner, "user");
+        assert_eq!(spec.repo, "dotfiles");
+        assert_eq!(spec.to_clone_url(), "file:///srv/git/user/dotfiles.git");
+        assert_eq!(spec.cache_key(), "git/localhost/user/dotfiles");
+    }
+
+    #[test]
+    fn test_local_install_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let dots = dir.path().join("dots");
+        std::fs::create_dir_all(dots.join("zsh")).unwrap();
+        let bare = dir.path().join("remote.git");
+        std::fs::create_dir_all(bare.join("objects")).unwrap();
+        std::fs::write(bare.join("HEAD"), "ref: refs/heads/main\n").unwrap();
+
+        let dots_str = dots.to_str().unwrap();
+        assert_eq!(local_install_path(dots_str), Some(dots.clone()));
+        assert_eq!(
+            local_install_path(&format!("file://{}", dots_str)),
+            Some(dots.clone())
+        );
+        assert_eq!(
+            local_install_path(&format!("file://{}", bare.display())),
+            None
+        );
+        assert_eq!(
+            local_install_path(dir.path().join("missing").to_str().unwrap()),
+            None
+        );
+        assert_eq!(local_install_path("user/repo"), None);
     }
 
     #[test]
@@ -361,12 +876,87 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_parse_df_available() {
+        let output = "Filesystem     1024-blocks      Used Available Capacity Mounted on\n\
+                      /dev/disk1s1     488245288 300000000 188245288      62% /\n";
+        assert_eq!(parse_df_available_kb(output), Some(188245288));
+        assert_eq!(parse_df_available_kb("Filesystem\n"), None);
+    }
+
+    #[test]
+    fn test_free_space_threshold() {
+        let path = Path::new("/tmp");
+        assert!(ensure_free_space(path, 500, 100).is_ok());
+        assert!(ensure_free_space(path, 100, 100).is_ok());
+        assert!(matches!(
+            ensure_free_space(path, 99, 100),
+            Err(RemoteError::InsufficientSpace {
+                available_mb: 99,
+                required_mb: 100,
+                ..
+            })
+        ));
+        assert!(check_free_space(path, 0).is_ok());
+    }
+
     #[test]
     fn test_empty_spec() {
         let result = parse_repo_spec("");
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_git_command_disables_prompts() {
+        let cmd = git_command();
+        let env: Vec<_> = cmd.get_envs().collect();
+        assert!(env.contains(&("GIT_TERMINAL_PROMPT".as_ref(), Some("0".as_ref()))));
+        assert!(env.contains(&("GCM_INTERACTIVE".as_ref(), Some("never".as_ref()))));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_hanging_git_times_out() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let shim = temp_dir.path().join("git");
+        std::fs::write(&shim, "#!/bin/sh\nexec sleep 30\n").unwrap();
+        std::fs::set_permissions(&shim, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let started = Instant::now();
+        let result = output_with_timeout(
+            Command::new(&shim).arg("pull"),
+            Some(Duration::from_millis(200)),
+        );
+        assert!(matches!(result, Err(RemoteError::Timeout(_))));
+        assert!(started.elapsed() < Duration::from_secs(10));
+
+        let output = output_with_timeout(
+            Command::new("sh").args(["-c", "echo out; echo err >&2"]),
+            Some(Duration::from_secs(10)),
+        )
+        .unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"out\n");
+        assert_eq!(output.stderr, b"err\n");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_timed_out_clone_leaves_no_partial_checkout() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let target = temp_dir.path().join("cache/user/repo");
+
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "mkdir -p \"$0/.git\" && exec sleep 30"])
+            .arg(&target);
+        let result = run_clone(&mut cmd, &target, Some(Duration::from_millis(500)), false);
+
+        assert!(matches!(result, Err(RemoteError::Timeout(_))));
+        assert!(!target.exists());
+    }
+
     #[test]
     fn test_cache_key_generation() {
         let spec = RepoSpec {
@@ -374,10 +964,22 @@ mod tests {
             owner: "user".to_string(),
             repo: "repo".to_string(),
             branch: None,
+            host: "github.com".to_string(),
+            source_url: None,
         };
         assert_eq!(spec.cache_key(), "github.com/user/repo");
     }
 
+    #[test]
+    fn test_generic_cache_key_includes_host() {
+        let first = parse_repo_spec("https://git.example.com/foo/bar").unwrap();
+        let second = parse_repo_spec("git@git.other.com:foo/bar.git").unwrap();
+
+        assert_eq!(first.cache_key(), "git/git.example.com/foo/bar");
+        assert_eq!(second.cache_key(), "git/git.other.com/foo/bar");
+        assert_ne!(get_repo_cache_path(&first), get_repo_cache_path(&second));
+    }
+
     #[test]
     fn test_clone_url_generation() {
         let spec = RepoSpec {
@@ -385,6 +987,8 @@ mod tests {
             owner: "user".to_string(),
             repo: "repo".to_string(),
             branch: None,
+            host: "github.com".to_string(),
+            source_url: None,
         };
         assert_eq!(spec.to_clone_url(), "https://github.com/user/repo.git");
     }
@@ -1,13 +1,20 @@
+use age::armor::{ArmoredReader, ArmoredWriter, Format};
 use age::{Decryptor, Encryptor};
 use regex::Regex;
 use secrecy::Secret as SecrecySecret;
 use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
 use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
 use std::io::{BufRead, BufReader, Write};
 use std::path::{Path, PathBuf};
+use std::process::Command;
 use thiserror::Error;
 
+use crate::config::Config;
+use crate::stow::free_backup_path;
+
 #[derive(Error, Debug)]
 pub enum SecretError {
     #[error("IO error: {0}")]
@@ -34,6 +41,12 @@ pub enum SecretError {
     #[allow(dead_code)]
     TemplateNotFound(String),
 
+    #[error("Edit aborted, store left unchanged: {0}")]
+    EditAborted(String),
+
+    #[error("Secret {0} is defined in more than one place; rename one so each can be restored")]
+    DuplicateName(String),
+
     #[error("Invalid passphrase")]
     #[allow(dead_code)]
     InvalidPassphrase,
@@ -71,6 +84,8 @@ struct SecretMetadata {
     line_number: usize,
 }
 
+const ARMOR_BEGIN: &[u8] = b"-----BEGIN AGE ENCRYPTED FILE-----";
+
 pub struct SecretStore {
     encrypted_data: Vec<u8>,
     secrets_path: PathBuf,
@@ -84,7 +99,6 @@ impl SecretStore {
         }
     }
 
-    #[allow(dead_code)]
     pub fn load(secrets_path: &Path) -> Result<Self, SecretError> {
         let encrypted_data = fs::read(secrets_path)?;
         Ok(Self {
@@ -93,6 +107,11 @@ impl SecretStore {
         })
     }
 
+    /// Whether the loaded store is ASCII-armored rather than binary age.
+    pub fn is_armored(&self) -> bool {
+        self.encrypted_data.starts_with(ARMOR_BEGIN)
+    }
+
     pub fn save(&self) -> Result<(), SecretError> {
         if let Some(parent) = self.secrets_path.parent() {
             fs::create_dir_all(parent)?;
@@ -106,7 +125,11 @@ impl SecretStore {
         &self,
         passphrase: &str,
     ) -> Result<HashMap<String, String>, SecretError> {
-        let decryptor = match Decryptor::new(&self.encrypted_data[..]) {
+        Ok(self.decrypt_data(passphrase)?.secrets)
+    }
+
+    fn decrypt_data(&self, passphrase: &str) -> Result<EncryptedData, SecretError> {
+        let decryptor = match Decryptor::new(ArmoredReader::new(&self.encrypted_data[..])) {
             Ok(Decryptor::Passphrase(d)) => d,
             Ok(_) => {
                 return Err(SecretError::Decryption(
@@ -125,15 +148,121 @@ impl SecretStore {
             SecretError::Decryption(format!("Failed to read decrypted data: {}", e))
         })?;
 
-        let encrypted_data: EncryptedData = serde_json::from_slice(&decrypted)?;
-        Ok(encrypted_data.secrets)
+        Ok(serde_json::from_slice(&decrypted)?)
+    }
+}
+
+/// How much of a file is inspected to decide whether it is binary.
+const BINARY_SNIFF_LEN: usize = 8192;
+
+/// Content is binary if it has a NUL byte or more than 30% control characters
+/// other than common whitespace.
+fn looks_binary(head: &[u8]) -> bool {
+    if head.contains(&0) {
+        return true;
     }
+
+    let control = head
+        .iter()
+        .filter(|&&b| (b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r' | 0x0c | 0x1b)) || b == 0x7f)
+        .count();
+    control * 10 > head.len() * 3
 }
 
+/// Names flagged on top of the built-in sensitive-name list, and names never
+/// flagged (`secret_patterns` and `secret_ignore_names` in the config).
+#[derive(Debug, Clone, Default)]
+pub struct SecretNameRules {
+    /// Regex fragments matched case-insensitively anywhere in a name, like
+    /// the built-ins.
+    pub patterns: Vec<String>,
+    /// Exact names that are known false positives (`PASSWORD_MIN_LENGTH`).
+    pub ignore_names: Vec<String>,
+}
+
+#[allow(dead_code)]
 pub fn scan_file_for_secrets(path: &Path) -> Result<Vec<Secret>, SecretError> {
+    scan_file_for_secrets_with(path, &SecretNameRules::default())
+}
+
+/// `scan_file_for_secrets` with configured name patterns merged into the
+/// built-ins and the ignored names suppressed. `# slnky:secret` still flags
+/// an ignored name.
+pub fn scan_file_for_secrets_with(
+    path: &Path,
+    rules: &SecretNameRules,
+) -> Result<Vec<Secret>, SecretError> {
     let file = File::open(path)?;
-    let reader = BufReader::new(file);
+    let mut reader = BufReader::with_capacity(BINARY_SNIFF_LEN, file);
+    if looks_binary(reader.fill_buf()?) {
+        return Ok(Vec::new());
+    }
+
+    scan_lines(reader.lines(), 1, path, rules)
+}
+
+/// How far `scan_file_for_secrets_since` got through a file: the length of
+/// the complete lines scanned and a hash of them, to tell an append from a
+/// rewrite.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScanMark {
+    len: usize,
+    hash: u64,
+}
+
+impl ScanMark {
+    fn of(content: &[u8]) -> Self {
+        let len = content
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .map_or(0, |newline| newline + 1);
+        Self {
+            len,
+            hash: hash_bytes(&content[..len]),
+        }
+    }
+}
+
+fn hash_bytes(bytes: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// `scan_file_for_secrets_with` that only scans what was appended since
+/// `since`, the mark returned by the previous scan of `path`. A file that
+/// shrank or changed before the mark is scanned in full. A trailing line
+/// without a newline is scanned again once more is written after it.
+pub fn scan_file_for_secrets_since(
+    path: &Path,
+    rules: &SecretNameRules,
+    since: Option<ScanMark>,
+) -> Result<(Vec<Secret>, ScanMark), SecretError> {
+    let content = fs::read(path)?;
+    let mark = ScanMark::of(&content);
+    if looks_binary(&content[..content.len().min(BINARY_SNIFF_LEN)]) {
+        return Ok((Vec::new(), mark));
+    }
+
+    let start = since
+        .filter(|since| {
+            since.len <= content.len() && hash_bytes(&content[..since.len]) == since.hash
+        })
+        .map_or(0, |since| since.len);
+    let first_line = content[..start].iter().filter(|&&b| b == b'\n').count() + 1;
+    let appended = String::from_utf8_lossy(&content[start..]);
+    let lines = appended.lines().map(|line| Ok(line.to_string()));
+
+    Ok((scan_lines(lines, first_line, path, rules)?, mark))
+}
 
+/// Scans `lines`, the first of which is line `first_line` of `path`.
+fn scan_lines(
+    lines: impl Iterator<Item = std::io::Result<String>>,
+    first_line: usize,
+    path: &Path,
+    rules: &SecretNameRules,
+) -> Result<Vec<Secret>, SecretError> {
     let sensitive_patterns = vec![
         "API_KEY",
         "APIKEY",
@@ -152,6 +281,7 @@ pub fn scan_file_for_secrets(path: &Path) -> Result<Vec<Secret>, SecretError> {
     let pattern_str = sensitive_patterns
         .iter()
         .map(|p| format!(r"(?i){}S?", p))
+        .chain(rules.patterns.iter().map(|p| format!("(?:{})", p)))
         .collect::<Vec<_>>()
         .join("|");
 
@@ -164,17 +294,31 @@ pub fn scan_file_for_secrets(path: &Path) -> Result<Vec<Secret>, SecretError> {
         r#"^\s*set\s+(?:-[gx]+\s+)?([A-Z_][A-Z0-9_]*)\s+["']?([^"'\n]+?)["']?\s*(?:#.*)?$"#,
     )?;
 
+    let annotation_regex = Regex::new(r"#\s*slnky:(secret|ignore)\s*$")?;
+
+    let is_sensitive = |name: &str| {
+        sensitive_regex.is_match(name) && !rules.ignore_names.iter().any(|n| n == name)
+    };
+
     let mut secrets = Vec::new();
 
-    for (line_num, line) in reader.lines().enumerate() {
+    for (line_num, line) in lines.enumerate() {
         let line = line?;
-        let line_number = line_num + 1;
+        let line_number = line_num + first_line;
+
+        let annotation = annotation_regex
+            .captures(&line)
+            .and_then(|caps| caps.get(1))
+            .map(|m| m.as_str());
+        if annotation == Some("ignore") {
+            continue;
+        }
 
         if let Some(caps) = bash_regex.captures(&line) {
             if let (Some(name), Some(value)) = (caps.get(1), caps.get(2)) {
                 let name_str = name.as_str();
                 let value_str = value.as_str();
-                if sensitive_regex.is_match(name_str)
+                if (annotation == Some("secret") || is_sensitive(name_str))
                     && !value_str.is_empty()
                     && !value_str.starts_with('$')
                 {
@@ -190,7 +334,7 @@ pub fn scan_file_for_secrets(path: &Path) -> Result<Vec<Secret>, SecretError> {
             if let (Some(name), Some(value)) = (caps.get(1), caps.get(2)) {
                 let name_str = name.as_str();
                 let value_str = value.as_str();
-                if sensitive_regex.is_match(name_str)
+                if (annotation == Some("secret") || is_sensitive(name_str))
                     && !value_str.is_empty()
                     && !value_str.starts_with('$')
                 {
@@ -208,49 +352,192 @@ pub fn scan_file_for_secrets(path: &Path) -> Result<Vec<Secret>, SecretError> {
     Ok(secrets)
 }
 
-pub fn create_template(file: &Path, secrets: &[Secret]) -> Result<PathBuf, SecretError> {
+/// `file` with each secret's value replaced by its `${NAME}` placeholder on
+/// the line it was found.
+fn templated_content(file: &Path, secrets: &[Secret]) -> Result<String, SecretError> {
     let file_content = fs::read_to_string(file)?;
-    let lines: Vec<&str> = file_content.lines().collect();
-    let mut templated_lines = lines.clone();
+    let mut lines: Vec<String> = file_content.lines().map(String::from).collect();
 
     for secret in secrets {
-        if secret.line_number > 0 && secret.line_number <= templated_lines.len() {
-            let line_idx = secret.line_number - 1;
-            let original_line = templated_lines[line_idx];
-
+        if secret.line_number > 0 && secret.line_number <= lines.len() {
+            let line = &mut lines[secret.line_number - 1];
             let placeholder = format!("${{{}}}", secret.name);
-
-            let templated_line = original_line.replace(&secret.value, &placeholder);
-
-            templated_lines[line_idx] = Box::leak(templated_line.into_boxed_str());
+            *line = line.replace(&secret.value, &placeholder);
         }
     }
 
-    let template_path = file.with_extension(
+    let mut content = lines.join("\n");
+    if file_content.ends_with('\n') {
+        content.push('\n');
+    }
+    Ok(content)
+}
+
+/// Where `create_template` writes the template for `file`.
+pub fn template_path(file: &Path) -> PathBuf {
+    file.with_extension(
         file.extension()
             .and_then(|e| e.to_str())
             .map(|e| format!("{}.template", e))
             .unwrap_or_else(|| "template".to_string()),
-    );
+    )
+}
+
+/// Whether `file` is a template written by `create_template`.
+pub fn is_template_file(file: &Path) -> bool {
+    file.extension() == Some("template".as_ref())
+}
 
-    let mut output_file = File::create(&template_path)?;
-    for (i, line) in templated_lines.iter().enumerate() {
-        if i > 0 {
-            writeln!(output_file)?;
+pub fn create_template(file: &Path, secrets: &[Secret]) -> Result<PathBuf, SecretError> {
+    let content = templated_content(file, secrets)?;
+    let template_path = template_path(file);
+    File::create(&template_path)?.write_all(content.as_bytes())?;
+    Ok(template_path)
+}
+
+/// Replaces the secrets in `file` itself with their placeholders, so the
+/// working copy is safe to commit. The original is first copied to
+/// `<file>.backup`, whose path is returned.
+pub fn template_in_place(file: &Path, secrets: &[Secret]) -> Result<PathBuf, SecretError> {
+    let content = templated_content(file, secrets)?;
+    let backup_path = free_backup_path(file);
+    fs::copy(file, &backup_path)?;
+    fs::write(file, content)?;
+    Ok(backup_path)
+}
+
+/// Reverses `template_in_place`: the placeholders in each file the stored
+/// secrets came from are replaced with the decrypted values. Returns the
+/// files that changed.
+pub fn restore_in_place(
+    store: &SecretStore,
+    passphrase: &str,
+) -> Result<Vec<PathBuf>, SecretError> {
+    let data = store.decrypt_data(passphrase)?;
+    let mut restored = Vec::new();
+
+    for file in stored_files(&data) {
+        if !file.exists() {
+            continue;
+        }
+        let content = fs::read_to_string(&file)?;
+        let substituted = substitute_placeholders(&content, &data, &file);
+        if substituted != content {
+            fs::write(&file, substituted)?;
+            restored.push(file);
         }
-        write!(output_file, "{}", line)?;
     }
 
-    Ok(template_path)
+    Ok(restored)
+}
+
+/// Writes each stored secret's original file back from its `.template`,
+/// filling in the decrypted values. Returns the files written.
+pub fn restore_templates(
+    store: &SecretStore,
+    passphrase: &str,
+) -> Result<Vec<PathBuf>, SecretError> {
+    let data = store.decrypt_data(passphrase)?;
+    let mut restored = Vec::new();
+
+    for file in stored_files(&data) {
+        let template = template_path(&file);
+        if !template.exists() {
+            continue;
+        }
+        let content = fs::read_to_string(&template)?;
+        fs::write(&file, substitute_placeholders(&content, &data, &file))?;
+        restored.push(file);
+    }
+
+    Ok(restored)
 }
 
-pub fn encrypt_secrets(secrets: &[Secret], passphrase: &str) -> Result<SecretStore, SecretError> {
-    let mut secret_map = HashMap::new();
-    let mut metadata = HashMap::new();
+fn stored_files(data: &EncryptedData) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = data.metadata.values().map(|m| m.file.clone()).collect();
+    files.sort();
+    files.dedup();
+    files
+}
+
+/// Fills in the placeholders of the secrets that were found in `file`, each
+/// only on the line it was found, so `${NAME}` references the user wrote
+/// elsewhere are left alone.
+fn substitute_placeholders(content: &str, data: &EncryptedData, file: &Path) -> String {
+    let mut lines: Vec<String> = content.lines().map(String::from).collect();
+    for (name, meta) in &data.metadata {
+        if meta.file != file || meta.line_number == 0 || meta.line_number > lines.len() {
+            continue;
+        }
+        if let Some(value) = data.secrets.get(name) {
+            let line = &mut lines[meta.line_number - 1];
+            *line = line.replace(&format!("${{{}}}", name), value);
+        }
+    }
+
+    let mut output = lines.join("\n");
+    if content.ends_with('\n') {
+        output.push('\n');
+    }
+    output
+}
 
+/// Encrypts `secrets` with `passphrase` and writes the store to
+/// `store_path` (see `secrets_store_path`), ASCII-armored when `armor` is set.
+pub fn encrypt_secrets(
+    secrets: &[Secret],
+    passphrase: &str,
+    store_path: &Path,
+    armor: bool,
+) -> Result<SecretStore, SecretError> {
+    let encrypted_data = EncryptedData {
+        secrets: HashMap::new(),
+        metadata: HashMap::new(),
+    };
+    write_store(encrypted_data, secrets, passphrase, store_path, armor)
+}
+
+/// Like `encrypt_secrets`, but keeps the secrets already stored at
+/// `store_path` that none of `secrets` replaces by name. In-place templating
+/// leaves only placeholders behind, so a later run cannot scan the earlier
+/// values again. The existing store must decrypt with `passphrase`.
+pub fn add_to_store(
+    secrets: &[Secret],
+    passphrase: &str,
+    store_path: &Path,
+    armor: bool,
+) -> Result<SecretStore, SecretError> {
+    if !store_path.exists() {
+        return encrypt_secrets(secrets, passphrase, store_path, armor);
+    }
+    let existing = SecretStore::load(store_path)?.decrypt_data(passphrase)?;
+    write_store(existing, secrets, passphrase, store_path, armor)
+}
+
+fn write_store(
+    mut encrypted_data: EncryptedData,
+    secrets: &[Secret],
+    passphrase: &str,
+    store_path: &Path,
+    armor: bool,
+) -> Result<SecretStore, SecretError> {
     for secret in secrets {
-        secret_map.insert(secret.name.clone(), secret.value.clone());
-        metadata.insert(
+        if let Some(meta) = encrypted_data.metadata.get(&secret.name) {
+            if meta.file != secret.file || meta.line_number != secret.line_number {
+                return Err(SecretError::DuplicateName(format!(
+                    "{} ({}:{} and {}:{})",
+                    secret.name,
+                    meta.file.display(),
+                    meta.line_number,
+                    secret.file.display(),
+                    secret.line_number
+                )));
+            }
+        }
+        encrypted_data
+            .secrets
+            .insert(secret.name.clone(), secret.value.clone());
+        encrypted_data.metadata.insert(
             secret.name.clone(),
             SecretMetadata {
                 file: secret.file.clone(),
@@ -259,18 +546,32 @@ pub fn encrypt_secrets(secrets: &[Secret], passphrase: &str) -> Result<SecretSto
         );
     }
 
-    let encrypted_data = EncryptedData {
-        secrets: secret_map,
-        metadata,
-    };
+    let mut store = SecretStore::new(store_path.to_path_buf());
+    store.encrypted_data = encrypt_data(&encrypted_data, passphrase, armor)?;
+    store.save()?;
 
-    let json_data = serde_json::to_vec(&encrypted_data)?;
+    Ok(store)
+}
+
+fn encrypt_data(
+    data: &EncryptedData,
+    passphrase: &str,
+    armor: bool,
+) -> Result<Vec<u8>, SecretError> {
+    let json_data = serde_json::to_vec(data)?;
 
     let encryptor = Encryptor::with_user_passphrase(SecrecySecret::new(passphrase.to_string()));
 
     let mut encrypted = Vec::new();
+    let format = if armor {
+        Format::AsciiArmor
+    } else {
+        Format::Binary
+    };
+    let output = ArmoredWriter::wrap_output(&mut encrypted, format)
+        .map_err(|e| SecretError::Encryption(format!("Failed to create encryptor: {}", e)))?;
     let mut writer = encryptor
-        .wrap_output(&mut encrypted)
+        .wrap_output(output)
         .map_err(|e| SecretError::Encryption(format!("Failed to create encryptor: {}", e)))?;
 
     writer
@@ -279,26 +580,148 @@ pub fn encrypt_secrets(secrets: &[Secret], passphrase: &str) -> Result<SecretSto
 
     writer
         .finish()
+        .and_then(|output| output.finish())
         .map_err(|e| SecretError::Encryption(format!("Failed to finish encryption: {}", e)))?;
 
-    let secrets_dir = directories::BaseDirs::new()
-        .ok_or_else(|| {
-            SecretError::Io(std::io::Error::new(
-                std::io::ErrorKind::NotFound,
-                "Could not determine home directory",
-            ))
-        })?
-        .data_local_dir()
-        .join("slinky");
+    Ok(encrypted)
+}
+
+/// `$EDITOR` may carry arguments (`code --wait`), so on Unix it is run by the
+/// shell, with the file passed as `$1` rather than spliced into the command.
+fn editor_command(editor: &str, file: &Path) -> Command {
+    if cfg!(unix) {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c")
+            .arg(format!("{} \"$1\"", editor))
+            .arg("sh")
+            .arg(file);
+        cmd
+    } else {
+        let mut cmd = Command::new(editor);
+        cmd.arg(file);
+        cmd
+    }
+}
+
+/// Decrypts the store into a private temporary `NAME=value` file, runs
+/// `editor` on it and re-encrypts whatever was saved, keeping the original
+/// file and line of secrets that are still there. The temporary file is
+/// overwritten with zeros and removed afterwards, even if the editor fails.
+/// The store keeps its binary or armored format. Returns false when nothing
+/// changed.
+pub fn edit_store(
+    store: &mut SecretStore,
+    passphrase: &str,
+    editor: &str,
+) -> Result<bool, SecretError> {
+    let mut data = store.decrypt_data(passphrase)?;
+    let listing = format_listing(&data.secrets);
+
+    let path = create_private_file(&listing)?;
+    let edited = editor_command(editor, &path)
+        .status()
+        .map_err(SecretError::Io)
+        .and_then(|status| {
+            if status.success() {
+                Ok(fs::read_to_string(&path)?)
+            } else {
+                Err(SecretError::EditAborted(format!(
+                    "editor exited with status {}",
+                    status.code().unwrap_or(-1)
+                )))
+            }
+        });
+    scrub_file(&path);
+    let edited = parse_listing(&edited?)?;
 
-    fs::create_dir_all(&secrets_dir)?;
-    let secrets_path = secrets_dir.join("secrets.age");
+    if edited == data.secrets {
+        return Ok(false);
+    }
 
-    let mut store = SecretStore::new(secrets_path);
-    store.encrypted_data = encrypted;
+    data.metadata.retain(|name, _| edited.contains_key(name));
+    for name in edited.keys() {
+        data.metadata
+            .entry(name.clone())
+            .or_insert_with(|| SecretMetadata {
+                file: PathBuf::new(),
+                line_number: 0,
+            });
+    }
+    data.secrets = edited;
+
+    store.encrypted_data = encrypt_data(&data, passphrase, store.is_armored())?;
     store.save()?;
+    Ok(true)
+}
 
-    Ok(store)
+fn format_listing(secrets: &HashMap<String, String>) -> String {
+    let mut names: Vec<&String> = secrets.keys().collect();
+    names.sort();
+    let mut listing =
+        String::from("# One NAME=value per line. Delete a line to remove that secret.\n");
+    for name in names {
+        listing.push_str(&format!("{}={}\n", name, secrets[name]));
+    }
+    listing
+}
+
+fn parse_listing(text: &str) -> Result<HashMap<String, String>, SecretError> {
+    let mut secrets = HashMap::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim_start();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match line.split_once('=') {
+            Some((name, value)) if !name.trim().is_empty() => {
+                secrets.insert(name.trim().to_string(), value.to_string());
+            }
+            _ => {
+                return Err(SecretError::EditAborted(format!(
+                    "line {} is not NAME=value",
+                    index + 1
+                )))
+            }
+        }
+    }
+    Ok(secrets)
+}
+
+/// Creates a new file only the current user can read, with a name that
+/// can't already exist.
+fn create_private_file(content: &str) -> Result<PathBuf, SecretError> {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    let path = std::env::temp_dir().join(format!(
+        "slinky-secrets-{}-{}.env",
+        std::process::id(),
+        nanos
+    ));
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(&path)?;
+    file.write_all(content.as_bytes())?;
+    Ok(path)
+}
+
+/// Overwrites `path` with zeros before removing it, so the plaintext isn't
+/// left in the freed blocks.
+fn scrub_file(path: &Path) {
+    if let Ok(len) = fs::metadata(path).map(|m| m.len()) {
+        if let Ok(mut file) = fs::OpenOptions::new().write(true).open(path) {
+            let _ = file.write_all(&vec![0; len as usize]);
+            let _ = file.sync_all();
+        }
+    }
+    let _ = fs::remove_file(path);
 }
 
 #[allow(dead_code)]
@@ -334,19 +757,26 @@ pub fn decrypt_and_substitute(
     Ok(())
 }
 
-#[allow(dead_code)]
+/// Where the encrypted store lives: `secrets_store_path` from the config
+/// (relative paths are inside the stow directory, so the store can be
+/// committed with the dotfiles), or `get_default_secrets_path()`.
+pub fn secrets_store_path(config: &Config) -> Result<PathBuf, SecretError> {
+    match &config.secrets_store_path {
+        Some(path) if path.is_relative() => Ok(config.stow_dir.join(path)),
+        Some(path) => Ok(path.clone()),
+        None => get_default_secrets_path(),
+    }
+}
+
 pub fn get_default_secrets_path() -> Result<PathBuf, SecretError> {
-    let base_dirs = directories::BaseDirs::new().ok_or_else(|| {
+    let secrets_dir = crate::config::secrets_dir().ok_or_else(|| {
         SecretError::Io(std::io::Error::new(
             std::io::ErrorKind::NotFound,
             "Could not determine home directory",
         ))
     })?;
 
-    Ok(base_dirs
-        .data_local_dir()
-        .join("slinky")
-        .join("secrets.age"))
+    Ok(secrets_dir.join("secrets.age"))
 }
 
 pub fn scan_shell_configs() -> Result<Vec<PathBuf>, SecretError> {
@@ -360,19 +790,73 @@ pub fn scan_shell_configs() -> Result<Vec<PathBuf>, SecretError> {
         .home_dir()
         .to_path_buf();
 
-    let config_files = vec![
-        home.join(".zshrc"),
-        home.join(".bashrc"),
-        home.join(".bash_profile"),
-        home.join(".profile"),
-        home.join(".config/fish/config.fish"),
-    ];
+    let config_files = shell_config_candidates(&home, |key| std::env::var(key).ok());
 
     let existing_files: Vec<PathBuf> = config_files.into_iter().filter(|p| p.exists()).collect();
 
     Ok(existing_files)
 }
 
+/// Lists possible shell rc files, honoring `ZDOTDIR` and `XDG_CONFIG_HOME`,
+/// with the files for the user's `$SHELL` first and duplicates removed.
+fn shell_config_candidates(home: &Path, env: impl Fn(&str) -> Option<String>) -> Vec<PathBuf> {
+    let non_empty = |key: &str| env(key).filter(|v| !v.is_empty()).map(PathBuf::from);
+    let zdotdir = non_empty("ZDOTDIR").unwrap_or_else(|| home.to_path_buf());
+    let xdg_config = non_empty("XDG_CONFIG_HOME").unwrap_or_else(|| home.join(".config"));
+
+    let mut groups: Vec<(&str, Vec<PathBuf>)> = vec![
+        (
+            "zsh",
+            vec![
+                zdotdir.join(".zshrc"),
+                zdotdir.join(".zshenv"),
+                zdotdir.join(".zprofile"),
+                xdg_config.join("zsh").join(".zshrc"),
+            ],
+        ),
+        (
+            "bash",
+            vec![home.join(".bashrc"), home.join(".bash_profile")],
+        ),
+        ("fish", vec![xdg_config.join("fish").join("config.fish")]),
+        (
+            "nu",
+            vec![
+                xdg_config.join("nushell").join("config.nu"),
+                xdg_config.join("nushell").join("env.nu"),
+            ],
+        ),
+        (
+            "elvish",
+            vec![
+                xdg_config.join("elvish").join("rc.elv"),
+                home.join(".elvish").join("rc.elv"),
+            ],
+        ),
+        ("sh", vec![home.join(".profile")]),
+    ];
+
+    if let Some(shell) = env("SHELL") {
+        let shell_name = Path::new(&shell)
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        if let Some(pos) = groups.iter().position(|(name, _)| *name == shell_name) {
+            let group = groups.remove(pos);
+            groups.insert(0, group);
+        }
+    }
+
+    let mut candidates = Vec::new();
+    for path in groups.into_iter().flat_map(|(_, paths)| paths) {
+        if !candidates.contains(&path) {
+            candidates.push(path);
+        }
+    }
+
+    candidates
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -393,6 +877,53 @@ mod tests {
         assert!(secrets.iter().any(|s| s.name == "GITHUB_TOKEN"));
     }
 
+    #[test]
+    fn test_configured_name_rules() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "export GITLAB_PAT=glpat-abc123").unwrap();
+        writeln!(file, "export SENTRY_DSN=https://key@sentry.example.com/1").unwrap();
+        writeln!(file, "export PASSWORD_MIN_LENGTH=12").unwrap();
+        writeln!(file, "export DB_PASSWORD=hunter2").unwrap();
+        file.flush().unwrap();
+
+        let names =
+            |secrets: Vec<Secret>| -> Vec<String> { secrets.into_iter().map(|s| s.name).collect() };
+        assert_eq!(
+            names(scan_file_for_secrets(file.path()).unwrap()),
+            vec!["PASSWORD_MIN_LENGTH", "DB_PASSWORD"]
+        );
+
+        let rules = SecretNameRules {
+            patterns: vec!["_PAT$".to_string(), "DSN".to_string()],
+            ignore_names: vec!["PASSWORD_MIN_LENGTH".to_string()],
+        };
+        assert_eq!(
+            names(scan_file_for_secrets_with(file.path(), &rules).unwrap()),
+            vec!["GITLAB_PAT", "SENTRY_DSN", "DB_PASSWORD"]
+        );
+
+        let invalid = SecretNameRules {
+            patterns: vec!["(".to_string()],
+            ..Default::default()
+        };
+        assert!(matches!(
+            scan_file_for_secrets_with(file.path(), &invalid),
+            Err(SecretError::Regex(_))
+        ));
+    }
+
+    #[test]
+    fn test_binary_file_is_skipped() {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR\xff\xfe")
+            .unwrap();
+        file.write_all(b"\nexport API_KEY=secret123\n").unwrap();
+        file.flush().unwrap();
+
+        let secrets = scan_file_for_secrets(file.path()).unwrap();
+        assert!(secrets.is_empty());
+    }
+
     #[test]
     fn test_scan_fish_syntax() {
         let mut file = NamedTempFile::new().unwrap();
@@ -415,13 +946,197 @@ mod tests {
             1,
         )];
 
+        let dir = tempfile::tempdir().unwrap();
         let passphrase = "test_passphrase_123";
-        let store = encrypt_secrets(&secrets, passphrase).unwrap();
+        let store =
+            encrypt_secrets(&secrets, passphrase, &dir.path().join("secrets.age"), false).unwrap();
 
         let decrypted = store.decrypt_with_passphrase(passphrase).unwrap();
         assert_eq!(decrypted.get("TEST_SECRET").unwrap(), "sensitive_value");
     }
 
+    #[test]
+    fn test_armored_store_roundtrip() {
+        let secrets = vec![Secret::new(
+            "API_KEY".to_string(),
+            "secret123".to_string(),
+            PathBuf::from("/test/.zshrc"),
+            1,
+        )];
+
+        let dir = tempfile::tempdir().unwrap();
+        let store_path = dir.path().join("secrets.age");
+        encrypt_secrets(&secrets, "armor_passphrase", &store_path, true).unwrap();
+
+        let contents = fs::read(&store_path).unwrap();
+        assert!(contents.starts_with(ARMOR_BEGIN));
+        assert!(contents
+            .iter()
+            .all(|&b| b.is_ascii_graphic() || b.is_ascii_whitespace()));
+
+        let store = SecretStore::load(&store_path).unwrap();
+        assert!(store.is_armored());
+        assert_eq!(
+            store.decrypt_with_passphrase("armor_passphrase").unwrap()["API_KEY"],
+            "secret123"
+        );
+
+        encrypt_secrets(&secrets, "armor_passphrase", &store_path, false).unwrap();
+        let store = SecretStore::load(&store_path).unwrap();
+        assert!(!store.is_armored());
+        assert_eq!(
+            store.decrypt_with_passphrase("armor_passphrase").unwrap()["API_KEY"],
+            "secret123"
+        );
+    }
+
+    #[test]
+    fn test_configured_store_path_is_written() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config {
+            stow_dir: dir.path().join("dotfiles"),
+            secrets_store_path: Some(PathBuf::from("secrets/store.age")),
+            ..Config::default()
+        };
+        let store_path = secrets_store_path(&config).unwrap();
+        assert_eq!(store_path, dir.path().join("dotfiles/secrets/store.age"));
+
+        let secrets = vec![Secret::new(
+            "API_KEY".to_string(),
+            "secret123".to_string(),
+            PathBuf::from("/test/.zshrc"),
+            1,
+        )];
+        encrypt_secrets(&secrets, "store_passphrase", &store_path, false).unwrap();
+        let store = SecretStore::load(&store_path).unwrap();
+        assert_eq!(
+            store.decrypt_with_passphrase("store_passphrase").unwrap()["API_KEY"],
+            "secret123"
+        );
+
+        let absolute = Config {
+            secrets_store_path: Some(dir.path().join("elsewhere.age")),
+            ..config
+        };
+        assert_eq!(
+            secrets_store_path(&absolute).unwrap(),
+            dir.path().join("elsewhere.age")
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_edit_store_with_scripted_editor() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let secrets = vec![
+            Secret::new(
+                "API_KEY".to_string(),
+                "old".to_string(),
+                PathBuf::from("/test/.zshrc"),
+                3,
+            ),
+            Secret::new(
+                "DROPPED".to_string(),
+                "gone".to_string(),
+                PathBuf::from("/test/.zshrc"),
+                4,
+            ),
+        ];
+        let passphrase = "edit_passphrase";
+        let store_path = dir.path().join("secrets.age");
+        let mut store = encrypt_secrets(&secrets, passphrase, &store_path, false).unwrap();
+
+        // The "editor" records the file it was given, checks the listing
+        // and rewrites it.
+        let seen = dir.path().join("seen");
+        let editor = dir.path().join("editor.sh");
+        fs::write(
+            &editor,
+            format!(
+                "#!/bin/sh\necho \"$1\" > {seen}\ngrep -qx 'API_KEY=old' \"$1\" || exit 1\nprintf 'API_KEY=new\\nADDED=value\\n' > \"$1\"\n",
+                seen = seen.display()
+            ),
+        )
+        .unwrap();
+        fs::set_permissions(&editor, fs::Permissions::from_mode(0o755)).unwrap();
+
+        // Run as `sh <script>`, like an $EDITOR of `code --wait`.
+        let command = format!("sh {}", editor.display());
+        assert!(edit_store(&mut store, passphrase, &command).unwrap());
+
+        let reloaded = SecretStore::load(&store_path).unwrap();
+        let data = reloaded.decrypt_data(passphrase).unwrap();
+        assert_eq!(data.secrets.len(), 2);
+        assert_eq!(data.secrets["API_KEY"], "new");
+        assert_eq!(data.secrets["ADDED"], "value");
+        assert_eq!(data.metadata["API_KEY"].line_number, 3);
+        assert!(!data.metadata.contains_key("DROPPED"));
+
+        let temp_file = PathBuf::from(fs::read_to_string(&seen).unwrap().trim());
+        assert!(!temp_file.exists());
+
+        // An editor that fails leaves the store alone.
+        assert!(edit_store(&mut store, passphrase, "false").is_err());
+        let data = SecretStore::load(&store_path)
+            .unwrap()
+            .decrypt_data(passphrase)
+            .unwrap();
+        assert_eq!(data.secrets["API_KEY"], "new");
+    }
+
+    #[test]
+    fn test_shell_configs_honor_zdotdir() {
+        let home = tempfile::tempdir().unwrap();
+        let zdotdir = tempfile::tempdir().unwrap();
+        let zshrc = zdotdir.path().join(".zshrc");
+        fs::write(&zshrc, "export A=1").unwrap();
+        fs::write(home.path().join(".bashrc"), "export B=1").unwrap();
+
+        let zdotdir_str = zdotdir.path().to_string_lossy().into_owned();
+        let env = |key: &str| match key {
+            "ZDOTDIR" => Some(zdotdir_str.clone()),
+            "SHELL" => Some("/bin/bash".to_string()),
+            _ => None,
+        };
+
+        let existing: Vec<PathBuf> = shell_config_candidates(home.path(), env)
+            .into_iter()
+            .filter(|p| p.exists())
+            .collect();
+
+        assert_eq!(existing, vec![home.path().join(".bashrc"), zshrc]);
+    }
+
+    #[test]
+    fn test_secret_annotation_flags_line() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "export DEPLOY_KEY=abc123 # slnky:secret").unwrap();
+        writeln!(file, "set -gx WEBHOOK hooks.example.com/x # slnky:secret").unwrap();
+        writeln!(file, "export EDITOR=nvim").unwrap();
+        file.flush().unwrap();
+
+        let secrets = scan_file_for_secrets(file.path()).unwrap();
+        assert_eq!(secrets.len(), 2);
+        assert!(secrets
+            .iter()
+            .any(|s| s.name == "DEPLOY_KEY" && s.value == "abc123"));
+        assert!(secrets.iter().any(|s| s.name == "WEBHOOK"));
+    }
+
+    #[test]
+    fn test_ignore_annotation_suppresses_line() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "export SESSION_NAME=main # slnky:ignore").unwrap();
+        writeln!(file, "export API_KEY=secret123").unwrap();
+        file.flush().unwrap();
+
+        let secrets = scan_file_for_secrets(file.path()).unwrap();
+        assert_eq!(secrets.len(), 1);
+        assert_eq!(secrets[0].name, "API_KEY");
+    }
+
     #[test]
     fn test_create_template() {
         let mut file = NamedTempFile::new().unwrap();
@@ -443,4 +1158,123 @@ mod tests {
         assert!(!content.contains("secret123"));
         assert!(content.contains("NORMAL=value"));
     }
+
+    #[test]
+    fn test_template_in_place_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join(".zshrc");
+        let original = "export API_KEY=secret123\nexport NORMAL=value\necho ${API_KEY}\n";
+        fs::write(&file, original).unwrap();
+
+        let secrets = scan_file_for_secrets(&file).unwrap();
+        assert_eq!(secrets.len(), 1);
+
+        let backup = template_in_place(&file, &secrets).unwrap();
+        assert_eq!(fs::read_to_string(&backup).unwrap(), original);
+        assert_eq!(
+            fs::read_to_string(&file).unwrap(),
+            "export API_KEY=${API_KEY}\nexport NORMAL=value\necho ${API_KEY}\n"
+        );
+
+        let passphrase = "in_place_passphrase";
+        let store =
+            encrypt_secrets(&secrets, passphrase, &dir.path().join("secrets.age"), false).unwrap();
+
+        let restored = restore_in_place(&store, passphrase).unwrap();
+        assert_eq!(restored, vec![file.clone()]);
+        assert_eq!(fs::read_to_string(&file).unwrap(), original);
+
+        assert!(restore_in_place(&store, passphrase).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_repeated_in_place_runs_keep_earlier_secrets() {
+        let dir = tempfile::tempdir().unwrap();
+        let store_path = dir.path().join("secrets.age");
+        let passphrase = "repeat_passphrase";
+        let zshrc = dir.path().join(".zshrc");
+        let profile = dir.path().join(".profile");
+        fs::write(&zshrc, "export API_KEY=secret123\n").unwrap();
+        fs::write(&profile, "export GITHUB_TOKEN=ghp_abcdef\n").unwrap();
+
+        let first = scan_file_for_secrets(&zshrc).unwrap();
+        let first_backup = template_in_place(&zshrc, &first).unwrap();
+        add_to_store(&first, passphrase, &store_path, false).unwrap();
+
+        // The second run finds only the new secret; the first file is
+        // already templated.
+        assert!(scan_file_for_secrets(&zshrc).unwrap().is_empty());
+        let second = scan_file_for_secrets(&profile).unwrap();
+        template_in_place(&profile, &second).unwrap();
+        let store = add_to_store(&second, passphrase, &store_path, false).unwrap();
+        assert!(add_to_store(&second, "wrong", &store_path, false).is_err());
+
+        assert_eq!(
+            restore_in_place(&store, passphrase).unwrap(),
+            vec![profile.clone(), zshrc.clone()]
+        );
+        assert_eq!(
+            fs::read_to_string(&zshrc).unwrap(),
+            "export API_KEY=secret123\n"
+        );
+        assert_eq!(
+            fs::read_to_string(&profile).unwrap(),
+            "export GITHUB_TOKEN=ghp_abcdef\n"
+        );
+
+        fs::write(&zshrc, "export API_KEY=rotated\n").unwrap();
+        let rotated = scan_file_for_secrets(&zshrc).unwrap();
+        let second_backup = template_in_place(&zshrc, &rotated).unwrap();
+        assert_ne!(second_backup, first_backup);
+        assert_eq!(
+            fs::read_to_string(&first_backup).unwrap(),
+            "export API_KEY=secret123\n"
+        );
+    }
+
+    #[test]
+    fn test_duplicate_names_are_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let store_path = dir.path().join("secrets.age");
+        let zshrc = dir.path().join(".zshrc");
+        let profile = dir.path().join(".profile");
+        fs::write(&zshrc, "export API_KEY=secret123\n").unwrap();
+        fs::write(&profile, "export API_KEY=other456\n").unwrap();
+
+        let mut secrets = scan_file_for_secrets(&zshrc).unwrap();
+        secrets.extend(scan_file_for_secrets(&profile).unwrap());
+        let result = encrypt_secrets(&secrets, "passphrase", &store_path, false);
+        assert!(matches!(result, Err(SecretError::DuplicateName(_))));
+        assert!(!store_path.exists());
+
+        let first = scan_file_for_secrets(&zshrc).unwrap();
+        add_to_store(&first, "passphrase", &store_path, false).unwrap();
+        let second = scan_file_for_secrets(&profile).unwrap();
+        assert!(add_to_store(&second, "passphrase", &store_path, false).is_err());
+        assert!(add_to_store(&first, "passphrase", &store_path, false).is_ok());
+    }
+
+    #[test]
+    fn test_restore_templates_writes_originals() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("env.sh");
+        fs::write(&file, "export GITHUB_TOKEN=ghp_abcdef\n").unwrap();
+
+        let secrets = scan_file_for_secrets(&file).unwrap();
+        let template = create_template(&file, &secrets).unwrap();
+        assert_eq!(template, dir.path().join("env.sh.template"));
+        fs::remove_file(&file).unwrap();
+
+        let passphrase = "template_passphrase";
+        let store =
+            encrypt_secrets(&secrets, passphrase, &dir.path().join("secrets.age"), false).unwrap();
+        assert_eq!(
+            restore_templates(&store, passphrase).unwrap(),
+            vec![file.clone()]
+        );
+        assert_eq!(
+            fs::read_to_string(&file).unwrap(),
+            "export GITHUB_TOKEN=ghp_abcdef\n"
+        );
+    }
 }
@@ -1,7 +1,15 @@
 use std::fs::{self, File};
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::time::Duration;
+
+#[cfg(target_os = "macos")]
+use crate::config::config_dir;
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+use crate::config::config_dir_override;
+use crate::config::daemon_log_path;
+use crate::daemon::{parse_log_timestamp, unix_now};
 
 #[derive(Debug)]
 pub enum ServiceError {
@@ -33,10 +41,34 @@ impl From<std::io::Error> for ServiceError {
     }
 }
 
+#[cfg(target_os = "macos")]
 const LAUNCHD_LABEL: &str = "com.slinky.daemon";
 #[cfg(target_os = "linux")]
 const SYSTEMD_SERVICE_NAME: &str = "slinky";
 
+// Service managers start the daemon with a minimal environment, so anything git
+// needs to reach the remote (PATH, ssh-agent socket, custom ssh command) is baked in.
+const SERVICE_ENV_VARS: &[&str] = &["HOME", "PATH", "SSH_AUTH_SOCK", "GIT_SSH_COMMAND"];
+
+fn service_environment() -> Vec<(String, String)> {
+    capture_environment(|key| std::env::var(key).ok())
+}
+
+fn capture_environment<F>(lookup: F) -> Vec<(String, String)>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    SERVICE_ENV_VARS
+        .iter()
+        .filter_map(|key| {
+            lookup(key)
+                .filter(|value| !value.is_empty())
+                .map(|value| (key.to_string(), value))
+        })
+        .collect()
+}
+
+#[cfg(target_os = "macos")]
 fn get_launchd_plist_path() -> PathBuf {
     let home = std::env::var("HOME").unwrap_or_else(|_| "/".to_string());
     PathBuf::from(home)
@@ -55,19 +87,43 @@ fn get_systemd_service_path() -> PathBuf {
         .join(format!("{}.service", SYSTEMD_SERVICE_NAME))
 }
 
-fn generate_launchd_plist() -> Result<String, ServiceError> {
+#[cfg(target_os = "macos")]
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(target_os = "macos")]
+fn generate_launchd_plist(
+    working_dir: &Path,
+    env: &[(String, String)],
+) -> Result<String, ServiceError> {
     let exe_path = std::env::current_exe()?;
     let exe_str = exe_path.to_string_lossy();
 
-    let home = std::env::var("HOME").unwrap_or_else(|_| "/".to_string());
-    let log_path = PathBuf::from(&home)
-        .join(".config")
-        .join("slinky")
-        .join("daemon.log");
-    let err_path = PathBuf::from(&home)
-        .join(".config")
-        .join("slinky")
-        .join("daemon.err");
+    let env_entries: String = env
+        .iter()
+        .map(|(key, value)| {
+            format!(
+                "        <key>{}</key>\n        <string>{}</string>\n",
+                xml_escape(key),
+                xml_escape(value)
+            )
+        })
+        .collect();
+
+    let config_dir_args: String = config_dir_override()
+        .map(|dir| {
+            format!(
+                "        <string>--config-dir</string>\n        <string>{}</string>\n",
+                xml_escape(&dir.to_string_lossy())
+            )
+        })
+        .unwrap_or_default();
+    let log_path = daemon_log_path();
+    let err_path = config_dir().join("daemon.err");
 
     let plist = format!(
         r#"<?xml version="1.0" encoding="UTF-8"?>
@@ -79,7 +135,7 @@ fn generate_launchd_plist() -> Result<String, ServiceError> {
     <key>ProgramArguments</key>
     <array>
         <string>{}</string>
-        <string>daemon</string>
+{}        <string>daemon</string>
         <string>run</string>
     </array>
     <key>RunAtLoad</key>
@@ -93,6 +149,11 @@ fn generate_launchd_plist() -> Result<String, ServiceError> {
     <string>{}</string>
     <key>StandardErrorPath</key>
     <string>{}</string>
+    <key>WorkingDirectory</key>
+    <string>{}</string>
+    <key>EnvironmentVariables</key>
+    <dict>
+{}    </dict>
     <key>ProcessType</key>
     <string>Background</string>
     <key>Nice</key>
@@ -104,38 +165,95 @@ fn generate_launchd_plist() -> Result<String, ServiceError> {
 "#,
         LAUNCHD_LABEL,
         exe_str,
+        config_dir_args,
         log_path.display(),
-        err_path.display()
+        err_path.display(),
+        xml_escape(&working_dir.to_string_lossy()),
+        env_entries
     );
 
     Ok(plist)
 }
 
 #[cfg(target_os = "linux")]
-fn generate_systemd_service() -> Result<String, ServiceError> {
+fn systemd_escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('%', "%%")
+}
+
+#[cfg(target_os = "linux")]
+fn generate_systemd_service(
+    working_dir: &Path,
+    env: &[(String, String)],
+) -> Result<String, ServiceError> {
     let exe_path = std::env::current_exe()?;
     let exe_str = exe_path.to_string_lossy();
 
+    let env_lines: String = env
+        .iter()
+        .map(|(key, value)| format!("Environment=\"{}={}\"\n", key, systemd_escape(value)))
+        .collect();
+    let config_dir_args: String = config_dir_override()
+        .map(|dir| {
+            format!(
+                " --config-dir \"{}\"",
+                systemd_escape(&dir.to_string_lossy())
+            )
+        })
+        .unwrap_or_default();
+
     let service = format!(
         r#"[Unit]
 Description=Slinky Dotfiles Sync Daemon
-After=network.target
+Wants=network-online.target
+After=network-online.target
 
 [Service]
 Type=simple
-ExecStart={} daemon run
-Restart=on-failure
+ExecStart={}{} daemon run
+WorkingDirectory={}
+{}Restart=on-failure
 RestartSec=10
 
 [Install]
 WantedBy=default.target
 "#,
-        exe_str
+        exe_str,
+        config_dir_args,
+        working_dir.display(),
+        env_lines
     );
 
     Ok(service)
 }
 
+pub fn preview_service(working_dir: &Path) -> Result<(PathBuf, String), ServiceError> {
+    let env = service_environment();
+
+    #[cfg(target_os = "macos")]
+    {
+        Ok((
+            get_launchd_plist_path(),
+            generate_launchd_plist(working_dir, &env)?,
+        ))
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        Ok((
+            get_systemd_service_path(),
+            generate_systemd_service(working_dir, &env)?,
+        ))
+    }
+
+    #[cfg(not(any(target_os = "macos", target_os = "linux")))]
+    {
+        Err(ServiceError::UnsupportedPlatform)
+    }
+}
+
 pub fn is_service_installed() -> bool {
     #[cfg(target_os = "macos")]
     {
@@ -190,11 +308,13 @@ pub fn get_service_status() -> Result<(bool, bool), ServiceError> {
     }
 }
 
-pub fn install_service() -> Result<String, ServiceError> {
+pub fn install_service(working_dir: &Path) -> Result<String, ServiceError> {
     if is_service_installed() {
         return Err(ServiceError::AlreadyInstalled);
     }
 
+    let env = service_environment();
+
     #[cfg(target_os = "macos")]
     {
         let plist_path = get_launchd_plist_path();
@@ -203,7 +323,7 @@ pub fn install_service() -> Result<String, ServiceError> {
             fs::create_dir_all(parent)?;
         }
 
-        let plist_content = generate_launchd_plist()?;
+        let plist_content = generate_launchd_plist(working_dir, &env)?;
         let mut file = File::create(&plist_path)?;
         file.write_all(plist_content.as_bytes())?;
 
@@ -232,7 +352,7 @@ pub fn install_service() -> Result<String, ServiceError> {
             fs::create_dir_all(parent)?;
         }
 
-        let service_content = generate_systemd_service()?;
+        let service_content = generate_systemd_service(working_dir, &env)?;
         let mut file = File::create(&service_path)?;
         file.write_all(service_content.as_bytes())?;
 
@@ -402,11 +522,7 @@ pub fn stop_service() -> Result<String, ServiceError> {
 pub fn service_logs(lines: usize) -> Result<String, ServiceError> {
     #[cfg(target_os = "macos")]
     {
-        let home = std::env::var("HOME").unwrap_or_else(|_| "/".to_string());
-        let log_path = PathBuf::from(&home)
-            .join(".config")
-            .join("slinky")
-            .join("daemon.log");
+        let log_path = daemon_log_path();
 
         if !log_path.exists() {
             return Ok("No logs available".to_string());
@@ -431,12 +547,8 @@ pub fn service_logs(lines: usize) -> Result<String, ServiceError> {
             ])
             .output()?;
 
-        if !output.status.success() {
-            let home = std::env::var("HOME").unwrap_or_else(|_| "/".to_string());
-            let log_path = PathBuf::from(&home)
-                .join(".config")
-                .join("slinky")
-                .join("daemon.log");
+        let Some(journal) = journal_entries(&output) else {
+            let log_path = daemon_log_path();
 
             if log_path.exists() {
                 let content = fs::read_to_string(&log_path)?;
@@ -446,9 +558,9 @@ pub fn service_logs(lines: usize) -> Result<String, ServiceError> {
             }
 
             return Ok("No logs available".to_string());
-        }
+        };
 
-        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+        Ok(journal)
     }
 
     #[cfg(not(any(target_os = "macos", target_os = "linux")))]
@@ -457,6 +569,86 @@ pub fn service_logs(lines: usize) -> Result<String, ServiceError> {
     }
 }
 
+/// Parses a relative duration such as `90s`, `30m`, `1h`, `2d` or `1w`.
+pub fn parse_since(spec: &str) -> Option<Duration> {
+    let spec = spec.trim();
+    let split = spec.find(|c: char| !c.is_ascii_digit())?;
+    let (amount, unit) = spec.split_at(split);
+    let amount: u64 = amount.parse().ok()?;
+
+    let unit_secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86400,
+        "w" => 7 * 86400,
+        _ => return None,
+    };
+
+    Some(Duration::from_secs(amount * unit_secs))
+}
+
+/// Keeps log lines stamped at or after `cutoff` (seconds since the epoch).
+/// Lines without a timestamp follow the decision for the line before them.
+fn filter_log_since(content: &str, cutoff: u64) -> String {
+    let mut keep = false;
+    content
+        .lines()
+        .filter(|line| {
+            if let Some(at) = parse_log_timestamp(line) {
+                keep = at >= cutoff;
+            }
+            keep
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub fn service_logs_since(since: Duration) -> Result<String, ServiceError> {
+    let log_path = daemon_log_path();
+    let from_log_file = || -> Result<String, ServiceError> {
+        if !log_path.exists() {
+            return Ok("No logs available".to_string());
+        }
+        let content = fs::read_to_string(&log_path)?;
+        let cutoff = unix_now().saturating_sub(since.as_secs());
+        Ok(filter_log_since(&content, cutoff))
+    };
+
+    #[cfg(target_os = "linux")]
+    {
+        let output = Command::new("journalctl")
+            .args([
+                "--user",
+                "-u",
+                SYSTEMD_SERVICE_NAME,
+                "--since",
+                &format!("-{}s", since.as_secs()),
+                "--no-pager",
+            ])
+            .output()?;
+
+        if let Some(journal) = journal_entries(&output) {
+            return Ok(journal);
+        }
+    }
+
+    from_log_file()
+}
+
+/// The output of a successful `journalctl` run, or `None` when it failed or
+/// found nothing, e.g. because the daemon runs outside systemd and only
+/// writes its log file.
+#[cfg(target_os = "linux")]
+fn journal_entries(output: &std::process::Output) -> Option<String> {
+    let text = String::from_utf8_lossy(&output.stdout);
+    let trimmed = text.trim();
+    if !output.status.success() || trimmed.is_empty() || trimmed == "-- No entries --" {
+        return None;
+    }
+    Some(text.to_string())
+}
+
 pub fn get_platform_info() -> (&'static str, &'static str) {
     #[cfg(target_os = "macos")]
     {
@@ -473,3 +665,136 @@ pub fn get_platform_info() -> (&'static str, &'static str) {
         ("Unknown", "none")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_env() -> Vec<(String, String)> {
+        vec![
+            ("HOME".to_string(), "/home/tester".to_string()),
+            ("PATH".to_string(), "/usr/local/bin:/usr/bin".to_string()),
+        ]
+    }
+
+    #[test]
+    fn test_parse_since() {
+        assert_eq!(parse_since("45s"), Some(Duration::from_secs(45)));
+        assert_eq!(parse_since("30m"), Some(Duration::from_secs(1800)));
+        assert_eq!(parse_since("1h"), Some(Duration::from_secs(3600)));
+        assert_eq!(parse_since("2d"), Some(Duration::from_secs(172_800)));
+        assert_eq!(parse_since("1w"), Some(Duration::from_secs(604_800)));
+        assert_eq!(parse_since("h"), None);
+        assert_eq!(parse_since("10"), None);
+        assert_eq!(parse_since("10y"), None);
+    }
+
+    #[test]
+    fn test_filter_log_since() {
+        let content = "[2024-01-01 09:00:00] old\n\
+                       [2024-01-01 10:00:00] Re-link failed:\n\
+                       \x20 permission denied\n\
+                       [12:00:00] unparseable\n\
+                       [2024-01-01 11:00:00] done";
+        let cutoff = parse_log_timestamp("[2024-01-01 10:00:00]").unwrap();
+
+        assert_eq!(
+            filter_log_since(content, cutoff),
+            "[2024-01-01 10:00:00] Re-link failed:\n  permission denied\n[12:00:00] unparseable\n[2024-01-01 11:00:00] done"
+        );
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_journal_entries_skips_empty_journal() {
+        use std::os::unix::process::ExitStatusExt;
+        use std::process::{ExitStatus, Output};
+
+        let output = |status: i32, stdout: &str| Output {
+            status: ExitStatus::from_raw(status),
+            stdout: stdout.as_bytes().to_vec(),
+            stderr: Vec::new(),
+        };
+
+        assert_eq!(journal_entries(&output(0, "")), None);
+        assert_eq!(journal_entries(&output(0, "-- No entries --\n")), None);
+        assert_eq!(journal_entries(&output(256, "oops\n")), None);
+        assert_eq!(
+            journal_entries(&output(0, "Oct 14 slnky: linked\n")).as_deref(),
+            Some("Oct 14 slnky: linked\n")
+        );
+    }
+
+    #[test]
+    fn test_capture_environment_propagates_ssh_auth_sock() {
+        let env = capture_environment(|key| match key {
+            "PATH" => Some("/usr/bin".to_string()),
+            "SSH_AUTH_SOCK" => Some("/tmp/ssh-agent.sock".to_string()),
+            _ => None,
+        });
+
+        assert!(env.contains(&("PATH".to_string(), "/usr/bin".to_string())));
+        assert!(env.contains(&(
+            "SSH_AUTH_SOCK".to_string(),
+            "/tmp/ssh-agent.sock".to_string()
+        )));
+        assert!(!env.iter().any(|(key, _)| key == "GIT_SSH_COMMAND"));
+    }
+
+    #[test]
+    fn test_capture_environment_skips_empty_values() {
+        let env = capture_environment(|key| match key {
+            "SSH_AUTH_SOCK" => Some(String::new()),
+            _ => None,
+        });
+
+        assert!(env.is_empty());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_systemd_unit_propagates_ssh_environment() {
+        let mut env = test_env();
+        env.push((
+            "SSH_AUTH_SOCK".to_string(),
+            "/run/user/1000/ssh-agent.sock".to_string(),
+        ));
+        env.push((
+            "GIT_SSH_COMMAND".to_string(),
+            "ssh -i \"/home/tester/.ssh/id\"".to_string(),
+        ));
+
+        let unit = generate_systemd_service(Path::new("/home/tester/.dotfiles"), &env).unwrap();
+
+        assert!(unit.contains("Environment=\"SSH_AUTH_SOCK=/run/user/1000/ssh-agent.sock\""));
+        assert!(
+            unit.contains("Environment=\"GIT_SSH_COMMAND=ssh -i \\\"/home/tester/.ssh/id\\\"\"")
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_systemd_unit_directives() {
+        let unit =
+            generate_systemd_service(Path::new("/home/tester/.dotfiles"), &test_env()).unwrap();
+
+        assert!(unit.contains("Wants=network-online.target"));
+        assert!(unit.contains("After=network-online.target"));
+        assert!(unit.contains("WorkingDirectory=/home/tester/.dotfiles"));
+        assert!(unit.contains("Environment=\"HOME=/home/tester\""));
+        assert!(unit.contains("Environment=\"PATH=/usr/local/bin:/usr/bin\""));
+    }
+
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn test_launchd_plist_environment() {
+        let plist =
+            generate_launchd_plist(Path::new("/Users/tester/.dotfiles"), &test_env()).unwrap();
+
+        assert!(plist.contains("<key>WorkingDirectory</key>"));
+        assert!(plist.contains("<string>/Users/tester/.dotfiles</string>"));
+        assert!(plist.contains("<key>EnvironmentVariables</key>"));
+        assert!(plist.contains("<key>PATH</key>"));
+        assert!(plist.contains("<string>/usr/local/bin:/usr/bin</string>"));
+    }
+}
@@ -0,0 +1,477 @@
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::{load_config, state_path, Config};
+use crate::daemon::unix_now;
+use crate::error::{Result, SlinkyError};
+use crate::hooks::{hook_dirs, run_hooks, HookContext, HookEvent};
+use crate::remote::{clone_or_update, get_head, git_command, output_with_timeout, parse_repo_spec};
+use crate::state::record_package_link;
+use crate::stow::{
+    analyze_package_with, detach_symlink, execute_operations_with, find_nested_stow_dir,
+    find_packages_with, package_status, AnalyzeOptions, OpType, PackageState, StowPackage,
+    SymlinkOp,
+};
+
+/// Library entry point wrapping a loaded `Config`. Methods return structured
+/// results and never print, so the crate can be embedded in other tools.
+///
+/// `link` and `unlink` are also split into a `plan_*` step that only analyzes
+/// and an `apply_*` step that runs the hooks and changes the target, which is
+/// how the CLI drives them: it reviews or prompts about the plan in between.
+#[derive(Debug, Clone)]
+pub struct Slinky {
+    config: Config,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LinkResult {
+    pub package: String,
+    pub created: Vec<PathBuf>,
+    pub already_linked: usize,
+    pub skipped: Vec<SkippedFile>,
+    /// Post-link hook failures; the links were made regardless.
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SkippedFile {
+    pub target: PathBuf,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct UnlinkResult {
+    pub package: String,
+    pub removed: Vec<PathBuf>,
+    /// Post-unlink hook failures; the links were removed regardless.
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PackageReport {
+    pub name: String,
+    pub state: PackageState,
+    pub linked: usize,
+    pub total: usize,
+    pub conflicts: Vec<ConflictReport>,
+    /// Why the package could not be analyzed, for `PackageState::Error`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConflictReport {
+    pub target: PathBuf,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct InstallResult {
+    pub stow_dir: PathBuf,
+    pub packages: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SyncResult {
+    pub pulled: bool,
+    pub linked: Vec<LinkResult>,
+}
+
+#[allow(dead_code)]
+impl Slinky {
+    pub fn new(config: Config) -> Self {
+        Self { config }
+    }
+
+    pub fn load() -> Result<Self> {
+        let config = load_config().map_err(|e| SlinkyError::Config(e.to_string()))?;
+        Ok(Self::new(config))
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn packages(&self) -> Result<Vec<StowPackage>> {
+        find_packages_with(&self.config.stow_dir, &self.config.include_hidden_packages)
+            .map_err(|e| SlinkyError::Stow(e.to_string()))
+    }
+
+    pub fn link(&self, name: &str) -> Result<LinkResult> {
+        let package_path = self.package_path(name)?;
+        let target = &self.config.target_dir;
+        let operations = self.plan_link(&package_path, target, &self.analyze_options())?;
+        self.apply_link(name, &package_path, target, &operations, |_, _, _| {})
+    }
+
+    /// The operations linking `package_path` into `target` takes. Nothing is
+    /// changed; pass the result to `apply_link`.
+    pub fn plan_link(
+        &self,
+        package_path: &Path,
+        target: &Path,
+        options: &AnalyzeOptions,
+    ) -> Result<Vec<SymlinkOp>> {
+        Ok(analyze_package_with(package_path, target, options)?)
+    }
+
+    /// Runs the pre-link hooks, which can veto the link before anything
+    /// changes, then `operations`, then the post-link hooks, and records the
+    /// link in `state.toml`. `on_progress` is called after each operation.
+    pub fn apply_link<F>(
+        &self,
+        name: &str,
+        package_path: &Path,
+        target: &Path,
+        operations: &[SymlinkOp],
+        on_progress: F,
+    ) -> Result<LinkResult>
+    where
+        F: FnMut(usize, usize, &SymlinkOp),
+    {
+        self.run_hooks(HookEvent::PreLink, name, package_path, target)?;
+        execute_operations_with(operations, false, self.config.dir_mode(), on_progress)?;
+        // Only bookkeeping for `status --detailed`, so a failed write is ignored.
+        let _ = record_package_link(&state_path(), name, unix_now());
+        let warnings = self
+            .run_hooks(HookEvent::PostLink, name, package_path, target)?
+            .into_iter()
+            .collect();
+
+        let mut result = LinkResult {
+            package: name.to_string(),
+            warnings,
+            ..Default::default()
+        };
+        for op in operations {
+            match &op.op_type {
+                OpType::Create | OpType::Relink | OpType::Replace { .. } => {
+                    result.created.push(op.target.clone())
+                }
+                OpType::Skip(reason) if reason.contains("Already linked") => {
+                    result.already_linked += 1;
+                }
+                OpType::Skip(reason) => result.skipped.push(SkippedFile {
+                    target: op.target.clone(),
+                    reason: reason.clone(),
+                }),
+                OpType::Remove | OpType::Conflict(_) => {}
+            }
+        }
+
+        Ok(result)
+    }
+
+    pub fn unlink(&self, name: &str) -> Result<UnlinkResult> {
+        let package_path = self.package_path(name)?;
+        let target = &self.config.target_dir;
+        let operations = self.plan_unlink(&package_path, target, &self.analyze_options())?;
+        self.apply_unlink(
+            name,
+            &package_path,
+            target,
+            &operations,
+            false,
+            |_, _, _| {},
+        )
+    }
+
+    /// `Remove` operations for the links `package_path` currently has in
+    /// `target`. Nothing is changed; pass the result to `apply_unlink`.
+    pub fn plan_unlink(
+        &self,
+        package_path: &Path,
+        target: &Path,
+        options: &AnalyzeOptions,
+    ) -> Result<Vec<SymlinkOp>> {
+        Ok(self
+            .plan_link(package_path, target, options)?
+            .into_iter()
+            .filter(|op| match &op.op_type {
+                OpType::Skip(reason) => reason.contains("Already linked"),
+                _ => false,
+            })
+            .map(|op| SymlinkOp {
+                op_type: OpType::Remove,
+                ..op
+            })
+            .collect())
+    }
+
+    /// Runs the pre-unlink hooks, removes the links in `operations` (with
+    /// `detach`, replaces them with copies of their files) and runs the
+    /// post-unlink hooks. `on_progress` is called after each operation.
+    pub fn apply_unlink<F>(
+        &self,
+        name: &str,
+        package_path: &Path,
+        target: &Path,
+        operations: &[SymlinkOp],
+        detach: bool,
+        mut on_progress: F,
+    ) -> Result<UnlinkResult>
+    where
+        F: FnMut(usize, usize, &SymlinkOp),
+    {
+        self.run_hooks(HookEvent::PreUnlink, name, package_path, target)?;
+        let mut removed = Vec::new();
+        for (index, op) in operations.iter().enumerate() {
+            if op.target.is_symlink() {
+                if detach {
+                    detach_symlink(&op.target).map_err(|e| SlinkyError::Stow(e.to_string()))?;
+                } else {
+                    fs::remove_file(&op.target)?;
+                }
+                removed.push(op.target.clone());
+            }
+            on_progress(index + 1, operations.len(), op);
+        }
+        let warnings = self
+            .run_hooks(HookEvent::PostUnlink, name, package_path, target)?
+            .into_iter()
+            .collect();
+
+        Ok(UnlinkResult {
+            package: name.to_string(),
+            removed,
+            warnings,
+        })
+    }
+
+    /// Runs the `hooks.d` scripts for `event` around an operation on package
+    /// `name`. A failing pre-hook is an error that aborts the operation; a
+    /// failing post-hook is returned as a warning, since the change has
+    /// already been made.
+    pub fn run_hooks(
+        &self,
+        event: HookEvent,
+        name: &str,
+        package_path: &Path,
+        target: &Path,
+    ) -> Result<Option<String>> {
+        let ctx = HookContext {
+            package: name,
+            package_dir: package_path,
+            stow_dir: &self.config.stow_dir,
+            target_dir: target,
+        };
+
+        match run_hooks(&hook_dirs(package_path), event, &ctx) {
+            Ok(_) => Ok(None),
+            Err(e) if event.is_pre() => Err(SlinkyError::Hook(format!(
+                "{} ({} '{}' aborted)",
+                e,
+                if event == HookEvent::PreLink {
+                    "linking"
+                } else {
+                    "unlinking"
+                },
+                name
+            ))),
+            Err(e) => Ok(Some(e.to_string())),
+        }
+    }
+
+    pub fn status(&self) -> Result<Vec<PackageReport>> {
+        let options = AnalyzeOptions {
+            report_conflicts: true,
+            ..self.analyze_options()
+        };
+
+        let mut reports = Vec::new();
+        for package in self.packages()? {
+            let ops = match analyze_package_with(&package.path, &self.config.target_dir, &options) {
+                Ok(ops) => ops,
+                Err(e) => {
+                    reports.push(PackageReport {
+                        name: package.name,
+                        state: PackageState::Error,
+                        linked: 0,
+                        total: 0,
+                        conflicts: Vec::new(),
+                        error: Some(e.to_string()),
+                    });
+                    continue;
+                }
+            };
+            let status = package_status(&ops);
+            let conflicts = ops
+                .into_iter()
+                .filter_map(|op| match op.op_type {
+                    OpType::Conflict(reason) => Some(ConflictReport {
+                        target: op.target,
+                        reason,
+                    }),
+                    _ => None,
+                })
+                .collect();
+
+            reports.push(PackageReport {
+                name: package.name,
+                state: status.state,
+                linked: status.linked,
+                total: status.total,
+                conflicts,
+                error: None,
+            });
+        }
+
+        Ok(reports)
+    }
+
+    /// Clones or updates `spec` and points this instance's `stow_dir` at it.
+    /// The change is not saved; pass `config()` to `save_config` to persist it.
+    pub fn install(&mut self, spec: &str) -> Result<InstallResult> {
+        let repo_spec =
+            parse_repo_spec(spec).map_err(|e| SlinkyError::InvalidRepoSpec(e.to_string()))?;
+        let repo_path = clone_or_update(
+            &repo_spec,
+            self.config.min_free_space_mb,
+            self.config.git_timeout(),
+            false,
+        )
+        .map_err(|e| SlinkyError::Remote(e.to_string()))?;
+
+        self.config.stow_dir = find_nested_stow_dir(&repo_path).unwrap_or(repo_path);
+
+        Ok(InstallResult {
+            stow_dir: self.config.stow_dir.clone(),
+            packages: self.packages()?.into_iter().map(|p| p.name).collect(),
+        })
+    }
+
+    pub fn sync(&self) -> Result<SyncResult> {
+        let stow_dir = &self.config.stow_dir;
+        if !stow_dir.exists() {
+            return Err(SlinkyError::Other(format!(
+                "Dotfiles directory not found: {}",
+                stow_dir.display()
+            )));
+        }
+
+        let mut result = SyncResult::default();
+
+        if self.config.git_enabled && stow_dir.join(".git").exists() {
+            let before = get_head(stow_dir);
+            let output = output_with_timeout(
+                git_command()
+                    .current_dir(stow_dir)
+                    .args(["pull", "--ff-only"]),
+                self.config.git_timeout(),
+            )
+            .map_err(|e| SlinkyError::Git(e.to_string()))?;
+            if !output.status.success() {
+                return Err(SlinkyError::Git(
+                    String::from_utf8_lossy(&output.stderr).trim().to_string(),
+                ));
+            }
+            result.pulled = get_head(stow_dir) != before;
+        }
+
+        for package in self.packages()? {
+            result.linked.push(self.link(&package.name)?);
+        }
+
+        Ok(result)
+    }
+
+    fn analyze_options(&self) -> AnalyzeOptions {
+        AnalyzeOptions {
+            max_depth: self.config.max_depth,
+            symlinks: self.config.package_symlinks,
+            ..Default::default()
+        }
+    }
+
+    fn package_path(&self, name: &str) -> Result<PathBuf> {
+        let path = self.config.stow_dir.join(name);
+        if !path.is_dir() {
+            return Err(SlinkyError::PackageNotFound(name.to_string()));
+        }
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn test_slinky(root: &std::path::Path) -> Slinky {
+        // Linking records state.toml, which must not be the real one.
+        crate::config::set_config_dir(std::env::temp_dir().join("slinky_test_cli_config"));
+        let stow_dir = root.join("dotfiles");
+        let target_dir = root.join("home");
+        fs::create_dir_all(stow_dir.join("zsh")).unwrap();
+        fs::create_dir_all(&target_dir).unwrap();
+        fs::write(stow_dir.join("zsh").join(".zshrc"), "export A=1").unwrap();
+
+        Slinky::new(Config {
+            stow_dir,
+            target_dir,
+            ..Config::default()
+        })
+    }
+
+    #[test]
+    fn test_link_status_unlink() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let slinky = test_slinky(temp_dir.path());
+        let zshrc = slinky.config().target_dir.join(".zshrc");
+
+        let linked = slinky.link("zsh").unwrap();
+        assert_eq!(linked.created, vec![zshrc.clone()]);
+        assert!(zshrc.is_symlink());
+
+        let status = slinky.status().unwrap();
+        assert_eq!(status.len(), 1);
+        assert_eq!(status[0].state, PackageState::Linked);
+
+        let unlinked = slinky.unlink("zsh").unwrap();
+        assert_eq!(unlinked.removed, vec![zshrc.clone()]);
+        assert!(!zshrc.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_link_runs_hooks_and_records_state() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let slinky = test_slinky(temp_dir.path());
+        let stow_dir = &slinky.config().stow_dir;
+        let target_dir = &slinky.config().target_dir;
+        for package in ["facade-vetoed", "facade-linked"] {
+            fs::create_dir_all(stow_dir.join(package)).unwrap();
+            fs::write(stow_dir.join(package).join(package), package).unwrap();
+        }
+        let hook = stow_dir.join("facade-vetoed/.slinky/hooks.d/pre-link");
+        fs::create_dir_all(hook.parent().unwrap()).unwrap();
+        fs::write(&hook, "#!/bin/sh\nexit 1\n").unwrap();
+        fs::set_permissions(&hook, fs::Permissions::from_mode(0o755)).unwrap();
+
+        assert!(matches!(
+            slinky.link("facade-vetoed"),
+            Err(SlinkyError::Hook(_))
+        ));
+        assert!(!target_dir.join("facade-vetoed").exists());
+
+        slinky.link("facade-linked").unwrap();
+        let state = crate::state::LinkState::load(&state_path());
+        assert!(state.last_linked("facade-linked").is_some());
+        assert!(state.last_linked("facade-vetoed").is_none());
+    }
+
+    #[test]
+    fn test_unknown_package() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let slinky = test_slinky(temp_dir.path());
+
+        assert!(matches!(
+            slinky.link("missing"),
+            Err(SlinkyError::PackageNotFound(_))
+        ));
+    }
+}
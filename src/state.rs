@@ -0,0 +1,86 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// When a package was last linked (Unix seconds) and how many times it has
+/// been linked in total.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackageRecord {
+    pub last_linked: u64,
+    pub link_count: u64,
+}
+
+/// The contents of `state.toml`: what slinky remembers between runs, as
+/// opposed to what the user configures.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LinkState {
+    #[serde(default)]
+    pub packages: BTreeMap<String, PackageRecord>,
+}
+
+impl LinkState {
+    /// Reads `path`; a missing or unparseable file is an empty state.
+    pub fn load(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|contents| toml::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    /// Writes through a temporary file so readers never see a partial file.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let contents = toml::to_string(self).map_err(io::Error::other)?;
+        let tmp = path.with_extension("toml.tmp");
+        fs::write(&tmp, contents)?;
+        fs::rename(&tmp, path)
+    }
+
+    pub fn record_link(&mut self, package: &str, now: u64) {
+        let record = self.packages.entry(package.to_string()).or_default();
+        record.last_linked = now;
+        record.link_count += 1;
+    }
+
+    pub fn last_linked(&self, package: &str) -> Option<u64> {
+        self.packages.get(package).map(|record| record.last_linked)
+    }
+}
+
+/// Records in the state file at `path` that `package` was linked at `now`.
+pub fn record_package_link(path: &Path, package: &str, now: u64) -> io::Result<()> {
+    let mut state = LinkState::load(path);
+    state.record_link(package, now);
+    state.save(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_package_link() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("slinky/state.toml");
+        assert_eq!(LinkState::load(&path), LinkState::default());
+
+        record_package_link(&path, "zsh", 100).unwrap();
+        record_package_link(&path, "nvim", 150).unwrap();
+        record_package_link(&path, "zsh", 200).unwrap();
+
+        let state = LinkState::load(&path);
+        assert_eq!(
+            state.packages["zsh"],
+            PackageRecord {
+                last_linked: 200,
+                link_count: 2
+            }
+        );
+        assert_eq!(state.last_linked("nvim"), Some(150));
+        assert_eq!(state.last_linked("tmux"), None);
+    }
+}
@@ -1,4 +1,5 @@
-use std::collections::HashSet;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
 use std::io;
 use std::path::{Path, PathBuf};
@@ -10,19 +11,150 @@ pub struct StowPackage {
     pub path: PathBuf,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SymlinkOp {
     pub source: PathBuf,
     pub target: PathBuf,
     pub op_type: OpType,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(into = "OpTypeRepr", from = "OpTypeRepr")]
 pub enum OpType {
     Create,
-    #[allow(dead_code)]
     Remove,
+    /// Replace a symlink that points at the wrong file in this package.
+    Relink,
+    /// Replace a conflicting real file with the link (`link --force`),
+    /// after moving it to a free `.backup` path if `backup` is set.
+    Replace {
+        backup: bool,
+    },
     Skip(String),
+    Conflict(String),
+}
+
+impl OpType {
+    /// Whether the operation leaves a new link at its target.
+    pub fn creates_link(&self) -> bool {
+        matches!(
+            self,
+            OpType::Create | OpType::Relink | OpType::Replace { .. }
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum OpTypeRepr {
+    Create,
+    Remove,
+    Relink,
+    Replace { backup: bool },
+    Skip { reason: String },
+    Conflict { reason: String },
+}
+
+impl From<OpType> for OpTypeRepr {
+    fn from(op_type: OpType) -> Self {
+        match op_type {
+            OpType::Create => OpTypeRepr::Create,
+            OpType::Remove => OpTypeRepr::Remove,
+            OpType::Relink => OpTypeRepr::Relink,
+            OpType::Replace { backup } => OpTypeRepr::Replace { backup },
+            OpType::Skip(reason) => OpTypeRepr::Skip { reason },
+            OpType::Conflict(reason) => OpTypeRepr::Conflict { reason },
+        }
+    }
+}
+
+impl From<OpTypeRepr> for OpType {
+    fn from(repr: OpTypeRepr) -> Self {
+        match repr {
+            OpTypeRepr::Create => OpType::Create,
+            OpTypeRepr::Remove => OpType::Remove,
+            OpTypeRepr::Relink => OpType::Relink,
+            OpTypeRepr::Replace { backup } => OpType::Replace { backup },
+            OpTypeRepr::Skip { reason } => OpType::Skip(reason),
+            OpTypeRepr::Conflict { reason } => OpType::Conflict(reason),
+        }
+    }
+}
+
+pub const PACKAGE_CONFIG_FILE: &str = ".slinky.toml";
+
+pub const OS_TOKENS: &[&str] = &["macos", "linux", "windows"];
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct PackageConfig {
+    /// Free-form summary shown by `slnky packages`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub remap: HashMap<String, String>,
+    #[serde(default)]
+    pub hosts: Vec<String>,
+    #[serde(default)]
+    pub max_depth: Option<usize>,
+    /// Links this package somewhere other than the target directory, or into
+    /// several places when given a list. `~/` expands to the home directory
+    /// and relative paths are taken from the target directory; `${HOST}` and
+    /// `${OS}` are substituted here and in remap targets.
+    #[serde(default, rename = "target", deserialize_with = "string_or_list")]
+    pub targets: Vec<String>,
+}
+
+fn string_or_list<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(target) => vec![target],
+        OneOrMany::Many(targets) => targets,
+    })
+}
+
+/// How a symlink found inside a package is linked into the target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SymlinkMode {
+    /// Link to the package's symlink, so the target follows it.
+    #[default]
+    Link,
+    /// Link straight to the symlink's resolved (canonical) destination.
+    Deref,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AnalyzeOptions {
+    pub force: bool,
+    pub hostname: Option<String>,
+    /// Record conflicting targets as `OpType::Conflict` instead of failing.
+    pub report_conflicts: bool,
+    /// Treat target paths differing only in case as colliding. Defaults to
+    /// the platform's usual filesystem (macOS and Windows are case-insensitive).
+    pub case_insensitive: Option<bool>,
+    /// Deepest level of the package tree to scan; a package's own
+    /// `max_depth` can only lower it.
+    pub max_depth: Option<usize>,
+    /// Replace a directory symlink into another package with a real
+    /// directory of per-entry links, so this package can link files inside
+    /// it (GNU Stow's unfolding). Without it such folds are conflicts.
+    pub unfold: bool,
+    pub symlinks: SymlinkMode,
+    /// Re-point symlinks that lead to the wrong file in this package (for
+    /// example after the file moved) instead of reporting them.
+    pub repair: bool,
+    /// Name the matching rule and where it came from in the skip reason of
+    /// ignored files, e.g. "Ignored by .stow-local-ignore: *.tmp".
+    pub trace_ignores: bool,
 }
 
 #[derive(Debug)]
@@ -30,7 +162,21 @@ pub enum StowError {
     Io(io::Error),
     InvalidPackage(String),
     ConflictDetected(String),
+    OwnedByPackage {
+        target: PathBuf,
+        package: String,
+    },
+    ForeignSymlink {
+        target: PathBuf,
+        link: PathBuf,
+    },
     InvalidPath(String),
+    Plan(String),
+    Misdirected {
+        target: PathBuf,
+        link: PathBuf,
+        source: PathBuf,
+    },
 }
 
 impl std::fmt::Display for StowError {
@@ -39,11 +185,44 @@ impl std::fmt::Display for StowError {
             StowError::Io(e) => write!(f, "IO error: {}", e),
             StowError::InvalidPackage(s) => write!(f, "Invalid package: {}", s),
             StowError::ConflictDetected(s) => write!(f, "Conflict detected: {}", s),
+            StowError::OwnedByPackage { target, package } => write!(
+                f,
+                "Conflict detected: {} is owned by package '{}' (use --force to re-point it)",
+                target.display(),
+                package
+            ),
+            StowError::ForeignSymlink { target, link } => write!(
+                f,
+                "Conflict detected: {} is a symlink to {} outside the stow directory (managed by another tool?)",
+                target.display(),
+                link.display()
+            ),
             StowError::InvalidPath(s) => write!(f, "Invalid path: {}", s),
+            StowError::Plan(s) => write!(f, "Invalid plan: {}", s),
+            StowError::Misdirected { target, link, source } => write!(
+                f,
+                "Conflict detected: {} is a misdirected symlink to {} but should point to {} (use --repair to re-point it)",
+                target.display(),
+                link.display(),
+                source.display()
+            ),
         }
     }
 }
 
+impl StowError {
+    /// Whether the error reports a conflicting target rather than a failure.
+    pub fn is_conflict(&self) -> bool {
+        matches!(
+            self,
+            StowError::ConflictDetected(_)
+                | StowError::OwnedByPackage { .. }
+                | StowError::ForeignSymlink { .. }
+                | StowError::Misdirected { .. }
+        )
+    }
+}
+
 impl std::error::Error for StowError {}
 
 impl From<io::Error> for StowError {
@@ -53,6 +232,22 @@ impl From<io::Error> for StowError {
 }
 
 pub fn find_packages(stow_dir: &Path) -> Result<Vec<StowPackage>, StowError> {
+    find_packages_with(stow_dir, &[])
+}
+
+/// Whether a top-level directory of the stow dir is a package. Dot-directories
+/// (`.git`, `.github`, `.vscode`) are not, unless listed in `include_hidden`;
+/// `.git` never is.
+pub fn is_package_name(name: &str, include_hidden: &[String]) -> bool {
+    !name.starts_with('.') || (name != ".git" && include_hidden.iter().any(|n| n == name))
+}
+
+/// `find_packages`, also treating the dot-directories in `include_hidden`
+/// (`include_hidden_packages`) as packages.
+pub fn find_packages_with(
+    stow_dir: &Path,
+    include_hidden: &[String],
+) -> Result<Vec<StowPackage>, StowError> {
     if !stow_dir.exists() {
         return Err(StowError::InvalidPath(format!(
             "Stow directory does not exist: {}",
@@ -76,7 +271,7 @@ pub fn find_packages(stow_dir: &Path) -> Result<Vec<StowPackage>, StowError> {
         if path.is_dir() {
             if let Some(name) = path.file_name() {
                 let name_str = name.to_string_lossy().to_string();
-                if !name_str.starts_with('.') {
+                if is_package_name(&name_str, include_hidden) {
                     packages.push(StowPackage {
                         name: name_str,
                         path,
@@ -89,9 +284,62 @@ pub fn find_packages(stow_dir: &Path) -> Result<Vec<StowPackage>, StowError> {
     Ok(packages)
 }
 
+/// Package names defined in more than one of `sources`, with every path that
+/// defines them, in source order.
+pub fn find_duplicate_packages(
+    sources: &[PathBuf],
+    include_hidden: &[String],
+) -> Result<Vec<(String, Vec<PathBuf>)>, StowError> {
+    let mut by_name: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+    for source in sources {
+        for package in find_packages_with(source, include_hidden)? {
+            by_name.entry(package.name).or_default().push(package.path);
+        }
+    }
+
+    Ok(by_name
+        .into_iter()
+        .filter(|(_, paths)| paths.len() > 1)
+        .collect())
+}
+
+/// Conventional subdirectories that dotfiles repos keep their packages in.
+pub const NESTED_PACKAGE_DIRS: &[&str] = &["home", "packages", "dotfiles", "config"];
+
+/// Returns the first conventional subdirectory of `repo_path` that holds
+/// packages, when the repo root itself only contains such container dirs.
+pub fn find_nested_stow_dir(repo_path: &Path) -> Option<PathBuf> {
+    let root_packages = find_packages(repo_path).ok()?;
+    if !root_packages
+        .iter()
+        .all(|p| NESTED_PACKAGE_DIRS.contains(&p.name.as_str()))
+    {
+        return None;
+    }
+
+    NESTED_PACKAGE_DIRS
+        .iter()
+        .map(|dir| repo_path.join(dir))
+        .find(|dir| {
+            dir.is_dir()
+                && find_packages(dir)
+                    .map(|pkgs| !pkgs.is_empty())
+                    .unwrap_or(false)
+        })
+}
+
+#[allow(dead_code)]
 pub fn analyze_package(
     package_path: &Path,
     target_dir: &Path,
+) -> Result<Vec<SymlinkOp>, StowError> {
+    analyze_package_with(package_path, target_dir, &AnalyzeOptions::default())
+}
+
+pub fn analyze_package_with(
+    package_path: &Path,
+    target_dir: &Path,
+    options: &AnalyzeOptions,
 ) -> Result<Vec<SymlinkOp>, StowError> {
     if !package_path.exists() {
         return Err(StowError::InvalidPackage(format!(
@@ -107,24 +355,131 @@ pub fn analyze_package(
         )));
     }
 
-    let ignore_patterns = load_stow_ignore(package_path)?;
+    let mut ignore_patterns = load_export_ignores(package_path)?;
+    ignore_patterns.extend(load_stow_ignore(package_path)?);
+    let mut package_config = load_package_config(package_path)?;
     let mut operations = Vec::new();
 
-    scan_package_recursive(
-        package_path,
-        package_path,
-        target_dir,
-        &ignore_patterns,
-        &mut operations,
-    )?;
+    let hostname = options
+        .hostname
+        .as_ref()
+        .map(|h| h.to_lowercase())
+        .or_else(current_hostname);
+    for to in package_config.remap.values_mut() {
+        *to = expand_placeholders(to, hostname.as_deref())?;
+    }
+    let target_dirs = if package_config.targets.is_empty() {
+        vec![target_dir.to_path_buf()]
+    } else {
+        package_config
+            .targets
+            .iter()
+            .map(|target| {
+                expand_placeholders(target, hostname.as_deref())
+                    .map(|target| resolve_package_target(&target, target_dir))
+            })
+            .collect::<Result<Vec<_>, _>>()?
+    };
+
+    for target_dir in &target_dirs {
+        let ctx = ScanContext {
+            package_root: package_path,
+            target_dir,
+            ignore_patterns: &ignore_patterns,
+            package_config: &package_config,
+            options,
+            hostname: hostname.clone(),
+            max_depth: match (options.max_depth, package_config.max_depth) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            },
+        };
+        scan_package_recursive(&ctx, package_path, &mut HashSet::new(), &mut operations)?;
+    }
+    check_target_collisions(&operations)?;
+    if options
+        .case_insensitive
+        .unwrap_or(cfg!(any(target_os = "macos", target_os = "windows")))
+    {
+        check_case_collisions(&operations)?;
+    }
 
     Ok(operations)
 }
 
+fn create_symlink(source: &Path, target: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(source, target)?;
+
+    #[cfg(windows)]
+    {
+        if source.is_dir() {
+            std::os::windows::fs::symlink_dir(source, target)?;
+        } else {
+            std::os::windows::fs::symlink_file(source, target)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Creates `dir` and any missing parents. With `mode` (Unix permission bits,
+/// e.g. `0o700`), every directory created here gets exactly that mode
+/// regardless of the umask; existing directories are left alone.
+fn create_dirs(dir: &Path, mode: Option<u32>) -> io::Result<()> {
+    let missing: Vec<&Path> = dir
+        .ancestors()
+        .take_while(|ancestor| !ancestor.as_os_str().is_empty() && !ancestor.exists())
+        .collect();
+    fs::create_dir_all(dir)?;
+
+    #[cfg(unix)]
+    if let Some(mode) = mode {
+        use std::os::unix::fs::PermissionsExt;
+        for created in missing.iter().rev() {
+            fs::set_permissions(created, fs::Permissions::from_mode(mode))?;
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = (missing, mode);
+
+    Ok(())
+}
+
+/// `path` with `.backup` appended, or `.backup.1`, `.backup.2`, ... when
+/// that is taken, so an earlier backup is never overwritten.
+pub fn free_backup_path(path: &Path) -> PathBuf {
+    let backup = PathBuf::from(format!("{}.backup", path.display()));
+    if !backup.exists() && !backup.is_symlink() {
+        return backup;
+    }
+    (1..)
+        .map(|n| PathBuf::from(format!("{}.backup.{}", path.display(), n)))
+        .find(|candidate| !candidate.exists() && !candidate.is_symlink())
+        .expect("some backup suffix is free")
+}
+
+#[allow(dead_code)]
 pub fn execute_operations(ops: &[SymlinkOp], dry_run: bool) -> Result<Vec<String>, StowError> {
+    execute_operations_with(ops, dry_run, None, |_, _, _| {})
+}
+
+/// Like `execute_operations`, calling `on_progress(done, total, op)` after
+/// each operation so callers can report progress. A conflict stops before
+/// its callback. Parent directories created for new links get `dir_mode`
+/// (see `Config::dir_mode`) instead of the umask's default.
+pub fn execute_operations_with<F>(
+    ops: &[SymlinkOp],
+    dry_run: bool,
+    dir_mode: Option<u32>,
+    mut on_progress: F,
+) -> Result<Vec<String>, StowError>
+where
+    F: FnMut(usize, usize, &SymlinkOp),
+{
     let mut results = Vec::new();
 
-    for op in ops {
+    for (index, op) in ops.iter().enumerate() {
         match &op.op_type {
             OpType::Create => {
                 let result = if dry_run {
@@ -136,21 +491,10 @@ pub fn execute_operations(ops: &[SymlinkOp], dry_run: bool) -> Result<Vec<String
                 } else {
                     if let Some(parent) = op.target.parent() {
                         if !parent.exists() {
-                            fs::create_dir_all(parent)?;
-                        }
-                    }
-
-                    #[cfg(unix)]
-                    std::os::unix::fs::symlink(&op.source, &op.target)?;
-
-                    #[cfg(windows)]
-                    {
-                        if op.source.is_dir() {
-                            std::os::windows::fs::symlink_dir(&op.source, &op.target)?;
-                        } else {
-                            std::os::windows::fs::symlink_file(&op.source, &op.target)?;
+                            create_dirs(parent, dir_mode)?;
                         }
                     }
+                    create_symlink(&op.source, &op.target)?;
 
                     format!(
                         "Created symlink: {} -> {}",
@@ -160,6 +504,39 @@ pub fn execute_operations(ops: &[SymlinkOp], dry_run: bool) -> Result<Vec<String
                 };
                 results.push(result);
             }
+            OpType::Relink => {
+                let result = if dry_run {
+                    format!(
+                        "[DRY-RUN] Would re-point symlink: {} -> {}",
+                        op.target.display(),
+                        op.source.display()
+                    )
+                } else if op.target.is_symlink() {
+                    fs::remove_file(&op.target)?;
+                    create_symlink(&op.source, &op.target)?;
+                    format!(
+                        "Re-pointed symlink: {} -> {}",
+                        op.target.display(),
+                        op.source.display()
+                    )
+                } else {
+                    format!("Skipped non-symlink: {}", op.target.display())
+                };
+                results.push(result);
+            }
+            OpType::Replace { backup } => {
+                let result = if dry_run {
+                    format!(
+                        "[DRY-RUN] Would {} {} and create symlink -> {}",
+                        if *backup { "back up" } else { "delete" },
+                        op.target.display(),
+                        op.source.display()
+                    )
+                } else {
+                    replace_with_symlink(op, *backup)?
+                };
+                results.push(result);
+            }
             OpType::Remove => {
                 let result = if dry_run {
                     format!("[DRY-RUN] Would remove symlink: {}", op.target.display())
@@ -174,51 +551,498 @@ pub fn execute_operations(ops: &[SymlinkOp], dry_run: bool) -> Result<Vec<String
             OpType::Skip(reason) => {
                 results.push(format!("Skipped {}: {}", op.target.display(), reason));
             }
+            OpType::Conflict(reason) => {
+                return Err(StowError::ConflictDetected(reason.clone()));
+            }
         }
+        on_progress(index + 1, ops.len(), op);
     }
 
     Ok(results)
 }
 
+/// Runs an `OpType::Replace`: moves the real file at the target aside (or
+/// deletes it) and links in its place. A backup is moved back if the link
+/// cannot be created.
+fn replace_with_symlink(op: &SymlinkOp, backup: bool) -> Result<String, StowError> {
+    let target = &op.target;
+    if target.is_symlink() || !target.exists() {
+        return Err(StowError::ConflictDetected(format!(
+            "{} changed since it was analyzed",
+            target.display()
+        )));
+    }
+
+    if backup {
+        let backup_path = free_backup_path(target);
+        fs::rename(target, &backup_path)?;
+        if let Err(e) = create_symlink(&op.source, target) {
+            fs::rename(&backup_path, target)?;
+            return Err(e.into());
+        }
+        return Ok(format!(
+            "Backed up {} to {} and created symlink -> {}",
+            target.display(),
+            backup_path.display(),
+            op.source.display()
+        ));
+    }
+
+    if target.is_dir() {
+        fs::remove_dir_all(target)?;
+    } else {
+        fs::remove_file(target)?;
+    }
+    create_symlink(&op.source, target)?;
+    Ok(format!(
+        "Replaced {} with symlink -> {}",
+        target.display(),
+        op.source.display()
+    ))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PackageState {
+    Linked,
+    Partial,
+    Unlinked,
+    Conflicted,
+    Empty,
+    /// The package could not be analyzed. Only status reports use this;
+    /// `package_status` never returns it.
+    Error,
+}
+
+impl PackageState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PackageState::Linked => "linked",
+            PackageState::Partial => "partial",
+            PackageState::Unlinked => "unlinked",
+            PackageState::Conflicted => "conflicted",
+            PackageState::Empty => "empty",
+            PackageState::Error => "error",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PackageStatus {
+    pub state: PackageState,
+    pub linked: usize,
+    pub total: usize,
+    pub conflicts: usize,
+    /// Files skipped by ignore rules; not counted in `total`.
+    pub ignored: usize,
+}
+
+/// Skip reason for files excluded by `.stow-local-ignore` or `export-ignore`.
+pub const IGNORED_REASON: &str = "Ignored by .stow-local-ignore";
+
+/// Whether a skip reason means an ignore rule excluded the file, with or
+/// without the rule traced (`AnalyzeOptions::trace_ignores`).
+pub fn is_ignored_reason(reason: &str) -> bool {
+    reason.starts_with("Ignored by ")
+}
+
+pub fn package_status(ops: &[SymlinkOp]) -> PackageStatus {
+    let mut linked = 0;
+    let mut total = 0;
+    let mut conflicts = 0;
+    let mut ignored = 0;
+
+    for op in ops {
+        match &op.op_type {
+            OpType::Create | OpType::Relink | OpType::Replace { .. } => total += 1,
+            OpType::Skip(reason) if is_ignored_reason(reason) => ignored += 1,
+            OpType::Skip(reason) if reason.contains("Already linked") => {
+                linked += 1;
+                total += 1;
+            }
+            OpType::Conflict(_) => {
+                conflicts += 1;
+                total += 1;
+            }
+            OpType::Skip(_) | OpType::Remove => {}
+        }
+    }
+
+    let state = if total == 0 {
+        PackageState::Empty
+    } else if conflicts > 0 {
+        PackageState::Conflicted
+    } else if linked == total {
+        PackageState::Linked
+    } else if linked > 0 {
+        PackageState::Partial
+    } else {
+        PackageState::Unlinked
+    };
+
+    PackageStatus {
+        state,
+        linked,
+        total,
+        conflicts,
+        ignored,
+    }
+}
+
+pub const PLAN_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Plan {
+    pub version: u32,
+    pub operations: Vec<SymlinkOp>,
+}
+
+pub fn write_plan(path: &Path, ops: &[SymlinkOp]) -> Result<(), StowError> {
+    let plan = Plan {
+        version: PLAN_VERSION,
+        operations: ops.to_vec(),
+    };
+    let contents =
+        serde_json::to_string_pretty(&plan).map_err(|e| StowError::Plan(e.to_string()))?;
+
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() && !parent.exists() {
+            fs::create_dir_all(parent)?;
+        }
+    }
+
+    fs::write(path, contents)?;
+    Ok(())
+}
+
+pub fn read_plan(path: &Path) -> Result<Vec<SymlinkOp>, StowError> {
+    let contents = fs::read_to_string(path)?;
+    let plan: Plan = serde_json::from_str(&contents).map_err(|e| StowError::Plan(e.to_string()))?;
+
+    if plan.version != PLAN_VERSION {
+        return Err(StowError::Plan(format!(
+            "unsupported plan version {} (expected {})",
+            plan.version, PLAN_VERSION
+        )));
+    }
+
+    Ok(plan.operations)
+}
+
+struct ScanContext<'a> {
+    package_root: &'a Path,
+    target_dir: &'a Path,
+    ignore_patterns: &'a [IgnoreRule],
+    package_config: &'a PackageConfig,
+    options: &'a AnalyzeOptions,
+    hostname: Option<String>,
+    max_depth: Option<usize>,
+}
+
+enum Condition {
+    None,
+    Matches(String),
+    Other(String),
+}
+
+fn file_condition(file_name: &str, ctx: &ScanContext) -> Condition {
+    let Some((base, suffix)) = file_name.rsplit_once('.') else {
+        return Condition::None;
+    };
+    if base.is_empty() {
+        return Condition::None;
+    }
+
+    let suffix = suffix.to_lowercase();
+    let is_current_host = ctx.hostname.as_deref() == Some(suffix.as_str());
+
+    if suffix == std::env::consts::OS || is_current_host {
+        Condition::Matches(base.to_string())
+    } else if OS_TOKENS.contains(&suffix.as_str())
+        || ctx
+            .package_config
+            .hosts
+            .iter()
+            .any(|host| host.to_lowercase() == suffix)
+    {
+        Condition::Other(suffix)
+    } else {
+        Condition::None
+    }
+}
+
+pub fn current_hostname() -> Option<String> {
+    static HOSTNAME: std::sync::OnceLock<Option<String>> = std::sync::OnceLock::new();
+
+    HOSTNAME
+        .get_or_init(|| {
+            let output = std::process::Command::new("hostname").output().ok()?;
+            if !output.status.success() {
+                return None;
+            }
+
+            let name = String::from_utf8_lossy(&output.stdout);
+            let short = name
+                .trim()
+                .split('.')
+                .next()
+                .unwrap_or_default()
+                .to_lowercase();
+            if short.is_empty() {
+                None
+            } else {
+                Some(short)
+            }
+        })
+        .clone()
+}
+
+/// Lists every file in a package that its ignore rules do not exclude,
+/// without deciding where (or whether) it would be linked.
+pub fn package_files(package_path: &Path) -> Result<Vec<PathBuf>, StowError> {
+    let mut rules = load_export_ignores(package_path)?;
+    rules.extend(load_stow_ignore(package_path)?);
+
+    let mut files = Vec::new();
+    let mut pending = vec![package_path.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            let relative = path.strip_prefix(package_path).unwrap_or(&path);
+            if path.is_symlink() || path.file_name() == Some(".git".as_ref()) {
+                continue;
+            }
+            if is_ignored(relative, &rules) {
+                continue;
+            }
+            if path.is_dir() {
+                pending.push(path);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+/// Symlinks inside the package are linked as-is rather than walked, even when
+/// they point at a directory, and `visited` holds the canonical directories
+/// already scanned, so a link pointing back into the package can't make the
+/// walk loop.
 fn scan_package_recursive(
-    package_root: &Path,
+    ctx: &ScanContext,
     current_path: &Path,
-    target_dir: &Path,
-    ignore_patterns: &HashSet<String>,
+    visited: &mut HashSet<PathBuf>,
     operations: &mut Vec<SymlinkOp>,
 ) -> Result<(), StowError> {
-    for entry in fs::read_dir(current_path)? {
-        let entry = entry?;
+    if !visited.insert(fs::canonicalize(current_path)?) {
+        return Ok(());
+    }
+
+    let entries = fs::read_dir(current_path)?.collect::<Result<Vec<_>, _>>()?;
+    // A file for this machine (`.gitconfig.linux`) takes the place of the
+    // generic one beside it (`.gitconfig`).
+    let overridden: HashSet<String> = entries
+        .iter()
+        .filter(|entry| !entry.path().is_dir())
+        .filter_map(|entry| {
+            let path = entry.path();
+            let relative = path.strip_prefix(ctx.package_root).ok()?;
+            if ignoring_rule(relative, ctx.ignore_patterns).is_some() {
+                return None;
+            }
+            match file_condition(&entry.file_name().to_string_lossy(), ctx) {
+                Condition::Matches(base) => Some(base),
+                _ => None,
+            }
+        })
+        .collect();
+
+    for entry in entries {
         let path = entry.path();
         let file_name = entry.file_name();
         let file_name_str = file_name.to_string_lossy();
 
-        if file_name_str == ".stow-local-ignore" {
+        if file_name_str == STOW_IGNORE_FILE
+            || file_name_str == PACKAGE_CONFIG_FILE
+            || (file_name_str == ".slinky" && current_path == ctx.package_root)
+        {
             continue;
         }
 
-        let relative_path = path.strip_prefix(package_root).map_err(|_| {
+        let relative_path = path.strip_prefix(ctx.package_root).map_err(|_| {
             StowError::InvalidPath(format!(
                 "Failed to compute relative path for {}",
                 path.display()
             ))
         })?;
 
-        if is_ignored(relative_path, ignore_patterns) {
+        let is_dir = path.is_dir() && !path.is_symlink();
+        let mut link_relative = relative_path.to_path_buf();
+        if !is_dir {
+            match file_condition(&file_name_str, ctx) {
+                Condition::None if overridden.contains(file_name_str.as_ref()) => {
+                    operations.push(SymlinkOp {
+                        source: path.clone(),
+                        target: ctx.target_dir.join(relative_path),
+                        op_type: OpType::Skip("Overridden by a file for this machine".to_string()),
+                    });
+                    continue;
+                }
+                Condition::None => {}
+                Condition::Matches(base) => link_relative.set_file_name(base),
+                Condition::Other(token) => {
+                    operations.push(SymlinkOp {
+                        source: path.clone(),
+                        target: ctx.target_dir.join(relative_path),
+                        op_type: OpType::Skip(format!("Not for this machine ({})", token)),
+                    });
+                    continue;
+                }
+            }
+        }
+
+        let target_path = ctx
+            .target_dir
+            .join(remap_relative(&link_relative, &ctx.package_config.remap));
+
+        if let Some(rule) = ignoring_rule(relative_path, ctx.ignore_patterns) {
+            let reason = if ctx.options.trace_ignores {
+                format!("Ignored by {}: {}", rule.source, rule)
+            } else {
+                IGNORED_REASON.to_string()
+            };
             operations.push(SymlinkOp {
                 source: path.clone(),
-                target: target_dir.join(relative_path),
-                op_type: OpType::Skip("Ignored by .stow-local-ignore".to_string()),
+                target: target_path,
+                op_type: OpType::Skip(reason),
             });
             continue;
         }
 
-        let target_path = target_dir.join(relative_path);
-
-        if path.is_dir() {
-            scan_package_recursive(package_root, &path, target_dir, ignore_patterns, operations)?;
+        if is_dir {
+            // A folded directory links the whole subtree, so count it as one
+            // linked entry instead of walking into it through the symlink.
+            if is_folded_link(&target_path, &path) {
+                operations.push(SymlinkOp {
+                    source: path.clone(),
+                    target: target_path,
+                    op_type: OpType::Skip(
+                        "Already linked correctly (folded directory)".to_string(),
+                    ),
+                });
+                continue;
+            }
+            if let Some(max_depth) = ctx.max_depth {
+                if relative_path.components().count() >= max_depth {
+                    operations.push(SymlinkOp {
+                        source: path.clone(),
+                        target: target_path,
+                        op_type: OpType::Skip(format!("Beyond max depth ({})", max_depth)),
+                    });
+                    continue;
+                }
+            }
+            if let Some((fold_source, package)) = foreign_fold(&target_path, ctx.package_root) {
+                if !ctx.options.unfold {
+                    let reason = format!(
+                        "{} is a folded directory owned by package '{}' (use --restow-on-conflict to unfold it)",
+                        target_path.display(),
+                        package
+                    );
+                    if !ctx.options.report_conflicts {
+                        return Err(StowError::ConflictDetected(reason));
+                    }
+                    operations.push(SymlinkOp {
+                        source: path.clone(),
+                        target: target_path,
+                        op_type: OpType::Conflict(StowError::ConflictDetected(reason).to_string()),
+                    });
+                    continue;
+                }
+                operations.push(SymlinkOp {
+                    source: fold_source.clone(),
+                    target: target_path.clone(),
+                    op_type: OpType::Remove,
+                });
+                let foreign_root = ctx.package_root.with_file_name(&package);
+                let mut foreign_rules = load_export_ignores(&foreign_root)?;
+                foreign_rules.extend(load_stow_ignore(&foreign_root)?);
+                let foreign = ForeignPackage {
+                    root: &foreign_root,
+                    ignore_patterns: &foreign_rules,
+                };
+                unfold_operations(&foreign, &fold_source, &target_path, &path, operations)?;
+            } else if target_path.symlink_metadata().is_ok() && !target_path.is_dir() {
+                // Package directories are linked file by file into an existing
+                // real directory, but there is no directory to link into here.
+                let reason = format!(
+                    "Target {} exists and is not a directory",
+                    target_path.display()
+                );
+                if !ctx.options.report_conflicts {
+                    return Err(StowError::ConflictDetected(reason));
+                }
+                operations.push(SymlinkOp {
+                    source: path.clone(),
+                    target: target_path,
+                    op_type: OpType::Conflict(StowError::ConflictDetected(reason).to_string()),
+                });
+                continue;
+            }
+            scan_package_recursive(ctx, &path, visited, operations)?;
         } else {
-            let op_type = determine_operation(&path, &target_path)?;
+            let path = if ctx.options.symlinks == SymlinkMode::Deref && path.is_symlink() {
+                match fs::canonicalize(&path) {
+                    Ok(resolved) => resolved,
+                    Err(_) => {
+                        operations.push(SymlinkOp {
+                            source: path,
+                            target: target_path,
+                            op_type: OpType::Skip("Broken symlink".to_string()),
+                        });
+                        continue;
+                    }
+                }
+            } else {
+                path
+            };
+            let planned = if ctx.options.unfold {
+                planned_unfold_link(&target_path, ctx.package_root, operations)
+            } else {
+                None
+            };
+            let result = match planned {
+                // The file will be another package's link once the fold
+                // above is unfolded; what is there now is that package's repo
+                // file, seen through the fold.
+                Some(Some(package)) => Err(StowError::OwnedByPackage {
+                    target: target_path.clone(),
+                    package,
+                }),
+                Some(None) => Ok(OpType::Create),
+                None => determine_operation(&path, &target_path, ctx.package_root),
+            };
+            let op_type = match result {
+                Err(StowError::OwnedByPackage { .. }) if ctx.options.force => {
+                    operations.push(SymlinkOp {
+                        source: path.clone(),
+                        target: target_path.clone(),
+                        op_type: OpType::Remove,
+                    });
+                    OpType::Create
+                }
+                Err(StowError::Misdirected { .. }) if ctx.options.repair => OpType::Relink,
+                Err(
+                    e @ (StowError::OwnedByPackage { .. }
+                    | StowError::ForeignSymlink { .. }
+                    | StowError::Misdirected { .. }
+                    | StowError::ConflictDetected(_)),
+                ) if ctx.options.report_conflicts => OpType::Conflict(e.to_string()),
+                result => result?,
+            };
             operations.push(SymlinkOp {
                 source: path,
                 target: target_path,
@@ -230,76 +1054,794 @@ fn scan_package_recursive(
     Ok(())
 }
 
-fn determine_operation(source: &Path, target: &Path) -> Result<OpType, StowError> {
-    if !target.exists() {
-        return Ok(OpType::Create);
+/// Substitutes `${HOST}` (the lowercased short hostname) and `${OS}`
+/// (`std::env::consts::OS`) in a configured target path.
+pub fn expand_placeholders(path: &str, hostname: Option<&str>) -> Result<String, StowError> {
+    let expanded = path.replace("${OS}", std::env::consts::OS);
+    if !expanded.contains("${HOST}") {
+        return Ok(expanded);
     }
 
-    if target.is_symlink() {
-        let target_link = fs::read_link(target)?;
-        if target_link == source {
-            return Ok(OpType::Skip("Already linked correctly".to_string()));
-        } else {
-            return Err(StowError::ConflictDetected(format!(
-                "Target {} is a symlink to {} but should point to {}",
-                target.display(),
-                target_link.display(),
-                source.display()
-            )));
+    match hostname {
+        Some(host) => Ok(expanded.replace("${HOST}", host)),
+        None => Err(StowError::InvalidPath(format!(
+            "Cannot expand ${{HOST}} in '{}': hostname is unknown",
+            path
+        ))),
+    }
+}
+
+fn resolve_package_target(target: &str, target_dir: &Path) -> PathBuf {
+    if let Some(rest) = target.strip_prefix("~/") {
+        if let Some(home) = crate::config::dirs::home_dir() {
+            return home.join(rest);
         }
     }
+    target_dir.join(target)
+}
 
-    Err(StowError::ConflictDetected(format!(
-        "Target {} exists and is not a symlink",
-        target.display()
-    )))
+fn remap_relative(relative: &Path, remap: &HashMap<String, String>) -> PathBuf {
+    let best = remap
+        .iter()
+        .filter(|(from, _)| relative.starts_with(from.trim_end_matches('/')))
+        .max_by_key(|(from, _)| Path::new(from.as_str()).components().count());
+
+    match best {
+        Some((from, to)) => {
+            let rest = relative
+                .strip_prefix(from.trim_end_matches('/'))
+                .unwrap_or(relative);
+            Path::new(to.trim_end_matches('/')).join(rest)
+        }
+        None => relative.to_path_buf(),
+    }
 }
 
-fn load_stow_ignore(package_path: &Path) -> Result<HashSet<String>, StowError> {
-    let ignore_file = package_path.join(".stow-local-ignore");
-    let mut patterns = HashSet::new();
+fn check_target_collisions(operations: &[SymlinkOp]) -> Result<(), StowError> {
+    let mut seen: HashMap<&Path, &Path> = HashMap::new();
 
-    if ignore_file.exists() {
-        let content = fs::read_to_string(&ignore_file)?;
-        for line in content.lines() {
-            let trimmed = line.trim();
-            if !trimmed.is_empty() && !trimmed.starts_with('#') {
-                patterns.insert(trimmed.to_string());
-            }
+    for op in operations {
+        // A removal frees the target for the link after it, as when an
+        // unfolded link of another package is replaced under --force.
+        if op.op_type == OpType::Remove {
+            seen.remove(op.target.as_path());
+            continue;
+        }
+        let links = match &op.op_type {
+            OpType::Skip(reason) => reason.contains("Already linked"),
+            OpType::Conflict(_) => false,
+            op_type => op_type.creates_link(),
+        };
+        if !links {
+            continue;
+        }
+        if let Some(existing) = seen.insert(&op.target, &op.source) {
+            return Err(StowError::ConflictDetected(format!(
+                "Remap collision: {} and {} both map to {}",
+                existing.display(),
+                op.source.display(),
+                op.target.display()
+            )));
         }
     }
 
-    Ok(patterns)
+    Ok(())
 }
 
-fn is_ignored(path: &Path, patterns: &HashSet<String>) -> bool {
-    let path_str = path.to_string_lossy();
+fn check_case_collisions(operations: &[SymlinkOp]) -> Result<(), StowError> {
+    let mut seen: HashMap<String, &Path> = HashMap::new();
 
-    for pattern in patterns {
-        if pattern.contains('*') {
-            if glob_match(&path_str, pattern) {
-                return true;
-            }
-        } else if path_str.contains(pattern.as_str()) {
-            return true;
+    for op in operations {
+        let linked = match &op.op_type {
+            OpType::Create | OpType::Relink | OpType::Replace { .. } | OpType::Conflict(_) => true,
+            OpType::Skip(reason) => reason.contains("Already linked"),
+            OpType::Remove => false,
+        };
+        if !linked {
+            continue;
         }
 
-        if let Some(file_name) = path.file_name() {
-            let file_name_str = file_name.to_string_lossy();
-            if file_name_str == pattern.as_str() {
-                return true;
+        let key = op.target.to_string_lossy().to_lowercase();
+        if let Some(existing) = seen.insert(key, &op.target) {
+            if existing != op.target {
+                return Err(StowError::ConflictDetected(format!(
+                    "Case collision: {} and {} differ only in case",
+                    existing.display(),
+                    op.target.display()
+                )));
             }
         }
     }
 
-    false
+    Ok(())
 }
 
-fn glob_match(text: &str, pattern: &str) -> bool {
-    let pattern_parts: Vec<&str> = pattern.split('*').collect();
+pub fn load_package_config(package_path: &Path) -> Result<PackageConfig, StowError> {
+    let config_file = package_path.join(PACKAGE_CONFIG_FILE);
 
-    if pattern_parts.is_empty() {
-        return text.is_empty();
+    if !config_file.exists() {
+        return Ok(PackageConfig::default());
+    }
+
+    let content = fs::read_to_string(&config_file)?;
+    toml::from_str(&content)
+        .map_err(|e| StowError::InvalidPackage(format!("Invalid {}: {}", config_file.display(), e)))
+}
+
+fn determine_operation(
+    source: &Path,
+    target: &Path,
+    package_root: &Path,
+) -> Result<OpType, StowError> {
+    // Checked before `exists()`, which is false for a dangling symlink.
+    if target.is_symlink() {
+        let target_link = fs::read_link(target)?;
+        let resolved = resolve_link(target, &target_link);
+        if target_link == source || resolved == source || same_file(&resolved, source) {
+            return Ok(OpType::Skip("Already linked correctly".to_string()));
+        } else if resolved.starts_with(package_root) {
+            return Err(StowError::Misdirected {
+                target: target.to_path_buf(),
+                link: target_link,
+                source: source.to_path_buf(),
+            });
+        } else if let Some(package) = owning_package(package_root, target, &target_link) {
+            return Err(StowError::OwnedByPackage {
+                target: target.to_path_buf(),
+                package,
+            });
+        } else if !is_inside_stow_dir(package_root, &resolved) {
+            return Err(StowError::ForeignSymlink {
+                target: target.to_path_buf(),
+                link: target_link,
+            });
+        } else {
+            return Err(StowError::ConflictDetected(format!(
+                "Target {} is a symlink to {} but should point to {}",
+                target.display(),
+                target_link.display(),
+                source.display()
+            )));
+        }
+    }
+
+    if !target.exists() {
+        return Ok(OpType::Create);
+    }
+
+    Err(StowError::ConflictDetected(format!(
+        "Target {} exists and is not a symlink",
+        target.display()
+    )))
+}
+
+fn is_folded_link(target: &Path, source_dir: &Path) -> bool {
+    target.is_symlink()
+        && fs::read_link(target).is_ok_and(|link| {
+            let resolved = resolve_link(target, &link);
+            resolved == source_dir || same_file(&resolved, source_dir)
+        })
+}
+
+/// Whether `a` and `b` are the same existing file once symlinked directories
+/// along either path are followed. GNU Stow computes its relative links from
+/// the real location of the stow directory, so they may not match a stow
+/// dir reached through a symlink (`~/.dotfiles -> src/dotfiles`) lexically.
+fn same_file(a: &Path, b: &Path) -> bool {
+    matches!((fs::canonicalize(a), fs::canonicalize(b)), (Ok(a), Ok(b)) if a == b)
+}
+
+/// If `target` is a directory symlink into another package of the same stow
+/// directory, returns the directory it points at and that package's name.
+fn foreign_fold(target: &Path, package_root: &Path) -> Option<(PathBuf, String)> {
+    if !target.is_symlink() {
+        return None;
+    }
+    let link = fs::read_link(target).ok()?;
+    let resolved = resolve_link(target, &link);
+    if !resolved.is_dir() || !is_inside_stow_dir(package_root, &resolved) {
+        return None;
+    }
+    let package = owning_package(package_root, target, &link)?;
+    Some((resolved, package))
+}
+
+/// The package owning a folded directory that is being unfolded.
+struct ForeignPackage<'a> {
+    root: &'a Path,
+    ignore_patterns: &'a [IgnoreRule],
+}
+
+/// Links each entry of a folded directory individually under `target`,
+/// leaving out what the owning package's own ignore rules exclude. Entries
+/// that `incoming` (the directory being linked on top) also has as a
+/// directory are unfolded in turn, so both packages' files end up side by
+/// side in real directories.
+fn unfold_operations(
+    foreign: &ForeignPackage,
+    fold_source: &Path,
+    target: &Path,
+    incoming: &Path,
+    operations: &mut Vec<SymlinkOp>,
+) -> Result<(), StowError> {
+    for entry in fs::read_dir(fold_source)? {
+        let source = entry?.path();
+        let Some(name) = source.file_name() else {
+            continue;
+        };
+        if name == STOW_IGNORE_FILE || name == PACKAGE_CONFIG_FILE {
+            continue;
+        }
+        let ignored = source
+            .strip_prefix(foreign.root)
+            .is_ok_and(|relative| ignoring_rule(relative, foreign.ignore_patterns).is_some());
+        if ignored {
+            continue;
+        }
+        if source.is_dir() && incoming.join(name).is_dir() {
+            unfold_operations(
+                foreign,
+                &source,
+                &target.join(name),
+                &incoming.join(name),
+                operations,
+            )?;
+        } else {
+            operations.push(SymlinkOp {
+                source: source.clone(),
+                target: target.join(name),
+                op_type: OpType::Create,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// For a target inside a fold being unfolded, what will be there once the
+/// unfold operations already planned have run: `Some(Some(package))` for a
+/// link of another package, `Some(None)` for nothing. `None` when `target`
+/// is not reached through a fold into the stow directory, so the file system
+/// can be checked as it is.
+fn planned_unfold_link(
+    target: &Path,
+    package_root: &Path,
+    operations: &[SymlinkOp],
+) -> Option<Option<String>> {
+    let stow_dir = fs::canonicalize(package_root.parent()?).ok()?;
+    let parent = fs::canonicalize(target.parent()?).ok()?;
+    if !parent.starts_with(&stow_dir) {
+        return None;
+    }
+
+    let planned = operations
+        .iter()
+        .rev()
+        .find(|op| op.target == target && op.op_type == OpType::Create);
+    Some(planned.and_then(|op| owning_package(package_root, target, &op.source)))
+}
+
+fn is_inside_stow_dir(package_root: &Path, path: &Path) -> bool {
+    package_root
+        .parent()
+        .is_some_and(|stow_dir| path.starts_with(stow_dir))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkOwner {
+    pub package: String,
+    pub source: PathBuf,
+}
+
+/// The package whose link `path` is, or is reached through: the first of
+/// `path` and its ancestors that is a symlink into `stow_dir`, so files
+/// inside a folded directory link are found too.
+pub fn find_link_owner(path: &Path, stow_dir: &Path) -> Result<Option<LinkOwner>, StowError> {
+    for link in path.ancestors().filter(|ancestor| ancestor.is_symlink()) {
+        let resolved = resolve_link(link, &fs::read_link(link)?);
+        let Ok(relative) = resolved.strip_prefix(stow_dir) else {
+            continue;
+        };
+        let Some(package) = relative.components().next() else {
+            continue;
+        };
+        let package = package.as_os_str().to_string_lossy().to_string();
+        let source = match path.strip_prefix(link) {
+            Ok(rest) if !rest.as_os_str().is_empty() => resolved.join(rest),
+            _ => resolved,
+        };
+        return Ok(Some(LinkOwner { package, source }));
+    }
+
+    Ok(None)
+}
+
+/// Replaces the symlink at `target` with a real copy of what it points to, so
+/// the file stays in place once its package is no longer managed. Folded
+/// directory links are copied recursively. The copy is made beside `target`
+/// first, so a failed copy leaves the symlink as it was.
+pub fn detach_symlink(target: &Path) -> Result<(), StowError> {
+    let source = resolve_link(target, &fs::read_link(target)?);
+    let name = target
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let staging = target.with_file_name(format!(".{}.slinky-detach", name));
+    if fs::symlink_metadata(&staging).is_ok() {
+        remove_path(&staging)?;
+    }
+
+    if let Err(e) = copy_recursive(&source, &staging) {
+        let _ = remove_path(&staging);
+        return Err(e.into());
+    }
+    fs::remove_file(target)?;
+    fs::rename(&staging, target)?;
+    Ok(())
+}
+
+/// Copies a file, or a directory and everything in it, following symlinks.
+/// A directory symlink back to one of the directories being copied is kept
+/// as a symlink rather than followed forever.
+fn copy_recursive(source: &Path, dest: &Path) -> io::Result<()> {
+    copy_tree(source, dest, &mut Vec::new())
+}
+
+fn copy_tree(source: &Path, dest: &Path, ancestors: &mut Vec<PathBuf>) -> io::Result<()> {
+    let metadata = fs::metadata(source)?;
+    if !metadata.is_dir() {
+        return fs::copy(source, dest).map(|_| ());
+    }
+
+    let canonical = fs::canonicalize(source)?;
+    if ancestors.contains(&canonical) {
+        return create_symlink(&fs::read_link(source)?, dest);
+    }
+
+    fs::create_dir(dest)?;
+    ancestors.push(canonical);
+    for entry in fs::read_dir(source)? {
+        let entry = entry?;
+        copy_tree(&entry.path(), &dest.join(entry.file_name()), ancestors)?;
+    }
+    ancestors.pop();
+    fs::set_permissions(dest, metadata.permissions())
+}
+
+fn remove_path(path: &Path) -> io::Result<()> {
+    if path.is_dir() && !path.is_symlink() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+/// Finds symlinks that point into `stow_dir` at sources which no longer exist.
+/// Only directories that current package files link into (and `target_dir`
+/// itself) are searched, so links left in directories that were removed from
+/// every package entirely are not found.
+pub fn find_orphaned_links(
+    stow_dir: &Path,
+    target_dir: &Path,
+    include_hidden: &[String],
+) -> Result<Vec<PathBuf>, StowError> {
+    let options = AnalyzeOptions {
+        report_conflicts: true,
+        ..Default::default()
+    };
+
+    let mut dirs = vec![target_dir.to_path_buf()];
+    for package in find_packages_with(stow_dir, include_hidden)? {
+        for op in analyze_package_with(&package.path, target_dir, &options)? {
+            if let Some(parent) = op.target.parent() {
+                if !dirs.iter().any(|d| d == parent) {
+                    dirs.push(parent.to_path_buf());
+                }
+            }
+        }
+    }
+
+    let mut orphans = dangling_links_into(&dirs, stow_dir)?;
+    orphans.sort();
+    Ok(orphans)
+}
+
+/// Symlinks directly inside `dirs` that point into `root` at paths that no
+/// longer exist.
+fn dangling_links_into(dirs: &[PathBuf], root: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut dangling = Vec::new();
+    for dir in dirs {
+        if !dir.is_dir() {
+            continue;
+        }
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if !path.is_symlink() {
+                continue;
+            }
+            let source = resolve_link(&path, &fs::read_link(&path)?);
+            if source.starts_with(root) && fs::symlink_metadata(&source).is_err() {
+                dangling.push(path);
+            }
+        }
+    }
+    Ok(dangling)
+}
+
+/// What `relink_package` changed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RelinkSummary {
+    pub removed: usize,
+    pub created: usize,
+}
+
+/// What `relink_package` would do: the links it removes, then the links it
+/// creates in their place.
+#[derive(Debug, Clone, Default)]
+pub struct RelinkPlan {
+    pub remove: Vec<PathBuf>,
+    pub create: Vec<SymlinkOp>,
+}
+
+/// Plans relinking the package at `package_path` without changing anything.
+/// Links to files since deleted from the package are found in the
+/// directories it links into, and, when it is in a git repository, in the
+/// directories of files git saw deleted from it. Fails if the new set of
+/// links would conflict.
+pub fn plan_relink(
+    package_path: &Path,
+    target_dir: &Path,
+    options: &AnalyzeOptions,
+) -> Result<RelinkPlan, StowError> {
+    let check = AnalyzeOptions {
+        report_conflicts: true,
+        repair: true,
+        ..options.clone()
+    };
+    let ops = analyze_package_with(package_path, target_dir, &check)?;
+    if let Some(OpType::Conflict(reason)) = ops
+        .iter()
+        .map(|op| &op.op_type)
+        .find(|op_type| matches!(op_type, OpType::Conflict(_)))
+    {
+        return Err(StowError::ConflictDetected(reason.clone()));
+    }
+
+    let deleted = crate::remote::deleted_files(package_path).unwrap_or_default();
+    let former_dirs = deleted
+        .iter()
+        .filter_map(|file| file.strip_prefix(package_path).ok()?.parent())
+        .map(|parent| target_dir.join(parent));
+    let mut dirs = vec![target_dir.to_path_buf()];
+    for dir in ops
+        .iter()
+        .filter_map(|op| op.target.parent().map(Path::to_path_buf))
+        .chain(former_dirs)
+    {
+        if !dirs.contains(&dir) {
+            dirs.push(dir);
+        }
+    }
+
+    let mut plan = RelinkPlan::default();
+    for op in ops {
+        let linked = match &op.op_type {
+            OpType::Skip(reason) => reason.contains("Already linked"),
+            OpType::Relink => true,
+            _ => false,
+        };
+        if linked {
+            plan.remove.push(op.target.clone());
+            plan.create.push(SymlinkOp {
+                op_type: OpType::Create,
+                ..op
+            });
+        } else if op.op_type == OpType::Create {
+            plan.create.push(op);
+        }
+    }
+    plan.remove
+        .extend(dangling_links_into(&dirs, package_path)?);
+    Ok(plan)
+}
+
+/// Removes every link the package at `package_path` has under `target_dir`
+/// (including links to files since deleted from it, see `plan_relink`) and
+/// creates its current set in one pass. Nothing is touched when the new set
+/// would conflict, and if creating fails part-way the new links are removed
+/// and the old ones put back.
+pub fn relink_package(
+    package_path: &Path,
+    target_dir: &Path,
+    options: &AnalyzeOptions,
+    dir_mode: Option<u32>,
+) -> Result<RelinkSummary, StowError> {
+    let plan = plan_relink(package_path, target_dir, options)?;
+
+    let mut removed = Vec::new();
+    for target in plan.remove {
+        let link = fs::read_link(&target)?;
+        fs::remove_file(&target)?;
+        removed.push((target, link));
+    }
+    let restore = |removed: &[(PathBuf, PathBuf)]| {
+        for (target, link) in removed {
+            let _ = create_symlink(link, target);
+        }
+    };
+
+    let new_ops = match analyze_package_with(package_path, target_dir, options) {
+        Ok(new_ops) => new_ops,
+        Err(e) => {
+            restore(&removed);
+            return Err(e);
+        }
+    };
+    let mut done = 0;
+    if let Err(e) = execute_operations_with(&new_ops, false, dir_mode, |count, _, _| done = count) {
+        for op in &new_ops[..done] {
+            if op.op_type == OpType::Create && op.target.is_symlink() {
+                let _ = fs::remove_file(&op.target);
+            }
+        }
+        restore(&removed);
+        return Err(e);
+    }
+
+    Ok(RelinkSummary {
+        removed: removed.len(),
+        created: new_ops
+            .iter()
+            .filter(|op| op.op_type == OpType::Create)
+            .count(),
+    })
+}
+
+fn owning_package(package_root: &Path, target: &Path, link: &Path) -> Option<String> {
+    let stow_dir = package_root.parent()?;
+    let resolved = resolve_link(target, link);
+
+    if resolved.starts_with(package_root) {
+        return None;
+    }
+
+    let relative = resolved.strip_prefix(stow_dir).ok()?;
+    let package = relative.components().next()?.as_os_str().to_string_lossy();
+    Some(package.to_string())
+}
+
+fn resolve_link(link_path: &Path, link: &Path) -> PathBuf {
+    let joined = if link.is_absolute() {
+        link.to_path_buf()
+    } else {
+        link_path
+            .parent()
+            .map(|parent| parent.join(link))
+            .unwrap_or_else(|| link.to_path_buf())
+    };
+
+    let mut resolved = PathBuf::new();
+    for component in joined.components() {
+        match component {
+            std::path::Component::CurDir => {}
+            std::path::Component::ParentDir => {
+                resolved.pop();
+            }
+            other => resolved.push(other),
+        }
+    }
+    resolved
+}
+
+pub const STOW_IGNORE_FILE: &str = ".stow-local-ignore";
+
+const EXPORT_IGNORE_SOURCE: &str = ".gitattributes export-ignore";
+
+#[derive(Debug, Clone, PartialEq)]
+struct IgnoreRule {
+    pattern: String,
+    anchored: bool,
+    negated: bool,
+    /// Match like gitattributes (`IgnoreRule::matches_components`) instead
+    /// of `.stow-local-ignore`'s looser substring matching.
+    components: bool,
+    /// Where the rule was read from, for tracing.
+    source: &'static str,
+}
+
+impl std::fmt::Display for IgnoreRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let negation = if self.negated { "!" } else { "" };
+        let anchor = if self.anchored { "/" } else { "" };
+        write!(f, "{}{}{}", negation, anchor, self.pattern)
+    }
+}
+
+impl IgnoreRule {
+    fn parse(line: &str) -> Option<Self> {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            return None;
+        }
+
+        let (negated, rest) = match trimmed.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, trimmed),
+        };
+        let (anchored, rest) = match rest.strip_prefix('/') {
+            Some(rest) => (true, rest),
+            None => (false, rest),
+        };
+
+        let pattern = rest.trim_end_matches('/');
+        if pattern.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            pattern: pattern.to_string(),
+            anchored,
+            negated,
+            components: false,
+            source: STOW_IGNORE_FILE,
+        })
+    }
+
+    fn matches(&self, path: &Path) -> bool {
+        if self.components {
+            return self.matches_components(path);
+        }
+
+        let path_str = path.to_string_lossy();
+        let pattern = self.pattern.as_str();
+
+        if self.anchored {
+            return if pattern.contains('*') {
+                glob_match(&path_str, pattern)
+            } else {
+                path.starts_with(pattern)
+            };
+        }
+
+        if pattern.contains('*') {
+            if glob_match(&path_str, pattern) {
+                return true;
+            }
+        } else if path_str.contains(pattern) {
+            return true;
+        }
+
+        path.file_name()
+            .is_some_and(|file_name| file_name.to_string_lossy() == pattern)
+    }
+
+    /// gitattributes matching: `*` never crosses a `/`, an unanchored
+    /// pattern matches any one path component and an anchored one the
+    /// leading components, so a matched directory covers what is inside it.
+    fn matches_components(&self, path: &Path) -> bool {
+        let components: Vec<_> = path
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect();
+        let component_matches = |component: &str, part: &str| {
+            if part.contains('*') {
+                glob_match(component, part)
+            } else {
+                component == part
+            }
+        };
+
+        if self.anchored {
+            let parts: Vec<&str> = self.pattern.split('/').collect();
+            parts.len() <= components.len()
+                && parts
+                    .iter()
+                    .zip(&components)
+                    .all(|(part, component)| component_matches(component, part))
+        } else {
+            components
+                .iter()
+                .any(|component| component_matches(component, &self.pattern))
+        }
+    }
+}
+
+fn load_stow_ignore(package_path: &Path) -> Result<Vec<IgnoreRule>, StowError> {
+    let ignore_file = package_path.join(STOW_IGNORE_FILE);
+    let mut rules = Vec::new();
+
+    if ignore_file.exists() {
+        let content = fs::read_to_string(&ignore_file)?;
+        rules.extend(content.lines().filter_map(IgnoreRule::parse));
+    }
+
+    Ok(rules)
+}
+
+/// Reads `export-ignore` entries from the stow dir's `.gitattributes` and turns
+/// the ones that apply to this package into ignore rules.
+fn load_export_ignores(package_path: &Path) -> Result<Vec<IgnoreRule>, StowError> {
+    let (Some(stow_dir), Some(package_name)) = (package_path.parent(), package_path.file_name())
+    else {
+        return Ok(Vec::new());
+    };
+
+    let attributes_file = stow_dir.join(".gitattributes");
+    if !attributes_file.exists() {
+        return Ok(Vec::new());
+    }
+
+    let package_name = package_name.to_string_lossy();
+    let content = fs::read_to_string(&attributes_file)?;
+    let rules = content
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let pattern = fields.next().filter(|p| !p.starts_with('#'))?;
+            if !fields.any(|attr| attr == "export-ignore") {
+                return None;
+            }
+            export_ignore_rule(pattern, &package_name)
+        })
+        .collect();
+
+    Ok(rules)
+}
+
+fn export_ignore_rule(pattern: &str, package_name: &str) -> Option<IgnoreRule> {
+    let rooted = pattern.starts_with('/');
+    let pattern = pattern.trim_start_matches('/').trim_end_matches('/');
+    if pattern.is_empty() {
+        return None;
+    }
+
+    if !rooted && !pattern.contains('/') {
+        return Some(IgnoreRule {
+            pattern: pattern.to_string(),
+            anchored: false,
+            negated: false,
+            components: true,
+            source: EXPORT_IGNORE_SOURCE,
+        });
+    }
+
+    let within_package = if pattern == package_name {
+        "*"
+    } else {
+        pattern.strip_prefix(package_name)?.strip_prefix('/')?
+    };
+
+    Some(IgnoreRule {
+        pattern: within_package.to_string(),
+        anchored: true,
+        negated: false,
+        components: true,
+        source: EXPORT_IGNORE_SOURCE,
+    })
+}
+
+fn is_ignored(path: &Path, rules: &[IgnoreRule]) -> bool {
+    ignoring_rule(path, rules).is_some()
+}
+
+/// The rule that leaves `path` ignored: the last match, unless a later `!`
+/// rule re-included it.
+fn ignoring_rule<'a>(path: &Path, rules: &'a [IgnoreRule]) -> Option<&'a IgnoreRule> {
+    let mut ignored_by = None;
+
+    for rule in rules {
+        if rule.negated == ignored_by.is_some() && rule.matches(path) {
+            ignored_by = if rule.negated { None } else { Some(rule) };
+        }
+    }
+
+    ignored_by
+}
+
+fn glob_match(text: &str, pattern: &str) -> bool {
+    let pattern_parts: Vec<&str> = pattern.split('*').collect();
+
+    if pattern_parts.is_empty() {
+        return text.is_empty();
     }
 
     let mut text_pos = 0;
@@ -378,50 +1920,1508 @@ mod tests {
     }
 
     #[test]
-    fn test_analyze_package_simple() {
-        let temp_dir = std::env::temp_dir().join("slinky_test_analyze");
+    fn test_find_packages_includes_listed_hidden_directories() {
+        let temp_dir = std::env::temp_dir().join("slinky_test_find_hidden");
         let _ = fs::remove_dir_all(&temp_dir);
 
-        let package_path = setup_test_package(&temp_dir, "testpkg");
-        create_test_file(&package_path.join(".config").join("test.conf"), "config");
+        setup_test_package(&temp_dir, "zsh");
+        let config_package = setup_test_package(&temp_dir, ".config");
+        create_test_file(&config_package.join(".config/nvim/init.lua"), "-- nvim");
+        fs::create_dir_all(temp_dir.join(".github/workflows")).unwrap();
+        fs::create_dir_all(temp_dir.join(".git")).unwrap();
+
+        let include = vec![".config".to_string(), ".git".to_string()];
+        let mut names: Vec<String> = find_packages_with(&temp_dir, &include)
+            .unwrap()
+            .into_iter()
+            .map(|p| p.name)
+            .collect();
+        names.sort();
+        assert_eq!(names, vec![".config", "zsh"]);
+        assert_eq!(find_packages(&temp_dir).unwrap().len(), 1);
 
-        let target_dir = temp_dir.join("target");
-        fs::create_dir_all(&target_dir).unwrap();
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
 
-        let ops = analyze_package(&package_path, &target_dir).unwrap();
-        assert_eq!(ops.len(), 1);
-        assert!(matches!(ops[0].op_type, OpType::Create));
+    #[test]
+    fn test_find_duplicate_packages() {
+        let temp_dir = std::env::temp_dir().join("slinky_test_duplicates");
+        let _ = fs::remove_dir_all(&temp_dir);
+
+        let personal = temp_dir.join("personal");
+        let work = temp_dir.join("work");
+        setup_test_package(&personal, "zsh");
+        setup_test_package(&personal, "nvim");
+        setup_test_package(&work, "zsh");
+        let sources = vec![personal.clone(), work.clone()];
+
+        assert_eq!(
+            find_duplicate_packages(&sources, &[]).unwrap(),
+            vec![(
+                "zsh".to_string(),
+                vec![personal.join("zsh"), work.join("zsh")]
+            )]
+        );
+        assert!(find_duplicate_packages(&[personal], &[])
+            .unwrap()
+            .is_empty());
 
         fs::remove_dir_all(&temp_dir).unwrap();
     }
 
     #[test]
-    fn test_stow_ignore() {
-        let temp_dir = std::env::temp_dir().join("slinky_test_ignore");
+    fn test_find_nested_stow_dir() {
+        let temp_dir = std::env::temp_dir().join("slinky_test_nested");
         let _ = fs::remove_dir_all(&temp_dir);
 
-        let package_path = setup_test_package(&temp_dir, "testpkg");
-        create_test_file(&package_path.join(".config").join("keep.conf"), "keep");
-        create_test_file(&package_path.join(".config").join("ignore.tmp"), "ignore");
-        create_test_file(&package_path.join(".stow-local-ignore"), "*.tmp");
+        let home = temp_dir.join("home");
+        setup_test_package(&home, "zsh");
+        create_test_file(&home.join("zsh").join(".zshrc"), "export A=1");
+        create_test_file(&temp_dir.join("README.md"), "dotfiles");
+
+        assert_eq!(find_nested_stow_dir(&temp_dir), Some(home.clone()));
+
+        setup_test_package(&temp_dir, "nvim");
+        assert_eq!(find_nested_stow_dir(&temp_dir), None);
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_existing_real_directory_is_linked_into_per_file() {
+        let temp_dir = std::env::temp_dir().join("slinky_test_real_dir");
+        let _ = fs::remove_dir_all(&temp_dir);
 
+        let package_path = setup_test_package(&temp_dir, "nvim");
+        create_test_file(&package_path.join(".config/nvim/init.lua"), "init");
+        create_test_file(&package_path.join(".config/nvim/lua/options.lua"), "opts");
         let target_dir = temp_dir.join("target");
-        fs::create_dir_all(&target_dir).unwrap();
+        let existing = target_dir.join(".config/nvim/lazy-lock.json");
+        create_test_file(&existing, "{}");
 
         let ops = analyze_package(&package_path, &target_dir).unwrap();
+        execute_operations(&ops, false).unwrap();
+
+        let nvim = target_dir.join(".config/nvim");
+        assert!(nvim.is_dir() && !nvim.is_symlink());
+        assert_eq!(
+            fs::read_link(nvim.join("init.lua")).unwrap(),
+            package_path.join(".config/nvim/init.lua")
+        );
+        assert_eq!(
+            fs::read_link(nvim.join("lua/options.lua")).unwrap(),
+            package_path.join(".config/nvim/lua/options.lua")
+        );
+        assert!(!existing.is_symlink());
+        assert_eq!(fs::read_to_string(&existing).unwrap(), "{}");
+
+        fs::remove_dir_all(nvim.join("lua")).unwrap();
+        create_test_file(&nvim.join("lua"), "not a directory");
+        match analyze_package(&package_path, &target_dir) {
+            Err(StowError::ConflictDetected(reason)) => {
+                assert!(reason.contains("exists and is not a directory"));
+            }
+            other => panic!("expected ConflictDetected, got {:?}", other),
+        }
 
-        let create_ops: Vec<_> = ops
-            .iter()
-            .filter(|op| matches!(op.op_type, OpType::Create))
-            .collect();
-        let skip_ops: Vec<_> = ops
-            .iter()
-            .filter(|op| matches!(op.op_type, OpType::Skip(_)))
-            .collect();
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_stow_relative_links_through_symlinked_stow_dir() {
+        let temp_dir = std::env::temp_dir().join("slinky_test_stow_links");
+        let _ = fs::remove_dir_all(&temp_dir);
+
+        let package_path = setup_test_package(&temp_dir.join("src/dotfiles"), "testpkg");
+        create_test_file(&package_path.join(".rc"), "rc");
+        create_test_file(&package_path.join(".config/app/conf"), "conf");
+        let stow_dir = temp_dir.join("dotfiles");
+        std::os::unix::fs::symlink("src/dotfiles", &stow_dir).unwrap();
+        // Links as GNU Stow writes them, relative to the real stow directory.
+        fs::create_dir_all(temp_dir.join(".config")).unwrap();
+        std::os::unix::fs::symlink("src/dotfiles/testpkg/.rc", temp_dir.join(".rc")).unwrap();
+        std::os::unix::fs::symlink(
+            "../src/dotfiles/testpkg/.config/app",
+            temp_dir.join(".config/app"),
+        )
+        .unwrap();
+
+        let ops = analyze_package(&stow_dir.join("testpkg"), &temp_dir).unwrap();
+        let status = package_status(&ops);
+        assert_eq!(status.state, PackageState::Linked);
+        assert_eq!(status.linked, 2);
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_relink_package_follows_changed_source() {
+        let temp_dir = std::env::temp_dir().join("slinky_test_relink");
+        let _ = fs::remove_dir_all(&temp_dir);
+
+        let package_path = setup_test_package(&temp_dir, "testpkg");
+        create_test_file(&package_path.join(".old"), "old");
+        create_test_file(&package_path.join(".kept"), "kept");
+        let target_dir = temp_dir.join("target");
+        fs::create_dir_all(&target_dir).unwrap();
+        let ops = analyze_package(&package_path, &target_dir).unwrap();
+        execute_operations(&ops, false).unwrap();
+
+        fs::rename(package_path.join(".old"), package_path.join(".new")).unwrap();
+        let options = AnalyzeOptions::default();
+        let summary = relink_package(&package_path, &target_dir, &options, None).unwrap();
+
+        assert_eq!(
+            summary,
+            RelinkSummary {
+                removed: 2,
+                created: 2
+            }
+        );
+        assert!(!target_dir.join(".old").is_symlink());
+        assert_eq!(
+            fs::read_link(target_dir.join(".new")).unwrap(),
+            package_path.join(".new")
+        );
+        assert!(target_dir.join(".kept").is_symlink());
+
+        create_test_file(&package_path.join(".taken"), "package");
+        create_test_file(&target_dir.join(".taken"), "local");
+        assert!(relink_package(&package_path, &target_dir, &options, None).is_err());
+        assert!(target_dir.join(".new").is_symlink());
+        assert!(target_dir.join(".kept").is_symlink());
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_relink_package_removes_links_in_deleted_directories() {
+        let git = |dir: &Path, args: &[&str]| {
+            let status = std::process::Command::new("git")
+                .current_dir(dir)
+                .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+                .args(args)
+                .output()
+                .unwrap()
+                .status;
+            assert!(status.success(), "git {:?} failed", args);
+        };
+        let temp_dir = std::env::temp_dir().join("slinky_test_relink_deleted_dirs");
+        let _ = fs::remove_dir_all(&temp_dir);
+
+        let stow_dir = temp_dir.join("stow");
+        let package_path = setup_test_package(&stow_dir, "testpkg");
+        create_test_file(&package_path.join(".zshrc"), "zsh");
+        create_test_file(&package_path.join(".config/old/conf"), "old");
+        create_test_file(&package_path.join(".local/gone/data"), "gone");
+        git(&stow_dir, &["init", "-q"]);
+        git(&stow_dir, &["add", "."]);
+        git(&stow_dir, &["commit", "-qm", "init"]);
+
+        let target_dir = temp_dir.join("target");
+        fs::create_dir_all(&target_dir).unwrap();
+        let ops = analyze_package(&package_path, &target_dir).unwrap();
+        execute_operations(&ops, false).unwrap();
+
+        // One directory removed in a commit, one only from the working tree.
+        git(&stow_dir, &["rm", "-rq", "testpkg/.config"]);
+        git(&stow_dir, &["commit", "-qm", "drop old"]);
+        fs::remove_dir_all(package_path.join(".local")).unwrap();
+
+        let options = AnalyzeOptions::default();
+        let plan = plan_relink(&package_path, &target_dir, &options).unwrap();
+        let mut removed = plan.remove.clone();
+        removed.sort();
+        assert_eq!(
+            removed,
+            vec![
+                target_dir.join(".config/old/conf"),
+                target_dir.join(".local/gone/data"),
+                target_dir.join(".zshrc"),
+            ]
+        );
+        assert_eq!(plan.create.len(), 1);
+        assert!(target_dir.join(".config/old/conf").is_symlink());
+
+        let summary = relink_package(&package_path, &target_dir, &options, None).unwrap();
+        assert_eq!(
+            summary,
+            RelinkSummary {
+                removed: 3,
+                created: 1
+            }
+        );
+        assert!(!target_dir.join(".config/old/conf").is_symlink());
+        assert!(!target_dir.join(".local/gone/data").is_symlink());
+        assert!(target_dir.join(".zshrc").is_symlink());
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_created_parent_dirs_get_dir_mode() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = std::env::temp_dir().join("slinky_test_dir_mode");
+        let _ = fs::remove_dir_all(&temp_dir);
+
+        let package_path = setup_test_package(&temp_dir, "testpkg");
+        create_test_file(&package_path.join(".config/app/nested/settings"), "x");
+        let target_dir = temp_dir.join("target");
+        fs::create_dir_all(&target_dir).unwrap();
+        fs::set_permissions(&target_dir, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let ops = analyze_package(&package_path, &target_dir).unwrap();
+        execute_operations_with(&ops, false, Some(0o700), |_, _, _| {}).unwrap();
+
+        let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o7777;
+        assert!(target_dir.join(".config/app/nested/settings").is_symlink());
+        assert_eq!(mode(&target_dir.join(".config")), 0o700);
+        assert_eq!(mode(&target_dir.join(".config/app/nested")), 0o700);
+        assert_eq!(mode(&target_dir), 0o755);
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_execute_operations_reports_progress() {
+        let temp_dir = std::env::temp_dir().join("slinky_test_progress");
+        let _ = fs::remove_dir_all(&temp_dir);
+
+        let package_path = setup_test_package(&temp_dir, "testpkg");
+        create_test_file(&package_path.join(".a"), "a");
+        create_test_file(&package_path.join(".b"), "b");
+        create_test_file(&package_path.join(".c"), "c");
+        let target_dir = temp_dir.join("target");
+        fs::create_dir_all(&target_dir).unwrap();
+
+        let ops = analyze_package(&package_path, &target_dir).unwrap();
+        let mut calls = Vec::new();
+        let results = execute_operations_with(&ops, true, None, |done, total, op| {
+            calls.push((done, total, op.target.clone()));
+        })
+        .unwrap();
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(
+            calls
+                .iter()
+                .map(|(done, total, _)| (*done, *total))
+                .collect::<Vec<_>>(),
+            vec![(1, 3), (2, 3), (3, 3)]
+        );
+        let targets: Vec<PathBuf> = ops.iter().map(|op| op.target.clone()).collect();
+        assert_eq!(
+            calls
+                .into_iter()
+                .map(|(_, _, target)| target)
+                .collect::<Vec<_>>(),
+            targets
+        );
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_analyze_package_simple() {
+        let temp_dir = std::env::temp_dir().join("slinky_test_analyze");
+        let _ = fs::remove_dir_all(&temp_dir);
+
+        let package_path = setup_test_package(&temp_dir, "testpkg");
+        create_test_file(&package_path.join(".config").join("test.conf"), "config");
+
+        let target_dir = temp_dir.join("target");
+        fs::create_dir_all(&target_dir).unwrap();
+
+        let ops = analyze_package(&package_path, &target_dir).unwrap();
+        assert_eq!(ops.len(), 1);
+        assert!(matches!(ops[0].op_type, OpType::Create));
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_stow_ignore() {
+        let temp_dir = std::env::temp_dir().join("slinky_test_ignore");
+        let _ = fs::remove_dir_all(&temp_dir);
+
+        let package_path = setup_test_package(&temp_dir, "testpkg");
+        create_test_file(&package_path.join(".config").join("keep.conf"), "keep");
+        create_test_file(&package_path.join(".config").join("ignore.tmp"), "ignore");
+        create_test_file(&package_path.join(".stow-local-ignore"), "*.tmp");
+
+        let target_dir = temp_dir.join("target");
+        fs::create_dir_all(&target_dir).unwrap();
+
+        let ops = analyze_package(&package_path, &target_dir).unwrap();
+
+        let create_ops: Vec<_> = ops
+            .iter()
+            .filter(|op| matches!(op.op_type, OpType::Create))
+            .collect();
+        let skip_ops: Vec<_> = ops
+            .iter()
+            .filter(|op| matches!(op.op_type, OpType::Skip(_)))
+            .collect();
 
         assert_eq!(create_ops.len(), 1);
         assert_eq!(skip_ops.len(), 1);
 
         fs::remove_dir_all(&temp_dir).unwrap();
     }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_folded_directory_counts_as_linked() {
+        let temp_dir = std::env::temp_dir().join("slinky_test_folded");
+        let _ = fs::remove_dir_all(&temp_dir);
+
+        let stow_dir = temp_dir.join("dotfiles");
+        let package_path = setup_test_package(&stow_dir, "nvim");
+        create_test_file(&package_path.join(".config/nvim/init.lua"), "init");
+        create_test_file(
+            &package_path.join(".config/nvim/lua/plugins.lua"),
+            "plugins",
+        );
+
+        let target_dir = temp_dir.join("target");
+        fs::create_dir_all(target_dir.join(".config")).unwrap();
+        std::os::unix::fs::symlink(
+            package_path.join(".config/nvim"),
+            target_dir.join(".config/nvim"),
+        )
+        .unwrap();
+
+        let options = AnalyzeOptions {
+            report_conflicts: true,
+            ..Default::default()
+        };
+        let ops = analyze_package_with(&package_path, &target_dir, &options).unwrap();
+        assert_eq!(ops.len(), 1);
+        assert_eq!(ops[0].target, target_dir.join(".config/nvim"));
+
+        let status = package_status(&ops);
+        assert_eq!(status.state, PackageState::Linked);
+        assert_eq!((status.linked, status.total), (1, 1));
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_unfold_directory_owned_by_other_package() {
+        let temp_dir = std::env::temp_dir().join("slinky_test_unfold");
+        let _ = fs::remove_dir_all(&temp_dir);
+
+        let stow_dir = temp_dir.join("dotfiles");
+        let base = setup_test_package(&stow_dir, "nvim");
+        create_test_file(&base.join(".config/nvim/init.lua"), "init");
+        create_test_file(&base.join(".config/nvim/lua/options.lua"), "options");
+        let extra = setup_test_package(&stow_dir, "nvim-work");
+        create_test_file(&extra.join(".config/nvim/lua/work.lua"), "work");
+
+        let target_dir = temp_dir.join("target");
+        fs::create_dir_all(target_dir.join(".config")).unwrap();
+        let fold = target_dir.join(".config/nvim");
+        std::os::unix::fs::symlink(base.join(".config/nvim"), &fold).unwrap();
+
+        match analyze_package(&extra, &target_dir) {
+            Err(StowError::ConflictDetected(reason)) => {
+                assert!(reason.contains("folded directory owned by package 'nvim'"));
+            }
+            other => panic!("expected ConflictDetected, got {:?}", other),
+        }
+
+        let options = AnalyzeOptions {
+            unfold: true,
+            ..Default::default()
+        };
+        let ops = analyze_package_with(&extra, &target_dir, &options).unwrap();
+        execute_operations(&ops, false).unwrap();
+
+        assert!(fold.is_dir() && !fold.is_symlink());
+        assert!(!fold.join("lua").is_symlink());
+        assert_eq!(
+            fs::read_link(fold.join("init.lua")).unwrap(),
+            base.join(".config/nvim/init.lua")
+        );
+        assert_eq!(
+            fs::read_link(fold.join("lua/options.lua")).unwrap(),
+            base.join(".config/nvim/lua/options.lua")
+        );
+        assert_eq!(
+            fs::read_link(fold.join("lua/work.lua")).unwrap(),
+            extra.join(".config/nvim/lua/work.lua")
+        );
+        assert!(!base.join(".config/nvim/lua/work.lua").exists());
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_unfold_keeps_other_package_ignores_and_files() {
+        let temp_dir = std::env::temp_dir().join("slinky_test_unfold_shared");
+        let _ = fs::remove_dir_all(&temp_dir);
+
+        let stow_dir = temp_dir.join("dotfiles");
+        let base = setup_test_package(&stow_dir, "nvim");
+        create_test_file(&base.join(".config/nvim/init.lua"), "base");
+        create_test_file(&base.join(".config/nvim/notes.tmp"), "scratch");
+        create_test_file(&base.join(".stow-local-ignore"), "*.tmp");
+        let extra = setup_test_package(&stow_dir, "nvim-work");
+        create_test_file(&extra.join(".config/nvim/init.lua"), "work");
+
+        let target_dir = temp_dir.join("target");
+        fs::create_dir_all(target_dir.join(".config")).unwrap();
+        let fold = target_dir.join(".config/nvim");
+        std::os::unix::fs::symlink(base.join(".config/nvim"), &fold).unwrap();
+
+        let options = AnalyzeOptions {
+            unfold: true,
+            report_conflicts: true,
+            ..Default::default()
+        };
+        let ops = analyze_package_with(&extra, &target_dir, &options).unwrap();
+        assert!(!ops.iter().any(|op| op.target.ends_with("notes.tmp")));
+        let init = ops
+            .iter()
+            .find(|op| op.source == extra.join(".config/nvim/init.lua"))
+            .unwrap();
+        match &init.op_type {
+            OpType::Conflict(reason) => assert!(reason.contains("owned by package 'nvim'")),
+            other => panic!("expected a conflict, got {:?}", other),
+        }
+
+        let options = AnalyzeOptions {
+            force: true,
+            ..options
+        };
+        let ops = analyze_package_with(&extra, &target_dir, &options).unwrap();
+        execute_operations(&ops, false).unwrap();
+
+        assert!(fold.is_dir() && !fold.is_symlink());
+        assert_eq!(
+            fs::read_link(fold.join("init.lua")).unwrap(),
+            extra.join(".config/nvim/init.lua")
+        );
+        assert!(!fold.join("notes.tmp").exists());
+        assert_eq!(
+            fs::read_to_string(base.join(".config/nvim/init.lua")).unwrap(),
+            "base"
+        );
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_conflict_with_foreign_symlink() {
+        let temp_dir = std::env::temp_dir().join("slinky_test_foreign");
+        let _ = fs::remove_dir_all(&temp_dir);
+
+        let stow_dir = temp_dir.join("dotfiles");
+        let package_path = setup_test_package(&stow_dir, "zsh");
+        create_test_file(&package_path.join(".zshrc"), "ours");
+
+        let other_manager = temp_dir.join("other-dotfiles/.zshrc");
+        create_test_file(&other_manager, "theirs");
+
+        let target_dir = temp_dir.join("target");
+        fs::create_dir_all(&target_dir).unwrap();
+        std::os::unix::fs::symlink(&other_manager, target_dir.join(".zshrc")).unwrap();
+
+        match analyze_package(&package_path, &target_dir) {
+            Err(e @ StowError::ForeignSymlink { .. }) => {
+                let message = e.to_string();
+                assert!(message.contains("managed by another tool?"));
+                assert!(message.contains(&other_manager.display().to_string()));
+            }
+            other => panic!("expected ForeignSymlink, got {:?}", other),
+        }
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_detach_symlink_leaves_real_copies() {
+        let temp_dir = std::env::temp_dir().join("slinky_test_detach");
+        let _ = fs::remove_dir_all(&temp_dir);
+
+        let package_path = setup_test_package(&temp_dir.join("dotfiles"), "nvim");
+        create_test_file(&package_path.join(".vimrc"), "set number");
+        create_test_file(&package_path.join(".config/nvim/init.lua"), "-- init");
+        create_test_file(
+            &package_path.join(".config/nvim/lua/plugins.lua"),
+            "return {}",
+        );
+
+        let target_dir = temp_dir.join("target");
+        fs::create_dir_all(target_dir.join(".config")).unwrap();
+        let vimrc = target_dir.join(".vimrc");
+        let nvim = target_dir.join(".config/nvim");
+        std::os::unix::fs::symlink(package_path.join(".vimrc"), &vimrc).unwrap();
+        std::os::unix::fs::symlink("../../dotfiles/nvim/.config/nvim", &nvim).unwrap();
+
+        detach_symlink(&vimrc).unwrap();
+        detach_symlink(&nvim).unwrap();
+
+        assert!(!vimrc.is_symlink());
+        assert_eq!(fs::read_to_string(&vimrc).unwrap(), "set number");
+        assert!(!nvim.is_symlink() && nvim.is_dir());
+        assert_eq!(
+            fs::read_to_string(nvim.join("init.lua")).unwrap(),
+            "-- init"
+        );
+        assert_eq!(
+            fs::read_to_string(nvim.join("lua/plugins.lua")).unwrap(),
+            "return {}"
+        );
+        assert!(package_path.join(".config/nvim/init.lua").is_file());
+        assert!(!target_dir.join(".config/.nvim.slinky-detach").exists());
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_detach_symlink_keeps_directory_loops_as_links() {
+        let temp_dir = std::env::temp_dir().join("slinky_test_detach_loop");
+        let _ = fs::remove_dir_all(&temp_dir);
+
+        let package_path = setup_test_package(&temp_dir.join("dotfiles"), "theme");
+        create_test_file(&package_path.join(".themes/dark/colors"), "dark");
+        std::os::unix::fs::symlink("..", package_path.join(".themes/dark/all")).unwrap();
+
+        let target_dir = temp_dir.join("target");
+        fs::create_dir_all(&target_dir).unwrap();
+        let themes = target_dir.join(".themes");
+        std::os::unix::fs::symlink(package_path.join(".themes"), &themes).unwrap();
+
+        detach_symlink(&themes).unwrap();
+
+        assert!(!themes.is_symlink() && themes.is_dir());
+        assert_eq!(
+            fs::read_to_string(themes.join("dark/colors")).unwrap(),
+            "dark"
+        );
+        let loop_link = themes.join("dark/all");
+        assert!(loop_link.is_symlink());
+        assert_eq!(fs::read_link(&loop_link).unwrap(), PathBuf::from(".."));
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_repair_misdirected_symlink() {
+        let temp_dir = std::env::temp_dir().join("slinky_test_misdirected");
+        let _ = fs::remove_dir_all(&temp_dir);
+
+        let package_path = setup_test_package(&temp_dir.join("dotfiles"), "zsh");
+        create_test_file(&package_path.join(".config/zsh/zshrc"), "moved here");
+        let target_dir = temp_dir.join("target");
+        let target = target_dir.join(".config/zsh/zshrc");
+        fs::create_dir_all(target.parent().unwrap()).unwrap();
+        // The file used to live at the package root.
+        std::os::unix::fs::symlink(package_path.join("zshrc"), &target).unwrap();
+
+        match analyze_package(&package_path, &target_dir) {
+            Err(e @ StowError::Misdirected { .. }) => {
+                assert!(e.to_string().contains("misdirected symlink"));
+                assert!(e.to_string().contains("--repair"));
+            }
+            other => panic!("expected Misdirected, got {:?}", other),
+        }
+
+        let status_options = AnalyzeOptions {
+            report_conflicts: true,
+            ..Default::default()
+        };
+        let ops = analyze_package_with(&package_path, &target_dir, &status_options).unwrap();
+        assert_eq!(package_status(&ops).conflicts, 1);
+
+        let options = AnalyzeOptions {
+            repair: true,
+            ..Default::default()
+        };
+        let ops = analyze_package_with(&package_path, &target_dir, &options).unwrap();
+        let source = package_path.join(".config/zsh/zshrc");
+        assert_eq!(find_op(&ops, &source).op_type, OpType::Relink);
+        execute_operations(&ops, false).unwrap();
+
+        assert_eq!(fs::read_link(&target).unwrap(), source);
+        let ops = analyze_package(&package_path, &target_dir).unwrap();
+        assert_eq!(package_status(&ops).state, PackageState::Linked);
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_conflict_owned_by_other_package() {
+        let temp_dir = std::env::temp_dir().join("slinky_test_owned");
+        let _ = fs::remove_dir_all(&temp_dir);
+
+        let stow_dir = temp_dir.join("dotfiles");
+        let first = setup_test_package(&stow_dir, "git-work");
+        let second = setup_test_package(&stow_dir, "git-home");
+        create_test_file(&first.join(".gitconfig"), "work");
+        create_test_file(&second.join(".gitconfig"), "home");
+
+        let target_dir = temp_dir.join("target");
+        fs::create_dir_all(&target_dir).unwrap();
+
+        let ops = analyze_package(&first, &target_dir).unwrap();
+        execute_operations(&ops, false).unwrap();
+
+        match analyze_package(&second, &target_dir) {
+            Err(StowError::OwnedByPackage { target, package }) => {
+                assert_eq!(target, target_dir.join(".gitconfig"));
+                assert_eq!(package, "git-work");
+            }
+            other => panic!("expected OwnedByPackage, got {:?}", other),
+        }
+
+        let options = AnalyzeOptions {
+            force: true,
+            ..Default::default()
+        };
+        let ops = analyze_package_with(&second, &target_dir, &options).unwrap();
+        assert_eq!(ops.len(), 2);
+        assert_eq!(ops[0].op_type, OpType::Remove);
+        assert_eq!(ops[1].op_type, OpType::Create);
+
+        execute_operations(&ops, false).unwrap();
+        assert_eq!(
+            fs::read_link(target_dir.join(".gitconfig")).unwrap(),
+            second.join(".gitconfig")
+        );
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_find_link_owner() {
+        let temp_dir = std::env::temp_dir().join("slinky_test_which");
+        let _ = fs::remove_dir_all(&temp_dir);
+
+        let stow_dir = temp_dir.join("dotfiles");
+        let package_path = setup_test_package(&stow_dir, "nvim");
+        create_test_file(&package_path.join(".config/nvim/init.lua"), "-- nvim");
+
+        let target_dir = temp_dir.join("target");
+        fs::create_dir_all(&target_dir).unwrap();
+        create_test_file(&target_dir.join(".bashrc"), "real file");
+
+        let ops = analyze_package(&package_path, &target_dir).unwrap();
+        execute_operations(&ops, false).unwrap();
+
+        let owner = find_link_owner(&target_dir.join(".config/nvim/init.lua"), &stow_dir)
+            .unwrap()
+            .unwrap();
+        assert_eq!(owner.package, "nvim");
+        assert_eq!(owner.source, package_path.join(".config/nvim/init.lua"));
+
+        assert!(find_link_owner(&target_dir.join(".bashrc"), &stow_dir)
+            .unwrap()
+            .is_none());
+
+        // A file reached through a folded directory link.
+        create_test_file(&package_path.join(".local/bin/tool"), "#!/bin/sh");
+        std::os::unix::fs::symlink(package_path.join(".local"), target_dir.join(".local")).unwrap();
+        let owner = find_link_owner(&target_dir.join(".local/bin/tool"), &stow_dir)
+            .unwrap()
+            .unwrap();
+        assert_eq!(owner.package, "nvim");
+        assert_eq!(owner.source, package_path.join(".local/bin/tool"));
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_remap_file_rename() {
+        let temp_dir = std::env::temp_dir().join("slinky_test_remap_file");
+        let _ = fs::remove_dir_all(&temp_dir);
+
+        let package_path = setup_test_package(&temp_dir, "git");
+        create_test_file(&package_path.join("gitconfig"), "[user]");
+        create_test_file(
+            &package_path.join(PACKAGE_CONFIG_FILE),
+            "[remap]\ngitconfig = \".gitconfig\"\n",
+        );
+
+        let target_dir = temp_dir.join("target");
+        fs::create_dir_all(&target_dir).unwrap();
+
+        let ops = analyze_package(&package_path, &target_dir).unwrap();
+        assert_eq!(ops.len(), 1);
+        assert_eq!(ops[0].target, target_dir.join(".gitconfig"));
+        assert_eq!(ops[0].op_type, OpType::Create);
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_remap_directory_rename() {
+        let temp_dir = std::env::temp_dir().join("slinky_test_remap_dir");
+        let _ = fs::remove_dir_all(&temp_dir);
+
+        let package_path = setup_test_package(&temp_dir, "nvim");
+        create_test_file(&package_path.join("config/nvim/init.lua"), "-- nvim");
+        create_test_file(
+            &package_path.join(PACKAGE_CONFIG_FILE),
+            "[remap]\n\"config\" = \".config\"\n",
+        );
+
+        let target_dir = temp_dir.join("target");
+        fs::create_dir_all(&target_dir).unwrap();
+
+        let ops = analyze_package(&package_path, &target_dir).unwrap();
+        assert_eq!(ops.len(), 1);
+        assert_eq!(ops[0].target, target_dir.join(".config/nvim/init.lua"));
+
+        execute_operations(&ops, false).unwrap();
+        assert!(target_dir.join(".config/nvim/init.lua").is_symlink());
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_remap_collision() {
+        let temp_dir = std::env::temp_dir().join("slinky_test_remap_collision");
+        let _ = fs::remove_dir_all(&temp_dir);
+
+        let package_path = setup_test_package(&temp_dir, "shell");
+        create_test_file(&package_path.join("config.local"), "local");
+        create_test_file(&package_path.join("config"), "generic");
+        create_test_file(
+            &package_path.join(PACKAGE_CONFIG_FILE),
+            "[remap]\n\"config.local\" = \"config\"\n",
+        );
+
+        let target_dir = temp_dir.join("target");
+        fs::create_dir_all(&target_dir).unwrap();
+
+        let result = analyze_package(&package_path, &target_dir);
+        assert!(matches!(result, Err(StowError::ConflictDetected(_))));
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    fn setup_conditional_package(name: &str) -> (PathBuf, PathBuf, PathBuf) {
+        let temp_dir = std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&temp_dir);
+
+        let package_path = setup_test_package(&temp_dir, "shell");
+        create_test_file(&package_path.join("config.linux"), "linux");
+        create_test_file(&package_path.join("config.macos"), "macos");
+
+        let target_dir = temp_dir.join("target");
+        fs::create_dir_all(&target_dir).unwrap();
+
+        (temp_dir, package_path, target_dir)
+    }
+
+    fn find_op<'a>(ops: &'a [SymlinkOp], source: &Path) -> &'a SymlinkOp {
+        ops.iter().find(|op| op.source == source).unwrap()
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_os_conditional_files_on_linux() {
+        let (temp_dir, package_path, target_dir) =
+            setup_conditional_package("slinky_test_conditional_linux");
+
+        let ops = analyze_package(&package_path, &target_dir).unwrap();
+
+        let linux = find_op(&ops, &package_path.join("config.linux"));
+        assert_eq!(linux.op_type, OpType::Create);
+        assert_eq!(linux.target, target_dir.join("config"));
+
+        let macos = find_op(&ops, &package_path.join("config.macos"));
+        assert!(matches!(macos.op_type, OpType::Skip(_)));
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn test_os_conditional_files_on_macos() {
+        let (temp_dir, package_path, target_dir) =
+            setup_conditional_package("slinky_test_conditional_macos");
+
+        let ops = analyze_package(&package_path, &target_dir).unwrap();
+
+        let macos = find_op(&ops, &package_path.join("config.macos"));
+        assert_eq!(macos.op_type, OpType::Create);
+        assert_eq!(macos.target, target_dir.join("config"));
+
+        let linux = find_op(&ops, &package_path.join("config.linux"));
+        assert!(matches!(linux.op_type, OpType::Skip(_)));
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_machine_specific_file_overrides_generic_one() {
+        let temp_dir = std::env::temp_dir().join("slinky_test_conditional_override");
+        let _ = fs::remove_dir_all(&temp_dir);
+
+        let package_path = setup_test_package(&temp_dir, "git");
+        let specific = package_path.join(format!(".gitconfig.{}", std::env::consts::OS));
+        create_test_file(&package_path.join(".gitconfig"), "generic");
+        create_test_file(&specific, "this machine");
+        create_test_file(&package_path.join(".gitattributes"), "* text=auto");
+
+        let target_dir = temp_dir.join("target");
+        fs::create_dir_all(&target_dir).unwrap();
+
+        let ops = analyze_package(&package_path, &target_dir).unwrap();
+        let specific_op = find_op(&ops, &specific);
+        assert_eq!(specific_op.op_type, OpType::Create);
+        assert_eq!(specific_op.target, target_dir.join(".gitconfig"));
+        assert!(matches!(
+            &find_op(&ops, &package_path.join(".gitconfig")).op_type,
+            OpType::Skip(reason) if reason.contains("Overridden")
+        ));
+        assert_eq!(
+            find_op(&ops, &package_path.join(".gitattributes")).op_type,
+            OpType::Create
+        );
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_expand_placeholders() {
+        assert_eq!(
+            expand_placeholders(".config-${HOST}", Some("server")).unwrap(),
+            ".config-server"
+        );
+        assert_eq!(
+            expand_placeholders("${OS}/${HOST}", Some("desktop")).unwrap(),
+            format!("{}/desktop", std::env::consts::OS)
+        );
+        assert_eq!(expand_placeholders(".config", None).unwrap(), ".config");
+        assert!(expand_placeholders(".config-${HOST}", None).is_err());
+    }
+
+    #[test]
+    fn test_package_target_with_host_placeholder() {
+        let temp_dir = std::env::temp_dir().join("slinky_test_target_placeholder");
+        let _ = fs::remove_dir_all(&temp_dir);
+
+        let package_path = setup_test_package(&temp_dir.join("stow"), "app");
+        create_test_file(&package_path.join("settings.toml"), "a = 1");
+        create_test_file(&package_path.join("themes/dark.toml"), "dark");
+        create_test_file(
+            &package_path.join(PACKAGE_CONFIG_FILE),
+            "target = \".config-${HOST}\"\n[remap]\nthemes = \"themes-${OS}\"\n",
+        );
+
+        let target_dir = temp_dir.join("target");
+        let options = AnalyzeOptions {
+            hostname: Some("Server".to_string()),
+            ..Default::default()
+        };
+        let ops = analyze_package_with(&package_path, &target_dir, &options).unwrap();
+        let mut targets: Vec<_> = ops.iter().map(|op| op.target.clone()).collect();
+        targets.sort();
+
+        let root = target_dir.join(".config-server");
+        assert_eq!(
+            targets,
+            vec![
+                root.join("settings.toml"),
+                root.join(format!("themes-{}", std::env::consts::OS))
+                    .join("dark.toml"),
+            ]
+        );
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_package_with_multiple_targets() {
+        let temp_dir = std::env::temp_dir().join("slinky_test_multi_target");
+        let _ = fs::remove_dir_all(&temp_dir);
+
+        let package_path = setup_test_package(&temp_dir.join("stow"), "shared");
+        create_test_file(&package_path.join("snippet.sh"), "alias ll='ls -l'");
+        create_test_file(
+            &package_path.join(PACKAGE_CONFIG_FILE),
+            "target = [\".config/a\", \".config/b\"]\n",
+        );
+
+        let target_dir = temp_dir.join("target");
+        let first = target_dir.join(".config/a/snippet.sh");
+        let second = target_dir.join(".config/b/snippet.sh");
+
+        let ops = analyze_package(&package_path, &target_dir).unwrap();
+        assert_eq!(ops.len(), 2);
+        execute_operations(&ops, false).unwrap();
+        assert!(first.is_symlink() && second.is_symlink());
+
+        fs::remove_file(&second).unwrap();
+        let status = package_status(&analyze_package(&package_path, &target_dir).unwrap());
+        assert_eq!(status.state, PackageState::Partial);
+        let ops = analyze_package(&package_path, &target_dir).unwrap();
+        execute_operations(&ops, false).unwrap();
+
+        let remove_ops: Vec<SymlinkOp> = analyze_package(&package_path, &target_dir)
+            .unwrap()
+            .into_iter()
+            .map(|op| SymlinkOp {
+                op_type: OpType::Remove,
+                ..op
+            })
+            .collect();
+        execute_operations(&remove_ops, false).unwrap();
+        assert!(!first.exists() && !second.exists());
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_hostname_conditional_files() {
+        let temp_dir = std::env::temp_dir().join("slinky_test_conditional_host");
+        let _ = fs::remove_dir_all(&temp_dir);
+
+        let package_path = setup_test_package(&temp_dir, "shell");
+        create_test_file(&package_path.join(".profile.desktop"), "desktop");
+        create_test_file(&package_path.join(".profile.server"), "server");
+        create_test_file(&package_path.join("notes.txt"), "plain");
+        create_test_file(
+            &package_path.join(PACKAGE_CONFIG_FILE),
+            "hosts = [\"desktop\", \"server\"]\n",
+        );
+
+        let target_dir = temp_dir.join("target");
+        fs::create_dir_all(&target_dir).unwrap();
+
+        let options = AnalyzeOptions {
+            hostname: Some("Desktop".to_string()),
+            ..Default::default()
+        };
+        let ops = analyze_package_with(&package_path, &target_dir, &options).unwrap();
+
+        let desktop = find_op(&ops, &package_path.join(".profile.desktop"));
+        assert_eq!(desktop.op_type, OpType::Create);
+        assert_eq!(desktop.target, target_dir.join(".profile"));
+
+        let server = find_op(&ops, &package_path.join(".profile.server"));
+        assert!(matches!(server.op_type, OpType::Skip(_)));
+
+        let notes = find_op(&ops, &package_path.join("notes.txt"));
+        assert_eq!(notes.target, target_dir.join("notes.txt"));
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_report_conflicts_as_operations() {
+        let temp_dir = std::env::temp_dir().join("slinky_test_report_conflicts");
+        let _ = fs::remove_dir_all(&temp_dir);
+
+        let package_path = setup_test_package(&temp_dir, "zsh");
+        create_test_file(&package_path.join(".zshrc"), "export A=1");
+        create_test_file(&package_path.join(".zprofile"), "profile");
+
+        let target_dir = temp_dir.join("target");
+        create_test_file(&target_dir.join(".zshrc"), "existing");
+
+        assert!(analyze_package(&package_path, &target_dir).is_err());
+
+        let options = AnalyzeOptions {
+            report_conflicts: true,
+            ..Default::default()
+        };
+        let ops = analyze_package_with(&package_path, &target_dir, &options).unwrap();
+        let conflict = ops
+            .iter()
+            .find(|op| matches!(op.op_type, OpType::Conflict(_)))
+            .unwrap();
+        assert_eq!(conflict.target, target_dir.join(".zshrc"));
+
+        let status = package_status(&ops);
+        assert_eq!(status.state, PackageState::Conflicted);
+        assert_eq!(status.conflicts, 1);
+        assert_eq!(status.total, 2);
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_case_collision_detection() {
+        let temp_dir = std::env::temp_dir().join("slinky_test_case_collision");
+        let _ = fs::remove_dir_all(&temp_dir);
+
+        let package_path = setup_test_package(&temp_dir, "app");
+        create_test_file(&package_path.join("Config"), "upper");
+        create_test_file(&package_path.join("config"), "lower");
+
+        let target_dir = temp_dir.join("target");
+        fs::create_dir_all(&target_dir).unwrap();
+
+        let insensitive = AnalyzeOptions {
+            case_insensitive: Some(true),
+            ..Default::default()
+        };
+        let err = analyze_package_with(&package_path, &target_dir, &insensitive).unwrap_err();
+        assert!(err.to_string().contains("Case collision"));
+
+        let sensitive = AnalyzeOptions {
+            case_insensitive: Some(false),
+            ..Default::default()
+        };
+        let ops = analyze_package_with(&package_path, &target_dir, &sensitive).unwrap();
+        assert_eq!(ops.len(), 2);
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_max_depth_limits_scan() {
+        let temp_dir = std::env::temp_dir().join("slinky_test_max_depth");
+        let _ = fs::remove_dir_all(&temp_dir);
+
+        let package_path = setup_test_package(&temp_dir, "deep");
+        create_test_file(&package_path.join("one"), "1");
+        create_test_file(&package_path.join("a").join("two"), "2");
+        create_test_file(&package_path.join("a").join("b").join("three"), "3");
+        create_test_file(
+            &package_path.join("a").join("b").join("c").join("four"),
+            "4",
+        );
+
+        let target_dir = temp_dir.join("target");
+        fs::create_dir_all(&target_dir).unwrap();
+
+        let options = AnalyzeOptions {
+            max_depth: Some(2),
+            ..Default::default()
+        };
+        let ops = analyze_package_with(&package_path, &target_dir, &options).unwrap();
+        let created: Vec<_> = ops
+            .iter()
+            .filter(|op| matches!(op.op_type, OpType::Create))
+            .map(|op| op.target.strip_prefix(&target_dir).unwrap().to_path_buf())
+            .collect();
+        assert_eq!(created.len(), 2);
+        assert!(created.contains(&PathBuf::from("one")));
+        assert!(created.contains(&PathBuf::from("a/two")));
+        assert!(ops.iter().any(|op| op.target == target_dir.join("a/b")
+            && matches!(&op.op_type, OpType::Skip(reason) if reason.contains("max depth"))));
+
+        create_test_file(&package_path.join(PACKAGE_CONFIG_FILE), "max_depth = 1\n");
+        let ops = analyze_package_with(&package_path, &target_dir, &options).unwrap();
+        assert_eq!(
+            ops.iter()
+                .filter(|op| matches!(op.op_type, OpType::Create))
+                .count(),
+            1
+        );
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_find_orphaned_links() {
+        let temp_dir = std::env::temp_dir().join("slinky_test_orphans");
+        let _ = fs::remove_dir_all(&temp_dir);
+
+        let stow_dir = temp_dir.join("stow");
+        let package_path = setup_test_package(&stow_dir, "nvim");
+        create_test_file(&package_path.join(".config/nvim/init.lua"), "init");
+        create_test_file(&package_path.join(".config/nvim/old.lua"), "old");
+
+        let target_dir = temp_dir.join("target");
+        fs::create_dir_all(&target_dir).unwrap();
+        let ops = analyze_package(&package_path, &target_dir).unwrap();
+        execute_operations(&ops, false).unwrap();
+
+        let unrelated = target_dir.join(".config/nvim/elsewhere.lua");
+        std::os::unix::fs::symlink(temp_dir.join("missing"), &unrelated).unwrap();
+
+        assert!(find_orphaned_links(&stow_dir, &target_dir, &[])
+            .unwrap()
+            .is_empty());
+
+        fs::remove_file(package_path.join(".config/nvim/old.lua")).unwrap();
+        let orphans = find_orphaned_links(&stow_dir, &target_dir, &[]).unwrap();
+        assert_eq!(orphans, vec![target_dir.join(".config/nvim/old.lua")]);
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_find_orphaned_links_in_hidden_package() {
+        let temp_dir = std::env::temp_dir().join("slinky_test_orphans_hidden");
+        let _ = fs::remove_dir_all(&temp_dir);
+
+        let stow_dir = temp_dir.join("stow");
+        let package_path = setup_test_package(&stow_dir, ".scripts");
+        create_test_file(&package_path.join("bin/tool"), "tool");
+        create_test_file(&package_path.join("bin/old"), "old");
+
+        let target_dir = temp_dir.join("target");
+        fs::create_dir_all(target_dir.join("bin")).unwrap();
+        let ops = analyze_package(&package_path, &target_dir).unwrap();
+        execute_operations(&ops, false).unwrap();
+        fs::remove_file(package_path.join("bin/old")).unwrap();
+
+        assert!(find_orphaned_links(&stow_dir, &target_dir, &[])
+            .unwrap()
+            .is_empty());
+        let orphans =
+            find_orphaned_links(&stow_dir, &target_dir, &[".scripts".to_string()]).unwrap();
+        assert_eq!(orphans, vec![target_dir.join("bin/old")]);
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_package_status_empty_package() {
+        let temp_dir = std::env::temp_dir().join("slinky_test_status_empty");
+        let _ = fs::remove_dir_all(&temp_dir);
+
+        let package_path = setup_test_package(&temp_dir, "empty");
+        let target_dir = temp_dir.join("target");
+        fs::create_dir_all(&target_dir).unwrap();
+
+        let ops = analyze_package(&package_path, &target_dir).unwrap();
+        let status = package_status(&ops);
+        assert_eq!(status.state, PackageState::Empty);
+        assert_eq!(status.total, 0);
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_package_status_all_ignored_package() {
+        let temp_dir = std::env::temp_dir().join("slinky_test_status_ignored");
+        let _ = fs::remove_dir_all(&temp_dir);
+
+        let package_path = setup_test_package(&temp_dir, "scratch");
+        create_test_file(&package_path.join("a.tmp"), "a");
+        create_test_file(&package_path.join("b.tmp"), "b");
+        create_test_file(&package_path.join(".stow-local-ignore"), "*.tmp");
+
+        let target_dir = temp_dir.join("target");
+        fs::create_dir_all(&target_dir).unwrap();
+
+        let ops = analyze_package(&package_path, &target_dir).unwrap();
+        assert_eq!(ops.len(), 2);
+        let status = package_status(&ops);
+        assert_eq!(status.state, PackageState::Empty);
+        assert_eq!(status.ignored, 2);
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_package_status_ignores_skipped_files_in_totals() {
+        let temp_dir = std::env::temp_dir().join("slinky_test_status_linked");
+        let _ = fs::remove_dir_all(&temp_dir);
+
+        let package_path = setup_test_package(&temp_dir, "zsh");
+        create_test_file(&package_path.join(".zshrc"), "zsh");
+        create_test_file(&package_path.join("notes.tmp"), "ignored");
+        create_test_file(&package_path.join(".stow-local-ignore"), "*.tmp");
+
+        let target_dir = temp_dir.join("target");
+        fs::create_dir_all(&target_dir).unwrap();
+
+        let ops = analyze_package(&package_path, &target_dir).unwrap();
+        assert_eq!(package_status(&ops).state, PackageState::Unlinked);
+
+        execute_operations(&ops, false).unwrap();
+        let ops = analyze_package(&package_path, &target_dir).unwrap();
+        let status = package_status(&ops);
+        assert_eq!(status.state, PackageState::Linked);
+        assert_eq!((status.linked, status.total), (1, 1));
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_trace_ignores_names_matching_rule() {
+        let temp_dir = std::env::temp_dir().join("slinky_test_ignore_trace");
+        let _ = fs::remove_dir_all(&temp_dir);
+
+        let package_path = setup_test_package(&temp_dir, "scratch");
+        create_test_file(&package_path.join("a.tmp"), "a");
+        create_test_file(&package_path.join("README.md"), "readme");
+        create_test_file(
+            &package_path.join(".stow-local-ignore"),
+            "*.tmp\n/README.md\n",
+        );
+
+        let target_dir = temp_dir.join("target");
+        fs::create_dir_all(&target_dir).unwrap();
+
+        let options = AnalyzeOptions {
+            trace_ignores: true,
+            ..Default::default()
+        };
+        let ops = analyze_package_with(&package_path, &target_dir, &options).unwrap();
+        assert_eq!(
+            find_op(&ops, &package_path.join("a.tmp")).op_type,
+            OpType::Skip("Ignored by .stow-local-ignore: *.tmp".to_string())
+        );
+        assert_eq!(
+            find_op(&ops, &package_path.join("README.md")).op_type,
+            OpType::Skip("Ignored by .stow-local-ignore: /README.md".to_string())
+        );
+        assert_eq!(package_status(&ops).ignored, 2);
+
+        let ops = analyze_package(&package_path, &target_dir).unwrap();
+        assert_eq!(
+            find_op(&ops, &package_path.join("a.tmp")).op_type,
+            OpType::Skip(IGNORED_REASON.to_string())
+        );
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_stow_ignore_anchored_pattern() {
+        let temp_dir = std::env::temp_dir().join("slinky_test_ignore_anchored");
+        let _ = fs::remove_dir_all(&temp_dir);
+
+        let package_path = setup_test_package(&temp_dir, "testpkg");
+        create_test_file(&package_path.join("README.md"), "top");
+        create_test_file(&package_path.join(".config/tool/README.md"), "nested");
+        create_test_file(&package_path.join(".stow-local-ignore"), "/README.md\n");
+
+        let target_dir = temp_dir.join("target");
+        fs::create_dir_all(&target_dir).unwrap();
+
+        let ops = analyze_package(&package_path, &target_dir).unwrap();
+
+        let top = find_op(&ops, &package_path.join("README.md"));
+        assert!(matches!(top.op_type, OpType::Skip(_)));
+
+        let nested = find_op(&ops, &package_path.join(".config/tool/README.md"));
+        assert_eq!(nested.op_type, OpType::Create);
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_gitattributes_export_ignore() {
+        let temp_dir = std::env::temp_dir().join("slinky_test_export_ignore");
+        let _ = fs::remove_dir_all(&temp_dir);
+
+        let package_path = setup_test_package(&temp_dir, "zsh");
+        create_test_file(&package_path.join(".zshrc"), "export A=1");
+        create_test_file(&package_path.join("README.md"), "docs");
+        create_test_file(&package_path.join("local").join("work.zsh"), "work");
+        create_test_file(&package_path.join(".zprofile"), "profile");
+        create_test_file(&package_path.join("localrc"), "kept");
+        create_test_file(&package_path.join("doc").join("notes"), "notes");
+        create_test_file(&package_path.join(".docker").join("config.json"), "{}");
+        create_test_file(&package_path.join("bin").join("doc-gen"), "kept");
+        create_test_file(
+            &temp_dir.join(".gitattributes"),
+            "*.md export-ignore\n/zsh/local export-ignore\nnvim/init.lua export-ignore\n.zprofile -export-ignore\ndoc export-ignore\n",
+        );
+
+        let target_dir = temp_dir.join("target");
+        fs::create_dir_all(&target_dir).unwrap();
+
+        let ops = analyze_package(&package_path, &target_dir).unwrap();
+        let is_created = |name: &str| {
+            ops.iter()
+                .any(|op| op.target.ends_with(name) && matches!(op.op_type, OpType::Create))
+        };
+
+        assert!(is_created(".zshrc"));
+        assert!(is_created(".zprofile"));
+        assert!(!is_created("README.md"));
+        assert!(!is_created("work.zsh"));
+        assert!(is_created("localrc"));
+        assert!(!is_created("notes"));
+        assert!(is_created("config.json"));
+        assert!(is_created("doc-gen"));
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_stow_ignore_negation() {
+        let temp_dir = std::env::temp_dir().join("slinky_test_ignore_negation");
+        let _ = fs::remove_dir_all(&temp_dir);
+
+        let package_path = setup_test_package(&temp_dir, "testpkg");
+        create_test_file(&package_path.join("notes.md"), "ignored");
+        create_test_file(&package_path.join("keep.md"), "kept");
+        create_test_file(
+            &package_path.join(".stow-local-ignore"),
+            "# docs are not config\n*.md\n!keep.md\n",
+        );
+
+        let target_dir = temp_dir.join("target");
+        fs::create_dir_all(&target_dir).unwrap();
+
+        let ops = analyze_package(&package_path, &target_dir).unwrap();
+
+        let notes = find_op(&ops, &package_path.join("notes.md"));
+        assert!(matches!(notes.op_type, OpType::Skip(_)));
+
+        let keep = find_op(&ops, &package_path.join("keep.md"));
+        assert_eq!(keep.op_type, OpType::Create);
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_self_referential_symlink_terminates() {
+        let temp_dir = std::env::temp_dir().join("slinky_test_symlink_loop");
+        let _ = fs::remove_dir_all(&temp_dir);
+
+        let package_path = setup_test_package(&temp_dir, "loop");
+        create_test_file(&package_path.join("config").join("settings"), "a=1");
+        std::os::unix::fs::symlink(&package_path, package_path.join("config").join("back"))
+            .unwrap();
+
+        let target_dir = temp_dir.join("target");
+        fs::create_dir_all(&target_dir).unwrap();
+
+        let ops = analyze_package(&package_path, &target_dir).unwrap();
+        assert_eq!(ops.len(), 2);
+
+        // The symlinked directory is linked as an entry, not walked.
+        let back = find_op(&ops, &package_path.join("config").join("back"));
+        assert_eq!(back.op_type, OpType::Create);
+        assert_eq!(back.target, target_dir.join("config").join("back"));
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_symlink_modes_for_in_package_symlinks() {
+        let temp_dir = std::env::temp_dir().join("slinky_test_symlink_modes");
+        let _ = fs::remove_dir_all(&temp_dir);
+
+        let external = temp_dir.join("external").join("theme.conf");
+        create_test_file(&external, "dark");
+        let package_path = setup_test_package(&temp_dir, "theme");
+        let in_package = package_path.join("theme.conf");
+        std::os::unix::fs::symlink(&external, &in_package).unwrap();
+
+        let target_dir = temp_dir.join("target");
+        fs::create_dir_all(&target_dir).unwrap();
+
+        let ops = analyze_package(&package_path, &target_dir).unwrap();
+        assert_eq!(ops[0].source, in_package);
+        assert_eq!(ops[0].op_type, OpType::Create);
+
+        let deref = AnalyzeOptions {
+            symlinks: SymlinkMode::Deref,
+            ..Default::default()
+        };
+        let ops = analyze_package_with(&package_path, &target_dir, &deref).unwrap();
+        assert_eq!(ops[0].source, fs::canonicalize(&external).unwrap());
+        assert_eq!(ops[0].target, target_dir.join("theme.conf"));
+        execute_operations(&ops, false).unwrap();
+        assert_eq!(
+            fs::read_link(target_dir.join("theme.conf")).unwrap(),
+            fs::canonicalize(&external).unwrap()
+        );
+
+        let ops = analyze_package_with(&package_path, &target_dir, &deref).unwrap();
+        assert!(matches!(&ops[0].op_type, OpType::Skip(r) if r.contains("Already linked")));
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_op_type_serde_round_trip() {
+        let cases = [
+            (OpType::Create, r#"{"type":"create"}"#),
+            (OpType::Remove, r#"{"type":"remove"}"#),
+            (
+                OpType::Skip("Already linked correctly".to_string()),
+                r#"{"type":"skip","reason":"Already linked correctly"}"#,
+            ),
+        ];
+
+        for (op_type, expected) in cases {
+            let json = serde_json::to_string(&op_type).unwrap();
+            assert_eq!(json, expected);
+
+            let decoded: OpType = serde_json::from_str(&json).unwrap();
+            assert_eq!(decoded, op_type);
+        }
+    }
+
+    #[test]
+    fn test_symlink_op_serde_round_trip() {
+        let op = SymlinkOp {
+            source: PathBuf::from("/dotfiles/zsh/.zshrc"),
+            target: PathBuf::from("/home/user/.zshrc"),
+            op_type: OpType::Skip("Ignored by .stow-local-ignore".to_string()),
+        };
+
+        let json = serde_json::to_value(&op).unwrap();
+        assert_eq!(json["op_type"]["type"], "skip");
+        assert_eq!(json["op_type"]["reason"], "Ignored by .stow-local-ignore");
+
+        let decoded: SymlinkOp = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded, op);
+    }
+
+    #[test]
+    fn test_plan_rejects_unknown_version() {
+        let temp_dir = std::env::temp_dir().join("slinky_test_plan_version");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).unwrap();
+
+        let plan_path = temp_dir.join("plan.json");
+        fs::write(&plan_path, r#"{"version":99,"operations":[]}"#).unwrap();
+
+        assert!(matches!(read_plan(&plan_path), Err(StowError::Plan(_))));
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_plan_round_trip() {
+        let temp_dir = std::env::temp_dir().join("slinky_test_plan");
+        let _ = fs::remove_dir_all(&temp_dir);
+
+        let package_path = setup_test_package(&temp_dir, "testpkg");
+        create_test_file(&package_path.join(".zshrc"), "zsh");
+        create_test_file(&package_path.join("notes.tmp"), "ignored");
+        create_test_file(&package_path.join(".stow-local-ignore"), "*.tmp");
+
+        let target_dir = temp_dir.join("target");
+        fs::create_dir_all(&target_dir).unwrap();
+
+        let ops = analyze_package(&package_path, &target_dir).unwrap();
+        let plan_path = temp_dir.join("plans").join("plan.json");
+        write_plan(&plan_path, &ops).unwrap();
+
+        let loaded = read_plan(&plan_path).unwrap();
+        assert_eq!(loaded, ops);
+
+        execute_operations(&loaded, false).unwrap();
+        assert!(target_dir.join(".zshrc").is_symlink());
+        assert!(!target_dir.join("notes.tmp").exists());
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
 }
@@ -0,0 +1,1967 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+fn slnky() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_slnky"))
+}
+
+fn write_config(home: &Path, stow_dir: &Path) {
+    let config_dir = home.join(".config").join("slinky");
+    fs::create_dir_all(&config_dir).unwrap();
+    fs::write(
+        config_dir.join("config.toml"),
+        format!(
+            "stow_dir = {:?}\ntarget_dir = {:?}\n",
+            stow_dir.display().to_string(),
+            home.display().to_string()
+        ),
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_unset_home_is_reported() {
+    let output = slnky().arg("status").env_remove("HOME").output().unwrap();
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("HOME is not set"));
+}
+
+#[test]
+fn test_empty_home_is_reported() {
+    let output = slnky().arg("status").env("HOME", "").output().unwrap();
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("HOME is not set"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_export_lists_linked_packages() {
+    let home = tempfile::tempdir().unwrap();
+    let stow_dir = home.path().join(".dotfiles");
+    fs::create_dir_all(stow_dir.join("zsh")).unwrap();
+    fs::create_dir_all(stow_dir.join("nvim")).unwrap();
+    fs::write(stow_dir.join("zsh").join(".zshrc"), "export A=1").unwrap();
+    fs::write(stow_dir.join("nvim").join(".vimrc"), "set nu").unwrap();
+    std::os::unix::fs::symlink(
+        stow_dir.join("zsh").join(".zshrc"),
+        home.path().join(".zshrc"),
+    )
+    .unwrap();
+
+    write_config(home.path(), &stow_dir);
+
+    let output = slnky()
+        .arg("export")
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("#!/bin/sh"));
+    assert!(stdout.contains("slnky link 'zsh'"));
+    assert!(!stdout.contains("'nvim'"));
+}
+
+#[test]
+fn test_status_json_reports_conflicts() {
+    let home = tempfile::tempdir().unwrap();
+    let stow_dir = home.path().join(".dotfiles");
+    fs::create_dir_all(stow_dir.join("zsh")).unwrap();
+    fs::write(stow_dir.join("zsh").join(".zshrc"), "export A=1").unwrap();
+    fs::write(home.path().join(".zshrc"), "existing").unwrap();
+
+    write_config(home.path(), &stow_dir);
+
+    let output = slnky()
+        .args(["status", "--json"])
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report[0]["name"], "zsh");
+    assert_eq!(report[0]["state"], "conflicted");
+    assert_eq!(
+        report[0]["conflicts"][0]["target"],
+        home.path().join(".zshrc").display().to_string()
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_status_porcelain_format() {
+    let home = tempfile::tempdir().unwrap();
+    let stow_dir = home.path().join(".dotfiles");
+    for (package, file) in [
+        ("git", ".gitconfig"),
+        ("nvim", ".vimrc"),
+        ("tmux", ".tmux.conf"),
+        ("zsh", ".zshrc"),
+    ] {
+        fs::create_dir_all(stow_dir.join(package)).unwrap();
+        fs::write(stow_dir.join(package).join(file), package).unwrap();
+    }
+    fs::write(stow_dir.join("nvim").join(".gvimrc"), "gui").unwrap();
+    fs::create_dir_all(stow_dir.join("empty")).unwrap();
+    write_config(home.path(), &stow_dir);
+
+    std::os::unix::fs::symlink(
+        stow_dir.join("git/.gitconfig"),
+        home.path().join(".gitconfig"),
+    )
+    .unwrap();
+    std::os::unix::fs::symlink(stow_dir.join("nvim/.vimrc"), home.path().join(".vimrc")).unwrap();
+    fs::write(home.path().join(".zshrc"), "existing").unwrap();
+
+    let output = slnky()
+        .args(["status", "--porcelain"])
+        .env("HOME", home.path())
+        .env("CLICOLOR_FORCE", "1")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let mut lines: Vec<String> = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(String::from)
+        .collect();
+    lines.sort();
+    assert_eq!(
+        lines,
+        [
+            "C zsh 0/1",
+            "E empty 0/0",
+            "L git 1/1",
+            "P nvim 1/2",
+            "U tmux 0/1"
+        ]
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_link_force_backs_up_conflicting_file() {
+    let home = tempfile::tempdir().unwrap();
+    let stow_dir = home.path().join(".dotfiles");
+    fs::create_dir_all(stow_dir.join("zsh")).unwrap();
+    fs::write(stow_dir.join("zsh").join(".zshrc"), "export A=1").unwrap();
+    fs::write(home.path().join(".zshrc"), "existing").unwrap();
+    set_age(&home.path().join(".zshrc"), 3600);
+
+    write_config(home.path(), &stow_dir);
+
+    let output = slnky()
+        .args(["link", "zsh"])
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+
+    let output = slnky()
+        .args(["link", "zsh", "--force"])
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let zshrc = home.path().join(".zshrc");
+    assert!(zshrc.is_symlink());
+    assert_eq!(
+        fs::read_link(&zshrc).unwrap(),
+        stow_dir.join("zsh").join(".zshrc")
+    );
+    assert_eq!(
+        fs::read_to_string(home.path().join(".zshrc.backup")).unwrap(),
+        "existing"
+    );
+}
+
+fn set_age(path: &Path, secs: u64) {
+    let modified = std::time::SystemTime::now() - std::time::Duration::from_secs(secs);
+    fs::File::options()
+        .write(true)
+        .open(path)
+        .unwrap()
+        .set_modified(modified)
+        .unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_link_force_protects_newer_target() {
+    let home = tempfile::tempdir().unwrap();
+    let stow_dir = home.path().join(".dotfiles");
+    fs::create_dir_all(stow_dir.join("zsh")).unwrap();
+    fs::write(stow_dir.join("zsh").join(".zshrc"), "export A=1").unwrap();
+    set_age(&stow_dir.join("zsh").join(".zshrc"), 3600);
+    fs::write(home.path().join(".zshrc"), "edited in place").unwrap();
+    write_config(home.path(), &stow_dir);
+
+    let zshrc = home.path().join(".zshrc");
+    let output = slnky()
+        .args(["link", "zsh", "--force"])
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("may have unsaved changes"));
+    assert!(!zshrc.is_symlink());
+    assert_eq!(fs::read_to_string(&zshrc).unwrap(), "edited in place");
+    assert!(!home.path().join(".zshrc.backup").exists());
+
+    let output = slnky()
+        .args(["link", "zsh", "--force", "--yes"])
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(zshrc.is_symlink());
+    assert_eq!(
+        fs::read_to_string(home.path().join(".zshrc.backup")).unwrap(),
+        "edited in place"
+    );
+}
+
+#[test]
+fn test_check_fails_on_conflicts() {
+    let home = tempfile::tempdir().unwrap();
+    let stow_dir = home.path().join(".dotfiles");
+    fs::create_dir_all(stow_dir.join("zsh")).unwrap();
+    fs::write(stow_dir.join("zsh").join(".zshrc"), "export A=1").unwrap();
+    write_config(home.path(), &stow_dir);
+
+    let output = slnky()
+        .arg("check")
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "ok\tzsh\n");
+
+    fs::write(home.path().join(".zshrc"), "existing").unwrap();
+    let output = slnky()
+        .arg("check")
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with(&format!(
+        "conflict\tzsh\t{}\t",
+        home.path().join(".zshrc").display()
+    )));
+    assert!(!home.path().join(".zshrc").is_symlink());
+}
+
+#[test]
+fn test_require_apply_makes_link_a_dry_run() {
+    let home = tempfile::tempdir().unwrap();
+    let stow_dir = home.path().join(".dotfiles");
+    fs::create_dir_all(stow_dir.join("zsh")).unwrap();
+    fs::write(stow_dir.join("zsh").join(".zshrc"), "export A=1").unwrap();
+    write_config(home.path(), &stow_dir);
+
+    let config_path = home.path().join(".config/slinky/config.toml");
+    let mut config = fs::read_to_string(&config_path).unwrap();
+    config.push_str("require_apply = true\n");
+    fs::write(&config_path, config).unwrap();
+
+    let output = slnky()
+        .args(["link", "zsh"])
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(!home.path().join(".zshrc").exists());
+
+    let output = slnky()
+        .args(["link", "zsh", "--apply"])
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(home.path().join(".zshrc").is_symlink());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_link_records_last_linked_time() {
+    let home = tempfile::tempdir().unwrap();
+    let stow_dir = home.path().join(".dotfiles");
+    fs::create_dir_all(stow_dir.join("zsh")).unwrap();
+    fs::write(stow_dir.join("zsh").join(".zshrc"), "export A=1").unwrap();
+    write_config(home.path(), &stow_dir);
+
+    let output = slnky()
+        .args(["link", "zsh"])
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    let state: toml::Value =
+        toml::from_str(&fs::read_to_string(home.path().join(".config/slinky/state.toml")).unwrap())
+            .unwrap();
+    let zsh = &state["packages"]["zsh"];
+    let last_linked = zsh["last_linked"].as_integer().unwrap();
+    assert!((now - 60..=now).contains(&last_linked));
+    assert_eq!(zsh["link_count"].as_integer(), Some(1));
+
+    let output = slnky()
+        .args(["status", "--detailed"])
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert!(String::from_utf8_lossy(&output.stdout).contains("last linked: "));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_unlink_detach_leaves_real_copies() {
+    let home = tempfile::tempdir().unwrap();
+    let stow_dir = home.path().join(".dotfiles");
+    fs::create_dir_all(stow_dir.join("zsh")).unwrap();
+    fs::write(stow_dir.join("zsh").join(".zshrc"), "export A=1").unwrap();
+    write_config(home.path(), &stow_dir);
+
+    let output = slnky()
+        .args(["link", "zsh"])
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let zshrc = home.path().join(".zshrc");
+    assert!(zshrc.is_symlink());
+
+    let output = slnky()
+        .args(["unlink", "--all", "--detach", "--yes"])
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(!zshrc.is_symlink());
+    assert_eq!(fs::read_to_string(&zshrc).unwrap(), "export A=1");
+    assert!(stow_dir.join("zsh").join(".zshrc").is_file());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_relative_target_dir_resolves_against_cwd() {
+    let home = tempfile::tempdir().unwrap();
+    let stow_dir = home.path().join(".dotfiles");
+    fs::create_dir_all(stow_dir.join("zsh")).unwrap();
+    fs::write(stow_dir.join("zsh").join(".zshrc"), "export A=1").unwrap();
+
+    let config_dir = home.path().join(".config").join("slinky");
+    fs::create_dir_all(&config_dir).unwrap();
+    fs::write(
+        config_dir.join("config.toml"),
+        format!(
+            "stow_dir = {:?}\ntarget_dir = \"sandbox\"\n",
+            stow_dir.display().to_string()
+        ),
+    )
+    .unwrap();
+
+    let cwd = tempfile::tempdir().unwrap();
+    fs::create_dir_all(cwd.path().join("sandbox")).unwrap();
+
+    let output = slnky()
+        .args(["link", "zsh"])
+        .env("HOME", home.path())
+        .current_dir(cwd.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let zshrc = cwd.path().join("sandbox").join(".zshrc");
+    assert!(zshrc.is_symlink());
+    assert_eq!(
+        fs::read_link(&zshrc).unwrap(),
+        stow_dir.join("zsh").join(".zshrc")
+    );
+    assert!(!home.path().join(".zshrc").exists());
+}
+
+#[test]
+fn test_secrets_scan_all_reports_stow_dir_secrets() {
+    let home = tempfile::tempdir().unwrap();
+    let stow_dir = home.path().join(".dotfiles");
+    fs::create_dir_all(stow_dir.join("zsh")).unwrap();
+    fs::write(stow_dir.join("zsh").join(".zshrc"), "export A=1\n").unwrap();
+    fs::write(
+        stow_dir.join("zsh").join("logo.png"),
+        b"\x89PNG\0\0API_KEY=x",
+    )
+    .unwrap();
+    fs::write(
+        stow_dir.join("zsh").join("local.zsh"),
+        "export GITHUB_TOKEN=ghp_abc123\n",
+    )
+    .unwrap();
+    fs::write(
+        stow_dir.join("zsh").join(".stow-local-ignore"),
+        "local.zsh\n",
+    )
+    .unwrap();
+    write_config(home.path(), &stow_dir);
+
+    let output = slnky()
+        .args(["secrets", "scan", "--all"])
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    fs::write(
+        stow_dir.join("zsh").join(".zshrc"),
+        "export A=1\nexport API_KEY=sk_live_123\n",
+    )
+    .unwrap();
+    let output = slnky()
+        .args(["secrets", "scan", "--all"])
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(".zshrc"));
+    assert!(stdout.contains("line 2"));
+    assert!(!stdout.contains("local.zsh"));
+    assert!(!stdout.contains("logo.png"));
+}
+
+#[test]
+fn test_secrets_scan_directory_groups_by_file() {
+    let home = tempfile::tempdir().unwrap();
+    let dir = home.path().join("zsh");
+    fs::create_dir_all(dir.join("conf.d")).unwrap();
+    fs::write(dir.join(".zshrc"), "export EDITOR=nvim\n").unwrap();
+    fs::write(
+        dir.join("conf.d").join("tokens.zsh"),
+        "export A=1\nexport GITHUB_TOKEN=ghp_abc123\n",
+    )
+    .unwrap();
+    fs::write(dir.join("font.ttf"), b"\0\x01\0API_KEY=x").unwrap();
+
+    let output = slnky()
+        .args(["secrets", "scan"])
+        .arg(&dir)
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(&format!("conf.d{}tokens.zsh", std::path::MAIN_SEPARATOR)));
+    assert!(stdout.contains("line 2: GITHUB_TOKEN"));
+    assert!(!stdout.contains(".zshrc"));
+    assert!(!stdout.contains("font.ttf"));
+}
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .current_dir(dir)
+        .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .output()
+        .unwrap()
+        .status;
+    assert!(status.success(), "git {:?} failed", args);
+}
+
+fn bare_repo(root: &Path, name: &str, package: &str) -> String {
+    let work = root.join("work").join(name);
+    fs::create_dir_all(work.join(package)).unwrap();
+    fs::write(work.join(package).join(".rc"), "set -o vi\n").unwrap();
+    git(&work, &["init", "-q"]);
+    git(&work, &["add", "."]);
+    git(&work, &["commit", "-qm", "init"]);
+
+    let bare = root.join("remotes").join(format!("{}.git", name));
+    git(
+        root,
+        &[
+            "clone",
+            "-q",
+            "--bare",
+            work.to_str().unwrap(),
+            bare.to_str().unwrap(),
+        ],
+    );
+    format!("file://{}", bare.display())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_install_clones_multiple_repos() {
+    let home = tempfile::tempdir().unwrap();
+    let first = bare_repo(home.path(), "dotfiles", "zsh");
+    let second = bare_repo(home.path(), "work-dotfiles", "tmux");
+    write_config(home.path(), &home.path().join(".dotfiles"));
+
+    let output = slnky()
+        .args(["install", &first, &second])
+        .env("HOME", home.path())
+        .env("XDG_DATA_HOME", home.path().join(".local/share"))
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("zsh"));
+    assert!(stdout.contains("tmux"));
+    assert!(stdout.contains("Found 2 package(s) across 2 repositories"));
+
+    let cache = home
+        .path()
+        .join(".local/share/slinky/repos/git/localhost/remotes");
+    assert!(cache.join("dotfiles/zsh/.rc").exists());
+    assert!(cache.join("work-dotfiles/tmux/.rc").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_sync_without_git_just_relinks() {
+    let home = tempfile::tempdir().unwrap();
+    let stow_dir = home.path().join(".dotfiles");
+    fs::create_dir_all(stow_dir.join("zsh")).unwrap();
+    fs::write(stow_dir.join("zsh").join(".zshrc"), "export A=1").unwrap();
+    write_config(home.path(), &stow_dir);
+
+    let config_path = home.path().join(".config/slinky/config.toml");
+    let mut config = fs::read_to_string(&config_path).unwrap();
+    config.push_str("git_enabled = false\n");
+    fs::write(&config_path, config).unwrap();
+
+    let output = slnky()
+        .arg("sync")
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("Not a git repository"));
+    assert!(home.path().join(".zshrc").is_symlink());
+
+    let output = slnky()
+        .args(["update", "user/repo"])
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("git is disabled"));
+}
+
+#[test]
+fn test_missing_git_is_reported() {
+    let home = tempfile::tempdir().unwrap();
+    let stow_dir = home.path().join(".dotfiles");
+    fs::create_dir_all(stow_dir.join(".git")).unwrap();
+    write_config(home.path(), &stow_dir);
+    let empty_path = home.path().join("bin");
+    fs::create_dir_all(&empty_path).unwrap();
+
+    for args in [&["sync"][..], &["install", "user/repo"][..]] {
+        let output = slnky()
+            .args(args)
+            .env("HOME", home.path())
+            .env("PATH", &empty_path)
+            .output()
+            .unwrap();
+
+        assert!(!output.status.success(), "{:?} should fail", args);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains("git is not installed or not found in PATH"),
+            "{:?}: {}",
+            args,
+            stderr
+        );
+    }
+}
+
+#[test]
+fn test_packages_shows_package_metadata() {
+    let home = tempfile::tempdir().unwrap();
+    let stow_dir = home.path().join(".dotfiles");
+    fs::create_dir_all(stow_dir.join("nvim")).unwrap();
+    fs::create_dir_all(stow_dir.join("zsh")).unwrap();
+    fs::write(
+        stow_dir.join("nvim").join(".slinky.toml"),
+        "description = \"Neovim setup\"\ntarget = \"~/.config/nvim\"\n",
+    )
+    .unwrap();
+    fs::write(
+        stow_dir.join("zsh").join(".slinky.toml"),
+        "hosts = [\"laptop\", \"server\"]\nmax_depth = 1\n",
+    )
+    .unwrap();
+    write_config(home.path(), &stow_dir);
+
+    let output = slnky()
+        .args(["packages", "--json"])
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let packages: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let nvim = packages
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|p| p["name"] == "nvim")
+        .unwrap();
+    assert_eq!(nvim["description"], "Neovim setup");
+    assert_eq!(nvim["target"], serde_json::json!(["~/.config/nvim"]));
+
+    let zsh = packages
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|p| p["name"] == "zsh")
+        .unwrap();
+    assert!(zsh.get("description").is_none());
+    assert_eq!(zsh["hosts"], serde_json::json!(["laptop", "server"]));
+    assert_eq!(zsh["max_depth"], 1);
+
+    let output = slnky()
+        .arg("packages")
+        .env("HOME", home.path())
+        .env("NO_COLOR", "1")
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Neovim setup"));
+    assert!(stdout.contains("laptop, server"));
+}
+
+#[test]
+fn test_config_dir_flag_isolates_instances() {
+    let home = tempfile::tempdir().unwrap();
+    let first = home.path().join("first");
+    let second = home.path().join("second");
+
+    for (dir, stow_dir) in [(&first, "/srv/first"), (&second, "/srv/second")] {
+        let output = slnky()
+            .arg("--config-dir")
+            .arg(dir)
+            .args(["config", "set", "stow_dir", stow_dir])
+            .env("HOME", home.path())
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    let output = slnky()
+        .arg("--config-dir")
+        .arg(&first)
+        .args(["config", "path"])
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        first.join("config.toml").display().to_string()
+    );
+
+    let first_config = fs::read_to_string(first.join("config.toml")).unwrap();
+    let second_config = fs::read_to_string(second.join("config.toml")).unwrap();
+    assert!(first_config.contains("/srv/first"));
+    assert!(second_config.contains("/srv/second"));
+    assert!(!home.path().join(".config/slinky/config.toml").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_daemon_run_once_links_prunes_and_exits() {
+    let home = tempfile::tempdir().unwrap();
+    let stow_dir = home.path().join(".dotfiles");
+    fs::create_dir_all(stow_dir.join("zsh")).unwrap();
+    fs::write(stow_dir.join("zsh").join(".zshrc"), "export A=1").unwrap();
+    std::os::unix::fs::symlink(
+        stow_dir.join("zsh").join(".zprofile"),
+        home.path().join(".zprofile"),
+    )
+    .unwrap();
+    write_config(home.path(), &stow_dir);
+
+    let output = slnky()
+        .args(["daemon", "run", "--once"])
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        fs::read_link(home.path().join(".zshrc")).unwrap(),
+        stow_dir.join("zsh").join(".zshrc")
+    );
+    assert!(fs::symlink_metadata(home.path().join(".zprofile")).is_err());
+    assert!(!home.path().join(".config/slinky/daemon.pid").exists());
+    let log = fs::read_to_string(home.path().join(".config/slinky/daemon.log")).unwrap();
+    assert!(log.contains("Re-linked package 'zsh'"));
+    assert!(log.contains("Reconcile finished"));
+}
+
+#[test]
+fn test_link_routes_packages_to_their_own_targets() {
+    let home = tempfile::tempdir().unwrap();
+    let stow_dir = home.path().join(".dotfiles");
+    for package in ["nvim", "zsh", "tmux"] {
+        fs::create_dir_all(stow_dir.join(package)).unwrap();
+        fs::write(stow_dir.join(package).join(format!(".{}rc", package)), "").unwrap();
+    }
+    write_config(home.path(), &stow_dir);
+
+    let elsewhere = tempfile::tempdir().unwrap();
+    let zsh_target = elsewhere.path().join("z");
+    fs::create_dir_all(&zsh_target).unwrap();
+
+    let output = slnky()
+        .args(["link", "nvim", &format!("zsh={}", zsh_target.display())])
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(home.path().join(".nvimrc").is_symlink());
+    assert!(zsh_target.join(".zshrc").is_symlink());
+    assert!(!home.path().join(".zshrc").exists());
+    assert!(!home.path().join(".tmuxrc").exists());
+
+    // With --all, only the named package is redirected.
+    let output = slnky()
+        .args(["link", "--all", "tmux=t"])
+        .env("HOME", home.path())
+        .current_dir(elsewhere.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(elsewhere.path().join("t").join(".tmuxrc").is_symlink());
+    assert!(!home.path().join(".tmuxrc").exists());
+    assert!(home.path().join(".zshrc").is_symlink());
+
+    let output = slnky()
+        .args(["link", "zsh="])
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_status_for_a_single_package() {
+    let home = tempfile::tempdir().unwrap();
+    let stow_dir = home.path().join(".dotfiles");
+    fs::create_dir_all(stow_dir.join("zsh")).unwrap();
+    fs::write(stow_dir.join("zsh").join(".zshrc"), "export A=1").unwrap();
+    fs::write(stow_dir.join("zsh").join(".zprofile"), "").unwrap();
+    fs::create_dir_all(stow_dir.join("nvim")).unwrap();
+    fs::write(stow_dir.join("nvim").join(".nvimrc"), "").unwrap();
+    fs::write(home.path().join(".zshrc"), "existing").unwrap();
+    write_config(home.path(), &stow_dir);
+
+    let output = slnky()
+        .args(["status", "zsh"])
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("zsh"));
+    assert!(!stdout.contains("nvim"));
+    assert!(stdout.contains(&format!(
+        "{} (would link)",
+        home.path().join(".zprofile").display()
+    )));
+    assert!(stdout.contains(&format!("{} (", home.path().join(".zshrc").display())));
+
+    let output = slnky()
+        .args(["status", "tmux"])
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("tmux"));
+    assert!(stderr.contains("Available packages: "));
+    assert!(stderr.contains("nvim") && stderr.contains("zsh"));
+}
+
+#[test]
+fn test_output_formats_for_scripts() {
+    let home = tempfile::tempdir().unwrap();
+    let stow_dir = home.path().join(".dotfiles");
+    fs::create_dir_all(stow_dir.join("zsh")).unwrap();
+    fs::write(stow_dir.join("zsh").join(".zshrc"), "export A=1").unwrap();
+    fs::create_dir_all(stow_dir.join("nvim")).unwrap();
+    fs::write(stow_dir.join("nvim").join(".nvimrc"), "").unwrap();
+    write_config(home.path(), &stow_dir);
+
+    let output = slnky()
+        .args(["link", "zsh"])
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let output = slnky()
+        .args(["status", "--output", "plain"])
+        .env("HOME", home.path())
+        .env("CLICOLOR_FORCE", "1")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains('\x1b'));
+    let mut lines: Vec<&str> = stdout.lines().collect();
+    lines.sort();
+    assert_eq!(lines, ["nvim\tunlinked\t0\t1\t0", "zsh\tlinked\t1\t1\t0"]);
+
+    let output = slnky()
+        .args(["config", "show", "--output", "plain"])
+        .env("HOME", home.path())
+        .env("CLICOLOR_FORCE", "1")
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains('\x1b'));
+    assert!(stdout.contains(&format!("stow_dir\t{}\n", stow_dir.display())));
+    assert!(stdout.contains("auto_sync.enabled\ttrue\n"));
+
+    for args in [
+        &["status", "--output", "json"][..],
+        &["packages", "--output", "json"],
+        &["config", "show", "--output", "json"],
+        &["daemon", "status", "--output", "json"],
+    ] {
+        let output = slnky()
+            .args(args)
+            .env("HOME", home.path())
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?} failed", args);
+        serde_json::from_slice::<serde_json::Value>(&output.stdout)
+            .unwrap_or_else(|e| panic!("{:?} printed invalid JSON: {}", args, e));
+    }
+}
+
+/// Polls `done` until it holds or 15 seconds pass, for tests that drive a
+/// running daemon. Returns whether it held.
+fn wait_for(done: impl Fn() -> bool) -> bool {
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(15);
+    while !done() && std::time::Instant::now() < deadline {
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+    done()
+}
+
+#[test]
+fn test_foreground_daemon_honors_stow_dir_override() {
+    let home = tempfile::tempdir().unwrap();
+    write_config(home.path(), &home.path().join(".dotfiles"));
+    // The daemon ignores paths containing ".tmp", which the default
+    // tempdir names do.
+    let other = tempfile::Builder::new()
+        .prefix("slinky-stow")
+        .tempdir()
+        .unwrap();
+    fs::create_dir_all(other.path().join("zsh")).unwrap();
+    fs::write(other.path().join("zsh").join(".zshrc"), "export A=1").unwrap();
+
+    let mut daemon = slnky()
+        .arg("--stow-dir")
+        .arg(other.path())
+        .args(["--no-git", "daemon", "start", "--foreground"])
+        .env("HOME", home.path())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let log_path = home.path().join(".config/slinky/daemon.log");
+    let started = wait_for(|| {
+        fs::read_to_string(&log_path).is_ok_and(|log| log.contains("Daemon started successfully"))
+    });
+
+    // A package added to the override directory is picked up and linked.
+    fs::create_dir_all(other.path().join("git")).unwrap();
+    fs::write(other.path().join("git").join(".gitconfig"), "[user]").unwrap();
+    let linked = started && wait_for(|| home.path().join(".gitconfig").is_symlink());
+
+    daemon.kill().unwrap();
+    daemon.wait().unwrap();
+
+    let log = fs::read_to_string(&log_path).unwrap_or_default();
+    assert!(started, "daemon did not start:\n{}", log);
+    assert!(log.contains(&format!("Watching: {}", other.path().display())));
+    assert!(linked, "new package was not linked:\n{}", log);
+    assert_eq!(
+        fs::read_link(home.path().join(".gitconfig")).unwrap(),
+        other.path().join("git").join(".gitconfig")
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_daemon_stop_uses_control_socket() {
+    let home = tempfile::tempdir().unwrap();
+    let stow_dir = home.path().join(".dotfiles");
+    fs::create_dir_all(stow_dir.join("zsh")).unwrap();
+    write_config(home.path(), &stow_dir);
+    // Left behind by a daemon that was killed; replaced on start.
+    let socket = home.path().join(".config/slinky/daemon.sock");
+    fs::write(&socket, "").unwrap();
+
+    let mut daemon = slnky()
+        .args(["--no-git", "daemon", "start", "--foreground"])
+        .env("HOME", home.path())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let log_path = home.path().join(".config/slinky/daemon.log");
+    let started = wait_for(|| {
+        fs::read_to_string(&log_path).is_ok_and(|log| log.contains("Daemon started successfully"))
+    });
+
+    let reload = slnky()
+        .args(["daemon", "reload"])
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    let stop = slnky()
+        .args(["daemon", "stop"])
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    let pid_path = home.path().join(".config/slinky/daemon.pid");
+    let stopped = wait_for(|| !pid_path.exists());
+
+    let _ = daemon.kill();
+    daemon.wait().unwrap();
+
+    let log = fs::read_to_string(&log_path).unwrap_or_default();
+    assert!(started, "daemon did not start:\n{}", log);
+    assert!(
+        String::from_utf8_lossy(&reload.stdout).contains("Config reloaded"),
+        "reload failed: {}",
+        String::from_utf8_lossy(&reload.stderr)
+    );
+    assert!(stop.status.success());
+    assert!(stopped, "daemon did not stop:\n{}", log);
+    assert!(log.contains("Shutdown requested"));
+    assert!(!socket.exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_daemon_sync_falls_back_to_sigusr1() {
+    let home = tempfile::tempdir().unwrap();
+    let stow_dir = home.path().join(".dotfiles");
+    fs::create_dir_all(stow_dir.join("zsh")).unwrap();
+    fs::write(stow_dir.join("zsh").join(".zshrc"), "export A=1").unwrap();
+    write_config(home.path(), &stow_dir);
+
+    let mut daemon = slnky()
+        .args(["--no-git", "daemon", "start", "--foreground"])
+        .env("HOME", home.path())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let log_path = home.path().join(".config/slinky/daemon.log");
+    let socket = home.path().join(".config/slinky/daemon.sock");
+    let started = wait_for(|| {
+        fs::read_to_string(&log_path).is_ok_and(|log| log.contains("Daemon started successfully"))
+    });
+    // Without the socket, `daemon sync` has to signal the daemon instead.
+    fs::remove_file(&socket).unwrap();
+
+    let sync = slnky()
+        .args(["daemon", "sync"])
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    let linked = wait_for(|| home.path().join(".zshrc").is_symlink());
+
+    daemon.kill().unwrap();
+    daemon.wait().unwrap();
+
+    let log = fs::read_to_string(&log_path).unwrap_or_default();
+    assert!(started, "daemon did not start:\n{}", log);
+    assert!(
+        sync.status.success(),
+        "{}",
+        String::from_utf8_lossy(&sync.stderr)
+    );
+    assert!(log.contains("Sync requested"), "{}", log);
+    assert!(linked, "packages were not re-linked:\n{}", log);
+}
+
+#[test]
+fn test_prompts_fail_without_a_terminal() {
+    let home = tempfile::tempdir().unwrap();
+
+    let output = slnky()
+        .arg("init")
+        .env("HOME", home.path())
+        .stdin(std::process::Stdio::piped())
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("input required but stdin is not a terminal"));
+    assert!(stderr.contains("Enter dotfiles directory"));
+    assert!(!home.path().join(".config/slinky/config.toml").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_no_input_turns_prompts_into_errors() {
+    let home = tempfile::tempdir().unwrap();
+    let stow_dir = home.path().join(".dotfiles");
+    fs::create_dir_all(stow_dir.join("zsh")).unwrap();
+    fs::write(stow_dir.join("zsh").join(".zshrc"), "export A=1").unwrap();
+    std::os::unix::fs::symlink(
+        stow_dir.join("zsh").join(".zshrc"),
+        home.path().join(".zshrc"),
+    )
+    .unwrap();
+    write_config(home.path(), &stow_dir);
+
+    let output = slnky()
+        .args(["--no-input", "unlink", "zsh"])
+        .env("HOME", home.path())
+        .stdin(std::process::Stdio::piped())
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("input required but --no-input set: Continue?"));
+    assert!(home.path().join(".zshrc").is_symlink());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_env_vars_override_config_dirs() {
+    let home = tempfile::tempdir().unwrap();
+    let configured = home.path().join(".dotfiles");
+    fs::create_dir_all(&configured).unwrap();
+    write_config(home.path(), &configured);
+
+    let stow_dir = home.path().join("ci-dotfiles");
+    let target = home.path().join("ci-home");
+    fs::create_dir_all(stow_dir.join("zsh")).unwrap();
+    fs::create_dir_all(&target).unwrap();
+    fs::write(stow_dir.join("zsh").join(".zshrc"), "export A=1").unwrap();
+
+    let output = slnky()
+        .args(["link", "zsh"])
+        .env("HOME", home.path())
+        .env("SLINKY_STOW_DIR", &stow_dir)
+        .env("SLINKY_TARGET_DIR", &target)
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(
+        fs::read_link(target.join(".zshrc")).unwrap(),
+        stow_dir.join("zsh").join(".zshrc")
+    );
+    assert!(!home.path().join(".zshrc").exists());
+
+    let flag_target = home.path().join("flag-home");
+    fs::create_dir_all(&flag_target).unwrap();
+    let output = slnky()
+        .args(["link", "zsh", "--target"])
+        .arg(&flag_target)
+        .env("HOME", home.path())
+        .env("SLINKY_STOW_DIR", &stow_dir)
+        .env("SLINKY_TARGET_DIR", &target)
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert!(flag_target.join(".zshrc").is_symlink());
+}
+
+#[test]
+fn test_clean_removes_only_selected_artifacts() {
+    let home = tempfile::tempdir().unwrap();
+    let stow_dir = home.path().join(".dotfiles");
+    fs::create_dir_all(stow_dir.join("zsh")).unwrap();
+    fs::write(stow_dir.join("zsh").join(".zshrc"), "export A=1").unwrap();
+    fs::write(
+        stow_dir.join("zsh").join(".zshrc.template"),
+        "export A=${A}",
+    )
+    .unwrap();
+    fs::write(
+        stow_dir.join("zsh").join("old.sh.template"),
+        "export B=${B}",
+    )
+    .unwrap();
+    fs::write(home.path().join(".zshrc.backup"), "old").unwrap();
+    set_age(&home.path().join(".zshrc.backup"), 90 * 24 * 3600);
+    write_config(home.path(), &stow_dir);
+
+    let output = slnky()
+        .args(["clean", "--templates", "--yes"])
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert!(!stow_dir.join("zsh").join("old.sh.template").exists());
+    assert!(stow_dir.join("zsh").join(".zshrc.template").exists());
+    assert!(home.path().join(".zshrc.backup").exists());
+
+    let output = slnky()
+        .args(["clean", "--backups", "--yes"])
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert!(!home.path().join(".zshrc.backup").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_install_uses_local_directory_without_git() {
+    let home = tempfile::tempdir().unwrap();
+    let configured = home.path().join(".dotfiles");
+    fs::create_dir_all(&configured).unwrap();
+    write_config(home.path(), &configured);
+
+    let local = home.path().join("mounted").join("dots");
+    fs::create_dir_all(local.join("zsh")).unwrap();
+    fs::write(local.join("zsh").join(".zshrc"), "export A=1").unwrap();
+    let empty_path = home.path().join("bin");
+    fs::create_dir_all(&empty_path).unwrap();
+
+    let output = slnky()
+        .args(["install", "--link", "--yes"])
+        .arg(&local)
+        .env("HOME", home.path())
+        .env("PATH", &empty_path)
+        .output()
+        .unwrap();
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        fs::read_link(home.path().join(".zshrc")).unwrap(),
+        local.join("zsh").join(".zshrc")
+    );
+    let config = fs::read_to_string(home.path().join(".config/slinky/config.toml")).unwrap();
+    assert!(config.contains(&format!("stow_dir = {:?}", local.display().to_string())));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_deleted_cached_stow_dir_suggests_install() {
+    let home = tempfile::tempdir().unwrap();
+    let data_home = home.path().join(".local/share");
+    let stow_dir = data_home.join("slinky/repos/github.com/user/dotfiles");
+    write_config(home.path(), &stow_dir);
+
+    for args in [vec!["sync"], vec!["link", "--all"], vec!["status"]] {
+        let output = slnky()
+            .args(&args)
+            .env("HOME", home.path())
+            .env("XDG_DATA_HOME", &data_home)
+            .output()
+            .unwrap();
+        let combined = format!(
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert!(
+            combined.contains("slnky install user/dotfiles"),
+            "{:?}: {}",
+            args,
+            combined
+        );
+    }
+}
+
+#[test]
+fn test_force_color_keeps_escapes_when_piped() {
+    let home = tempfile::tempdir().unwrap();
+    let stow_dir = home.path().join(".dotfiles");
+    fs::create_dir_all(stow_dir.join("zsh")).unwrap();
+    fs::write(stow_dir.join("zsh/.zshrc"), "export A=1").unwrap();
+    write_config(home.path(), &stow_dir);
+
+    let status = |args: &[&str], env: &[(&str, &str)]| {
+        let output = slnky()
+            .arg("status")
+            .args(args)
+            .env("HOME", home.path())
+            .env_remove("CLICOLOR_FORCE")
+            .env_remove("NO_COLOR")
+            .envs(env.iter().copied())
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    assert!(!status(&[], &[]).contains('\x1b'));
+    assert!(status(&["--force-color"], &[]).contains('\x1b'));
+    assert!(status(&["--force-color"], &[("NO_COLOR", "1")]).contains('\x1b'));
+    assert!(status(&[], &[("CLICOLOR_FORCE", "1")]).contains('\x1b'));
+    assert!(!status(&["--no-color"], &[("CLICOLOR_FORCE", "1")]).contains('\x1b'));
+
+    let output = slnky()
+        .args(["status", "--force-color", "--no-color"])
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_status_security_reports_tracked_secrets() {
+    let home = tempfile::tempdir().unwrap();
+    let stow_dir = home.path().join(".dotfiles");
+    fs::create_dir_all(stow_dir.join("zsh")).unwrap();
+    fs::create_dir_all(stow_dir.join("nvim")).unwrap();
+    fs::write(
+        stow_dir.join("zsh/.zshrc"),
+        "alias ll='ls -l'\nexport API_KEY=abc123\n",
+    )
+    .unwrap();
+    fs::write(
+        stow_dir.join("zsh/.zshenv.template"),
+        "export TOKEN=example\n",
+    )
+    .unwrap();
+    fs::write(stow_dir.join("zsh/.stow-local-ignore"), "notes.txt\n").unwrap();
+    fs::write(stow_dir.join("zsh/notes.txt"), "PASSWORD=hunter2\n").unwrap();
+    fs::write(stow_dir.join("nvim/.env"), "GITHUB_TOKEN=untracked\n").unwrap();
+    git(&stow_dir, &["init", "-q"]);
+    git(&stow_dir, &["add", "zsh"]);
+    write_config(home.path(), &stow_dir);
+
+    let output = slnky()
+        .args(["status", "--security"])
+        .env("HOME", home.path())
+        .env("NO_COLOR", "1")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("zsh/.zshrc:2 API_KEY"), "{}", stdout);
+    assert!(!stdout.contains("TOKEN"), "{}", stdout);
+    assert!(stdout.contains("zsh/notes.txt:1 PASSWORD"), "{}", stdout);
+}
+
+#[test]
+fn test_spinner_is_a_plain_line_without_a_terminal() {
+    let home = tempfile::tempdir().unwrap();
+    let stow_dir = home.path().join(".dotfiles");
+    fs::create_dir_all(stow_dir.join("zsh")).unwrap();
+    fs::write(stow_dir.join("zsh/.zshrc"), "alias ll='ls -l'\n").unwrap();
+    write_config(home.path(), &stow_dir);
+
+    let output = slnky()
+        .args(["secrets", "scan", "--all"])
+        .env("HOME", home.path())
+        .env_remove("CI")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(stderr, "Scanning for secrets...\n");
+    assert!(!stderr.contains('\x1b'));
+    assert!(!stderr.contains('\r'));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_migrate_from_stow_adopts_stowed_dotfiles() {
+    use std::os::unix::fs::symlink;
+
+    // A stow directory reached through a symlink, stowed with the relative
+    // links GNU Stow computes from its real location.
+    let home = tempfile::tempdir().unwrap();
+    let real_dir = home.path().join("src/dotfiles");
+    fs::create_dir_all(real_dir.join("zsh")).unwrap();
+    fs::create_dir_all(real_dir.join("nvim/.config/nvim")).unwrap();
+    fs::create_dir_all(real_dir.join("tmux")).unwrap();
+    fs::write(real_dir.join("zsh/.zshrc"), "export A=1\n").unwrap();
+    fs::write(real_dir.join("nvim/.config/nvim/init.vim"), "set nu\n").unwrap();
+    fs::write(real_dir.join("tmux/.tmux.conf"), "set -g mouse on\n").unwrap();
+    fs::write(real_dir.join(".stow-local-ignore"), "README.*\n").unwrap();
+    symlink("src/dotfiles", home.path().join(".dotfiles")).unwrap();
+    symlink("src/dotfiles/zsh/.zshrc", home.path().join(".zshrc")).unwrap();
+    fs::create_dir_all(home.path().join(".config")).unwrap();
+    symlink(
+        "../src/dotfiles/nvim/.config/nvim",
+        home.path().join(".config/nvim"),
+    )
+    .unwrap();
+    // An unlinked candidate that must lose to the stowed one.
+    fs::create_dir_all(home.path().join("dotfiles/git")).unwrap();
+    fs::write(home.path().join("dotfiles/git/.gitconfig"), "[user]\n").unwrap();
+
+    let output = slnky()
+        .args(["migrate-from-stow", "--yes"])
+        .env("HOME", home.path())
+        .env("NO_COLOR", "1")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("zsh (1/1 linked)"), "{}", stdout);
+    assert!(stdout.contains("nvim (1/1 linked)"), "{}", stdout);
+    assert!(stdout.contains("tmux (0/1 linked)"), "{}", stdout);
+    assert!(
+        stdout.contains(".stow-local-ignore is not read"),
+        "{}",
+        stdout
+    );
+
+    let config = fs::read_to_string(home.path().join(".config/slinky/config.toml")).unwrap();
+    let stow_dir = home.path().join(".dotfiles");
+    assert!(
+        config.contains(&format!("stow_dir = {:?}", stow_dir.display().to_string())),
+        "{}",
+        config
+    );
+
+    let output = slnky()
+        .args(["status", "--porcelain"])
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("L zsh 1/1"), "{}", stdout);
+    assert!(stdout.contains("L nvim 1/1"), "{}", stdout);
+    assert!(stdout.contains("U tmux 0/1"), "{}", stdout);
+}
+
+#[test]
+fn test_link_plan_is_not_written_with_conflicts() {
+    let home = tempfile::tempdir().unwrap();
+    let stow_dir = home.path().join(".dotfiles");
+    fs::create_dir_all(stow_dir.join("zsh")).unwrap();
+    fs::create_dir_all(stow_dir.join("nvim")).unwrap();
+    fs::write(stow_dir.join("zsh/.zshrc"), "export A=1\n").unwrap();
+    fs::write(stow_dir.join("nvim/.vimrc"), "set nu\n").unwrap();
+    fs::write(home.path().join(".zshrc"), "local edits\n").unwrap();
+    write_config(home.path(), &stow_dir);
+    let plan = home.path().join("plan.json");
+
+    let output = slnky()
+        .args(["link", "--all", "--plan"])
+        .arg(&plan)
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("no plan written"), "{}", stderr);
+    assert!(!plan.exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_link_force_dry_run_keeps_conflicting_file() {
+    let home = tempfile::tempdir().unwrap();
+    let stow_dir = home.path().join(".dotfiles");
+    fs::create_dir_all(stow_dir.join("zsh")).unwrap();
+    fs::write(stow_dir.join("zsh/.zshrc"), "export A=1\n").unwrap();
+    let zshrc = home.path().join(".zshrc");
+    fs::write(&zshrc, "existing\n").unwrap();
+    set_age(&zshrc, 3600);
+    fs::write(home.path().join(".zshrc.backup"), "older backup\n").unwrap();
+    write_config(home.path(), &stow_dir);
+
+    let output = slnky()
+        .args(["link", "zsh", "--force", "--dry-run"])
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("existing file backed up"), "{}", stdout);
+    assert!(!zshrc.is_symlink());
+    assert_eq!(fs::read_to_string(&zshrc).unwrap(), "existing\n");
+
+    let output = slnky()
+        .args(["link", "zsh", "--force"])
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(zshrc.is_symlink());
+    assert_eq!(
+        fs::read_to_string(home.path().join(".zshrc.backup")).unwrap(),
+        "older backup\n"
+    );
+    assert_eq!(
+        fs::read_to_string(home.path().join(".zshrc.backup.1")).unwrap(),
+        "existing\n"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_failing_pre_link_hook_leaves_conflicting_file() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let home = tempfile::tempdir().unwrap();
+    let stow_dir = home.path().join(".dotfiles");
+    let hooks = stow_dir.join("zsh/.slinky/hooks.d");
+    fs::create_dir_all(&hooks).unwrap();
+    fs::write(stow_dir.join("zsh/.zshrc"), "export A=1\n").unwrap();
+    fs::write(hooks.join("pre-link"), "#!/bin/sh\nexit 1\n").unwrap();
+    fs::set_permissions(hooks.join("pre-link"), fs::Permissions::from_mode(0o755)).unwrap();
+    let zshrc = home.path().join(".zshrc");
+    fs::write(&zshrc, "existing\n").unwrap();
+    set_age(&zshrc, 3600);
+    write_config(home.path(), &stow_dir);
+
+    let output = slnky()
+        .args(["link", "zsh", "--force"])
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(!zshrc.is_symlink());
+    assert_eq!(fs::read_to_string(&zshrc).unwrap(), "existing\n");
+    assert!(!home.path().join(".zshrc.backup").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_daemon_run_once_dry_run_changes_nothing() {
+    let home = tempfile::tempdir().unwrap();
+    let stow_dir = home.path().join(".dotfiles");
+    fs::create_dir_all(stow_dir.join("zsh")).unwrap();
+    fs::write(stow_dir.join("zsh/.zshrc"), "export A=1").unwrap();
+    let orphan = home.path().join(".zprofile");
+    std::os::unix::fs::symlink(stow_dir.join("zsh/.zprofile"), &orphan).unwrap();
+    write_config(home.path(), &stow_dir);
+    require_apply(home.path());
+
+    for args in [
+        &["daemon", "run", "--once", "--dry-run"][..],
+        &["daemon", "run", "--once"][..],
+    ] {
+        let output = slnky()
+            .args(args)
+            .env("HOME", home.path())
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("Would re-link package 'zsh'"), "{}", stderr);
+        assert!(
+            stderr.contains("Would prune orphaned symlink"),
+            "{}",
+            stderr
+        );
+        assert!(!home.path().join(".zshrc").exists());
+        assert!(orphan.is_symlink());
+        assert!(!home.path().join(".config/slinky/daemon.log").exists());
+    }
+}
+
+fn require_apply(home: &Path) {
+    let config_path = home.join(".config/slinky/config.toml");
+    let config = fs::read_to_string(&config_path).unwrap();
+    fs::write(&config_path, format!("{}require_apply = true\n", config)).unwrap();
+}
+
+#[test]
+fn test_require_apply_previews_apply() {
+    let home = tempfile::tempdir().unwrap();
+    let stow_dir = home.path().join(".dotfiles");
+    fs::create_dir_all(stow_dir.join("zsh")).unwrap();
+    fs::write(stow_dir.join("zsh/.zshrc"), "export A=1\n").unwrap();
+    write_config(home.path(), &stow_dir);
+    let plan = home.path().join("plan.json");
+    let output = slnky()
+        .args(["link", "zsh", "--plan"])
+        .arg(&plan)
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    require_apply(home.path());
+
+    let output = slnky()
+        .arg("apply")
+        .arg(&plan)
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Safe mode"));
+    assert!(fs::symlink_metadata(home.path().join(".zshrc")).is_err());
+
+    let output = slnky()
+        .args(["apply", "--apply"])
+        .arg(&plan)
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(home.path().join(".zshrc").is_symlink());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_require_apply_previews_install_link() {
+    let home = tempfile::tempdir().unwrap();
+    let configured = home.path().join(".dotfiles");
+    fs::create_dir_all(&configured).unwrap();
+    write_config(home.path(), &configured);
+    require_apply(home.path());
+    let local = home.path().join("dots");
+    fs::create_dir_all(local.join("zsh")).unwrap();
+    fs::write(local.join("zsh/.zshrc"), "export A=1\n").unwrap();
+
+    let output = slnky()
+        .args(["install", "--link"])
+        .arg(&local)
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Safe mode"));
+    assert!(fs::symlink_metadata(home.path().join(".zshrc")).is_err());
+    let config = fs::read_to_string(home.path().join(".config/slinky/config.toml")).unwrap();
+    assert!(!config.contains(&local.display().to_string()), "{}", config);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_require_apply_previews_migrate_from_stow() {
+    let home = tempfile::tempdir().unwrap();
+    let configured = home.path().join(".dotfiles");
+    fs::create_dir_all(&configured).unwrap();
+    write_config(home.path(), &configured);
+    require_apply(home.path());
+    let stow = home.path().join("stow");
+    fs::create_dir_all(stow.join("zsh")).unwrap();
+    fs::write(stow.join("zsh/.zshrc"), "export A=1\n").unwrap();
+
+    let output = slnky()
+        .arg("migrate-from-stow")
+        .arg(&stow)
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Would write to"), "{}", stdout);
+    let config = fs::read_to_string(home.path().join(".config/slinky/config.toml")).unwrap();
+    assert!(!config.contains(&stow.display().to_string()), "{}", config);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_clean_repos_keeps_configured_and_modified_clones() {
+    let home = tempfile::tempdir().unwrap();
+    let upstream = home.path().join("upstream");
+    fs::create_dir_all(upstream.join("zsh")).unwrap();
+    fs::write(upstream.join("zsh/.zshrc"), "export A=1\n").unwrap();
+    git(&upstream, &["init", "-q"]);
+    git(&upstream, &["add", "."]);
+    git(&upstream, &["commit", "-qm", "init"]);
+
+    let repos = home
+        .path()
+        .join(".local/share/slinky/repos/github.com/user");
+    fs::create_dir_all(&repos).unwrap();
+    for name in ["configured", "unused", "modified"] {
+        git(&repos, &["clone", "-q", upstream.to_str().unwrap(), name]);
+    }
+    fs::write(repos.join("modified/zsh/.zshrc"), "export A=2\n").unwrap();
+    write_config(home.path(), &repos.join("configured"));
+
+    let clean = |args: &[&str], stow_dir: &str| {
+        let output = slnky()
+            .arg("clean")
+            .args(args)
+            .env("HOME", home.path())
+            .env_remove("XDG_DATA_HOME")
+            .env("SLINKY_STOW_DIR", repos.join(stow_dir))
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    clean(&["--yes"], "unused");
+    assert!(repos.join("unused").exists());
+
+    let stdout = clean(&["--repos", "--yes"], "unused");
+    assert!(repos.join("unused").exists(), "{}", stdout);
+    assert!(repos.join("configured").exists());
+
+    let stdout = clean(&["--repos", "--yes"], "configured");
+    assert!(!repos.join("unused").exists(), "{}", stdout);
+    assert!(repos.join("configured").exists());
+    assert!(repos.join("modified").exists());
+    assert!(
+        stdout.contains("uncommitted changes or unpushed commits"),
+        "{}",
+        stdout
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_relink_all_reports_failures_and_previews() {
+    let home = tempfile::tempdir().unwrap();
+    let stow_dir = home.path().join(".dotfiles");
+    fs::create_dir_all(stow_dir.join("git")).unwrap();
+    fs::create_dir_all(stow_dir.join("zsh")).unwrap();
+    fs::write(stow_dir.join("git/.gitconfig"), "[user]\n").unwrap();
+    fs::write(stow_dir.join("zsh/.zshrc"), "export A=1\n").unwrap();
+    write_config(home.path(), &stow_dir);
+    let link = slnky()
+        .args(["link", "--all"])
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert!(link.status.success());
+
+    fs::write(stow_dir.join("git/.gitignore"), "*.swp\n").unwrap();
+    fs::write(home.path().join(".gitignore"), "local\n").unwrap();
+
+    let output = slnky()
+        .args(["relink", "zsh", "--dry-run"])
+        .env("HOME", home.path())
+        .env("NO_COLOR", "1")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let zshrc = home.path().join(".zshrc");
+    assert!(
+        stdout.contains(&format!("- {}", zshrc.display())),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains(&format!(
+            "+ {} → {}",
+            zshrc.display(),
+            stow_dir.join("zsh/.zshrc").display()
+        )),
+        "{}",
+        stdout
+    );
+
+    let output = slnky()
+        .args(["relink", "--all"])
+        .env("HOME", home.path())
+        .env("NO_COLOR", "1")
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("✓ zsh - 1 removed, 1 created"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("✗ git - "), "{}", stdout);
+    assert!(stdout.contains("1 package(s) relinked"), "{}", stdout);
+    assert!(stdout.contains("1 package(s) failed: git"), "{}", stdout);
+    assert!(home.path().join(".gitconfig").is_symlink());
+    assert_eq!(
+        fs::read_to_string(home.path().join(".gitignore")).unwrap(),
+        "local\n"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_install_saves_stow_dir_without_run_overrides() {
+    let home = tempfile::tempdir().unwrap();
+    let configured = home.path().join(".dotfiles");
+    fs::create_dir_all(&configured).unwrap();
+    write_config(home.path(), &configured);
+    let local = home.path().join("mounted/dots");
+    fs::create_dir_all(local.join("zsh")).unwrap();
+    fs::write(local.join("zsh/.zshrc"), "export A=1").unwrap();
+
+    let output = slnky()
+        .args(["--no-git", "--yes", "install"])
+        .arg(&local)
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let config = fs::read_to_string(home.path().join(".config/slinky/config.toml")).unwrap();
+    assert!(
+        config.contains(&format!("stow_dir = {:?}", local.display().to_string())),
+        "{}",
+        config
+    );
+    assert!(!config.contains("git_enabled = false"), "{}", config);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_install_does_not_save_env_overrides() {
+    let home = tempfile::tempdir().unwrap();
+    let configured = home.path().join(".dotfiles");
+    fs::create_dir_all(&configured).unwrap();
+    write_config(home.path(), &configured);
+    let local = home.path().join("mounted/dots");
+    fs::create_dir_all(local.join("zsh")).unwrap();
+    fs::write(local.join("zsh/.zshrc"), "export A=1").unwrap();
+    let sandbox = home.path().join("sandbox");
+
+    let output = slnky()
+        .args(["--yes", "install"])
+        .arg(&local)
+        .env("HOME", home.path())
+        .env("SLINKY_TARGET_DIR", &sandbox)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let config = fs::read_to_string(home.path().join(".config/slinky/config.toml")).unwrap();
+    assert!(
+        config.contains(&format!(
+            "target_dir = {:?}",
+            home.path().display().to_string()
+        )),
+        "{}",
+        config
+    );
+    assert!(!config.contains("sandbox"), "{}", config);
+}
+
+#[test]
+fn test_missing_config_include_is_an_error() {
+    let home = tempfile::tempdir().unwrap();
+    let stow_dir = home.path().join("dots");
+    fs::create_dir_all(&stow_dir).unwrap();
+    write_config(home.path(), &stow_dir);
+    let config_path = home.path().join(".config/slinky/config.toml");
+    let config = fs::read_to_string(&config_path).unwrap();
+    fs::write(
+        &config_path,
+        format!("include = [\"work.toml\"]\n{}", config),
+    )
+    .unwrap();
+
+    let output = slnky()
+        .arg("status")
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("work.toml"), "{}", stderr);
+
+    let output = slnky()
+        .args(["config", "path"])
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_invalid_dir_mode_is_reported() {
+    let home = tempfile::tempdir().unwrap();
+    let stow_dir = home.path().join("dots");
+    fs::create_dir_all(stow_dir.join("zsh")).unwrap();
+    fs::write(stow_dir.join("zsh/.zshrc"), "export A=1").unwrap();
+    write_config(home.path(), &stow_dir);
+    let config_path = home.path().join(".config/slinky/config.toml");
+    let mut config = fs::read_to_string(&config_path).unwrap();
+    config.push_str("dir_mode = \"0799\"\n");
+    fs::write(&config_path, config).unwrap();
+
+    let output = slnky()
+        .args(["link", "zsh"])
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Invalid dir_mode '0799'"), "{}", stderr);
+    assert!(!home.path().join(".zshrc").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_install_reports_packages_in_more_than_one_repo() {
+    let home = tempfile::tempdir().unwrap();
+    let first = bare_repo(home.path(), "dotfiles", "zsh");
+    let second = bare_repo(home.path(), "work-dotfiles", "zsh");
+    write_config(home.path(), &home.path().join(".dotfiles"));
+
+    let output = slnky()
+        .args(["install", &first, &second])
+        .env("HOME", home.path())
+        .env("XDG_DATA_HOME", home.path().join(".local/share"))
+        .env("NO_COLOR", "1")
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let cache = home
+        .path()
+        .join(".local/share/slinky/repos/git/localhost/remotes");
+    assert!(
+        stdout.contains("Package zsh is defined in more than one repository"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains(&cache.join("dotfiles/zsh").display().to_string()));
+    assert!(stdout.contains(&cache.join("work-dotfiles/zsh").display().to_string()));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("1 package(s) are defined in more than one repository"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn test_status_reports_packages_that_cannot_be_analyzed() {
+    let home = tempfile::tempdir().unwrap();
+    let stow_dir = home.path().join("dots");
+    fs::create_dir_all(stow_dir.join("shell")).unwrap();
+    fs::create_dir_all(stow_dir.join("zsh")).unwrap();
+    fs::write(stow_dir.join("shell/config"), "generic").unwrap();
+    fs::write(stow_dir.join("shell/config.local"), "local").unwrap();
+    fs::write(
+        stow_dir.join("shell/.slinky.toml"),
+        "[remap]\n\"config.local\" = \"config\"\n",
+    )
+    .unwrap();
+    fs::write(stow_dir.join("zsh/.zshrc"), "export A=1").unwrap();
+    write_config(home.path(), &stow_dir);
+
+    let status = |args: &[&str]| {
+        let output = slnky()
+            .arg("status")
+            .args(args)
+            .env("HOME", home.path())
+            .env("NO_COLOR", "1")
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let stdout = status(&[]);
+    assert!(stdout.contains("shell (error: "), "{}", stdout);
+    assert!(stdout.contains("Remap collision"), "{}", stdout);
+    assert!(!stdout.contains("shell (empty)"), "{}", stdout);
+    assert!(stdout.contains("zsh (not linked)"), "{}", stdout);
+    assert!(
+        stdout.contains("1 package(s) could not be analyzed"),
+        "{}",
+        stdout
+    );
+
+    let porcelain = status(&["--porcelain"]);
+    let mut lines: Vec<&str> = porcelain.lines().collect();
+    lines.sort();
+    assert_eq!(lines, ["U zsh 0/1", "X shell 0/0"]);
+
+    let reports: Vec<serde_json::Value> =
+        serde_json::from_str(&status(&["--output", "json"])).unwrap();
+    let report = |name: &str| reports.iter().find(|r| r["name"] == name).unwrap();
+    assert_eq!(report("shell")["state"], "error");
+    assert!(report("shell")["error"]
+        .as_str()
+        .unwrap()
+        .contains("Remap collision"));
+    assert_eq!(report("zsh")["state"], "unlinked");
+    assert!(report("zsh").get("error").is_none());
+}
+
+#[test]
+fn test_daemon_status_json_schema() {
+    let home = tempfile::tempdir().unwrap();
+    let stow_dir = home.path().join(".dotfiles");
+    fs::create_dir_all(&stow_dir).unwrap();
+    write_config(home.path(), &stow_dir);
+    fs::write(
+        home.path().join(".config/slinky/daemon.stats"),
+        r#"{"started_at":100,"links_created":4,"pulls":1,"conflicts":0,"errors":0,"last_activity":160}"#,
+    )
+    .unwrap();
+
+    let output = slnky()
+        .args(["daemon", "status", "--json"])
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["running"], false);
+    assert_eq!(report["pid"], serde_json::Value::Null);
+    assert!(report["service_installed"].is_boolean());
+    assert!(report["service_active"].is_boolean());
+    assert_eq!(report["auto_sync"]["enabled"], true);
+    assert_eq!(report["auto_sync"]["conflict_resolution"], "backup");
+    assert_eq!(report["last_activity"], 160);
+    assert_eq!(report["stats"]["links_created"], 4);
+}